@@ -0,0 +1,116 @@
+//! File fingerprints used to key cache entries
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Bytes hashed from the start, middle and end of a file
+const SAMPLE_SIZE: u64 = 64 * 1024;
+
+/// Identity of a file: size, modification time and a sampled content hash
+///
+/// Hashing three samples instead of the whole file keeps fingerprinting a
+/// multi-gigabyte video cheap while still telling apart files that happen to
+/// have the same size.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub size: u64,
+
+    /// Modification time in nanoseconds since the Unix epoch
+    pub modified: u64,
+
+    /// SHA-256 of the sampled content
+    pub sample: String,
+}
+
+impl FileFingerprint {
+    /// Fingerprint a file, reading at most three samples of it
+    pub fn of(path: &Path) -> std::io::Result<Self> {
+        let mut file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let size = metadata.len();
+
+        let mut hasher = Sha256::new();
+        let mut buf = vec![0u8; SAMPLE_SIZE as usize];
+        // Small files are hashed whole
+        let samples = if size <= 3 * SAMPLE_SIZE {
+            vec![(0, size)]
+        } else {
+            vec![
+                (0, SAMPLE_SIZE),
+                (size / 2 - SAMPLE_SIZE / 2, SAMPLE_SIZE),
+                (size - SAMPLE_SIZE, SAMPLE_SIZE),
+            ]
+        };
+        for (offset, len) in samples {
+            file.seek(SeekFrom::Start(offset))?;
+            let mut remaining = len;
+            while remaining > 0 {
+                let chunk = remaining.min(SAMPLE_SIZE) as usize;
+                let read = file.read(&mut buf[..chunk])?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buf[..read]);
+                remaining -= read as u64;
+            }
+        }
+
+        Ok(Self {
+            size,
+            modified: modified_nanos(&metadata),
+            sample: format!("{:x}", hasher.finalize()),
+        })
+    }
+
+    /// Whether the file still has the size and modification time recorded
+    ///
+    /// Only reads metadata, so it is cheap enough to check every entry.
+    pub fn is_current(&self, path: &Path) -> bool {
+        std::fs::metadata(path)
+            .map(|metadata| {
+                metadata.len() == self.size && modified_nanos(&metadata) == self.modified
+            })
+            .unwrap_or(false)
+    }
+}
+
+fn modified_nanos(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_same_size_different_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let a = temp_dir.path().join("a.mkv");
+        let b = temp_dir.path().join("b.mkv");
+
+        // Large enough to be sampled, differing only in the middle sample
+        let mut content = vec![0u8; 1024 * 1024];
+        std::fs::write(&a, &content).unwrap();
+        content[512 * 1024] = 1;
+        std::fs::write(&b, &content).unwrap();
+
+        let fa = FileFingerprint::of(&a).unwrap();
+        let fb = FileFingerprint::of(&b).unwrap();
+        assert_eq!(fa.size, fb.size);
+        assert_ne!(fa.sample, fb.sample);
+        assert!(fa.is_current(&a));
+
+        std::fs::write(&a, b"shorter").unwrap();
+        assert!(!fa.is_current(&a));
+    }
+}
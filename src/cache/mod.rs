@@ -0,0 +1,319 @@
+//! Content-based cache for ffprobe results and extracted subtitles
+//!
+//! Everything lives under `.anytron_cache` in the input directory:
+//!
+//! ```text
+//! .anytron_cache/
+//! ├── manifest.json     # key -> source file, its fingerprint, cached file
+//! ├── probe/            # ffprobe stream listings (JSON)
+//! └── subtitles/        # extracted subtitle tracks
+//! ```
+//!
+//! Keys hash the source's [`FileFingerprint`] together with whatever else the
+//! output depends on (e.g. the selected stream). Entries whose source changed
+//! size or modification time are dropped when the cache is opened.
+
+mod fingerprint;
+
+pub use fingerprint::FileFingerprint;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::error::{AnytronError, Result};
+
+/// Name of the cache directory inside the input directory
+pub const CACHE_DIR: &str = ".anytron_cache";
+
+/// Manifest file inside the cache directory
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Cache format version; a mismatch discards the cache
+const CACHE_VERSION: u32 = 1;
+
+/// Kinds of cached artifacts, each stored in its own subdirectory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    /// ffprobe stream listing
+    Probe,
+
+    /// Subtitle track extracted from a container
+    Subtitle,
+}
+
+impl CacheKind {
+    fn dir(self) -> &'static str {
+        match self {
+            Self::Probe => "probe",
+            Self::Subtitle => "subtitles",
+        }
+    }
+}
+
+/// A cached file and what it was derived from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub kind: CacheKind,
+
+    /// File the entry was derived from
+    pub source: PathBuf,
+
+    /// Fingerprint of the source when the entry was written
+    pub fingerprint: FileFingerprint,
+
+    /// Cached file, relative to the cache directory
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheManifest {
+    version: u32,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+/// Cache directory with its manifest, safe to share between threads
+#[derive(Debug)]
+pub struct Cache {
+    root: PathBuf,
+    manifest: Mutex<CacheManifest>,
+    changed: AtomicBool,
+}
+
+impl Cache {
+    /// Open the cache at `root`, dropping entries that are out of date
+    ///
+    /// A missing or unreadable manifest gives an empty cache.
+    pub fn open(root: &Path) -> Self {
+        let manifest = std::fs::read_to_string(root.join(MANIFEST_FILE))
+            .ok()
+            .and_then(
+                |content| match serde_json::from_str::<CacheManifest>(&content) {
+                    Ok(manifest) if manifest.version == CACHE_VERSION => Some(manifest),
+                    Ok(_) => None,
+                    Err(e) => {
+                        log::warn!("Ignoring unreadable cache manifest: {}", e);
+                        None
+                    }
+                },
+            );
+        let loaded = manifest.is_some();
+        let mut manifest = manifest.unwrap_or(CacheManifest {
+            version: CACHE_VERSION,
+            entries: BTreeMap::new(),
+        });
+
+        let before = manifest.entries.len();
+        manifest.entries.retain(|key, entry| {
+            let file = root.join(&entry.file);
+            if entry.fingerprint.is_current(&entry.source) && file.exists() {
+                return true;
+            }
+            log::debug!("Dropping stale cache entry {} for {:?}", key, entry.source);
+            let _ = std::fs::remove_file(file);
+            false
+        });
+
+        Self {
+            root: root.to_path_buf(),
+            changed: AtomicBool::new(loaded && manifest.entries.len() != before),
+            manifest: Mutex::new(manifest),
+        }
+    }
+
+    /// Delete everything cached at `root` and open it empty
+    ///
+    /// Only the cache's own files are removed, so a `root` shared with other
+    /// files is safe.
+    pub fn refresh(root: &Path) -> Result<Self> {
+        for kind in [CacheKind::Probe, CacheKind::Subtitle] {
+            let dir = root.join(kind.dir());
+            if dir.exists() {
+                std::fs::remove_dir_all(&dir).map_err(|e| {
+                    AnytronError::Output(format!("Failed to clear cache {:?}: {}", dir, e))
+                })?;
+            }
+        }
+        let manifest = root.join(MANIFEST_FILE);
+        if manifest.exists() {
+            std::fs::remove_file(&manifest).map_err(|e| {
+                AnytronError::Output(format!("Failed to clear cache {:?}: {}", manifest, e))
+            })?;
+        }
+        Ok(Self::open(root))
+    }
+
+    /// Directory the cache lives in
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Key for an artifact of `kind` derived from a file and `variant`
+    pub fn key(kind: CacheKind, fingerprint: &FileFingerprint, variant: &str) -> String {
+        let input = format!(
+            "{}:{}:{}:{}:{}",
+            kind.dir(),
+            fingerprint.size,
+            fingerprint.modified,
+            fingerprint.sample,
+            variant
+        );
+        format!("{:x}", Sha256::digest(input.as_bytes()))[..32].to_string()
+    }
+
+    /// Path of a cached file, if the entry exists
+    pub fn get(&self, key: &str) -> Option<PathBuf> {
+        let manifest = self.manifest.lock().unwrap();
+        let file = self.root.join(&manifest.entries.get(key)?.file);
+        file.exists().then_some(file)
+    }
+
+    /// Path to write a new `kind` artifact to, creating its directory
+    ///
+    /// `name` is a readable prefix for the file; the key keeps it unique.
+    pub fn path_for(&self, kind: CacheKind, key: &str, name: &str, ext: &str) -> Result<PathBuf> {
+        let dir = self.root.join(kind.dir());
+        std::fs::create_dir_all(&dir).map_err(|e| AnytronError::OutputDir {
+            path: dir.clone(),
+            source: e,
+        })?;
+        Ok(dir.join(format!("{}-{}.{}", name, &key[..16], ext)))
+    }
+
+    /// Record a file written to [`Cache::path_for`]
+    pub fn insert(
+        &self,
+        key: &str,
+        kind: CacheKind,
+        source: &Path,
+        fingerprint: FileFingerprint,
+        path: &Path,
+    ) {
+        let file = path.strip_prefix(&self.root).unwrap_or(path).to_path_buf();
+        let entry = CacheEntry {
+            kind,
+            source: source.to_path_buf(),
+            fingerprint,
+            file,
+        };
+        self.manifest
+            .lock()
+            .unwrap()
+            .entries
+            .insert(key.to_string(), entry);
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.manifest.lock().unwrap().entries.len()
+    }
+
+    /// Whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write the manifest if entries were added or dropped
+    pub fn save(&self) -> Result<()> {
+        if !self.changed.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.root).map_err(|e| AnytronError::OutputDir {
+            path: self.root.clone(),
+            source: e,
+        })?;
+        let json = serde_json::to_string_pretty(&*self.manifest.lock().unwrap()).map_err(|e| {
+            AnytronError::Output(format!("Failed to serialize cache manifest: {}", e))
+        })?;
+        let path = self.root.join(MANIFEST_FILE);
+        std::fs::write(&path, json).map_err(|e| AnytronError::FileWrite { path, source: e })?;
+
+        self.changed.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cache_file(cache: &Cache, video: &Path, variant: &str) -> String {
+        let fingerprint = FileFingerprint::of(video).unwrap();
+        let key = Cache::key(CacheKind::Subtitle, &fingerprint, variant);
+        let path = cache
+            .path_for(CacheKind::Subtitle, &key, "S01E01", "ass")
+            .unwrap();
+        std::fs::write(&path, "[Script Info]").unwrap();
+        cache.insert(&key, CacheKind::Subtitle, video, fingerprint, &path);
+        key
+    }
+
+    #[test]
+    fn test_entries_survive_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let video = temp_dir.path().join("S01E01.mkv");
+        std::fs::write(&video, "video").unwrap();
+        let root = temp_dir.path().join(CACHE_DIR);
+
+        let cache = Cache::open(&root);
+        let key = cache_file(&cache, &video, "stream=2");
+        assert_ne!(
+            key,
+            Cache::key(
+                CacheKind::Subtitle,
+                &FileFingerprint::of(&video).unwrap(),
+                "stream=3"
+            )
+        );
+        cache.save().unwrap();
+
+        let cache = Cache::open(&root);
+        let path = cache.get(&key).unwrap();
+        assert_eq!(path.extension().unwrap(), "ass");
+        assert!(path.starts_with(root.join("subtitles")));
+    }
+
+    #[test]
+    fn test_changed_source_invalidates() {
+        let temp_dir = TempDir::new().unwrap();
+        let video = temp_dir.path().join("S01E01.mkv");
+        std::fs::write(&video, "video").unwrap();
+        let root = temp_dir.path().join(CACHE_DIR);
+
+        let cache = Cache::open(&root);
+        let key = cache_file(&cache, &video, "");
+        let path = cache.get(&key).unwrap();
+        cache.save().unwrap();
+
+        std::fs::write(&video, "re-encoded video").unwrap();
+        let cache = Cache::open(&root);
+        assert!(cache.get(&key).is_none());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_refresh_clears_only_cache_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let video = temp_dir.path().join("S01E01.mkv");
+        std::fs::write(&video, "video").unwrap();
+        let root = temp_dir.path().to_path_buf();
+        std::fs::write(root.join("notes.txt"), "keep me").unwrap();
+
+        let cache = Cache::open(&root);
+        cache_file(&cache, &video, "");
+        cache.save().unwrap();
+
+        let cache = Cache::refresh(&root).unwrap();
+        assert!(cache.is_empty());
+        assert!(!root.join("subtitles").exists());
+        assert!(root.join("notes.txt").exists());
+        assert!(video.exists());
+    }
+}
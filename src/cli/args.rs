@@ -31,6 +31,12 @@ pub enum Commands {
 
     /// Serve the generated site locally for preview
     Serve(ServeArgs),
+
+    /// Export quotes to other tools
+    Export(ExportArgs),
+
+    /// Work with subtitle files
+    Subs(SubsArgs),
 }
 
 /// Arguments for the generate command
@@ -64,21 +70,46 @@ pub struct GenerateArgs {
     #[arg(long, value_delimiter = ',')]
     pub episodes: Option<Vec<String>>,
 
-    /// Frame extraction interval in milliseconds
-    #[arg(long, default_value = "1000")]
-    pub interval: u64,
+    /// Minimum spacing between extracted frames in milliseconds, 0 for one
+    /// frame per cue [default: 0]
+    #[arg(long)]
+    pub interval: Option<u64>,
+
+    /// JPEG quality for frames (1-100) [default: 85]
+    #[arg(long)]
+    pub quality: Option<u8>,
+
+    /// Full-size frame width in pixels, 0 keeps the original [default: 0]
+    #[arg(long)]
+    pub frame_width: Option<u32>,
+
+    /// Thumbnail width in pixels [default: 320]
+    #[arg(long)]
+    pub thumb_width: Option<u32>,
 
-    /// JPEG quality for frames (1-100)
-    #[arg(long, default_value = "85")]
-    pub quality: u8,
+    /// JPEG quality for thumbnails (1-100) [default: 70]
+    #[arg(long)]
+    pub thumb_quality: Option<u8>,
+
+    /// Line subtitles up with the speech in each episode's audio
+    #[arg(long)]
+    pub auto_sync: bool,
 
-    /// Thumbnail width in pixels
-    #[arg(long, default_value = "320")]
-    pub thumb_width: u32,
+    /// Confidence (0-100) an audio sync correction needs to be applied
+    #[arg(long, default_value = "75", value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub sync_confidence: u8,
 
     /// Clean output directory before generating
     #[arg(long)]
     pub clean: bool,
+
+    /// Discard cached probe results and extracted subtitles
+    #[arg(long)]
+    pub refresh_cache: bool,
+
+    /// Fail on any subtitle problem, treating warnings as errors
+    #[arg(long)]
+    pub strict: bool,
 }
 
 /// Arguments for the validate command
@@ -116,3 +147,119 @@ pub struct ServeArgs {
     #[arg(long)]
     pub open: bool,
 }
+
+/// Arguments for the export command
+#[derive(Parser, Debug)]
+pub struct ExportArgs {
+    #[command(subcommand)]
+    pub format: ExportFormat,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportFormat {
+    /// Export lines as an Anki deck with frames and audio clips
+    Anki(AnkiArgs),
+}
+
+/// Arguments for `export anki`
+#[derive(Parser, Debug)]
+pub struct AnkiArgs {
+    /// Input directory containing video and subtitle files
+    #[arg(value_name = "INPUT_DIR")]
+    pub input: PathBuf,
+
+    /// Output directory for the deck and its media
+    #[arg(short, long, default_value = "anki")]
+    pub output: PathBuf,
+
+    /// Configuration file path (default: INPUT_DIR/anytron.toml)
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Number of parallel workers (default: number of CPU cores)
+    #[arg(short = 'j', long)]
+    pub jobs: Option<usize>,
+
+    /// Only export specific seasons (e.g., 1,2,3)
+    #[arg(long, value_delimiter = ',')]
+    pub seasons: Option<Vec<u32>>,
+
+    /// Only export specific episodes (e.g., S01E01,S01E02)
+    #[arg(long, value_delimiter = ',')]
+    pub episodes: Option<Vec<String>>,
+
+    /// Only export lines containing every word of this query
+    #[arg(long)]
+    pub query: Option<String>,
+
+    /// Language to study (default: the site's primary language)
+    #[arg(long)]
+    pub language: Option<String>,
+
+    /// Language of the translation shown on each card (default: the site's
+    /// second language)
+    #[arg(long)]
+    pub translation: Option<String>,
+
+    /// Deck name (default: the show's name)
+    #[arg(long)]
+    pub deck: Option<String>,
+
+    /// Add an audio clip of each line
+    #[arg(long)]
+    pub audio: bool,
+
+    /// Leave frames off the cards
+    #[arg(long)]
+    pub skip_frames: bool,
+
+    /// Frame width in pixels, 0 keeps the original
+    #[arg(long, default_value = "640")]
+    pub frame_width: u32,
+}
+
+/// Arguments for the subs command
+#[derive(Parser, Debug)]
+pub struct SubsArgs {
+    #[command(subcommand)]
+    pub command: SubsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SubsCommand {
+    /// Write the corrected subtitles used for each episode in another format
+    Convert(ConvertArgs),
+}
+
+/// Arguments for `subs convert`
+#[derive(Parser, Debug)]
+pub struct ConvertArgs {
+    /// Input directory containing video and subtitle files
+    #[arg(value_name = "INPUT_DIR")]
+    pub input: PathBuf,
+
+    /// Output directory, mirroring the input's folder layout
+    #[arg(short, long, default_value = "subs")]
+    pub output: PathBuf,
+
+    /// Configuration file path (default: INPUT_DIR/anytron.toml)
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+
+    /// Number of parallel workers (default: number of CPU cores)
+    #[arg(short = 'j', long)]
+    pub jobs: Option<usize>,
+
+    /// Only convert specific seasons (e.g., 1,2,3)
+    #[arg(long, value_delimiter = ',')]
+    pub seasons: Option<Vec<u32>>,
+
+    /// Only convert specific episodes (e.g., S01E01,S01E02)
+    #[arg(long, value_delimiter = ',')]
+    pub episodes: Option<Vec<String>>,
+
+    /// Output format: srt, vtt, ass, ttml, smi, microdvd or subviewer (sub is
+    /// taken as microdvd)
+    #[arg(short, long, default_value = "srt")]
+    pub format: String,
+}
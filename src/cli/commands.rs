@@ -2,21 +2,45 @@
 
 use anyhow::{Context, Result};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::BTreeMap;
 use std::path::Path;
 
-use crate::cli::args::{GenerateArgs, ServeArgs, ValidateArgs};
-use crate::config::Config;
-use crate::discovery::Scanner;
-use crate::extractor::FrameExtractor;
-use crate::generator::SiteGenerator;
-use crate::indexer::SearchIndexer;
+use crate::cache::{Cache, FileFingerprint, CACHE_DIR};
+use crate::cli::args::{
+    AnkiArgs, ConvertArgs, ExportArgs, ExportFormat, GenerateArgs, ServeArgs, SubsArgs,
+    SubsCommand, ValidateArgs,
+};
+use crate::config::{Config, EpisodeOverride, FrameFlags, FrameSettings};
+use crate::discovery::{Episode, Scanner};
+use crate::export::AnkiExporter;
+use crate::extractor::{
+    normalize_language, AudioSync, FrameExtractor, SubtitleExtractor, SubtitleStream,
+};
+use crate::generator::{BuildManifest, BuildPlan, SiteGenerator};
+use crate::indexer::{Language, SearchIndexer};
+use crate::subtitle::diagnostics::log_diagnostics;
+use crate::subtitle::{self, Detection, Severity, SubtitleEntry, SubtitleFormat};
 
 /// Execute the generate command
 pub fn generate(args: GenerateArgs, verbose: u8) -> Result<()> {
     let config = load_config(&args.input, args.config.as_deref())?;
 
+    let frame_settings = FrameSettings::resolve(
+        &config,
+        &FrameFlags {
+            interval_ms: args.interval,
+            quality: args.quality,
+            frame_width: args.frame_width,
+            thumb_width: args.thumb_width,
+            thumb_quality: args.thumb_quality,
+        },
+    )?;
+
     if verbose > 0 {
         log::info!("Configuration loaded: {:?}", config);
+        for line in frame_settings.describe() {
+            log::info!("  {}", line);
+        }
     }
 
     // Create output directory
@@ -35,19 +59,35 @@ pub fn generate(args: GenerateArgs, verbose: u8) -> Result<()> {
         .unwrap()
         .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏");
 
+    // Auto-sync adds a step after parsing, moving the later ones along
+    let synced = usize::from(args.auto_sync);
+    let step = |n: usize| format!("[{}/{}]", n, 4 + synced);
+
     // Phase 1: Discovery
-    let discover_pb = multi_progress.add(ProgressBar::new_spinner());
-    discover_pb.set_style(spinner_style.clone());
-    discover_pb.set_prefix("[1/4]");
+    let discover_pb = multi_progress.add(ProgressBar::new(0));
+    discover_pb.set_style(
+        ProgressStyle::with_template(
+            "{spinner:.green} {prefix:.bold} [{bar:40.cyan/blue}] {pos}/{len} {msg}",
+        )
+        .unwrap()
+        .progress_chars("█▓▒░"),
+    );
+    discover_pb.set_prefix(step(1));
     discover_pb.set_message("Scanning for video and subtitle files...");
     discover_pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     let scanner = Scanner::new(&args.input)
         .with_seasons(args.seasons.clone())
-        .with_episodes(args.episodes.clone());
+        .with_episodes(args.episodes.clone())
+        .with_overrides(config.overrides()?)
+        .with_subtitles(config.subtitles.clone())
+        .with_speakers(config.show.speaker_aliases())
+        .with_languages(&config.site.languages)
+        .with_refresh_cache(args.refresh_cache)
+        .with_jobs(args.jobs);
 
     let episodes = scanner
-        .scan()
+        .scan_with_progress(&discover_pb)
         .with_context(|| format!("Failed to scan directory: {:?}", args.input))?;
 
     discover_pb.finish_with_message(format!("Found {} episodes", episodes.len()));
@@ -65,24 +105,103 @@ pub fn generate(args: GenerateArgs, verbose: u8) -> Result<()> {
         .unwrap()
         .progress_chars("█▓▒░"),
     );
-    subtitle_pb.set_prefix("[2/4]");
+    subtitle_pb.set_prefix(step(2));
     subtitle_pb.set_message("Parsing subtitles...");
 
     let mut all_entries = Vec::new();
+    let mut translations = Vec::new();
+    let mut problems = Vec::new();
     for episode in &episodes {
-        let entries = episode
-            .parse_subtitles()
+        let parsed = episode
+            .parse_subtitles_lenient()
             .with_context(|| format!("Failed to parse subtitles for {:?}", episode.video_path))?;
-        all_entries.push((episode.clone(), entries));
+        problems.extend(log_diagnostics(&episode.subtitle_path, &parsed.diagnostics));
+        all_entries.push((episode.clone(), parsed.entries));
+
+        let translated = episode.parse_translations_lenient().with_context(|| {
+            format!(
+                "Failed to parse translated subtitles for {:?}",
+                episode.video_path
+            )
+        })?;
+        let mut episode_translations = Vec::new();
+        for (track, parsed) in translated {
+            problems.extend(log_diagnostics(&track.path, &parsed.diagnostics));
+            episode_translations.push((track.language.unwrap_or_default(), parsed.entries));
+        }
+        translations.push(episode_translations);
         subtitle_pb.inc(1);
     }
-    subtitle_pb.finish_with_message("Subtitles parsed");
+    if args.strict && !problems.is_empty() {
+        subtitle_pb.abandon_with_message("Subtitle problems found");
+        anyhow::bail!(
+            "{} subtitle problem(s) with --strict:\n  {}",
+            problems.len(),
+            problems.join("\n  ")
+        );
+    }
+    subtitle_pb.finish_with_message(if problems.is_empty() {
+        "Subtitles parsed".to_string()
+    } else {
+        format!("Subtitles parsed with {} problem(s)", problems.len())
+    });
 
     let total_entries: usize = all_entries.iter().map(|(_, e)| e.len()).sum();
     log::info!("Total subtitle entries: {}", total_entries);
 
+    let mut build_manifest = BuildManifest::new(&config, &frame_settings, &episodes)
+        .context("Failed to fingerprint inputs")?;
+    let previous = BuildManifest::load(&args.output);
+
+    if args.auto_sync {
+        let sync_pb = multi_progress.add(ProgressBar::new(episodes.len() as u64));
+        sync_pb.set_style(
+            ProgressStyle::with_template(
+                "{spinner:.green} {prefix:.bold} [{bar:40.cyan/blue}] {pos}/{len} {msg}",
+            )
+            .unwrap()
+            .progress_chars("█▓▒░"),
+        );
+        sync_pb.set_prefix(step(3));
+        sync_pb.set_message("Syncing subtitles to audio...");
+
+        let audio_sync = AudioSync::new()
+            .with_min_confidence(args.sync_confidence)
+            .with_jobs(args.jobs);
+        auto_sync(
+            &audio_sync,
+            &mut all_entries,
+            &mut translations,
+            &mut build_manifest,
+            previous.as_ref(),
+            &sync_pb,
+        )?;
+        sync_pb.finish_with_message("Subtitles synced");
+    }
+
+    // Compare inputs against the previous build to find what changed
+    let prune = args.seasons.is_none() && args.episodes.is_none();
+    let plan = BuildPlan::new(previous, &build_manifest, prune);
+    if plan.is_full() {
+        log::info!("Full rebuild");
+    } else {
+        log::info!(
+            "Incremental rebuild: {} of {} episodes changed",
+            plan.dirty().count(),
+            episodes.len()
+        );
+    }
+
+    let generator = SiteGenerator::new(&config, &args.output).with_jobs(args.jobs);
+
     // Phase 3: Frame extraction
     if !args.skip_frames {
+        for id in plan.stale_frames() {
+            generator
+                .remove_frames(id)
+                .with_context(|| format!("Failed to remove stale frames for {}", id))?;
+        }
+
         let frame_pb = multi_progress.add(ProgressBar::new(total_entries as u64));
         frame_pb.set_style(
             ProgressStyle::with_template(
@@ -91,40 +210,43 @@ pub fn generate(args: GenerateArgs, verbose: u8) -> Result<()> {
             .unwrap()
             .progress_chars("█▓▒░"),
         );
-        frame_pb.set_prefix("[3/4]");
+        frame_pb.set_prefix(step(3 + synced));
         frame_pb.set_message("Extracting frames...");
 
         let extractor = FrameExtractor::new()
-            .with_quality(args.quality)
-            .with_thumb_width(args.thumb_width)
+            .with_interval(frame_settings.interval_ms.value)
+            .with_quality(frame_settings.quality.value)
+            .with_frame_width(frame_settings.frame_width.value)
+            .with_thumb_width(frame_settings.thumb_width.value)
+            .with_thumb_quality(frame_settings.thumb_quality.value)
             .with_jobs(args.jobs);
 
-        for (episode, entries) in &all_entries {
-            extractor
-                .extract_frames(episode, entries, &args.output, &frame_pb)
-                .with_context(|| {
-                    format!("Failed to extract frames for {:?}", episode.video_path)
-                })?;
-        }
+        extractor
+            .extract_all(&all_entries, &args.output, &frame_pb)
+            .context("Failed to extract frames")?;
         frame_pb.finish_with_message("Frames extracted");
     } else {
         log::info!("Skipping frame extraction (--skip-frames)");
+        plan.keep_stale_frames(&mut build_manifest);
     }
 
     // Phase 4: Generate site
     let gen_pb = multi_progress.add(ProgressBar::new_spinner());
     gen_pb.set_style(spinner_style);
-    gen_pb.set_prefix("[4/4]");
+    gen_pb.set_prefix(step(4 + synced));
     gen_pb.set_message("Generating site...");
     gen_pb.enable_steady_tick(std::time::Duration::from_millis(100));
 
     // Build search index
-    let indexer = SearchIndexer::new();
-    let index = indexer.build_index(&all_entries)?;
+    let indexer = SearchIndexer::new()
+        .with_languages(&config.site.languages)
+        .with_speakers(config.show.speaker_aliases())
+        .with_sdh(config.search.sdh)
+        .with_sound_effects(config.search.index_sound_effects);
+    let index = indexer.build_translated(&all_entries, &translations)?;
 
     // Generate HTML and assets
-    let generator = SiteGenerator::new(&config, &args.output);
-    generator.generate(&all_entries, &index)?;
+    generator.generate_incremental(&all_entries, &index, &plan, &mut build_manifest)?;
 
     gen_pb.finish_with_message("Site generated");
 
@@ -135,6 +257,101 @@ pub fn generate(args: GenerateArgs, verbose: u8) -> Result<()> {
     Ok(())
 }
 
+/// Estimate audio sync corrections and retime the episodes that pass
+///
+/// Corrections from the previous build are reused while an episode's
+/// subtitle, video and overrides are unchanged. Every estimate is recorded in
+/// the build manifest, including refused ones. Translations timed like the
+/// episode's subtitles get the same correction; others are left as they are.
+fn auto_sync(
+    audio_sync: &AudioSync,
+    all_entries: &mut [(Episode, Vec<SubtitleEntry>)],
+    translations: &mut [Vec<(String, Vec<SubtitleEntry>)>],
+    build_manifest: &mut BuildManifest,
+    previous: Option<&BuildManifest>,
+    progress: &ProgressBar,
+) -> Result<()> {
+    let mut corrections: Vec<_> = all_entries
+        .iter()
+        .map(|(episode, _)| {
+            let id = episode.id.to_string();
+            let old = previous?.episodes.get(&id)?;
+            let current = build_manifest.episodes.get(&id)?;
+            old.same_sources(current)
+                .then(|| old.auto_sync.clone())
+                .flatten()
+        })
+        .collect();
+    progress.inc(corrections.iter().flatten().count() as u64);
+
+    let pending: Vec<usize> = (0..all_entries.len())
+        .filter(|&i| corrections[i].is_none())
+        .collect();
+    let videos: Vec<_> = pending
+        .iter()
+        .map(|&i| {
+            let (episode, entries) = &all_entries[i];
+            (episode.video_path.as_path(), entries.as_slice())
+        })
+        .collect();
+    let results = audio_sync
+        .analyse_all(&videos, progress)
+        .context("Failed to analyse audio")?;
+
+    for (i, result) in pending.into_iter().zip(results) {
+        match result {
+            Ok(correction) => corrections[i] = Some(correction),
+            Err(e) => log::warn!("{}: audio sync failed: {}", all_entries[i].0.id, e),
+        }
+    }
+
+    for (((episode, entries), translated), correction) in all_entries
+        .iter_mut()
+        .zip(translations.iter_mut())
+        .zip(corrections)
+    {
+        let Some(mut correction) = correction else {
+            continue;
+        };
+        correction.applied = audio_sync.accepts(correction.confidence);
+        if correction.applied {
+            let transform = correction.transform();
+            for (language, lines) in translated.iter_mut() {
+                if subtitle::shares_timing(entries, lines) {
+                    transform.apply_all(lines);
+                } else {
+                    log::warn!(
+                        "{}: {} subtitles are timed differently, leaving them unsynced",
+                        episode.id,
+                        language
+                    );
+                }
+            }
+            transform.apply_all(entries);
+            log::info!(
+                "{}: audio sync offset {:+} ms, drift {:+} ppm ({}% confidence)",
+                episode.id,
+                correction.offset_ms,
+                correction.drift_ppm,
+                correction.confidence
+            );
+        } else {
+            log::warn!(
+                "{}: refusing audio sync offset {:+} ms, drift {:+} ppm ({}% confidence)",
+                episode.id,
+                correction.offset_ms,
+                correction.drift_ppm,
+                correction.confidence
+            );
+        }
+        if let Some(record) = build_manifest.episodes.get_mut(&episode.id.to_string()) {
+            record.auto_sync = Some(correction);
+        }
+    }
+
+    Ok(())
+}
+
 /// Execute the validate command
 pub fn validate(args: ValidateArgs, verbose: u8) -> Result<()> {
     let config = load_config(&args.input, args.config.as_deref())?;
@@ -146,7 +363,12 @@ pub fn validate(args: ValidateArgs, verbose: u8) -> Result<()> {
     println!("Validating directory: {:?}", args.input);
     println!();
 
-    let scanner = Scanner::new(&args.input);
+    let overrides = config.overrides()?;
+    let scanner = Scanner::new(&args.input)
+        .with_overrides(overrides.clone())
+        .with_subtitles(config.subtitles.clone())
+        .with_speakers(config.show.speaker_aliases())
+        .with_languages(&config.site.languages);
     let episodes = scanner.scan()?;
 
     if episodes.is_empty() {
@@ -157,21 +379,55 @@ pub fn validate(args: ValidateArgs, verbose: u8) -> Result<()> {
     println!("✓ Found {} episodes", episodes.len());
 
     let mut total_errors = 0;
-    let total_warnings = 0;
+    let mut total_warnings = 0;
+
+    // Override tables that name seasons or episodes that don't exist
+    for table in overrides.unmatched(&scanner.episode_ids()?) {
+        println!("⚠ Override [{}] matches no episode", table);
+        total_warnings += 1;
+    }
 
+    let cache = Cache::open(&args.input.join(CACHE_DIR));
+    let mut encodings: BTreeMap<String, usize> = BTreeMap::new();
     for episode in &episodes {
         if args.detailed {
             println!();
             println!("  Episode: {}", episode.id);
             println!("    Video: {:?}", episode.video_path);
             println!("    Subtitle: {:?}", episode.subtitle_path);
+            match episode.subtitle_encoding() {
+                Ok(encoding) => println!("    Encoding: {}", encoding),
+                Err(e) => println!("    Encoding: unknown ({})", e),
+            }
+            if episode.overrides != EpisodeOverride::default() {
+                let settings = toml::to_string(&episode.overrides).unwrap_or_default();
+                println!("    Overrides: {}", settings.trim().replace('\n', ", "));
+            }
+            print_tracks(episode, &cache);
+            for track in &episode.languages {
+                println!(
+                    "    Language {}: {:?}",
+                    track.language.as_deref().unwrap_or("und"),
+                    track.path
+                );
+            }
         }
 
-        match episode.parse_subtitles() {
-            Ok(entries) => {
+        if let Ok(encoding) = episode.subtitle_encoding() {
+            let name = match encoding.detection {
+                Detection::Guess => format!("{} (guessed)", encoding.name()),
+                _ => encoding.name().to_string(),
+            };
+            *encodings.entry(name).or_default() += 1;
+        }
+
+        let mut diagnostics = Vec::new();
+        match episode.parse_subtitles_lenient() {
+            Ok(parsed) => {
                 if args.detailed {
-                    println!("    ✓ {} subtitle entries", entries.len());
+                    println!("    ✓ {} subtitle entries", parsed.entries.len());
                 }
+                diagnostics.push((episode.subtitle_path.clone(), parsed.diagnostics));
             }
             Err(e) => {
                 if args.detailed {
@@ -180,8 +436,42 @@ pub fn validate(args: ValidateArgs, verbose: u8) -> Result<()> {
                 total_errors += 1;
             }
         }
+        match episode.parse_translations_lenient() {
+            Ok(translated) => {
+                for (track, parsed) in translated {
+                    diagnostics.push((track.path, parsed.diagnostics));
+                }
+            }
+            Err(e) => {
+                println!("✗ {}: translated subtitles: {}", episode.id, e);
+                total_errors += 1;
+            }
+        }
+        for (path, diagnostics) in diagnostics {
+            for diagnostic in diagnostics {
+                let mark = match diagnostic.severity {
+                    Severity::Warning => {
+                        total_warnings += 1;
+                        "⚠"
+                    }
+                    Severity::Error => {
+                        total_errors += 1;
+                        "✗"
+                    }
+                };
+                println!("{} {}: {:?} {}", mark, episode.id, path, diagnostic);
+            }
+        }
     }
 
+    cache.save()?;
+
+    let encodings: Vec<String> = encodings
+        .iter()
+        .map(|(name, count)| format!("{} ×{}", name, count))
+        .collect();
+    println!("ℹ Subtitle encodings: {}", encodings.join(", "));
+
     println!();
     if total_errors == 0 && total_warnings == 0 {
         println!("✓ Validation passed with no issues");
@@ -195,6 +485,67 @@ pub fn validate(args: ValidateArgs, verbose: u8) -> Result<()> {
     Ok(())
 }
 
+/// List the subtitle streams in an episode's video and which were extracted
+///
+/// `✓` marks the track used for the site and `+` other extracted tracks.
+fn print_tracks(episode: &Episode, cache: &Cache) {
+    let streams = FileFingerprint::of(&episode.video_path)
+        .map_err(Into::into)
+        .and_then(|fingerprint| {
+            SubtitleExtractor::probe_streams_cached(&episode.video_path, &fingerprint, cache)
+        });
+    let streams = match streams {
+        Ok(streams) if streams.is_empty() => {
+            println!("    Tracks: none embedded");
+            return;
+        }
+        Ok(streams) => streams,
+        Err(e) => {
+            println!("    Tracks: could not probe ({})", e);
+            return;
+        }
+    };
+
+    println!("    Tracks:");
+    for stream in &streams {
+        let track = episode
+            .tracks
+            .iter()
+            .find(|t| t.stream == Some(stream.index));
+        let marker = match track {
+            Some(t) if t.path == episode.subtitle_path => "✓",
+            Some(_) => "+",
+            None => " ",
+        };
+        println!("      {} {}", marker, describe_stream(stream));
+    }
+}
+
+/// One-line summary of a subtitle stream with its selection score
+fn describe_stream(stream: &SubtitleStream) -> String {
+    let mut line = format!(
+        "#{} {} {}",
+        stream.index,
+        stream.codec,
+        stream.language.as_deref().unwrap_or("und")
+    );
+    if let Some(title) = &stream.title {
+        line.push_str(&format!(" {:?}", title));
+    }
+    for (flag, set) in [
+        ("default", stream.is_default),
+        ("forced", stream.is_forced),
+        ("SDH", stream.appears_to_be_sdh()),
+        ("bitmap", !stream.is_text()),
+    ] {
+        if set {
+            line.push_str(&format!(" [{}]", flag));
+        }
+    }
+    line.push_str(&format!(" score {}", stream.priority_score()));
+    line
+}
+
 /// Execute the serve command
 pub fn serve(args: ServeArgs) -> Result<()> {
     if !args.directory.exists() {
@@ -244,6 +595,193 @@ pub fn serve(args: ServeArgs) -> Result<()> {
     Ok(())
 }
 
+/// Execute the export command
+pub fn export(args: ExportArgs) -> Result<()> {
+    match args.format {
+        ExportFormat::Anki(args) => export_anki(args),
+    }
+}
+
+/// Export lines as an Anki deck
+fn export_anki(args: AnkiArgs) -> Result<()> {
+    let config = load_config(&args.input, args.config.as_deref())?;
+
+    // The studied language comes first so it becomes each episode's subtitle
+    let language = args
+        .language
+        .clone()
+        .or_else(|| config.site.languages.first().cloned());
+    let translation = args
+        .translation
+        .clone()
+        .or_else(|| config.site.languages.get(1).cloned());
+    let languages: Vec<String> = match (&language, &translation) {
+        (Some(language), Some(translation)) => vec![language.clone(), translation.clone()],
+        (Some(language), None) => vec![language.clone()],
+        (None, Some(translation)) => anyhow::bail!(
+            "No language to study alongside the {} translation; pass --language or set [site] languages",
+            translation
+        ),
+        (None, None) => Vec::new(),
+    };
+
+    let scanner = Scanner::new(&args.input)
+        .with_seasons(args.seasons.clone())
+        .with_episodes(args.episodes.clone())
+        .with_overrides(config.overrides()?)
+        .with_subtitles(config.subtitles.clone())
+        .with_speakers(config.show.speaker_aliases())
+        .with_languages(&languages)
+        .with_jobs(args.jobs);
+    let episodes = scanner
+        .scan()
+        .with_context(|| format!("Failed to scan directory: {:?}", args.input))?;
+    if episodes.is_empty() {
+        anyhow::bail!("No episodes found in {:?}", args.input);
+    }
+
+    let translation_code = translation.as_deref().map(normalize_language);
+    let mut all_entries = Vec::new();
+    let mut translations = Vec::new();
+    for episode in &episodes {
+        let parsed = episode
+            .parse_subtitles_lenient()
+            .with_context(|| format!("Failed to parse subtitles for {:?}", episode.video_path))?;
+        log_diagnostics(&episode.subtitle_path, &parsed.diagnostics);
+        all_entries.push((episode.clone(), parsed.entries));
+        let translated = episode.parse_translations_lenient().with_context(|| {
+            format!(
+                "Failed to parse translated subtitles for {:?}",
+                episode.video_path
+            )
+        })?;
+        translations.push(
+            translated
+                .into_iter()
+                .find(|(track, _)| track.language == translation_code)
+                .map(|(track, parsed)| {
+                    log_diagnostics(&track.path, &parsed.diagnostics);
+                    parsed.entries
+                }),
+        );
+    }
+
+    let deck = args.deck.as_deref().unwrap_or(&config.show.name);
+    let exporter = AnkiExporter::new(deck, &config.show.slug)
+        .with_language(language.as_deref().map(Language::new).unwrap_or_default())
+        .with_query(args.query.clone())
+        .with_frames(!args.skip_frames)
+        .with_audio(args.audio)
+        .with_frame_width(args.frame_width)
+        .with_jobs(args.jobs);
+
+    let progress = ProgressBar::new(0);
+    progress.set_style(
+        ProgressStyle::with_template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("█▓▒░"),
+    );
+    progress.set_message("Exporting cards...");
+    let cards = exporter
+        .export(&all_entries, &translations, &args.output, &progress)
+        .context("Failed to export Anki deck")?;
+    progress.finish_and_clear();
+
+    if cards.is_empty() {
+        log::warn!("No lines matched; the deck is empty");
+    }
+    println!("✓ Exported {} cards to {:?}", cards.len(), args.output);
+    println!("  Import deck.txt in Anki and copy media/ into your collection.media folder");
+
+    Ok(())
+}
+
+/// Execute the subs command
+pub fn subs(args: SubsArgs) -> Result<()> {
+    match args.command {
+        SubsCommand::Convert(args) => convert_subtitles(args),
+    }
+}
+
+/// Write the subtitles each episode is indexed with in another format
+///
+/// Files are named after the video with the track's language code, e.g.
+/// `Show.S01E01.en.srt`, in the same folder layout as the input.
+fn convert_subtitles(args: ConvertArgs) -> Result<()> {
+    let config = load_config(&args.input, args.config.as_deref())?;
+    let format = SubtitleFormat::from_name(&args.format)
+        .with_context(|| format!("Unsupported output format: {}", args.format))?;
+
+    let scanner = Scanner::new(&args.input)
+        .with_seasons(args.seasons.clone())
+        .with_episodes(args.episodes.clone())
+        .with_overrides(config.overrides()?)
+        .with_subtitles(config.subtitles.clone())
+        .with_speakers(config.show.speaker_aliases())
+        .with_languages(&config.site.languages)
+        .with_jobs(args.jobs);
+    let episodes = scanner
+        .scan()
+        .with_context(|| format!("Failed to scan directory: {:?}", args.input))?;
+    if episodes.is_empty() {
+        anyhow::bail!("No episodes found in {:?}", args.input);
+    }
+
+    let mut written = 0;
+    for episode in &episodes {
+        let language = episode
+            .languages
+            .iter()
+            .find(|track| track.path == episode.subtitle_path)
+            .and_then(|track| track.language.clone());
+        let entries = episode
+            .parse_subtitles()
+            .with_context(|| format!("Failed to parse subtitles for {:?}", episode.video_path))?;
+        let translations = episode.parse_translations().with_context(|| {
+            format!(
+                "Failed to parse translated subtitles for {:?}",
+                episode.video_path
+            )
+        })?;
+
+        let relative = episode
+            .video_path
+            .strip_prefix(&args.input)
+            .ok()
+            .and_then(|path| path.parent())
+            .unwrap_or(Path::new(""));
+        let dir = args.output.join(relative);
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create output directory: {:?}", dir))?;
+        let stem = episode
+            .video_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| episode.id.to_string());
+
+        // Frame counts follow the video; others are written at the default
+        let framerate = match format {
+            SubtitleFormat::MicroDvd => episode.framerate.or_else(|| episode.video_framerate()),
+            _ => None,
+        };
+
+        let tracks = std::iter::once((language, entries))
+            .chain(translations.into_iter().map(|(code, e)| (Some(code), e)));
+        for (language, entries) in tracks {
+            let name = match language {
+                Some(code) => format!("{}.{}.{}", stem, code, format.extension()),
+                None => format!("{}.{}", stem, format.extension()),
+            };
+            subtitle::write_file(&dir.join(name), format, &entries, framerate)?;
+            written += 1;
+        }
+    }
+
+    println!("✓ Wrote {} subtitle files to {:?}", written, args.output);
+
+    Ok(())
+}
+
 /// Load configuration from file or use defaults
 fn load_config(input_dir: &Path, config_path: Option<&Path>) -> Result<Config> {
     let config_file = config_path
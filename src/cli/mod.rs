@@ -3,4 +3,7 @@
 pub mod args;
 pub mod commands;
 
-pub use args::{Cli, Commands, GenerateArgs, ServeArgs, ValidateArgs};
+pub use args::{
+    AnkiArgs, Cli, Commands, ConvertArgs, ExportArgs, ExportFormat, GenerateArgs, ServeArgs,
+    SubsArgs, SubsCommand, ValidateArgs,
+};
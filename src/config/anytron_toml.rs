@@ -1,9 +1,12 @@
 //! Configuration file parsing for anytron.toml
 
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
 
+use crate::config::overrides::{EpisodeOverride, Overrides};
 use crate::error::{AnytronError, Result};
+use crate::subtitle::{NormalizeRules, SpeakerAliases};
 
 /// Main configuration structure
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -20,6 +23,21 @@ pub struct Config {
 
     /// Search settings
     pub search: SearchConfig,
+
+    /// Embedded subtitle track settings
+    pub subtitles: SubtitleConfig,
+
+    /// Overrides for every episode of a season, keyed by season number
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub seasons: BTreeMap<String, EpisodeOverride>,
+
+    /// Overrides for single episodes, keyed by episode ID (e.g. `S03E07`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub episodes: BTreeMap<String, EpisodeOverride>,
+
+    /// Dotted keys (e.g. `frames.quality`) explicitly set in the loaded file
+    #[serde(skip)]
+    explicit_keys: BTreeSet<String>,
 }
 
 impl Config {
@@ -30,12 +48,31 @@ impl Config {
             source: e,
         })?;
 
-        toml::from_str(&content).map_err(|e| AnytronError::ConfigParse {
+        Self::from_toml_str(&content).map_err(|message| AnytronError::ConfigParse {
             path: path.to_path_buf(),
-            message: e.to_string(),
+            message,
         })
     }
 
+    /// Parse configuration from TOML content, remembering which keys were set
+    pub(crate) fn from_toml_str(content: &str) -> std::result::Result<Self, String> {
+        let mut config: Config = toml::from_str(content).map_err(|e| e.to_string())?;
+        let table: toml::Table = toml::from_str(content).map_err(|e| e.to_string())?;
+        collect_keys(&table, "", &mut config.explicit_keys);
+        Overrides::from_config(&config).map_err(|e| e.to_string())?;
+        Ok(config)
+    }
+
+    /// The per-season and per-episode override tables
+    pub fn overrides(&self) -> Result<Overrides> {
+        Overrides::from_config(self)
+    }
+
+    /// Whether a dotted key (e.g. `frames.quality`) was set in the config file
+    pub fn is_set(&self, key: &str) -> bool {
+        self.explicit_keys.contains(key)
+    }
+
     /// Save configuration to a TOML file
     pub fn to_file(&self, path: &Path) -> Result<()> {
         let content =
@@ -48,6 +85,23 @@ impl Config {
     }
 }
 
+/// Record every leaf key of a TOML table as a dotted path
+fn collect_keys(table: &toml::Table, prefix: &str, keys: &mut BTreeSet<String>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::Table(inner) => collect_keys(inner, &path, keys),
+            _ => {
+                keys.insert(path);
+            }
+        }
+    }
+}
+
 /// Show metadata configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -63,6 +117,18 @@ pub struct ShowConfig {
 
     /// Number of seasons (for validation)
     pub seasons: Option<u32>,
+
+    /// Spelling variants of character names in the subtitles, keyed by the
+    /// name to show (e.g. `Homer = ["HOMER SIMPSON", "Homer J."]`)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub speakers: BTreeMap<String, Vec<String>>,
+}
+
+impl ShowConfig {
+    /// The `speakers` table as a lookup
+    pub fn speaker_aliases(&self) -> SpeakerAliases {
+        SpeakerAliases::new(&self.speakers)
+    }
 }
 
 impl Default for ShowConfig {
@@ -72,6 +138,7 @@ impl Default for ShowConfig {
             description: "TV show quote search and meme generator".to_string(),
             slug: "myshow".to_string(),
             seasons: None,
+            speakers: BTreeMap::new(),
         }
     }
 }
@@ -80,7 +147,8 @@ impl Default for ShowConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct FrameConfig {
-    /// Frame extraction interval in milliseconds
+    /// Minimum spacing between extracted frames in milliseconds
+    /// (cues closer together than this share a frame; 0 = a frame per cue)
     pub interval_ms: u64,
 
     /// JPEG quality (1-100)
@@ -99,7 +167,7 @@ pub struct FrameConfig {
 impl Default for FrameConfig {
     fn default() -> Self {
         Self {
-            interval_ms: 1000,
+            interval_ms: 0,
             quality: 85,
             frame_width: 0,
             thumb_width: 320,
@@ -138,6 +206,11 @@ pub struct SiteConfig {
 
     /// Results per page in caption listing
     pub results_per_page: usize,
+
+    /// Languages to index and show, primary first (e.g. `["en", "es"]`)
+    ///
+    /// Empty keeps a single index of the best subtitle per episode.
+    pub languages: Vec<String>,
 }
 
 impl Default for SiteConfig {
@@ -152,6 +225,7 @@ impl Default for SiteConfig {
             enable_memes: true,
             max_results: 100,
             results_per_page: 50,
+            languages: Vec::new(),
         }
     }
 }
@@ -171,6 +245,23 @@ pub struct SearchConfig {
 
     /// Fields to search (text, episode)
     pub fields: Vec<String>,
+
+    /// How the search index is written: single file, shards, or auto
+    pub layout: IndexLayout,
+
+    /// Target size of each index shard in kilobytes
+    pub shard_size_kb: usize,
+
+    /// With `layout = "auto"`, shard once the single-file index would
+    /// exceed this many megabytes
+    pub shard_threshold_mb: usize,
+
+    /// Index SDH cues as dialogue only, with sound effects, music and speaker
+    /// labels moved into fields of their own
+    pub sdh: bool,
+
+    /// With `sdh`, index cues that are nothing but sound effects
+    pub index_sound_effects: bool,
 }
 
 impl Default for SearchConfig {
@@ -180,10 +271,53 @@ impl Default for SearchConfig {
             fuzzy: true,
             exact_boost: 2.0,
             fields: vec!["text".to_string()],
+            layout: IndexLayout::Auto,
+            shard_size_kb: 512,
+            shard_threshold_mb: 10,
+            sdh: false,
+            index_sound_effects: true,
         }
     }
 }
 
+/// Embedded subtitle track settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SubtitleConfig {
+    /// Which embedded tracks to extract
+    pub tracks: TrackSelection,
+
+    /// With `tracks = "all"`, only extract these languages (empty = all)
+    pub languages: Vec<String>,
+
+    /// How parsed cues are merged, split and deduplicated
+    pub normalize: NormalizeRules,
+}
+
+/// Which embedded subtitle tracks are extracted from a video
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackSelection {
+    /// Only the highest-scoring track
+    #[default]
+    Best,
+    /// Every text track
+    All,
+}
+
+/// Search index layout on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndexLayout {
+    /// Single file for small libraries, shards for large ones
+    #[default]
+    Auto,
+    /// One `search/index.json` with every entry
+    Single,
+    /// Term shards and per-episode document files behind a manifest
+    Sharded,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,4 +351,43 @@ enable_memes = true
         assert_eq!(config.frames.interval_ms, 500);
         assert_eq!(config.site.title, "Simpsons Search");
     }
+
+    #[test]
+    fn test_subtitle_tracks() {
+        assert_eq!(Config::default().subtitles.tracks, TrackSelection::Best);
+
+        let config =
+            Config::from_toml_str("[subtitles]\ntracks = \"all\"\nlanguages = [\"en\", \"es\"]\n")
+                .unwrap();
+        assert_eq!(config.subtitles.tracks, TrackSelection::All);
+        assert_eq!(config.subtitles.languages, vec!["en", "es"]);
+        assert!(Config::from_toml_str("[subtitles]\ntracks = \"some\"\n").is_err());
+
+        let config =
+            Config::from_toml_str("[subtitles.normalize]\ndedupe = true\nmerge_gap_ms = 250\n")
+                .unwrap();
+        assert!(config.subtitles.normalize.dedupe);
+        assert!(!config.subtitles.normalize.split_dialogue);
+        assert_eq!(config.subtitles.normalize.merge_gap_ms, 250);
+        assert_eq!(
+            Config::default().subtitles.normalize,
+            NormalizeRules::none()
+        );
+    }
+
+    #[test]
+    fn test_explicit_keys() {
+        let config = Config::from_toml_str("[frames]\nquality = 90\n").unwrap();
+        assert!(config.is_set("frames.quality"));
+        assert!(!config.is_set("frames.thumb_width"));
+        assert_eq!(config.frames.thumb_width, 320);
+    }
+
+    #[test]
+    fn test_index_layout() {
+        assert_eq!(Config::default().search.layout, IndexLayout::Auto);
+        let config = Config::from_toml_str("[search]\nlayout = \"sharded\"\n").unwrap();
+        assert_eq!(config.search.layout, IndexLayout::Sharded);
+        assert!(Config::from_toml_str("[search]\nlayout = \"huge\"\n").is_err());
+    }
 }
@@ -1,5 +1,12 @@
 //! Configuration module
 
 mod anytron_toml;
+mod overrides;
+mod resolve;
 
-pub use anytron_toml::{Config, FrameConfig, SearchConfig, ShowConfig, SiteConfig};
+pub use anytron_toml::{
+    Config, FrameConfig, IndexLayout, SearchConfig, ShowConfig, SiteConfig, SubtitleConfig,
+    TrackSelection,
+};
+pub use overrides::{EpisodeOverride, Overrides};
+pub use resolve::{env_var_name, resolve, FrameFlags, FrameSettings, Resolved, ValueSource};
@@ -0,0 +1,329 @@
+//! Per-season and per-episode overrides
+//!
+//! One-off fixes live in `anytron.toml` next to the rest of the config:
+//!
+//! ```toml
+//! [seasons.5]
+//! subtitle_stream = 3
+//!
+//! [episodes."S03E07"]
+//! subtitle_offset_ms = -1200
+//!
+//! [episodes."S03E08"]
+//! subtitle_fps = "25"
+//! video_fps = "24000/1001"
+//!
+//! [episodes."S04E12"]
+//! subtitle_encoding = "windows-1252"
+//!
+//! [episodes."S04E13"]
+//! skip = true
+//! ```
+//!
+//! Season tables apply to every episode of the season; an episode table wins
+//! over its season's table field by field.
+
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::discovery::EpisodeId;
+use crate::error::{AnytronError, Result};
+use crate::subtitle::encoding::encoding_for_label;
+use crate::subtitle::{parse_framerate, SubtitleEntry, Timestamp, TimingTransform};
+
+/// Settings that can be overridden for a season or a single episode
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EpisodeOverride {
+    /// Leave the episode out entirely
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip: Option<bool>,
+
+    /// Video file to use, relative to the input directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video: Option<PathBuf>,
+
+    /// Subtitle file to use, relative to the input directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<PathBuf>,
+
+    /// Index of the embedded subtitle stream to extract
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_stream: Option<u32>,
+
+    /// Shift every subtitle cue by this many milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_offset_ms: Option<i64>,
+
+    /// Character encoding of the subtitle file (e.g. `"windows-1252"`),
+    /// instead of detecting it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_encoding: Option<String>,
+
+    /// Framerate the subtitles were timed for (e.g. `"25"`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle_fps: Option<String>,
+
+    /// Framerate of the video (e.g. `"24000/1001"`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_fps: Option<String>,
+
+    /// Where the first cue should start, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_first_ms: Option<u64>,
+
+    /// Where the last cue should start, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sync_last_ms: Option<u64>,
+
+    /// Grab frames this many milliseconds away from each cue's midpoint
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_offset_ms: Option<i64>,
+}
+
+impl EpisodeOverride {
+    /// Whether the episode should be skipped
+    pub fn is_skipped(&self) -> bool {
+        self.skip.unwrap_or(false)
+    }
+
+    /// Subtitle offset in milliseconds (0 when not overridden)
+    pub fn subtitle_offset(&self) -> i64 {
+        self.subtitle_offset_ms.unwrap_or(0)
+    }
+
+    /// Timing correction for the episode's parsed subtitles
+    ///
+    /// Two-point sync, when set, replaces framerate conversion since it
+    /// already fixes the scale. The subtitle offset is applied last.
+    pub fn timing(&self, entries: &[SubtitleEntry]) -> Result<TimingTransform> {
+        let base = match (self.sync_first_ms, self.sync_last_ms) {
+            (Some(first), Some(last)) => {
+                TimingTransform::sync(entries, Timestamp(first), Timestamp(last))?
+            }
+            (None, None) => match (&self.subtitle_fps, &self.video_fps) {
+                (Some(subtitle), Some(video)) => {
+                    TimingTransform::framerate(parse_framerate(subtitle)?, parse_framerate(video)?)?
+                }
+                // Alone, video_fps only times frame-based subtitle formats
+                (None, _) => TimingTransform::identity(),
+                (Some(_), None) => {
+                    return Err(AnytronError::Config(
+                        "subtitle_fps needs video_fps to be set too".to_string(),
+                    ))
+                }
+            },
+            _ => {
+                return Err(AnytronError::Config(
+                    "sync_first_ms and sync_last_ms must be set together".to_string(),
+                ))
+            }
+        };
+
+        Ok(base.then(TimingTransform::offset(self.subtitle_offset())))
+    }
+
+    /// Frame offset in milliseconds (0 when not overridden)
+    pub fn frame_offset(&self) -> i64 {
+        self.frame_offset_ms.unwrap_or(0)
+    }
+
+    /// Encoding the subtitle file is decoded from, when not detected
+    pub fn subtitle_encoding(&self) -> Result<Option<&'static Encoding>> {
+        self.subtitle_encoding
+            .as_deref()
+            .map(encoding_for_label)
+            .transpose()
+    }
+
+    /// Fail early on framerates that don't parse
+    fn check_framerates(&self) -> Result<()> {
+        for fps in [&self.subtitle_fps, &self.video_fps].into_iter().flatten() {
+            parse_framerate(fps)?;
+        }
+        Ok(())
+    }
+
+    /// Fields set in `other` replace the ones in `self`
+    fn merge(mut self, other: &Self) -> Self {
+        self.skip = other.skip.or(self.skip);
+        self.video = other.video.clone().or(self.video);
+        self.subtitle = other.subtitle.clone().or(self.subtitle);
+        self.subtitle_stream = other.subtitle_stream.or(self.subtitle_stream);
+        self.subtitle_offset_ms = other.subtitle_offset_ms.or(self.subtitle_offset_ms);
+        self.subtitle_encoding = other.subtitle_encoding.clone().or(self.subtitle_encoding);
+        self.subtitle_fps = other.subtitle_fps.clone().or(self.subtitle_fps);
+        self.video_fps = other.video_fps.clone().or(self.video_fps);
+        self.sync_first_ms = other.sync_first_ms.or(self.sync_first_ms);
+        self.sync_last_ms = other.sync_last_ms.or(self.sync_last_ms);
+        self.frame_offset_ms = other.frame_offset_ms.or(self.frame_offset_ms);
+        self
+    }
+}
+
+/// The `[seasons.N]` and `[episodes."SxxEyy"]` tables, keyed by parsed IDs
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    seasons: BTreeMap<u32, EpisodeOverride>,
+    episodes: BTreeMap<EpisodeId, EpisodeOverride>,
+}
+
+impl Overrides {
+    /// Parse the override table keys of a config
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let seasons = config
+            .seasons
+            .iter()
+            .map(|(key, table)| {
+                let season = key.trim().parse().map_err(|_| {
+                    AnytronError::Config(format!("Invalid season override key: {:?}", key))
+                })?;
+                table.check_framerates()?;
+                table.subtitle_encoding()?;
+                if table.video.is_some() || table.subtitle.is_some() {
+                    return Err(AnytronError::Config(format!(
+                        "seasons.{}: video and subtitle can only be set per episode",
+                        key
+                    )));
+                }
+                Ok((season, table.clone()))
+            })
+            .collect::<Result<_>>()?;
+
+        let episodes = config
+            .episodes
+            .iter()
+            .map(|(key, table)| {
+                let id = EpisodeId::from_filename(key).map_err(|_| {
+                    AnytronError::Config(format!("Invalid episode override key: {:?}", key))
+                })?;
+                table.check_framerates()?;
+                table.subtitle_encoding()?;
+                Ok((id, table.clone()))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { seasons, episodes })
+    }
+
+    /// Effective overrides of an episode, season table first
+    pub fn get(&self, id: &EpisodeId) -> EpisodeOverride {
+        let season = self.seasons.get(&id.season).cloned().unwrap_or_default();
+        match self.episodes.get(id) {
+            Some(episode) => season.merge(episode),
+            None => season,
+        }
+    }
+
+    /// Episodes that have their own table, whether or not they were found
+    pub fn episode_ids(&self) -> impl Iterator<Item = &EpisodeId> {
+        self.episodes.keys()
+    }
+
+    /// Override tables that apply to none of `found`
+    pub fn unmatched(&self, found: &[EpisodeId]) -> Vec<String> {
+        let seasons = self
+            .seasons
+            .keys()
+            .filter(|season| !found.iter().any(|id| id.season == **season))
+            .map(|season| format!("seasons.{}", season));
+        let episodes = self
+            .episodes
+            .keys()
+            .filter(|id| !found.contains(id))
+            .map(|id| format!("episodes.{}", id));
+        seasons.chain(episodes).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OVERRIDES: &str = r#"
+[seasons.5]
+subtitle_stream = 3
+frame_offset_ms = 200
+
+[episodes."S05E02"]
+frame_offset_ms = -100
+
+[episodes."S03E07"]
+skip = true
+"#;
+
+    #[test]
+    fn test_episode_wins_over_season() {
+        let config = Config::from_toml_str(OVERRIDES).unwrap();
+        let overrides = Overrides::from_config(&config).unwrap();
+
+        let merged = overrides.get(&EpisodeId::new(5, 2));
+        assert_eq!(merged.subtitle_stream, Some(3));
+        assert_eq!(merged.frame_offset(), -100);
+
+        assert_eq!(overrides.get(&EpisodeId::new(5, 1)).frame_offset(), 200);
+        assert!(overrides.get(&EpisodeId::new(3, 7)).is_skipped());
+        assert_eq!(
+            overrides.get(&EpisodeId::new(1, 1)),
+            EpisodeOverride::default()
+        );
+    }
+
+    #[test]
+    fn test_unmatched() {
+        let config = Config::from_toml_str(OVERRIDES).unwrap();
+        let overrides = Overrides::from_config(&config).unwrap();
+
+        let unmatched = overrides.unmatched(&[EpisodeId::new(5, 1), EpisodeId::new(3, 7)]);
+        assert_eq!(unmatched, vec!["episodes.S05E02"]);
+    }
+
+    #[test]
+    fn test_invalid_tables() {
+        assert!(Config::from_toml_str("[seasons.five]\nskip = true\n").is_err());
+        assert!(Config::from_toml_str("[episodes.pilot]\nskip = true\n").is_err());
+        assert!(Config::from_toml_str("[episodes.\"S01E01\"]\nskipp = true\n").is_err());
+        assert!(Config::from_toml_str("[seasons.1]\nvideo = \"a.mkv\"\n").is_err());
+        assert!(Config::from_toml_str("[seasons.1]\nsubtitle_fps = \"pal\"\n").is_err());
+        assert!(Config::from_toml_str("[seasons.1]\nsubtitle_encoding = \"ebcdic\"\n").is_err());
+    }
+
+    #[test]
+    fn test_timing() {
+        let config = Config::from_toml_str(
+            "[seasons.2]\nsubtitle_fps = \"25\"\nvideo_fps = \"24\"\n\n\
+             [episodes.\"S02E03\"]\nsubtitle_offset_ms = -500\n\n\
+             [episodes.\"S02E04\"]\nsync_first_ms = 2000\nsync_last_ms = 8000\n",
+        )
+        .unwrap();
+        let overrides = Overrides::from_config(&config).unwrap();
+        let entries = vec![
+            SubtitleEntry::new(1, Timestamp(1000), Timestamp(2000), "a".to_string()),
+            SubtitleEntry::new(2, Timestamp(4000), Timestamp(5000), "b".to_string()),
+        ];
+
+        // Framerate from the season, offset from the episode
+        let timing = overrides
+            .get(&EpisodeId::new(2, 3))
+            .timing(&entries)
+            .unwrap();
+        assert_eq!(timing.apply(Timestamp(24_000)), Timestamp(24_500));
+
+        // Two-point sync wins over the season's framerate
+        let timing = overrides
+            .get(&EpisodeId::new(2, 4))
+            .timing(&entries)
+            .unwrap();
+        assert_eq!(timing.apply(Timestamp(1000)), Timestamp(2000));
+        assert_eq!(timing.apply(Timestamp(4000)), Timestamp(8000));
+
+        let half = EpisodeOverride {
+            sync_first_ms: Some(1000),
+            ..Default::default()
+        };
+        assert!(half.timing(&entries).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! Layered setting resolution
+//!
+//! Settings are resolved with the following precedence (highest first):
+//! 1. Explicit command line flags
+//! 2. `ANYTRON_*` environment variables (e.g. `ANYTRON_FRAMES_QUALITY`)
+//! 3. Values set in `anytron.toml`
+//! 4. Built-in defaults
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::Config;
+use crate::error::{AnytronError, Result};
+
+/// Where a resolved setting value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSource {
+    /// Built-in default
+    Default,
+    /// Set in the configuration file
+    ConfigFile,
+    /// Set through the named environment variable
+    Env(String),
+    /// Set through the named command line flag
+    Cli(String),
+}
+
+impl fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::ConfigFile => write!(f, "anytron.toml"),
+            Self::Env(var) => write!(f, "env {}", var),
+            Self::Cli(flag) => write!(f, "flag {}", flag),
+        }
+    }
+}
+
+/// A setting value together with its source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: ValueSource,
+}
+
+/// Environment variable name for a dotted config key
+///
+/// `frames.thumb_width` becomes `ANYTRON_FRAMES_THUMB_WIDTH`.
+pub fn env_var_name(key: &str) -> String {
+    format!("ANYTRON_{}", key.replace('.', "_").to_uppercase())
+}
+
+/// Resolve a single setting across CLI, environment, config file and defaults
+///
+/// `config_value` is the value from `config` (the file value if set there,
+/// otherwise the built-in default). `env` looks up environment variables.
+pub fn resolve<T>(
+    key: &str,
+    config: &Config,
+    config_value: T,
+    cli: Option<(T, &str)>,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> Result<Resolved<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    if let Some((value, flag)) = cli {
+        return Ok(Resolved {
+            value,
+            source: ValueSource::Cli(flag.to_string()),
+        });
+    }
+
+    let var = env_var_name(key);
+    if let Some(raw) = env(&var) {
+        let value = raw
+            .trim()
+            .parse()
+            .map_err(|e| AnytronError::Config(format!("Invalid value for {}: {}", var, e)))?;
+        return Ok(Resolved {
+            value,
+            source: ValueSource::Env(var),
+        });
+    }
+
+    let source = if config.is_set(key) {
+        ValueSource::ConfigFile
+    } else {
+        ValueSource::Default
+    };
+
+    Ok(Resolved {
+        value: config_value,
+        source,
+    })
+}
+
+/// Frame settings given explicitly on the command line
+#[derive(Debug, Clone, Default)]
+pub struct FrameFlags {
+    pub interval_ms: Option<u64>,
+    pub quality: Option<u8>,
+    pub frame_width: Option<u32>,
+    pub thumb_width: Option<u32>,
+    pub thumb_quality: Option<u8>,
+}
+
+/// The `[frames]` section after applying every configuration layer
+#[derive(Debug, Clone)]
+pub struct FrameSettings {
+    pub interval_ms: Resolved<u64>,
+    pub quality: Resolved<u8>,
+    pub frame_width: Resolved<u32>,
+    pub thumb_width: Resolved<u32>,
+    pub thumb_quality: Resolved<u8>,
+}
+
+impl FrameSettings {
+    /// Resolve frame settings using the process environment
+    pub fn resolve(config: &Config, flags: &FrameFlags) -> Result<Self> {
+        Self::resolve_with_env(config, flags, &|var| std::env::var(var).ok())
+    }
+
+    /// Resolve frame settings with a custom environment lookup
+    pub fn resolve_with_env(
+        config: &Config,
+        flags: &FrameFlags,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let frames = &config.frames;
+        Ok(Self {
+            interval_ms: resolve(
+                "frames.interval_ms",
+                config,
+                frames.interval_ms,
+                flags.interval_ms.map(|v| (v, "--interval")),
+                env,
+            )?,
+            quality: resolve(
+                "frames.quality",
+                config,
+                frames.quality,
+                flags.quality.map(|v| (v, "--quality")),
+                env,
+            )?,
+            frame_width: resolve(
+                "frames.frame_width",
+                config,
+                frames.frame_width,
+                flags.frame_width.map(|v| (v, "--frame-width")),
+                env,
+            )?,
+            thumb_width: resolve(
+                "frames.thumb_width",
+                config,
+                frames.thumb_width,
+                flags.thumb_width.map(|v| (v, "--thumb-width")),
+                env,
+            )?,
+            thumb_quality: resolve(
+                "frames.thumb_quality",
+                config,
+                frames.thumb_quality,
+                flags.thumb_quality.map(|v| (v, "--thumb-quality")),
+                env,
+            )?,
+        })
+    }
+
+    /// One `key = value (source)` line per setting, for verbose output
+    pub fn describe(&self) -> Vec<String> {
+        fn line<T: fmt::Display>(key: &str, setting: &Resolved<T>) -> String {
+            format!("{} = {} ({})", key, setting.value, setting.source)
+        }
+
+        vec![
+            line("frames.interval_ms", &self.interval_ms),
+            line("frames.quality", &self.quality),
+            line("frames.frame_width", &self.frame_width),
+            line("frames.thumb_width", &self.thumb_width),
+            line("frames.thumb_quality", &self.thumb_quality),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(
+            env_var_name("frames.thumb_width"),
+            "ANYTRON_FRAMES_THUMB_WIDTH"
+        );
+    }
+
+    #[test]
+    fn test_precedence() {
+        let config = Config::from_toml_str("[frames]\nquality = 90\nthumb_width = 400\n").unwrap();
+        let flags = FrameFlags {
+            thumb_width: Some(200),
+            ..Default::default()
+        };
+        let env = |var: &str| match var {
+            "ANYTRON_FRAMES_QUALITY" => Some("75".to_string()),
+            "ANYTRON_FRAMES_THUMB_WIDTH" => Some("300".to_string()),
+            _ => None,
+        };
+
+        let settings = FrameSettings::resolve_with_env(&config, &flags, &env).unwrap();
+
+        // CLI beats environment and file
+        assert_eq!(settings.thumb_width.value, 200);
+        assert_eq!(
+            settings.thumb_width.source,
+            ValueSource::Cli("--thumb-width".to_string())
+        );
+        // Environment beats file
+        assert_eq!(settings.quality.value, 75);
+        assert_eq!(
+            settings.quality.source,
+            ValueSource::Env("ANYTRON_FRAMES_QUALITY".to_string())
+        );
+        // Untouched keys fall back to defaults
+        assert_eq!(settings.thumb_quality.value, 70);
+        assert_eq!(settings.thumb_quality.source, ValueSource::Default);
+        // A frame per cue unless thinning is asked for
+        assert_eq!(settings.interval_ms.value, 0);
+    }
+
+    #[test]
+    fn test_config_file_source() {
+        let config = Config::from_toml_str("[frames]\nframe_width = 1280\n").unwrap();
+        let settings =
+            FrameSettings::resolve_with_env(&config, &FrameFlags::default(), &|_| None).unwrap();
+        assert_eq!(settings.frame_width.value, 1280);
+        assert_eq!(settings.frame_width.source, ValueSource::ConfigFile);
+    }
+
+    #[test]
+    fn test_invalid_env_value() {
+        let env = |var: &str| (var == "ANYTRON_FRAMES_QUALITY").then(|| "high".to_string());
+        let result =
+            FrameSettings::resolve_with_env(&Config::default(), &FrameFlags::default(), &env);
+        assert!(result.is_err());
+    }
+}
@@ -4,4 +4,4 @@ pub mod episode;
 pub mod scanner;
 
 pub use episode::EpisodeId;
-pub use scanner::{Episode, Scanner, SubtitleSource};
+pub use scanner::{Episode, Scanner, SubtitleSource, SubtitleTrack};
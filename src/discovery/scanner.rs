@@ -1,19 +1,34 @@
 //! Directory scanner for video and subtitle files
 
+use indicatif::ProgressBar;
+use rayon::prelude::*;
 use std::collections::HashMap;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::cache::{Cache, CACHE_DIR};
+use crate::config::{EpisodeOverride, Overrides, SubtitleConfig, TrackSelection};
 use crate::discovery::episode::EpisodeId;
 use crate::error::{AnytronError, Result};
-use crate::extractor::SubtitleExtractor;
-use crate::subtitle::{self, SubtitleEntry};
+use crate::extractor::{
+    is_known_language, normalize_language, FrameExtractor, SubtitleExtractor, SubtitleStream,
+};
+use crate::subtitle::diagnostics::log_diagnostics;
+use crate::subtitle::{
+    self, parse_framerate, Detection, NormalizeRules, ParseOptions, Parsed, ParserRegistry,
+    SpeakerAliases, SubtitleEntry, TextEncoding,
+};
 
 /// Video file extensions to look for
 const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "wmv", "webm", "m4v"];
 
-/// Subtitle file extensions to look for
-const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "ass", "ssa", "vtt"];
+/// Subtitle extensions other files share, taken only when the content is
+/// recognised as subtitles (`.sub` is also VobSub's binary image data)
+const SNIFFED_EXTENSIONS: &[&str] = &["xml", "sub"];
+
+/// Bytes read when sniffing a file's content
+const SNIFF_BYTES: u64 = 4096;
 
 /// Patterns indicating English language in filenames (case-insensitive)
 const ENGLISH_PATTERNS: &[&str] = &[
@@ -63,6 +78,12 @@ const NON_ENGLISH_PATTERNS: &[&str] = &[
     ".chinese.",
 ];
 
+/// Patterns marking SDH/CC/HI subtitle files
+const SDH_PATTERNS: &[&str] = &[
+    ".sdh.", "_sdh.", "-sdh.", ".cc.", "_cc.", "-cc.", ".hi.", "_hi.", "-hi.", "[sdh]", "[cc]",
+    "[hi]",
+];
+
 /// Source of subtitle data
 #[derive(Debug, Clone)]
 pub enum SubtitleSource {
@@ -89,15 +110,228 @@ pub struct Episode {
 
     /// Source of the subtitle
     pub subtitle_source: SubtitleSource,
+
+    /// Overrides from `anytron.toml` that apply to this episode
+    pub overrides: EpisodeOverride,
+
+    /// Every subtitle track found, including the one at `subtitle_path`
+    pub tracks: Vec<SubtitleTrack>,
+
+    /// One subtitle per site language that was found, in site order, each
+    /// tagged with its normalized language code
+    pub languages: Vec<SubtitleTrack>,
+
+    /// Framerate of the video, found only when a subtitle is timed in frames
+    pub framerate: Option<f64>,
+
+    /// Display names for the speakers the subtitles give
+    pub speakers: SpeakerAliases,
+
+    /// How parsed cues are merged, split and deduplicated
+    pub normalize: NormalizeRules,
+}
+
+/// A subtitle track available for an episode
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtitleTrack {
+    /// Subtitle file (external or extracted)
+    pub path: PathBuf,
+
+    /// Stream index in the container, for embedded tracks
+    pub stream: Option<u32>,
+
+    /// Language tag, if known
+    pub language: Option<String>,
+
+    /// Track title, if any
+    pub title: Option<String>,
+
+    /// Whether the track only covers forced parts
+    pub forced: bool,
+
+    /// Whether the track appears to be SDH/CC
+    pub sdh: bool,
+}
+
+impl SubtitleTrack {
+    /// Track for an external subtitle file
+    pub fn external(path: PathBuf) -> Self {
+        Self {
+            path,
+            stream: None,
+            language: None,
+            title: None,
+            forced: false,
+            sdh: false,
+        }
+    }
+
+    /// Track extracted from a container stream
+    pub fn embedded(stream: &SubtitleStream, path: PathBuf) -> Self {
+        Self {
+            path,
+            stream: Some(stream.index),
+            language: stream.language.clone(),
+            title: stream.title.clone(),
+            forced: stream.is_forced,
+            sdh: stream.appears_to_be_sdh(),
+        }
+    }
 }
 
 impl Episode {
     /// Parse the subtitle file and return entries
+    ///
+    /// Cue timing is corrected by the episode's offset, framerate and sync
+    /// overrides.
+    ///
+    /// Cues that can't be read are skipped and logged; see
+    /// [`parse_subtitles_lenient`](Self::parse_subtitles_lenient).
     pub fn parse_subtitles(&self) -> Result<Vec<SubtitleEntry>> {
-        subtitle::parse_file(&self.subtitle_path)
+        let parsed = self.parse_subtitles_lenient()?;
+        log_diagnostics(&self.subtitle_path, &parsed.diagnostics);
+        Ok(parsed.entries)
+    }
+
+    /// Parse the subtitle file, returning the problems found with the
+    /// entries
+    pub fn parse_subtitles_lenient(&self) -> Result<Parsed> {
+        let language = self
+            .languages
+            .iter()
+            .chain(&self.tracks)
+            .find(|track| track.path == self.subtitle_path)
+            .and_then(|track| track.language.as_deref());
+        self.parse_track(&self.subtitle_path, language)
+    }
+
+    /// Character encoding of the subtitle file, from its override or detected
+    pub fn subtitle_encoding(&self) -> Result<TextEncoding> {
+        match self.overrides.subtitle_encoding()? {
+            Some(encoding) => Ok(TextEncoding {
+                encoding,
+                detection: Detection::Override,
+            }),
+            None => subtitle::encoding::detect_file(&self.subtitle_path),
+        }
+    }
+
+    /// Parse the subtitles of every other site language
+    ///
+    /// Each file gets the episode's timing overrides, like the main one.
+    pub fn parse_translations(&self) -> Result<Vec<(String, Vec<SubtitleEntry>)>> {
+        Ok(self
+            .parse_translations_lenient()?
+            .into_iter()
+            .map(|(track, parsed)| {
+                log_diagnostics(&track.path, &parsed.diagnostics);
+                (track.language.unwrap_or_default(), parsed.entries)
+            })
+            .collect())
+    }
+
+    /// Parse the subtitles of every other site language, returning the
+    /// problems found in each with its entries
+    pub fn parse_translations_lenient(&self) -> Result<Vec<(SubtitleTrack, Parsed)>> {
+        self.languages
+            .iter()
+            .filter(|track| track.path != self.subtitle_path && track.language.is_some())
+            .map(|track| {
+                let parsed = self.parse_track(&track.path, track.language.as_deref())?;
+                Ok((track.clone(), parsed))
+            })
+            .collect()
+    }
+
+    /// The video's framerate, from the `video_fps` override or else ffprobe
+    pub fn video_framerate(&self) -> Option<f64> {
+        if let Some(fps) = self.overrides.video_fps.as_deref() {
+            return parse_framerate(fps).ok();
+        }
+        match FrameExtractor::probe_framerate(&self.video_path) {
+            Ok(Some(fps)) => {
+                log::debug!("{} plays at {} fps", self.id, fps);
+                Some(fps)
+            }
+            Ok(None) => {
+                log::warn!("No framerate found for {:?}", self.video_path);
+                None
+            }
+            Err(e) => {
+                log::warn!(
+                    "Could not probe the framerate of {:?}: {}",
+                    self.video_path,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Parse one of the episode's subtitle files; the encoding override
+    /// applies to the main one, and `language` picks the language of files
+    /// that hold several
+    ///
+    /// Frame-timed files count frames at `subtitle_fps` when it is set, so
+    /// the framerate override then converts them like any other file.
+    fn parse_track(&self, path: &Path, language: Option<&str>) -> Result<Parsed> {
+        let encoding = if path == self.subtitle_path {
+            self.overrides.subtitle_encoding()?
+        } else {
+            None
+        };
+        let framerate = match &self.overrides.subtitle_fps {
+            Some(fps) => Some(parse_framerate(fps)?),
+            None => self.framerate,
+        };
+        let options = ParseOptions::default()
+            .with_encoding(encoding)
+            .with_language(language.map(str::to_string))
+            .with_framerate(framerate);
+        let mut parsed = subtitle::parse_file_lenient(path, &options)?;
+        let entries = &mut parsed.entries;
+        self.normalize.apply(entries);
+        self.speakers.apply(entries);
+        let timing = self.overrides.timing(entries)?;
+        if !timing.is_identity() {
+            timing.apply_all(entries);
+        }
+        Ok(parsed)
     }
 }
 
+/// File name of a path for progress messages
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Files found for each episode during a scan
+struct ScannedFiles {
+    videos: HashMap<EpisodeId, PathBuf>,
+    subtitles: HashMap<EpisodeId, Vec<PathBuf>>,
+
+    /// Subtitle files named in episode override tables
+    subtitle_overrides: HashMap<EpisodeId, PathBuf>,
+}
+
+/// A video whose container has to be probed
+struct PendingVideo {
+    id: EpisodeId,
+    video_path: PathBuf,
+    overrides: EpisodeOverride,
+
+    /// External subtitle chosen for the episode, if any
+    external: Option<PathBuf>,
+
+    /// Site languages found as external files
+    languages: Vec<SubtitleTrack>,
+
+    /// Site languages still to look for among the embedded tracks
+    missing: Vec<String>,
+}
+
 /// Scanner for discovering episodes in a directory
 pub struct Scanner {
     /// Root directory to scan
@@ -109,8 +343,26 @@ pub struct Scanner {
     /// Filter by specific episodes (e.g., "S01E01")
     episodes_filter: Option<Vec<String>>,
 
-    /// Directory for extracted subtitles cache
+    /// Cache directory (default: `.anytron_cache` in the root)
     cache_dir: Option<PathBuf>,
+
+    /// Clear the cache before scanning
+    refresh_cache: bool,
+
+    /// Per-season and per-episode overrides
+    overrides: Overrides,
+
+    /// Number of videos probed in parallel (None = use rayon default)
+    jobs: Option<usize>,
+
+    /// Which embedded tracks to extract
+    subtitles: SubtitleConfig,
+
+    /// Display names for subtitle speakers
+    speakers: SpeakerAliases,
+
+    /// Site languages to find a subtitle for, primary first
+    languages: Vec<String>,
 }
 
 impl Scanner {
@@ -121,6 +373,12 @@ impl Scanner {
             seasons_filter: None,
             episodes_filter: None,
             cache_dir: None,
+            refresh_cache: false,
+            overrides: Overrides::default(),
+            jobs: None,
+            subtitles: SubtitleConfig::default(),
+            speakers: SpeakerAliases::default(),
+            languages: Vec::new(),
         }
     }
 
@@ -136,14 +394,430 @@ impl Scanner {
         self
     }
 
-    /// Set cache directory for extracted subtitles
+    /// Set cache directory for probe results and extracted subtitles
     pub fn with_cache_dir(mut self, cache_dir: Option<PathBuf>) -> Self {
         self.cache_dir = cache_dir;
         self
     }
 
+    /// Clear the cache before scanning, so every video is probed again
+    pub fn with_refresh_cache(mut self, refresh_cache: bool) -> Self {
+        self.refresh_cache = refresh_cache;
+        self
+    }
+
+    /// Apply per-season and per-episode overrides
+    pub fn with_overrides(mut self, overrides: Overrides) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Set number of videos probed and extracted in parallel
+    pub fn with_jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Choose which embedded subtitle tracks are extracted
+    pub fn with_subtitles(mut self, subtitles: SubtitleConfig) -> Self {
+        self.subtitles = subtitles;
+        self
+    }
+
+    /// Normalize the speakers of parsed cues with a show's alias table
+    pub fn with_speakers(mut self, speakers: SpeakerAliases) -> Self {
+        self.speakers = speakers;
+        self
+    }
+
+    /// Keep one subtitle per language (e.g. `["en", "es"]`), primary first
+    ///
+    /// Language-tagged external files (`S01E01.es.srt`, `Spanish/S01E01.srt`)
+    /// are used before embedded tracks. The primary language's subtitle
+    /// becomes the episode's main one.
+    pub fn with_languages(mut self, languages: &[String]) -> Self {
+        self.languages = languages
+            .iter()
+            .map(|tag| normalize_language(tag))
+            .collect();
+        self
+    }
+
     /// Scan the directory and return discovered episodes
     pub fn scan(&self) -> Result<Vec<Episode>> {
+        self.scan_with_progress(&ProgressBar::hidden())
+    }
+
+    /// Scan the directory, advancing `progress` once per video
+    ///
+    /// The filesystem walk and matching of external subtitles are quick;
+    /// videos without one are then probed and their subtitles extracted up to
+    /// `jobs` at a time.
+    pub fn scan_with_progress(&self, progress: &ProgressBar) -> Result<Vec<Episode>> {
+        let ScannedFiles {
+            videos: video_files,
+            subtitles: subtitle_files,
+            mut subtitle_overrides,
+        } = self.collect_files()?;
+        progress.set_length(video_files.len() as u64);
+
+        // Match videos with subtitles
+        let all_tracks = self.subtitles.tracks == TrackSelection::All;
+        let mut episodes: Vec<Episode> = Vec::new();
+        let mut pending: Vec<PendingVideo> = Vec::new();
+
+        for (id, video_path) in video_files {
+            let overrides = self.overrides.get(&id);
+            if overrides.is_skipped() {
+                log::info!("Skipping {} (override)", id);
+                progress.inc(1);
+                continue;
+            }
+
+            let candidates = subtitle_files
+                .get(&id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let override_subtitle = subtitle_overrides.remove(&id);
+
+            // An override subtitle stands in for the primary language, and so
+            // do files without a language tag
+            let mut languages = Vec::new();
+            let mut missing = Vec::new();
+            for (i, language) in self.languages.iter().enumerate() {
+                let path = match &override_subtitle {
+                    Some(path) if i == 0 => Some(path.clone()),
+                    _ if i == 0 => Self::select_language_subtitle(candidates, language)
+                        .or_else(|| Self::select_untagged_subtitle(candidates)),
+                    _ => Self::select_language_subtitle(candidates, language),
+                };
+                match path {
+                    Some(path) => languages.push(SubtitleTrack {
+                        language: Some(language.clone()),
+                        ..SubtitleTrack::external(path)
+                    }),
+                    None => missing.push(language.clone()),
+                }
+            }
+
+            // An override subtitle first, then the primary language, then the
+            // best external file
+            let primary = languages
+                .first()
+                .filter(|track| track.language.as_ref() == self.languages.first())
+                .map(|track| track.path.clone());
+            let external = match override_subtitle {
+                Some(subtitle_path) => {
+                    log::debug!("Using override subtitle for {}: {:?}", id, subtitle_path);
+                    Some(subtitle_path)
+                }
+                None => primary
+                    .or_else(|| Self::select_best_external_subtitle(candidates))
+                    .map(|subtitle_path| {
+                        log::debug!("Using external subtitle for {}: {:?}", id, subtitle_path);
+                        subtitle_path
+                    }),
+            };
+
+            match external {
+                Some(subtitle_path) if !all_tracks && missing.is_empty() => {
+                    episodes.push(Episode {
+                        id,
+                        video_path,
+                        subtitle_path: subtitle_path.clone(),
+                        subtitle_source: SubtitleSource::External(subtitle_path.clone()),
+                        overrides,
+                        tracks: vec![SubtitleTrack::external(subtitle_path)],
+                        languages,
+                        framerate: None,
+                        speakers: self.speakers.clone(),
+                        normalize: self.subtitles.normalize.clone(),
+                    });
+                    progress.inc(1);
+                }
+                // Probe the container for its tracks, or for a subtitle at all
+                external => pending.push(PendingVideo {
+                    id,
+                    video_path,
+                    overrides,
+                    external,
+                    languages,
+                    missing,
+                }),
+            }
+        }
+
+        if !pending.is_empty() {
+            let cache_dir = self.cache_dir();
+            let cache = if self.refresh_cache {
+                Cache::refresh(&cache_dir)?
+            } else {
+                Cache::open(&cache_dir)
+            };
+
+            let mut builder = rayon::ThreadPoolBuilder::new();
+            if let Some(num_jobs) = self.jobs {
+                builder = builder.num_threads(num_jobs);
+            }
+            let pool = builder.build().map_err(|e| {
+                AnytronError::Discovery(format!("Failed to build thread pool: {}", e))
+            })?;
+
+            let extracted: Vec<Episode> = pool.install(|| {
+                pending
+                    .into_par_iter()
+                    .filter_map(|video| {
+                        progress
+                            .set_message(format!("Probing {}", display_name(&video.video_path)));
+                        let episode = self.probe_episode(video, &cache);
+                        progress.inc(1);
+                        episode
+                    })
+                    .collect()
+            });
+            episodes.extend(extracted);
+
+            cache.save()?;
+        } else if self.refresh_cache {
+            Cache::refresh(&self.cache_dir())?;
+        }
+
+        // Sort by episode ID
+        episodes.sort_by_key(|e| e.id);
+
+        // Subtitles timed in frames need the video's framerate
+        for episode in &mut episodes {
+            episode.framerate = Self::frame_based_framerate(episode);
+        }
+
+        if episodes.is_empty() {
+            return Err(AnytronError::NoVideosFound(self.root.clone()));
+        }
+
+        Ok(episodes)
+    }
+
+    /// Build an episode whose subtitles come from (or include) its container
+    ///
+    /// Site languages without an external file are taken from embedded
+    /// tracks. With an `external` subtitle, other embedded tracks are only
+    /// listed alongside it. Otherwise the primary language's track, or the
+    /// chosen embedded track, becomes the episode's subtitle, and the episode
+    /// is dropped if there is none.
+    fn probe_episode(&self, video: PendingVideo, cache: &Cache) -> Option<Episode> {
+        let PendingVideo {
+            id,
+            video_path,
+            overrides,
+            external,
+            mut languages,
+            missing,
+        } = video;
+
+        let embedded_languages = self.embedded_languages(&video_path, &missing, cache);
+        languages.extend(embedded_languages.iter().map(|(_, track)| track.clone()));
+        languages.sort_by_key(|track| {
+            self.languages
+                .iter()
+                .position(|language| track.language.as_ref() == Some(language))
+        });
+        for language in &missing {
+            if !languages
+                .iter()
+                .any(|t| t.language.as_ref() == Some(language))
+            {
+                log::warn!("No {} subtitle found for {}", language, id);
+            }
+        }
+
+        if let Some(subtitle_path) = external {
+            let mut tracks = vec![SubtitleTrack::external(subtitle_path.clone())];
+            if self.subtitles.tracks == TrackSelection::All {
+                match SubtitleExtractor::extract_text_tracks(
+                    &video_path,
+                    cache,
+                    &self.subtitles.languages,
+                ) {
+                    Ok(embedded) => tracks.extend(
+                        embedded
+                            .into_iter()
+                            .map(|(stream, path)| SubtitleTrack::embedded(&stream, path)),
+                    ),
+                    Err(e) => {
+                        log::warn!("Failed to extract subtitles from {:?}: {}", video_path, e)
+                    }
+                }
+            }
+            for (stream, track) in &embedded_languages {
+                if !tracks.iter().any(|t| t.path == track.path) {
+                    tracks.push(SubtitleTrack::embedded(stream, track.path.clone()));
+                }
+            }
+            return Some(Episode {
+                id,
+                video_path,
+                subtitle_path: subtitle_path.clone(),
+                subtitle_source: SubtitleSource::External(subtitle_path),
+                overrides,
+                tracks,
+                languages,
+                framerate: None,
+                speakers: self.speakers.clone(),
+                normalize: self.subtitles.normalize.clone(),
+            });
+        }
+
+        // A stream override wins over the primary language's track
+        let primary = embedded_languages
+            .iter()
+            .find(|(_, track)| track.language.as_ref() == self.languages.first())
+            .filter(|_| overrides.subtitle_stream.is_none())
+            .map(|(stream, _)| stream.index);
+        let stream = overrides.subtitle_stream.or(primary);
+
+        match self.embedded_tracks(&video_path, stream, cache) {
+            Ok(Some((extracted_path, mut tracks))) => {
+                log::info!(
+                    "Extracted embedded subtitle for {}: {:?}",
+                    id,
+                    extracted_path
+                );
+                for (stream, track) in &embedded_languages {
+                    if !tracks.iter().any(|t| t.path == track.path) {
+                        tracks.push(SubtitleTrack::embedded(stream, track.path.clone()));
+                    }
+                }
+                tracks.sort_by_key(|t| t.stream);
+                Some(Episode {
+                    id,
+                    video_path: video_path.clone(),
+                    subtitle_path: extracted_path.clone(),
+                    subtitle_source: SubtitleSource::Embedded {
+                        video_path,
+                        extracted_path,
+                    },
+                    overrides,
+                    tracks,
+                    languages,
+                    framerate: None,
+                    speakers: self.speakers.clone(),
+                    normalize: self.subtitles.normalize.clone(),
+                })
+            }
+            Ok(None) => {
+                log::warn!(
+                    "No subtitle found for video: {:?} ({}) - no external file or embedded track",
+                    video_path,
+                    id
+                );
+                None
+            }
+            Err(e) => {
+                log::warn!("Failed to extract subtitle from {:?}: {}", video_path, e);
+                None
+            }
+        }
+    }
+
+    /// Extract the best embedded text track for each of `languages`
+    fn embedded_languages(
+        &self,
+        video_path: &Path,
+        languages: &[String],
+        cache: &Cache,
+    ) -> Vec<(SubtitleStream, SubtitleTrack)> {
+        if languages.is_empty() {
+            return Vec::new();
+        }
+        let extracted = match SubtitleExtractor::extract_text_tracks(video_path, cache, languages) {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                log::warn!("Failed to extract subtitles from {:?}: {}", video_path, e);
+                return Vec::new();
+            }
+        };
+
+        languages
+            .iter()
+            .filter_map(|language| {
+                let (stream, path) = extracted
+                    .iter()
+                    .filter(|(stream, _)| stream.matches_language(language))
+                    .max_by_key(|(stream, _)| stream.priority_score())?;
+                let track = SubtitleTrack {
+                    language: Some(language.clone()),
+                    ..SubtitleTrack::embedded(stream, path.clone())
+                };
+                Some((stream.clone(), track))
+            })
+            .collect()
+    }
+
+    /// Extract a video's embedded tracks and pick the one to use
+    ///
+    /// The pick is the `stream` override if given, else the best-scoring
+    /// track. Returns its path and every extracted track.
+    fn embedded_tracks(
+        &self,
+        video_path: &Path,
+        stream: Option<u32>,
+        cache: &Cache,
+    ) -> Result<Option<(PathBuf, Vec<SubtitleTrack>)>> {
+        let mut extracted = match self.subtitles.tracks {
+            TrackSelection::All => SubtitleExtractor::extract_text_tracks(
+                video_path,
+                cache,
+                &self.subtitles.languages,
+            )?,
+            TrackSelection::Best => Vec::new(),
+        };
+
+        let chosen = match stream {
+            Some(index) => extracted.iter().position(|(s, _)| s.index == index),
+            None => extracted
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, (s, _))| s.priority_score())
+                .map(|(i, _)| i),
+        };
+        let primary = match chosen {
+            Some(i) => extracted[i].1.clone(),
+            None => match SubtitleExtractor::extract_best_track(video_path, cache, stream)? {
+                Some((best, path)) => {
+                    extracted.push((best, path.clone()));
+                    extracted.sort_by_key(|(s, _)| s.index);
+                    path
+                }
+                None => return Ok(None),
+            },
+        };
+
+        let tracks = extracted
+            .iter()
+            .map(|(s, path)| SubtitleTrack::embedded(s, path.clone()))
+            .collect();
+        Ok(Some((primary, tracks)))
+    }
+
+    /// IDs of every episode with a video, including ones skipped by overrides
+    pub fn episode_ids(&self) -> Result<Vec<EpisodeId>> {
+        let mut ids: Vec<EpisodeId> = self.collect_files()?.videos.into_keys().collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Cache directory in use
+    fn cache_dir(&self) -> PathBuf {
+        self.cache_dir
+            .clone()
+            .unwrap_or_else(|| self.root.join(CACHE_DIR))
+    }
+
+    /// Walk the root and group video and subtitle files by episode
+    ///
+    /// Extracted subtitles in the cache directory are not picked up as
+    /// external ones.
+    fn collect_files(&self) -> Result<ScannedFiles> {
         if !self.root.exists() {
             return Err(AnytronError::Discovery(format!(
                 "Directory does not exist: {:?}",
@@ -155,9 +829,12 @@ impl Scanner {
         let mut video_files: HashMap<EpisodeId, PathBuf> = HashMap::new();
         let mut subtitle_files: HashMap<EpisodeId, Vec<PathBuf>> = HashMap::new();
 
+        let cache_dir = self.cache_dir();
+        let parsers = subtitle::registry();
         for entry in WalkDir::new(&self.root)
             .follow_links(true)
             .into_iter()
+            .filter_entry(|e| e.path() != cache_dir)
             .filter_map(|e| e.ok())
         {
             let path = entry.path();
@@ -183,17 +860,8 @@ impl Scanner {
             };
 
             // Apply filters
-            if let Some(ref seasons) = self.seasons_filter {
-                if !seasons.contains(&episode_id.season) {
-                    continue;
-                }
-            }
-
-            if let Some(ref episodes) = self.episodes_filter {
-                let ep_str = episode_id.to_string();
-                if !episodes.iter().any(|e| e.eq_ignore_ascii_case(&ep_str)) {
-                    continue;
-                }
+            if !self.is_selected(&episode_id) {
+                continue;
             }
 
             // Categorize by file type
@@ -201,7 +869,10 @@ impl Scanner {
                 video_files
                     .entry(episode_id)
                     .or_insert_with(|| path.to_path_buf());
-            } else if SUBTITLE_EXTENSIONS.contains(&extension.as_str()) {
+            } else if parsers.supports_extension(&extension)
+                && (!SNIFFED_EXTENSIONS.contains(&extension.as_str())
+                    || Self::looks_like_subtitles(&parsers, path))
+            {
                 subtitle_files
                     .entry(episode_id)
                     .or_default()
@@ -209,69 +880,115 @@ impl Scanner {
             }
         }
 
-        // Determine cache directory for extracted subtitles
-        let cache_dir = self
-            .cache_dir
-            .clone()
-            .unwrap_or_else(|| self.root.join(".anytron_cache").join("subtitles"));
+        // Files named explicitly in `[episodes."SxxEyy"]` tables
+        let mut subtitle_overrides: HashMap<EpisodeId, PathBuf> = HashMap::new();
+        for id in self.overrides.episode_ids() {
+            if !self.is_selected(id) {
+                continue;
+            }
+            let overrides = self.overrides.get(id);
+            if let Some(video) = &overrides.video {
+                video_files.insert(*id, self.override_path(id, video)?);
+            }
+            if let Some(subtitle) = &overrides.subtitle {
+                subtitle_overrides.insert(*id, self.override_path(id, subtitle)?);
+            }
+        }
 
-        // Match videos with subtitles
-        let mut episodes: Vec<Episode> = Vec::new();
+        Ok(ScannedFiles {
+            videos: video_files,
+            subtitles: subtitle_files,
+            subtitle_overrides,
+        })
+    }
 
-        for (id, video_path) in video_files {
-            // First, try to find external subtitle file
-            if let Some(subs) = subtitle_files.get(&id) {
-                if let Some(subtitle_path) = Self::select_best_external_subtitle(subs) {
-                    log::debug!("Using external subtitle for {}: {:?}", id, subtitle_path);
-                    episodes.push(Episode {
-                        id,
-                        video_path,
-                        subtitle_path: subtitle_path.clone(),
-                        subtitle_source: SubtitleSource::External(subtitle_path),
-                    });
-                    continue;
-                }
+    /// Whether the season and episode filters let an episode through
+    fn is_selected(&self, id: &EpisodeId) -> bool {
+        if let Some(ref seasons) = self.seasons_filter {
+            if !seasons.contains(&id.season) {
+                return false;
             }
+        }
 
-            // No external subtitle found, try to extract from video container
-            match SubtitleExtractor::extract_best_subtitle(&video_path, &cache_dir) {
-                Ok(Some(extracted_path)) => {
-                    log::info!(
-                        "Extracted embedded subtitle for {}: {:?}",
-                        id,
-                        extracted_path
-                    );
-                    episodes.push(Episode {
-                        id,
-                        video_path: video_path.clone(),
-                        subtitle_path: extracted_path.clone(),
-                        subtitle_source: SubtitleSource::Embedded {
-                            video_path,
-                            extracted_path,
-                        },
-                    });
-                }
-                Ok(None) => {
-                    log::warn!(
-                        "No subtitle found for video: {:?} ({}) - no external file or embedded track",
-                        video_path,
-                        id
-                    );
-                }
-                Err(e) => {
-                    log::warn!("Failed to extract subtitle from {:?}: {}", video_path, e);
-                }
+        if let Some(ref episodes) = self.episodes_filter {
+            let ep_str = id.to_string();
+            if !episodes.iter().any(|e| e.eq_ignore_ascii_case(&ep_str)) {
+                return false;
             }
         }
 
-        // Sort by episode ID
-        episodes.sort_by(|a, b| a.id.cmp(&b.id));
+        true
+    }
 
-        if episodes.is_empty() {
-            return Err(AnytronError::NoVideosFound(self.root.clone()));
+    /// Resolve a file named in an override table against the scanned root
+    fn override_path(&self, id: &EpisodeId, path: &Path) -> Result<PathBuf> {
+        let path = self.root.join(path);
+        if !path.is_file() {
+            return Err(AnytronError::Discovery(format!(
+                "Override file for {} does not exist: {:?}",
+                id, path
+            )));
         }
+        Ok(path)
+    }
 
-        Ok(episodes)
+    /// Select the best external subtitle tagged with `language`
+    ///
+    /// A file is tagged by one of the last two parts of its name
+    /// (`S01E01.es.srt`, `S01E01.spa.sdh.srt`) or by its directory
+    /// (`Spanish/S01E01.srt`).
+    fn select_language_subtitle(paths: &[PathBuf], language: &str) -> Option<PathBuf> {
+        let tagged: Vec<&PathBuf> = paths
+            .iter()
+            .filter(|path| {
+                Self::language_tags(path).any(|tag| normalize_language(&tag) == language)
+            })
+            .collect();
+        Self::prefer_plain(&tagged)
+    }
+
+    /// Select the best external subtitle without a language tag
+    fn select_untagged_subtitle(paths: &[PathBuf]) -> Option<PathBuf> {
+        let untagged: Vec<&PathBuf> = paths
+            .iter()
+            .filter(|path| !Self::language_tags(path).any(|tag| is_known_language(&tag)))
+            .collect();
+        Self::prefer_plain(&untagged)
+    }
+
+    /// Pick a non-SDH file, preferring SRT, from files of one language
+    fn prefer_plain(paths: &[&PathBuf]) -> Option<PathBuf> {
+        paths
+            .iter()
+            .max_by_key(|path| {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                (
+                    !SDH_PATTERNS.iter().any(|p| name.contains(p)),
+                    name.ends_with(".srt"),
+                )
+            })
+            .map(|path| (*path).clone())
+    }
+
+    /// Possible language tags in a subtitle's file and directory name
+    fn language_tags(path: &Path) -> impl Iterator<Item = String> {
+        let stem = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let mut tags: Vec<String> = stem
+            .rsplit(['.', '_', '-'])
+            .take(2)
+            .map(|tag| tag.trim().to_string())
+            .collect();
+        if let Some(parent) = path.parent().and_then(|p| p.file_name()) {
+            tags.push(parent.to_string_lossy().to_lowercase());
+        }
+        tags.into_iter()
     }
 
     /// Select the best external subtitle file from a list
@@ -292,7 +1009,7 @@ impl Scanner {
             .collect();
 
         // Sort by score descending
-        scored.sort_by(|a, b| b.0.cmp(&a.0));
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
 
         scored.first().map(|(_, p)| (*p).clone())
     }
@@ -337,19 +1054,7 @@ impl Scanner {
         }
 
         // Penalize SDH/CC/HI subtitles
-        if filename.contains(".sdh.")
-            || filename.contains("_sdh.")
-            || filename.contains("-sdh.")
-            || filename.contains(".cc.")
-            || filename.contains("_cc.")
-            || filename.contains("-cc.")
-            || filename.contains(".hi.")
-            || filename.contains("_hi.")
-            || filename.contains("-hi.")
-            || filename.contains("[sdh]")
-            || filename.contains("[cc]")
-            || filename.contains("[hi]")
-        {
+        if SDH_PATTERNS.iter().any(|p| filename.contains(p)) {
             score -= 100;
         }
 
@@ -361,16 +1066,51 @@ impl Scanner {
         score
     }
 
+    /// Whether the start of a file is in a registered subtitle format
+    fn looks_like_subtitles(parsers: &ParserRegistry, path: &Path) -> bool {
+        Self::read_head(path).is_some_and(|content| parsers.detect(&content).is_some())
+    }
+
+    /// The video's framerate when one of the episode's subtitles is timed in
+    /// frames, from the `video_fps` override or else ffprobe
+    fn frame_based_framerate(episode: &Episode) -> Option<f64> {
+        let parsers = subtitle::registry();
+        let frame_based = std::iter::once(&episode.subtitle_path)
+            .chain(episode.languages.iter().map(|track| &track.path))
+            .any(|path| {
+                Self::read_head(path)
+                    .and_then(|content| {
+                        parsers.select(path, &content).ok().map(|p| p.frame_based())
+                    })
+                    .unwrap_or(false)
+            });
+        if !frame_based {
+            return None;
+        }
+
+        episode.video_framerate()
+    }
+
+    /// The start of a file, decoded
+    fn read_head(path: &Path) -> Option<String> {
+        let mut head = Vec::new();
+        std::fs::File::open(path)
+            .and_then(|file| file.take(SNIFF_BYTES).read_to_end(&mut head))
+            .ok()?;
+        Some(subtitle::encoding::decode(&head, None).0)
+    }
+
     /// Find subtitle file for a video (legacy method for compatibility)
     pub fn find_subtitle_for_video(&self, video_path: &Path) -> Option<PathBuf> {
         let video_stem = video_path.file_stem()?.to_str()?;
         let video_dir = video_path.parent()?;
+        let extensions = subtitle::registry().extensions();
 
         // Collect all matching subtitles
         let mut matches = Vec::new();
 
         // Check for subtitle with same name
-        for ext in SUBTITLE_EXTENSIONS {
+        for ext in &extensions {
             let subtitle_path = video_dir.join(format!("{}.{}", video_stem, ext));
             if subtitle_path.exists() {
                 matches.push(subtitle_path);
@@ -388,7 +1128,7 @@ impl Scanner {
         // Check for subtitle in a "subs" subdirectory
         let subs_dir = video_dir.join("subs");
         if subs_dir.exists() {
-            for ext in SUBTITLE_EXTENSIONS {
+            for ext in &extensions {
                 let subtitle_path = subs_dir.join(format!("{}.{}", video_stem, ext));
                 if subtitle_path.exists() {
                     matches.push(subtitle_path);
@@ -399,7 +1139,7 @@ impl Scanner {
         // Check English subdirectory
         let eng_dir = video_dir.join("English");
         if eng_dir.exists() {
-            for ext in SUBTITLE_EXTENSIONS {
+            for ext in &extensions {
                 let subtitle_path = eng_dir.join(format!("{}.{}", video_stem, ext));
                 if subtitle_path.exists() {
                     matches.push(subtitle_path);
@@ -414,6 +1154,121 @@ impl Scanner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_progress_counts_every_video() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for name in ["S01E01.mkv", "S01E01.srt", "S01E02.mkv", "S01E02.srt"] {
+            std::fs::write(root.join(name), "").unwrap();
+        }
+        // No external subtitle and no usable embedded track
+        std::fs::write(root.join("S01E03.mkv"), "").unwrap();
+
+        let progress = ProgressBar::hidden();
+        let episodes = Scanner::new(root)
+            .with_jobs(Some(2))
+            .scan_with_progress(&progress)
+            .unwrap();
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(progress.position(), 3);
+        assert_eq!(progress.length(), Some(3));
+    }
+
+    #[test]
+    fn test_scan_ignores_cache_dir() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("Show.S01E01.mkv"), "").unwrap();
+        std::fs::write(root.join("Show.S01E01.srt"), "").unwrap();
+
+        // An extracted track that would otherwise win as an English subtitle
+        let extracted = root.join(CACHE_DIR).join("subtitles");
+        std::fs::create_dir_all(&extracted).unwrap();
+        std::fs::write(extracted.join("Show.S01E01-0123456789abcdef.en.srt"), "").unwrap();
+
+        let episodes = Scanner::new(root).scan().unwrap();
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].subtitle_path, root.join("Show.S01E01.srt"));
+    }
+
+    #[test]
+    fn test_scan_sniffs_xml_subtitles() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("Show.S01E01.mkv"), "").unwrap();
+        std::fs::write(root.join("Show.S01E02.mkv"), "").unwrap();
+        // Media server metadata sharing the extension
+        std::fs::write(
+            root.join("Show.S01E01.xml"),
+            "<episodedetails><title>Pilot</title></episodedetails>",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("Show.S01E02.xml"),
+            "<?xml version=\"1.0\"?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\"><body/></tt>",
+        )
+        .unwrap();
+
+        let episodes = Scanner::new(root).scan().unwrap();
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].subtitle_path, root.join("Show.S01E02.xml"));
+    }
+
+    #[test]
+    fn test_scan_frame_based_subtitles() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("Show.S01E01.mkv"), "").unwrap();
+        std::fs::write(root.join("Show.S01E02.mkv"), "").unwrap();
+        // VobSub image data sharing the extension
+        std::fs::write(
+            root.join("Show.S01E01.sub"),
+            [0x00, 0x00, 0x01, 0xba, 0x44, 0x00, 0x04, 0x00],
+        )
+        .unwrap();
+        std::fs::write(root.join("Show.S01E02.sub"), "{50}{100}Hello.\n").unwrap();
+
+        let config =
+            crate::config::Config::from_toml_str("[episodes.\"S01E02\"]\nvideo_fps = \"25\"\n")
+                .unwrap();
+        let episodes = Scanner::new(root)
+            .with_overrides(Overrides::from_config(&config).unwrap())
+            .scan()
+            .unwrap();
+        assert_eq!(episodes.len(), 1);
+        assert_eq!(episodes[0].framerate, Some(25.0));
+
+        let entries = episodes[0].parse_subtitles().unwrap();
+        assert_eq!(entries[0].start.0, 2000);
+        assert_eq!(entries[0].end.0, 4000);
+    }
+
+    #[test]
+    fn test_speaker_aliases() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("Show.S01E01.mkv"), "").unwrap();
+        std::fs::write(
+            root.join("Show.S01E01.srt"),
+            "1\n00:00:01,000 --> 00:00:02,000\nHOMER SIMPSON: Doh!\n\n\
+             2\n00:00:03,000 --> 00:00:04,000\nMR. BURNS: Excellent.\n",
+        )
+        .unwrap();
+
+        let config =
+            crate::config::Config::from_toml_str("[show.speakers]\nHomer = [\"Homer Simpson\"]\n")
+                .unwrap();
+        let episodes = Scanner::new(root)
+            .with_speakers(config.show.speaker_aliases())
+            .scan()
+            .unwrap();
+        let entries = episodes[0].parse_subtitles().unwrap();
+        assert_eq!(entries[0].speaker.as_deref(), Some("Homer"));
+        assert_eq!(entries[0].text, "Doh!");
+        assert_eq!(entries[1].speaker.as_deref(), Some("Mr. Burns"));
+    }
 
     #[test]
     fn test_video_extensions() {
@@ -424,9 +1279,9 @@ mod tests {
 
     #[test]
     fn test_subtitle_extensions() {
-        assert!(SUBTITLE_EXTENSIONS.contains(&"srt"));
-        assert!(SUBTITLE_EXTENSIONS.contains(&"ass"));
-        assert!(!SUBTITLE_EXTENSIONS.contains(&"mp4"));
+        assert!(subtitle::is_subtitle_extension("srt"));
+        assert!(subtitle::is_subtitle_extension("ass"));
+        assert!(!subtitle::is_subtitle_extension("mp4"));
     }
 
     #[test]
@@ -462,4 +1317,23 @@ mod tests {
         assert!(best.to_string_lossy().contains(".en."));
         assert!(!best.to_string_lossy().contains(".sdh."));
     }
+
+    #[test]
+    fn test_select_language_subtitle() {
+        let paths = vec![
+            PathBuf::from("Show.S01E01.srt"),
+            PathBuf::from("Show.S01E01.spa.sdh.srt"),
+            PathBuf::from("Show.S01E01.es.srt"),
+            PathBuf::from("French/Show.S01E01.srt"),
+        ];
+
+        let spanish = Scanner::select_language_subtitle(&paths, "spa").unwrap();
+        assert_eq!(spanish, PathBuf::from("Show.S01E01.es.srt"));
+        let french = Scanner::select_language_subtitle(&paths, "fre").unwrap();
+        assert_eq!(french, PathBuf::from("French/Show.S01E01.srt"));
+        assert!(Scanner::select_language_subtitle(&paths, "ger").is_none());
+
+        let untagged = Scanner::select_untagged_subtitle(&paths).unwrap();
+        assert_eq!(untagged, PathBuf::from("Show.S01E01.srt"));
+    }
 }
@@ -0,0 +1,441 @@
+//! Anki deck export
+//!
+//! A deck is a tab-separated `deck.txt` with Anki's import headers, plus a
+//! `media/` folder holding each card's frame and audio clip:
+//!
+//! ```text
+//! anki/
+//! ├── deck.txt
+//! └── media/
+//!     ├── myshow-S01E01-1a2b3c4d.jpg
+//!     └── myshow-S01E01-1a2b3c4d.mp3
+//! ```
+//!
+//! Anki's media folder is flat, so media files are prefixed with the show's
+//! slug. Notes use the prefixed caption ID as their GUID, so importing a
+//! re-exported deck updates notes instead of duplicating them.
+
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use std::path::Path;
+
+use crate::discovery::Episode;
+use crate::error::{AnytronError, Result};
+use crate::extractor::{extract_audio_clip, FrameExtractor};
+use crate::indexer::{caption_ids, Language};
+use crate::subtitle::{aligned_lines, SubtitleEntry, Timestamp};
+
+/// Name of the deck file inside the output directory
+const DECK_FILE: &str = "deck.txt";
+
+/// Name of the media folder inside the output directory
+const MEDIA_DIR: &str = "media";
+
+/// Audio kept before and after each line, so clips don't start mid-word
+const AUDIO_PADDING_MS: u64 = 250;
+
+/// Columns of the deck file, in order
+const COLUMNS: &[&str] = &[
+    "GUID",
+    "Image",
+    "Line",
+    "Translation",
+    "Audio",
+    "Episode",
+    "Time",
+    "Tags",
+];
+
+/// One flashcard: a line, its frame and audio
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnkiCard {
+    /// Caption ID, as used for the line's caption page
+    pub id: String,
+
+    /// Episode identifier (S01E01)
+    pub episode: String,
+
+    /// Start of the line
+    pub start: Timestamp,
+
+    /// End of the line
+    pub end: Timestamp,
+
+    /// The line in the language being studied
+    pub text: String,
+
+    /// The line in the translation language, aligned by time
+    pub translation: Option<String>,
+
+    /// Frame file in the media folder
+    pub image: Option<String>,
+
+    /// Audio clip file in the media folder
+    pub audio: Option<String>,
+}
+
+/// Builder for Anki decks of an episode list
+pub struct AnkiExporter {
+    /// Deck the notes are imported into
+    deck: String,
+
+    /// Prefix for media file names and note GUIDs
+    prefix: String,
+
+    /// Language of the lines, for matching the query
+    language: Language,
+
+    /// Words every exported line must contain
+    query: Option<String>,
+
+    /// Extract a frame for each card
+    frames: bool,
+
+    /// Extract an audio clip for each card
+    audio: bool,
+
+    /// Frame width in pixels (0 = original)
+    frame_width: u32,
+
+    /// Number of parallel jobs (None = use rayon default)
+    jobs: Option<usize>,
+}
+
+impl AnkiExporter {
+    /// Create an exporter for a deck; `prefix` keeps media names unique
+    pub fn new(deck: &str, prefix: &str) -> Self {
+        Self {
+            deck: deck.to_string(),
+            prefix: prefix.to_string(),
+            language: Language::default(),
+            query: None,
+            frames: true,
+            audio: false,
+            frame_width: 640,
+            jobs: None,
+        }
+    }
+
+    /// Set the language of the lines, used to match the query
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Only export lines containing every word of `query`
+    ///
+    /// Words are compared after stemming, so `run` also matches `running`.
+    pub fn with_query(mut self, query: Option<String>) -> Self {
+        self.query = query.filter(|query| !query.trim().is_empty());
+        self
+    }
+
+    /// Set whether cards get a frame
+    pub fn with_frames(mut self, frames: bool) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Set whether cards get an audio clip
+    pub fn with_audio(mut self, audio: bool) -> Self {
+        self.audio = audio;
+        self
+    }
+
+    /// Set the frame width (0 keeps the original resolution)
+    pub fn with_frame_width(mut self, width: u32) -> Self {
+        self.frame_width = width;
+        self
+    }
+
+    /// Set number of parallel jobs
+    pub fn with_jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Cards for the lines of one episode that match the query
+    ///
+    /// `translation` is the episode's subtitles in the translation language;
+    /// each line takes the cues that overlap it.
+    pub fn cards(
+        &self,
+        episode: &Episode,
+        entries: &[SubtitleEntry],
+        translation: Option<&[SubtitleEntry]>,
+    ) -> Vec<AnkiCard> {
+        let episode_id = episode.id.to_string();
+        let mut translations = match translation {
+            Some(cues) => aligned_lines(entries, cues),
+            None => vec![None; entries.len()],
+        };
+        let query = self
+            .query
+            .as_deref()
+            .map(|query| QueryMatcher::new(query, &self.language));
+
+        let mut cards: Vec<AnkiCard> = entries
+            .iter()
+            .zip(caption_ids(&episode.id, entries))
+            .zip(translations.iter_mut())
+            .filter(|((entry, _), _)| !entry.text_clean.is_empty())
+            .filter(|((entry, _), _)| {
+                query
+                    .as_ref()
+                    .map_or(true, |q| q.matches(&entry.text_clean))
+            })
+            .map(|((entry, id), translation)| AnkiCard {
+                image: self.frames.then(|| format!("{}-{}.jpg", self.prefix, id)),
+                audio: self.audio.then(|| format!("{}-{}.mp3", self.prefix, id)),
+                id,
+                episode: episode_id.clone(),
+                start: entry.start,
+                end: entry.end,
+                text: entry.text_clean.clone(),
+                translation: translation.take(),
+            })
+            .collect();
+        cards.sort_by_key(|card| card.start);
+        cards
+    }
+
+    /// Write the deck and its media to `output_dir`, returning the cards
+    ///
+    /// `translations[i]` holds the translation subtitles of `episodes[i]`.
+    /// Media files already in the folder are kept, so re-exports only cut
+    /// what's new. A frame or clip that fails to extract is left off its card.
+    pub fn export(
+        &self,
+        episodes: &[(Episode, Vec<SubtitleEntry>)],
+        translations: &[Option<Vec<SubtitleEntry>>],
+        output_dir: &Path,
+        progress: &ProgressBar,
+    ) -> Result<Vec<AnkiCard>> {
+        let mut cards: Vec<(&Episode, AnkiCard)> = episodes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, (episode, entries))| {
+                let translation = translations.get(i).and_then(|t| t.as_deref());
+                self.cards(episode, entries, translation)
+                    .into_iter()
+                    .map(move |card| (episode, card))
+            })
+            .collect();
+
+        std::fs::create_dir_all(output_dir).map_err(|e| AnytronError::OutputDir {
+            path: output_dir.to_path_buf(),
+            source: e,
+        })?;
+
+        progress.set_length(cards.len() as u64);
+        if self.frames || self.audio {
+            self.extract_media(&mut cards, &output_dir.join(MEDIA_DIR), progress)?;
+        } else {
+            progress.inc(cards.len() as u64);
+        }
+
+        let cards: Vec<AnkiCard> = cards.into_iter().map(|(_, card)| card).collect();
+        let path = output_dir.join(DECK_FILE);
+        std::fs::write(&path, self.render_deck(&cards))
+            .map_err(|e| AnytronError::FileWrite { path, source: e })?;
+
+        Ok(cards)
+    }
+
+    /// Deck file contents: Anki's import headers, then one note per line
+    pub fn render_deck(&self, cards: &[AnkiCard]) -> String {
+        let mut deck = String::new();
+        deck.push_str("#separator:tab\n");
+        deck.push_str("#html:true\n");
+        deck.push_str(&format!("#deck:{}\n", field(&self.deck)));
+        deck.push_str(&format!("#columns:{}\n", COLUMNS.join("\t")));
+        deck.push_str("#guid column:1\n");
+        deck.push_str(&format!("#tags column:{}\n", COLUMNS.len()));
+
+        for card in cards {
+            let season = &card.episode[..card.episode.find('E').unwrap_or(card.episode.len())];
+            let row = [
+                format!("{}-{}", self.prefix, card.id),
+                card.image
+                    .as_ref()
+                    .map(|image| format!("<img src=\"{}\">", image))
+                    .unwrap_or_default(),
+                field(&card.text),
+                card.translation.as_deref().map(field).unwrap_or_default(),
+                card.audio
+                    .as_ref()
+                    .map(|audio| format!("[sound:{}]", audio))
+                    .unwrap_or_default(),
+                card.episode.clone(),
+                card.start.to_string(),
+                format!("{} {} {}", self.prefix, season, card.episode),
+            ];
+            deck.push_str(&row.join("\t"));
+            deck.push('\n');
+        }
+
+        deck
+    }
+
+    /// Extract the frame and clip of every card, in parallel
+    fn extract_media(
+        &self,
+        cards: &mut [(&Episode, AnkiCard)],
+        media_dir: &Path,
+        progress: &ProgressBar,
+    ) -> Result<()> {
+        FrameExtractor::check_ffmpeg()?;
+        std::fs::create_dir_all(media_dir).map_err(|e| AnytronError::OutputDir {
+            path: media_dir.to_path_buf(),
+            source: e,
+        })?;
+
+        let extractor = FrameExtractor::new().with_frame_width(self.frame_width);
+
+        let mut builder = rayon::ThreadPoolBuilder::new();
+        if let Some(num_jobs) = self.jobs {
+            builder = builder.num_threads(num_jobs);
+        }
+        let pool = builder
+            .build()
+            .map_err(|e| AnytronError::Ffmpeg(format!("Failed to build thread pool: {}", e)))?;
+
+        pool.install(|| {
+            cards.par_iter_mut().for_each(|(episode, card)| {
+                if let Some(image) = &card.image {
+                    let path = media_dir.join(image);
+                    let midpoint = (card.start.0 + card.end.0) / 2;
+                    let at =
+                        Timestamp(midpoint.saturating_add_signed(episode.overrides.frame_offset()));
+                    if !path.exists() {
+                        if let Err(e) =
+                            extractor.extract_single_frame(&episode.video_path, at, &path)
+                        {
+                            log::warn!("{}: {}", card.id, e);
+                            card.image = None;
+                        }
+                    }
+                }
+                if let Some(audio) = &card.audio {
+                    let path = media_dir.join(audio);
+                    let start = Timestamp(card.start.0.saturating_sub(AUDIO_PADDING_MS));
+                    let end = Timestamp(card.end.0 + AUDIO_PADDING_MS);
+                    if !path.exists() {
+                        if let Err(e) = extract_audio_clip(&episode.video_path, start, end, &path) {
+                            log::warn!("{}: {}", card.id, e);
+                            card.audio = None;
+                        }
+                    }
+                }
+                progress.inc(1);
+            })
+        });
+
+        Ok(())
+    }
+}
+
+/// Query words, analyzed like the lines they're matched against
+struct QueryMatcher<'a> {
+    language: &'a Language,
+    terms: Vec<String>,
+    phrase: String,
+}
+
+impl<'a> QueryMatcher<'a> {
+    fn new(query: &str, language: &'a Language) -> Self {
+        Self {
+            language,
+            terms: language.analyze(query),
+            phrase: query.trim().to_lowercase(),
+        }
+    }
+
+    /// Whether `text` holds every query term
+    ///
+    /// A query of nothing but stop words matches as a plain phrase.
+    fn matches(&self, text: &str) -> bool {
+        if self.terms.is_empty() {
+            return text.to_lowercase().contains(&self.phrase);
+        }
+        let words = self.language.analyze(text);
+        self.terms.iter().all(|term| words.contains(term))
+    }
+}
+
+/// Text made safe for a tab-separated HTML field
+fn field(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::discovery::{EpisodeId, SubtitleSource};
+    use std::path::PathBuf;
+
+    fn episode() -> Episode {
+        Episode {
+            id: EpisodeId::new(1, 2),
+            video_path: PathBuf::from("S01E02.mkv"),
+            subtitle_path: PathBuf::from("S01E02.srt"),
+            subtitle_source: SubtitleSource::External(PathBuf::from("S01E02.srt")),
+            overrides: Default::default(),
+            tracks: Vec::new(),
+            languages: Vec::new(),
+            framerate: None,
+            speakers: Default::default(),
+            normalize: Default::default(),
+        }
+    }
+
+    fn cue(start: u64, end: u64, text: &str) -> SubtitleEntry {
+        SubtitleEntry::new(0, Timestamp(start), Timestamp(end), text.to_string())
+    }
+
+    #[test]
+    fn test_cards_filter_and_translate() {
+        let entries = vec![
+            cue(1000, 3000, "The dogs are running."),
+            cue(4000, 5000, "Cats nap."),
+        ];
+        let translation = vec![cue(1100, 3100, "Los perros corren.")];
+
+        let exporter = AnkiExporter::new("Show", "show").with_query(Some("run dog".to_string()));
+        let cards = exporter.cards(&episode(), &entries, Some(&translation));
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].translation.as_deref(), Some("Los perros corren."));
+        assert_eq!(cards[0].image, Some(format!("show-{}.jpg", cards[0].id)));
+        assert_eq!(cards[0].audio, None);
+
+        let cards = AnkiExporter::new("Show", "show").cards(&episode(), &entries, None);
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[1].translation, None);
+    }
+
+    #[test]
+    fn test_render_deck() {
+        let exporter = AnkiExporter::new("My Show", "show")
+            .with_frames(false)
+            .with_audio(true);
+        let cards = exporter.cards(&episode(), &[cue(61_000, 62_500, "Fish & chips <3")], None);
+        let deck = exporter.render_deck(&cards);
+        let lines: Vec<&str> = deck.lines().collect();
+
+        assert_eq!(lines[2], "#deck:My Show");
+        assert_eq!(lines[3], format!("#columns:{}", COLUMNS.join("\t")));
+        assert_eq!(lines[5], "#tags column:8");
+
+        let row: Vec<&str> = lines[6].split('\t').collect();
+        assert_eq!(row.len(), COLUMNS.len());
+        assert_eq!(row[0], format!("show-{}", cards[0].id));
+        assert_eq!(row[1], "");
+        assert_eq!(row[2], "Fish &amp; chips &lt;3");
+        assert_eq!(row[4], format!("[sound:show-{}.mp3]", cards[0].id));
+        assert_eq!(row[6], "00:01:01.000");
+        assert_eq!(row[7], "show S01 S01E02");
+    }
+}
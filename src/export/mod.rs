@@ -0,0 +1,7 @@
+//! Export quotes to other tools
+//!
+//! Currently Anki decks, for studying lines from the show.
+
+mod anki;
+
+pub use anki::{AnkiCard, AnkiExporter};
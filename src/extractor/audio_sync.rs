@@ -0,0 +1,616 @@
+//! Automatic subtitle synchronisation against the audio track
+//!
+//! The episode's audio is run through ffmpeg's `silencedetect` (band-limited to
+//! speech frequencies) to get a speech activity signal at 100 ms resolution.
+//! The cue intervals are split into windows, and each window is cross-correlated
+//! against the speech signal to find the shift that best lines it up.
+//!
+//! A consistent correction makes the per-window shifts fall on a line
+//! `shift = offset + drift * t`. The line is fitted robustly (the pair of
+//! windows with the most agreeing windows wins, then a least-squares refit on
+//! those), and the share of agreeing windows is the confidence. Common
+//! framerate ratios are tried first, so that PAL/NTSC drift of several seconds
+//! per window doesn't smear the correlation.
+
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::{AnytronError, Result};
+use crate::extractor::FrameExtractor;
+use crate::subtitle::{SubtitleEntry, TimingTransform};
+
+/// Resolution of the activity signals in milliseconds
+const FRAME_MS: u64 = 100;
+
+/// Number of windows the cues are split into
+const WINDOWS: usize = 8;
+
+/// Windows with fewer cues are not used
+const MIN_WINDOW_CUES: usize = 5;
+
+/// Windows whose best correlation is below this don't vote
+const MIN_WINDOW_CORRELATION: f64 = 0.05;
+
+/// How far a window's shift may be from the fitted line and still agree
+const TOLERANCE_MS: f64 = 300.0;
+
+/// Playback speed ratios tried before fitting (film, PAL and NTSC rates)
+const SPEED_RATIOS: &[f64] = &[
+    1.0,
+    25.0 / (24000.0 / 1001.0),
+    (24000.0 / 1001.0) / 25.0,
+    25.0 / 24.0,
+    24.0 / 25.0,
+    24.0 / (24000.0 / 1001.0),
+    (24000.0 / 1001.0) / 24.0,
+];
+
+/// A timing correction estimated from the audio, as recorded in the build
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCorrection {
+    /// Milliseconds added to every cue after scaling
+    pub offset_ms: i64,
+
+    /// Timing drift in parts per million (`t' = t * (1 + drift) + offset`)
+    pub drift_ppm: i64,
+
+    /// Share of windows agreeing on the correction, in percent
+    pub confidence: u8,
+
+    /// Whether the correction was applied to the subtitles
+    pub applied: bool,
+}
+
+impl SyncCorrection {
+    /// The correction as a timing transform
+    pub fn transform(&self) -> TimingTransform {
+        TimingTransform {
+            scale: 1.0 + self.drift_ppm as f64 / 1_000_000.0,
+            offset_ms: self.offset_ms as f64,
+        }
+    }
+}
+
+/// Number of frames needed to cover `ms` milliseconds
+fn frames_in(ms: u64) -> usize {
+    ((ms + FRAME_MS - 1) / FRAME_MS) as usize
+}
+
+/// Speech activity of an audio track, one flag per 100 ms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpeechActivity {
+    frames: Vec<bool>,
+}
+
+impl SpeechActivity {
+    /// Detect speech in the first audio stream of a video with ffmpeg
+    pub fn detect(video_path: &Path) -> Result<Self> {
+        let output = Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostdin", "-nostats", "-i"])
+            .arg(video_path)
+            .args([
+                "-map",
+                "0:a:0",
+                "-vn",
+                "-af",
+                "aresample=8000,highpass=f=200,lowpass=f=3000,silencedetect=noise=-35dB:d=0.3",
+                "-f",
+                "null",
+                "-",
+            ])
+            .output()
+            .map_err(|e| AnytronError::Ffmpeg(format!("Failed to run ffmpeg: {}", e)))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            return Err(AnytronError::Ffmpeg(format!(
+                "Failed to analyse audio of {:?}: {}",
+                video_path,
+                stderr.lines().last().unwrap_or_default()
+            )));
+        }
+
+        let (silences, duration) = parse_silencedetect(&stderr);
+        let duration = duration.ok_or_else(|| {
+            AnytronError::Ffmpeg(format!("No audio duration reported for {:?}", video_path))
+        })?;
+        Ok(Self::from_silences(&silences, duration))
+    }
+
+    /// Build the signal from silent intervals of a track `duration_ms` long
+    ///
+    /// A silence without an end runs to the end of the track.
+    pub fn from_silences(silences: &[(u64, Option<u64>)], duration_ms: u64) -> Self {
+        let mut frames = vec![true; frames_in(duration_ms)];
+        for &(start, end) in silences {
+            let first = (start / FRAME_MS) as usize;
+            let last = (frames_in(end.unwrap_or(duration_ms))).min(frames.len());
+            for frame in frames.iter_mut().take(last).skip(first) {
+                *frame = false;
+            }
+        }
+        Self { frames }
+    }
+
+    /// Build the signal from speech intervals of a track `duration_ms` long
+    pub fn from_speech(speech: &[(u64, u64)], duration_ms: u64) -> Self {
+        let mut frames = vec![false; frames_in(duration_ms)];
+        for &(start, end) in speech {
+            let first = (start / FRAME_MS) as usize;
+            let last = (frames_in(end)).min(frames.len());
+            for frame in frames.iter_mut().take(last).skip(first) {
+                *frame = true;
+            }
+        }
+        Self { frames }
+    }
+
+    /// Number of speech frames before each frame index
+    fn prefix_sums(&self) -> Vec<u32> {
+        let mut sums = Vec::with_capacity(self.frames.len() + 1);
+        let mut total = 0;
+        sums.push(0);
+        for &speech in &self.frames {
+            total += speech as u32;
+            sums.push(total);
+        }
+        sums
+    }
+}
+
+/// Estimates timing corrections from speech activity
+#[derive(Debug, Clone)]
+pub struct AudioSync {
+    /// Corrections below this confidence (percent) are not applied
+    min_confidence: u8,
+
+    /// Largest shift searched for in each window
+    max_shift_ms: u64,
+
+    /// Number of parallel jobs (None = use rayon default)
+    jobs: Option<usize>,
+}
+
+impl Default for AudioSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioSync {
+    /// Create an estimator with the default thresholds
+    pub fn new() -> Self {
+        Self {
+            min_confidence: 75,
+            max_shift_ms: 60_000,
+            jobs: None,
+        }
+    }
+
+    /// Set the confidence (percent) a correction needs to be applied
+    pub fn with_min_confidence(mut self, min_confidence: u8) -> Self {
+        self.min_confidence = min_confidence.min(100);
+        self
+    }
+
+    /// Set the largest shift searched for in each window
+    pub fn with_max_shift(mut self, max_shift_ms: u64) -> Self {
+        self.max_shift_ms = max_shift_ms;
+        self
+    }
+
+    /// Set number of parallel jobs
+    pub fn with_jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Whether a correction with this confidence is applied
+    pub fn accepts(&self, confidence: u8) -> bool {
+        confidence >= self.min_confidence
+    }
+
+    /// Analyse several videos, running up to `jobs` ffmpeg processes at once
+    ///
+    /// Returns one result per video, in order; a failed analysis doesn't stop
+    /// the others.
+    pub fn analyse_all(
+        &self,
+        videos: &[(&Path, &[SubtitleEntry])],
+        progress: &ProgressBar,
+    ) -> Result<Vec<Result<SyncCorrection>>> {
+        FrameExtractor::check_ffmpeg()?;
+
+        let mut builder = rayon::ThreadPoolBuilder::new();
+        if let Some(num_jobs) = self.jobs {
+            builder = builder.num_threads(num_jobs);
+        }
+        let pool = builder
+            .build()
+            .map_err(|e| AnytronError::Ffmpeg(format!("Failed to build thread pool: {}", e)))?;
+
+        Ok(pool.install(|| {
+            videos
+                .par_iter()
+                .map(|(video, entries)| {
+                    let result = self.analyse(video, entries);
+                    progress.inc(1);
+                    result
+                })
+                .collect()
+        }))
+    }
+
+    /// Analyse a video's audio and estimate a correction for its cues
+    pub fn analyse(&self, video_path: &Path, entries: &[SubtitleEntry]) -> Result<SyncCorrection> {
+        let speech = SpeechActivity::detect(video_path)?;
+        Ok(self.estimate(entries, &speech))
+    }
+
+    /// Estimate the correction lining `entries` up with `speech`
+    ///
+    /// `applied` is set when the confidence reaches the threshold.
+    pub fn estimate(&self, entries: &[SubtitleEntry], speech: &SpeechActivity) -> SyncCorrection {
+        let prefix = speech.prefix_sums();
+        let max_lag = (self.max_shift_ms / FRAME_MS) as i64;
+
+        let mut best: Option<(Fit, f64)> = None;
+        for &speed in SPEED_RATIOS {
+            let cues: Vec<(f64, f64)> = entries
+                .iter()
+                .map(|e| (e.start.0 as f64 * speed, e.end.0 as f64 * speed))
+                .collect();
+            let windows = window_shifts(&cues, &prefix, max_lag);
+            let Some(fit) = fit_line(&windows) else {
+                continue;
+            };
+            if best.as_ref().map_or(true, |(b, _)| fit.better_than(b)) {
+                best = Some((fit, speed));
+            }
+        }
+
+        let Some((fit, speed)) = best else {
+            return SyncCorrection {
+                offset_ms: 0,
+                drift_ppm: 0,
+                confidence: 0,
+                applied: false,
+            };
+        };
+
+        // t' = (t * speed) * (1 + slope) + intercept
+        let scale = speed * (1.0 + fit.slope);
+        let confidence = (100.0 * fit.inliers as f64 / fit.windows as f64).round() as u8;
+        SyncCorrection {
+            offset_ms: fit.intercept.round() as i64,
+            drift_ppm: ((scale - 1.0) * 1_000_000.0).round() as i64,
+            confidence,
+            applied: self.accepts(confidence),
+        }
+    }
+}
+
+/// Best shift of one window of cues
+#[derive(Debug, Clone, Copy)]
+struct WindowShift {
+    /// Middle of the window in (scaled) subtitle time
+    center_ms: f64,
+
+    /// Shift lining the window up with the speech
+    shift_ms: f64,
+
+    /// Correlation at that shift
+    correlation: f64,
+}
+
+/// Estimate the shift of each window of cues
+///
+/// Returns one entry per window with enough cues; windows whose correlation is
+/// too weak are `None` but still count towards the confidence.
+fn window_shifts(cues: &[(f64, f64)], prefix: &[u32], max_lag: i64) -> Vec<Option<WindowShift>> {
+    let mut sorted: Vec<(f64, f64)> = cues.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let (Some(first), Some(last)) = (sorted.first(), sorted.last()) else {
+        return Vec::new();
+    };
+    let span_start = first.0;
+    let span = (last.1 - span_start).max(1.0);
+
+    (0..WINDOWS)
+        .filter_map(|w| {
+            let from = span_start + span * w as f64 / WINDOWS as f64;
+            let to = span_start + span * (w + 1) as f64 / WINDOWS as f64;
+            let window: Vec<(i64, i64)> = sorted
+                .iter()
+                .filter(|(start, _)| *start >= from && (*start < to || w == WINDOWS - 1))
+                .map(|&(start, end)| {
+                    let first = (start / FRAME_MS as f64).round() as i64;
+                    let last = (end / FRAME_MS as f64).round() as i64;
+                    (first, last.max(first + 1))
+                })
+                .collect();
+            if window.len() < MIN_WINDOW_CUES {
+                return None;
+            }
+
+            let a = (from / FRAME_MS as f64).floor() as i64;
+            let b = (to / FRAME_MS as f64).ceil() as i64;
+            let best = best_lag(&window, a, b, prefix, max_lag);
+            Some(
+                best.filter(|(_, r)| *r >= MIN_WINDOW_CORRELATION)
+                    .map(|(lag, correlation)| WindowShift {
+                        center_ms: (from + to) / 2.0,
+                        shift_ms: (lag * FRAME_MS as i64) as f64,
+                        correlation,
+                    }),
+            )
+        })
+        .collect()
+}
+
+/// Lag with the highest Pearson correlation between the cue frames of a
+/// window `[a, b)` and the speech frames
+fn best_lag(
+    cues: &[(i64, i64)],
+    a: i64,
+    b: i64,
+    prefix: &[u32],
+    max_lag: i64,
+) -> Option<(i64, f64)> {
+    let len = prefix.len() as i64 - 1;
+    let speech_between = |from: i64, to: i64| -> f64 {
+        let from = from.clamp(0, len) as usize;
+        let to = to.clamp(0, len) as usize;
+        (prefix[to] - prefix[from.min(to)]) as f64
+    };
+
+    let n = (b - a) as f64;
+    let cue_frames: f64 = cues
+        .iter()
+        .map(|&(s, e)| (e.min(b) - s.max(a)).max(0) as f64)
+        .sum();
+    let cue_var = n * cue_frames - cue_frames * cue_frames;
+    if cue_var <= 0.0 {
+        return None;
+    }
+
+    let mut best: Option<(i64, f64)> = None;
+    for lag in -max_lag..=max_lag {
+        let speech = speech_between(a + lag, b + lag);
+        let speech_var = n * speech - speech * speech;
+        if speech_var <= 0.0 {
+            continue;
+        }
+        let both: f64 = cues
+            .iter()
+            .map(|&(s, e)| speech_between(s.max(a) + lag, e.min(b) + lag))
+            .sum();
+        let r = (n * both - cue_frames * speech) / (cue_var * speech_var).sqrt();
+        if best.map_or(true, |(_, best_r)| r > best_r) {
+            best = Some((lag, r));
+        }
+    }
+    best
+}
+
+/// A line through the window shifts
+#[derive(Debug, Clone, Copy)]
+struct Fit {
+    intercept: f64,
+    slope: f64,
+
+    /// Windows within tolerance of the line
+    inliers: usize,
+
+    /// Windows with enough cues to be considered
+    windows: usize,
+
+    /// Mean correlation of the inliers
+    correlation: f64,
+}
+
+impl Fit {
+    fn better_than(&self, other: &Fit) -> bool {
+        (self.inliers, self.correlation) > (other.inliers, other.correlation)
+    }
+}
+
+/// Fit `shift = intercept + slope * t` to the windows that agree the most
+fn fit_line(windows: &[Option<WindowShift>]) -> Option<Fit> {
+    let votes: Vec<&WindowShift> = windows.iter().flatten().collect();
+    let inliers_of = |intercept: f64, slope: f64| -> Vec<&WindowShift> {
+        votes
+            .iter()
+            .copied()
+            .filter(|w| (w.shift_ms - (intercept + slope * w.center_ms)).abs() <= TOLERANCE_MS)
+            .collect()
+    };
+
+    // Candidate lines through single windows (pure offsets) and window pairs
+    let mut candidates: Vec<(f64, f64)> = votes.iter().map(|w| (w.shift_ms, 0.0)).collect();
+    for (i, p) in votes.iter().enumerate() {
+        for q in &votes[i + 1..] {
+            if (q.center_ms - p.center_ms).abs() > f64::EPSILON {
+                let slope = (q.shift_ms - p.shift_ms) / (q.center_ms - p.center_ms);
+                candidates.push((p.shift_ms - slope * p.center_ms, slope));
+            }
+        }
+    }
+
+    let (intercept, slope) = candidates
+        .into_iter()
+        .max_by_key(|&(intercept, slope)| inliers_of(intercept, slope).len())?;
+    let inliers = inliers_of(intercept, slope);
+
+    // Least-squares refit on the agreeing windows
+    let (intercept, slope) = if inliers.len() >= 2 {
+        let count = inliers.len() as f64;
+        let mean_t = inliers.iter().map(|w| w.center_ms).sum::<f64>() / count;
+        let mean_s = inliers.iter().map(|w| w.shift_ms).sum::<f64>() / count;
+        let var_t: f64 = inliers.iter().map(|w| (w.center_ms - mean_t).powi(2)).sum();
+        let cov: f64 = inliers
+            .iter()
+            .map(|w| (w.center_ms - mean_t) * (w.shift_ms - mean_s))
+            .sum();
+        let slope = if var_t > 0.0 { cov / var_t } else { 0.0 };
+        (mean_s - slope * mean_t, slope)
+    } else {
+        (intercept, slope)
+    };
+
+    let correlation =
+        inliers.iter().map(|w| w.correlation).sum::<f64>() / inliers.len().max(1) as f64;
+    Some(Fit {
+        intercept,
+        slope,
+        inliers: inliers.len(),
+        windows: windows.len(),
+        correlation,
+    })
+}
+
+/// Parse `silencedetect` output into silent intervals and the input duration
+pub fn parse_silencedetect(stderr: &str) -> (Vec<(u64, Option<u64>)>, Option<u64>) {
+    let seconds_to_ms = |s: &str| -> Option<u64> {
+        let secs: f64 = s.trim().parse().ok()?;
+        Some((secs.max(0.0) * 1000.0).round() as u64)
+    };
+
+    let mut silences: Vec<(u64, Option<u64>)> = Vec::new();
+    let mut duration = None;
+    for line in stderr.lines() {
+        if let Some((_, rest)) = line.split_once("silence_start:") {
+            if let Some(start) = seconds_to_ms(rest) {
+                silences.push((start, None));
+            }
+        } else if let Some((_, rest)) = line.split_once("silence_end:") {
+            let end = rest.split('|').next().and_then(seconds_to_ms);
+            if let (Some(last), Some(end)) = (silences.last_mut(), end) {
+                last.1 = Some(end);
+            }
+        } else if let Some((_, rest)) = line.split_once("Duration:") {
+            duration = duration.or_else(|| parse_duration(rest.split(',').next()?));
+        }
+    }
+    (silences, duration)
+}
+
+/// Parse an ffmpeg `HH:MM:SS.cc` duration
+fn parse_duration(s: &str) -> Option<u64> {
+    let mut parts = s.trim().split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3_600_000 + minutes * 60_000 + (seconds * 1000.0).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle::Timestamp;
+
+    /// Irregular cues spread over 40 minutes, and speech exactly under them
+    fn cues() -> Vec<SubtitleEntry> {
+        let mut entries = Vec::new();
+        let mut t = 5_000;
+        for i in 0..600u64 {
+            let length = 900 + (i * 7919) % 2500;
+            let gap = 300 + (i * 104_729) % 3100;
+            entries.push(SubtitleEntry::new(
+                i as usize,
+                Timestamp(t),
+                Timestamp(t + length),
+                format!("Line {}", i),
+            ));
+            t += length + gap;
+        }
+        entries
+    }
+
+    fn speech_for(entries: &[SubtitleEntry], transform: &TimingTransform) -> SpeechActivity {
+        let speech: Vec<(u64, u64)> = entries
+            .iter()
+            .map(|e| (transform.apply(e.start).0, transform.apply(e.end).0))
+            .collect();
+        let end = speech.last().unwrap().1 + 10_000;
+        SpeechActivity::from_speech(&speech, end)
+    }
+
+    #[test]
+    fn test_estimates_constant_offset() {
+        let entries = cues();
+        let speech = speech_for(&entries, &TimingTransform::offset(2300));
+
+        let correction = AudioSync::new().estimate(&entries, &speech);
+        assert!(correction.applied);
+        assert!(correction.confidence >= 90);
+        assert!(
+            (correction.offset_ms - 2300).abs() <= 100,
+            "{:?}",
+            correction
+        );
+        assert!(correction.drift_ppm.abs() < 500, "{:?}", correction);
+    }
+
+    #[test]
+    fn test_estimates_pal_drift() {
+        let entries = cues();
+        let pal = TimingTransform::framerate(25.0, 24000.0 / 1001.0)
+            .unwrap()
+            .then(TimingTransform::offset(-1500));
+        let speech = speech_for(&entries, &pal);
+
+        let correction = AudioSync::new().estimate(&entries, &speech);
+        assert!(correction.applied, "{:?}", correction);
+
+        let transform = correction.transform();
+        for entry in [&entries[0], &entries[300], &entries[599]] {
+            let expected = pal.apply(entry.start).0 as i64;
+            let actual = transform.apply(entry.start).0 as i64;
+            assert!(
+                (expected - actual).abs() <= 200,
+                "{} vs {}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_refuses_unrelated_audio() {
+        let entries = cues();
+        // Regular on/off speech that has nothing to do with the cues
+        let speech: Vec<(u64, u64)> = (0..1500u64).map(|i| (i * 1700, i * 1700 + 800)).collect();
+        let speech = SpeechActivity::from_speech(&speech, 1500 * 1700);
+
+        let correction = AudioSync::new().estimate(&entries, &speech);
+        assert!(!correction.applied, "{:?}", correction);
+    }
+
+    #[test]
+    fn test_parse_silencedetect() {
+        let stderr = "\
+  Duration: 00:42:10.12, start: 0.000000, bitrate: 1234 kb/s
+[silencedetect @ 0x5581] silence_start: 0
+[silencedetect @ 0x5581] silence_end: 1.504 | silence_duration: 1.504
+[silencedetect @ 0x5581] silence_start: 12.25
+[silencedetect @ 0x5581] silence_end: 13.5 | silence_duration: 1.25
+[silencedetect @ 0x5581] silence_start: 2529.9
+";
+        let (silences, duration) = parse_silencedetect(stderr);
+        assert_eq!(duration, Some(2_530_120));
+        assert_eq!(
+            silences,
+            vec![(0, Some(1504)), (12_250, Some(13_500)), (2_529_900, None)]
+        );
+
+        let activity = SpeechActivity::from_silences(&silences, 2_530_120);
+        assert!(!activity.frames[0]);
+        assert!(activity.frames[20]);
+        assert!(!activity.frames[125]);
+        assert!(!activity.frames[25_300]);
+    }
+}
@@ -0,0 +1,52 @@
+//! Audio clips of single lines, for flashcards
+
+use std::path::Path;
+use std::process::Command;
+
+use crate::error::{AnytronError, Result};
+use crate::subtitle::Timestamp;
+
+/// Cut the audio between `start` and `end` from a video into an MP3 file
+///
+/// Only the first audio stream is used, downmixed to mono.
+pub fn extract_audio_clip(
+    video_path: &Path,
+    start: Timestamp,
+    end: Timestamp,
+    output_path: &Path,
+) -> Result<()> {
+    let duration = Timestamp(end.0.saturating_sub(start.0));
+    let output = Command::new("ffmpeg")
+        .args(["-hide_banner", "-nostdin", "-loglevel", "error", "-ss"])
+        .arg(start.to_ffmpeg())
+        .arg("-i")
+        .arg(video_path)
+        .arg("-t")
+        .arg(duration.to_ffmpeg())
+        .args([
+            "-map",
+            "0:a:0",
+            "-vn",
+            "-ac",
+            "1",
+            "-c:a",
+            "libmp3lame",
+            "-q:a",
+            "5",
+            "-y",
+        ])
+        .arg(output_path)
+        .output()
+        .map_err(|e| AnytronError::Ffmpeg(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(AnytronError::Ffmpeg(format!(
+            "Failed to cut audio at {}ms from {:?}: {}",
+            start.0,
+            video_path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
@@ -1,22 +1,38 @@
 //! FFmpeg wrapper for frame extraction
+//!
+//! Each episode is decoded exactly once. A `select` filter keeps the first
+//! frame at or after every requested midpoint, and a `split` feeds both the
+//! full-size and the scaled thumbnail outputs from that single pass.
 
 use indicatif::ProgressBar;
 use rayon::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use crate::discovery::Episode;
 use crate::error::{AnytronError, Result};
-use crate::subtitle::{SubtitleEntry, Timestamp};
+use crate::subtitle::{parse_framerate, SubtitleEntry, Timestamp};
 
 /// Frame extractor using FFmpeg
 pub struct FrameExtractor {
     /// JPEG quality (1-100)
     quality: u8,
 
+    /// Full-size frame width in pixels (0 = original)
+    frame_width: u32,
+
     /// Thumbnail width in pixels
     thumb_width: u32,
 
+    /// Thumbnail JPEG quality (1-100)
+    thumb_quality: u8,
+
+    /// Minimum spacing between decoded frames in milliseconds
+    interval_ms: u64,
+
     /// Number of parallel jobs (None = use rayon default)
     jobs: Option<usize>,
 }
@@ -32,7 +48,10 @@ impl FrameExtractor {
     pub fn new() -> Self {
         Self {
             quality: 85,
+            frame_width: 0,
             thumb_width: 320,
+            thumb_quality: 70,
+            interval_ms: 0,
             jobs: None,
         }
     }
@@ -43,12 +62,33 @@ impl FrameExtractor {
         self
     }
 
+    /// Set full-size frame width (0 keeps the original resolution)
+    pub fn with_frame_width(mut self, width: u32) -> Self {
+        self.frame_width = width;
+        self
+    }
+
     /// Set thumbnail width
     pub fn with_thumb_width(mut self, width: u32) -> Self {
         self.thumb_width = width;
         self
     }
 
+    /// Set thumbnail JPEG quality
+    pub fn with_thumb_quality(mut self, quality: u8) -> Self {
+        self.thumb_quality = quality.clamp(1, 100);
+        self
+    }
+
+    /// Set the minimum spacing between decoded frames
+    ///
+    /// Cues whose midpoints fall within `interval_ms` of the previously decoded
+    /// frame reuse that frame instead of decoding a new one.
+    pub fn with_interval(mut self, interval_ms: u64) -> Self {
+        self.interval_ms = interval_ms;
+        self
+    }
+
     /// Set number of parallel jobs
     pub fn with_jobs(mut self, jobs: Option<usize>) -> Self {
         self.jobs = jobs;
@@ -69,7 +109,66 @@ impl FrameExtractor {
         Ok(())
     }
 
+    /// Framerate of a video's first video stream, if ffprobe reports one
+    ///
+    /// The average rate is preferred; variable-rate streams may only have a
+    /// base rate.
+    pub fn probe_framerate(video_path: &Path) -> Result<Option<f64>> {
+        let output = Command::new("ffprobe")
+            .args([
+                "-v",
+                "quiet",
+                "-print_format",
+                "json",
+                "-select_streams",
+                "v:0",
+                "-show_entries",
+                "stream=avg_frame_rate,r_frame_rate",
+            ])
+            .arg(video_path)
+            .output()
+            .map_err(|e| AnytronError::Ffmpeg(format!("Failed to run ffprobe: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(AnytronError::Ffmpeg(format!(
+                "ffprobe failed on {:?}: {}",
+                video_path,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        parse_probe_framerate(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Extract frames for every episode, running up to `jobs` episodes at once
+    pub fn extract_all(
+        &self,
+        episodes: &[(Episode, Vec<SubtitleEntry>)],
+        output_dir: &Path,
+        progress: &ProgressBar,
+    ) -> Result<()> {
+        Self::check_ffmpeg()?;
+
+        let mut builder = rayon::ThreadPoolBuilder::new();
+        if let Some(num_jobs) = self.jobs {
+            builder = builder.num_threads(num_jobs);
+        }
+        let pool = builder
+            .build()
+            .map_err(|e| AnytronError::Ffmpeg(format!("Failed to build thread pool: {}", e)))?;
+
+        pool.install(|| {
+            episodes.par_iter().try_for_each(|(episode, entries)| {
+                self.extract_frames(episode, entries, output_dir, progress)
+            })
+        })
+    }
+
     /// Extract frames for all subtitle entries in an episode
+    ///
+    /// Only midpoints that are missing a frame or a thumbnail are decoded, and
+    /// all of them are produced by a single ffmpeg process. The episode's
+    /// `frame_offset_ms` override moves where each frame is grabbed.
     pub fn extract_frames(
         &self,
         episode: &Episode,
@@ -77,59 +176,85 @@ impl FrameExtractor {
         output_dir: &Path,
         progress: &ProgressBar,
     ) -> Result<()> {
-        Self::check_ffmpeg()?;
-
         let episode_id = episode.id.to_string();
 
         let frames_dir = output_dir.join("img").join("frames").join(&episode_id);
         let thumbs_dir = output_dir.join("img").join("thumbs").join(&episode_id);
 
-        std::fs::create_dir_all(&frames_dir).map_err(|e| AnytronError::OutputDir {
-            path: frames_dir.clone(),
-            source: e,
-        })?;
-        std::fs::create_dir_all(&thumbs_dir).map_err(|e| AnytronError::OutputDir {
-            path: thumbs_dir.clone(),
-            source: e,
-        })?;
+        for dir in [&frames_dir, &thumbs_dir] {
+            std::fs::create_dir_all(dir).map_err(|e| AnytronError::OutputDir {
+                path: dir.clone(),
+                source: e,
+            })?;
+        }
 
-        let tasks: Vec<ExtractionTask> = entries
+        // Midpoints still missing an image, with the number of entries sharing each
+        let mut pending: BTreeMap<u64, u64> = BTreeMap::new();
+        for entry in entries {
+            let timestamp = entry.midpoint().0;
+            let frame_name = format!("{}.jpg", timestamp);
+            if frames_dir.join(&frame_name).exists() && thumbs_dir.join(&frame_name).exists() {
+                progress.inc(1);
+            } else {
+                *pending.entry(timestamp).or_insert(0) += 1;
+            }
+        }
+
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let staging_dir = output_dir.join("img").join(".staging").join(&episode_id);
+        let targets: Vec<u64> = pending.keys().copied().collect();
+        let (samples, target_samples) = sample_targets(&targets, self.interval_ms);
+
+        // Frames are named after the cue midpoint but may be grabbed elsewhere
+        let offset = episode.overrides.frame_offset();
+        let samples: Vec<u64> = samples
             .iter()
-            .map(|entry| {
-                let timestamp = entry.midpoint();
-                let frame_name = format!("{}.jpg", timestamp.0);
-                ExtractionTask {
-                    video_path: episode.video_path.clone(),
-                    timestamp,
-                    frame_path: frames_dir.join(&frame_name),
-                    thumb_path: thumbs_dir.join(&frame_name),
-                    quality: self.quality,
-                    thumb_width: self.thumb_width,
-                }
-            })
+            .map(|ms| ms.saturating_add_signed(offset))
             .collect();
 
-        if let Some(num_jobs) = self.jobs {
-            rayon::ThreadPoolBuilder::new()
-                .num_threads(num_jobs)
-                .build_global()
-                .ok();
-        }
+        let pass = FramePass {
+            video_path: &episode.video_path,
+            targets: &samples,
+            staging_dir: staging_dir.clone(),
+            qscale: quality_to_qscale(self.quality),
+            thumb_qscale: quality_to_qscale(self.thumb_quality),
+            frame_width: self.frame_width,
+            thumb_width: self.thumb_width,
+        };
 
-        let results: Vec<Result<()>> = tasks
-            .par_iter()
-            .map(|task| {
-                let result = task.execute();
-                progress.inc(1);
-                result
+        let mut reached = 0;
+        let result = pass
+            .run(|frame_time| {
+                // Every target whose sample is at or before this frame is done
+                while reached < targets.len()
+                    && frame_time.is_at_or_after(samples[target_samples[reached]])
+                {
+                    progress.inc(pending[&targets[reached]]);
+                    reached += 1;
+                }
             })
-            .collect();
+            .and_then(|frame_times| {
+                let sample_frames = assign_frames(&samples, &frame_times);
+                let assignments: Vec<Option<usize>> = target_samples
+                    .iter()
+                    .map(|&sample| sample_frames[sample])
+                    .collect();
+                pass.publish(&targets, &assignments, &frames_dir, &thumbs_dir)
+            });
 
-        for result in results {
-            result?;
+        // Targets past the last decoded frame never advanced the progress bar
+        for target in &targets[reached..] {
+            progress.inc(pending[target]);
         }
 
-        Ok(())
+        if let Err(e) = std::fs::remove_dir_all(&staging_dir) {
+            log::debug!("Failed to remove staging dir {:?}: {}", staging_dir, e);
+        }
+
+        result
     }
 
     /// Extract a single frame at a specific timestamp
@@ -139,146 +264,325 @@ impl FrameExtractor {
         timestamp: Timestamp,
         output_path: &Path,
     ) -> Result<()> {
-        let task = ExtractionTask {
-            video_path: video_path.to_path_buf(),
-            timestamp,
-            frame_path: output_path.to_path_buf(),
-            thumb_path: PathBuf::new(), // No thumbnail
-            quality: self.quality,
-            thumb_width: 0,
-        };
+        let output = Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostdin", "-loglevel", "error", "-ss"])
+            .arg(timestamp.to_ffmpeg())
+            .arg("-i")
+            .arg(video_path)
+            .args(["-frames:v", "1"])
+            .args(if self.frame_width > 0 {
+                vec!["-vf".to_string(), format!("scale={}:-1", self.frame_width)]
+            } else {
+                Vec::new()
+            })
+            .arg("-q:v")
+            .arg(quality_to_qscale(self.quality).to_string())
+            .arg("-y")
+            .arg(output_path)
+            .output()
+            .map_err(|e| AnytronError::Ffmpeg(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(AnytronError::FrameExtraction {
+                video: video_path.to_path_buf(),
+                timestamp: timestamp.0,
+                message: stderr.to_string(),
+            });
+        }
 
-        task.execute_frame_only()
+        Ok(())
     }
 }
 
-/// A single frame extraction task
-struct ExtractionTask {
-    video_path: PathBuf,
-    timestamp: Timestamp,
-    frame_path: PathBuf,
-    thumb_path: PathBuf,
-    quality: u8,
-    thumb_width: u32,
+#[derive(Debug, Deserialize)]
+struct FramerateProbe {
+    #[serde(default)]
+    streams: Vec<FramerateStream>,
 }
 
-impl ExtractionTask {
-    /// Execute the extraction task (frame + thumbnail)
-    fn execute(&self) -> Result<()> {
-        // Skip if both already exist
-        if self.frame_path.exists() && self.thumb_path.exists() {
-            return Ok(());
-        }
+#[derive(Debug, Deserialize)]
+struct FramerateStream {
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+}
 
-        let seek_time = self.timestamp.to_ffmpeg();
-
-        // Extract full frame if needed
-        if !self.frame_path.exists() {
-            let output = Command::new("ffmpeg")
-                .args([
-                    "-hide_banner",
-                    "-loglevel",
-                    "error",
-                    "-ss",
-                    &seek_time,
-                    "-i",
-                ])
-                .arg(&self.video_path)
-                .args(["-frames:v", "1", "-q:v"])
-                .arg(self.quality_to_qscale().to_string())
-                .arg("-y")
-                .arg(&self.frame_path)
-                .output()
-                .map_err(|e| AnytronError::Ffmpeg(e.to_string()))?;
-
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                return Err(AnytronError::FrameExtraction {
-                    video: self.video_path.clone(),
-                    timestamp: self.timestamp.0,
-                    message: stderr.to_string(),
-                });
-            }
-        }
+/// Framerate from `ffprobe -show_entries stream=avg_frame_rate,r_frame_rate`
+/// JSON; `0/0` means unknown
+fn parse_probe_framerate(json: &str) -> Result<Option<f64>> {
+    let probe: FramerateProbe = serde_json::from_str(json)
+        .map_err(|e| AnytronError::Ffmpeg(format!("Failed to parse ffprobe output: {}", e)))?;
+    Ok(probe.streams.first().and_then(|stream| {
+        [&stream.avg_frame_rate, &stream.r_frame_rate]
+            .into_iter()
+            .flatten()
+            .find_map(|rate| parse_framerate(rate).ok())
+    }))
+}
 
-        // Extract thumbnail if needed
-        if self.thumb_width > 0
-            && !self.thumb_path.as_os_str().is_empty()
-            && !self.thumb_path.exists()
-        {
-            let output = Command::new("ffmpeg")
-                .args([
-                    "-hide_banner",
-                    "-loglevel",
-                    "error",
-                    "-ss",
-                    &seek_time,
-                    "-i",
-                ])
-                .arg(&self.video_path)
-                .args(["-frames:v", "1", "-vf"])
-                .arg(format!("scale={}:-1", self.thumb_width))
-                .arg("-q:v")
-                .arg((self.quality_to_qscale() + 2).min(31).to_string())
-                .arg("-y")
-                .arg(&self.thumb_path)
-                .output()
-                .map_err(|e| AnytronError::Ffmpeg(e.to_string()))?;
-
-            if !output.status.success() {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                return Err(AnytronError::FrameExtraction {
-                    video: self.video_path.clone(),
-                    timestamp: self.timestamp.0,
-                    message: format!("thumbnail: {}", stderr),
-                });
-            }
-        }
+/// Presentation time of a decoded frame, as a rational number of seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FrameTime {
+    pts: i64,
+    tb_num: i64,
+    tb_den: i64,
+}
 
-        Ok(())
+impl FrameTime {
+    /// Whether this frame is shown at or after `ms` milliseconds
+    fn is_at_or_after(&self, ms: u64) -> bool {
+        (self.pts as i128) * (self.tb_num as i128) * 1000 >= (ms as i128) * (self.tb_den as i128)
     }
+}
 
-    /// Execute extraction for frame only (no thumbnail)
-    fn execute_frame_only(&self) -> Result<()> {
-        let seek_time = self.timestamp.to_ffmpeg();
+/// A single decoding pass over one video that emits every requested frame
+struct FramePass<'a> {
+    video_path: &'a Path,
+    targets: &'a [u64],
+    staging_dir: PathBuf,
+    qscale: u8,
+    thumb_qscale: u8,
+    frame_width: u32,
+    thumb_width: u32,
+}
 
-        let output = Command::new("ffmpeg")
-            .arg("-hide_banner")
-            .arg("-loglevel")
-            .arg("error")
-            .arg("-ss")
-            .arg(&seek_time)
+impl FramePass<'_> {
+    /// Build the filtergraph selecting the first frame at or after each target
+    fn filter_graph(&self) -> String {
+        let select = self
+            .targets
+            .iter()
+            .map(|ms| {
+                let secs = format!("{}.{:03}", ms / 1000, ms % 1000);
+                format!("gte(t,{0})*not(gte(prev_t,{0}))", secs)
+            })
+            .collect::<Vec<_>>()
+            .join("+");
+
+        let full = if self.frame_width > 0 {
+            format!("split=2[fs][th];[fs]scale={}:-1[full]", self.frame_width)
+        } else {
+            "split=2[full][th]".to_string()
+        };
+
+        format!(
+            "[0:v]select='gt({},0)',showinfo,{};[th]scale={}:-1[thumb]",
+            select, full, self.thumb_width
+        )
+    }
+
+    /// Run ffmpeg, calling `on_frame` as each selected frame is decoded
+    ///
+    /// Returns the presentation times of the selected frames, in output order.
+    /// Frame `n` (0-based) is written to the staging dirs as `n + 1`.jpg.
+    fn run(&self, mut on_frame: impl FnMut(FrameTime)) -> Result<Vec<FrameTime>> {
+        let frames_staging = self.staging_dir.join("frames");
+        let thumbs_staging = self.staging_dir.join("thumbs");
+        for dir in [&frames_staging, &thumbs_staging] {
+            std::fs::create_dir_all(dir).map_err(|e| AnytronError::OutputDir {
+                path: dir.clone(),
+                source: e,
+            })?;
+        }
+
+        // The select expression grows with the number of cues, so it goes in a
+        // script file rather than on the command line
+        let script_path = self.staging_dir.join("filter.txt");
+        std::fs::write(&script_path, self.filter_graph()).map_err(|e| AnytronError::FileWrite {
+            path: script_path.clone(),
+            source: e,
+        })?;
+
+        // `-fps_mode` needs ffmpeg 5.1 or newer
+        let mut child = Command::new("ffmpeg")
+            .args(["-hide_banner", "-nostdin", "-nostats", "-loglevel", "info"])
             .arg("-i")
-            .arg(&self.video_path)
-            .arg("-frames:v")
-            .arg("1")
-            .arg("-q:v")
-            .arg(self.quality_to_qscale().to_string())
+            .arg(self.video_path)
+            .arg("-filter_complex_script")
+            .arg(&script_path)
+            .args(["-map", "[full]", "-fps_mode", "passthrough", "-q:v"])
+            .arg(self.qscale.to_string())
+            .arg(frames_staging.join("%d.jpg"))
+            .args(["-map", "[thumb]", "-fps_mode", "passthrough", "-q:v"])
+            .arg(self.thumb_qscale.to_string())
+            .arg(thumbs_staging.join("%d.jpg"))
             .arg("-y")
-            .arg(&self.frame_path)
-            .output()
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
             .map_err(|e| AnytronError::Ffmpeg(e.to_string()))?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut frame_times = Vec::with_capacity(self.targets.len());
+        let mut time_base: Option<(i64, i64)> = None;
+        let mut log_tail: Vec<String> = Vec::new();
+
+        if let Some(stderr) = child.stderr.take() {
+            let mut reader = BufReader::new(stderr);
+            let mut buf = Vec::new();
+            while reader.read_until(b'\n', &mut buf).unwrap_or(0) > 0 {
+                let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+                buf.clear();
+
+                if !line.contains("Parsed_showinfo") {
+                    log_tail.push(line);
+                    if log_tail.len() > 20 {
+                        log_tail.remove(0);
+                    }
+                    continue;
+                }
+
+                if let Some(tb) = parse_time_base(&line) {
+                    time_base = Some(tb);
+                } else if let Some(frame_time) = parse_frame_time(&line, time_base) {
+                    on_frame(frame_time);
+                    frame_times.push(frame_time);
+                }
+            }
+        }
+
+        let status = child
+            .wait()
+            .map_err(|e| AnytronError::Ffmpeg(e.to_string()))?;
+
+        if !status.success() {
             return Err(AnytronError::FrameExtraction {
-                video: self.video_path.clone(),
-                timestamp: self.timestamp.0,
-                message: stderr.to_string(),
+                video: self.video_path.to_path_buf(),
+                timestamp: self.targets.first().copied().unwrap_or(0),
+                message: log_tail.join("\n"),
             });
         }
 
+        Ok(frame_times)
+    }
+
+    /// Move staged frames into place under their target names
+    ///
+    /// `assignments[i]` is the staged frame index for `targets[i]`.
+    fn publish(
+        &self,
+        targets: &[u64],
+        assignments: &[Option<usize>],
+        frames_dir: &Path,
+        thumbs_dir: &Path,
+    ) -> Result<()> {
+        let mut remaining_uses: BTreeMap<usize, usize> = BTreeMap::new();
+        for frame in assignments.iter().flatten() {
+            *remaining_uses.entry(*frame).or_insert(0) += 1;
+        }
+
+        for (target, frame) in targets.iter().zip(assignments) {
+            let Some(frame) = frame else {
+                log::warn!(
+                    "No frame found at {} in {:?} (past end of video?)",
+                    Timestamp(*target),
+                    self.video_path
+                );
+                continue;
+            };
+
+            let uses = remaining_uses.get_mut(frame).expect("counted above");
+            *uses -= 1;
+            let staged_name = format!("{}.jpg", frame + 1);
+            let final_name = format!("{}.jpg", target);
+
+            for (staging, dest_dir) in [("frames", frames_dir), ("thumbs", thumbs_dir)] {
+                let src = self.staging_dir.join(staging).join(&staged_name);
+                let dest = dest_dir.join(&final_name);
+                // The last target sharing a frame can take the staged file itself
+                let result = if *uses == 0 {
+                    std::fs::rename(&src, &dest)
+                } else {
+                    std::fs::copy(&src, &dest).map(|_| ())
+                };
+                result.map_err(|e| AnytronError::FileWrite {
+                    path: dest,
+                    source: e,
+                })?;
+            }
+        }
+
         Ok(())
     }
+}
+
+/// Thin sorted targets so decoded frames are at least `interval_ms` apart
+///
+/// Returns the sample timestamps to decode, and for each target the index of
+/// the sample whose frame it will reuse.
+fn sample_targets(targets: &[u64], interval_ms: u64) -> (Vec<u64>, Vec<usize>) {
+    let mut samples: Vec<u64> = Vec::new();
+    let target_samples = targets
+        .iter()
+        .map(|&target| {
+            match samples.last() {
+                Some(&last) if target - last < interval_ms => {}
+                _ => samples.push(target),
+            }
+            samples.len() - 1
+        })
+        .collect();
+    (samples, target_samples)
+}
+
+/// Map each sorted target to the index of the first frame shown at or after it
+fn assign_frames(targets: &[u64], frame_times: &[FrameTime]) -> Vec<Option<usize>> {
+    let mut frame = 0;
+    targets
+        .iter()
+        .map(|&target| {
+            while frame < frame_times.len() && !frame_times[frame].is_at_or_after(target) {
+                frame += 1;
+            }
+            (frame < frame_times.len()).then_some(frame)
+        })
+        .collect()
+}
+
+/// Parse the `config in time_base: 1/1000, ...` line printed by showinfo
+fn parse_time_base(line: &str) -> Option<(i64, i64)> {
+    let rest = line.split_once("time_base:")?.1;
+    let (num, den) = rest.split(',').next()?.trim().split_once('/')?;
+    let num = num.trim().parse().ok()?;
+    let den: i64 = den.trim().parse().ok()?;
+    (den != 0).then_some((num, den))
+}
 
-    /// Convert quality (1-100) to FFmpeg qscale (31-1)
-    fn quality_to_qscale(&self) -> u8 {
-        // FFmpeg qscale: 1 = best, 31 = worst
-        // Our quality: 1 = worst, 100 = best
-        let normalized = (self.quality as f32 / 100.0).clamp(0.0, 1.0);
-        let qscale = 31.0 - (normalized * 30.0);
-        qscale.round() as u8
+/// Parse the presentation time from a showinfo frame line
+///
+/// Prefers the exact `pts` in the announced time base and falls back to the
+/// (rounded) `pts_time` when the time base line was not seen.
+fn parse_frame_time(line: &str, time_base: Option<(i64, i64)>) -> Option<FrameTime> {
+    let field = |name: &str| {
+        line.split_once(name)
+            .and_then(|(_, rest)| rest.split_whitespace().next())
+    };
+
+    if let (Some((tb_num, tb_den)), Some(pts)) = (time_base, field(" pts:")) {
+        if let Ok(pts) = pts.parse() {
+            return Some(FrameTime {
+                pts,
+                tb_num,
+                tb_den,
+            });
+        }
     }
+
+    let secs: f64 = field("pts_time:")?.parse().ok()?;
+    Some(FrameTime {
+        pts: (secs * 1_000_000.0).round() as i64,
+        tb_num: 1,
+        tb_den: 1_000_000,
+    })
+}
+
+/// Convert quality (1-100) to FFmpeg qscale (31-1)
+fn quality_to_qscale(quality: u8) -> u8 {
+    // FFmpeg qscale: 1 = best, 31 = worst
+    // Our quality: 1 = worst, 100 = best
+    let normalized = (quality as f32 / 100.0).clamp(0.0, 1.0);
+    let qscale = 31.0 - (normalized * 30.0);
+    qscale.round() as u8
 }
 
 #[cfg(test)]
@@ -287,24 +591,107 @@ mod tests {
 
     #[test]
     fn test_quality_to_qscale() {
-        let task = ExtractionTask {
-            video_path: PathBuf::new(),
-            timestamp: Timestamp(0),
-            frame_path: PathBuf::new(),
-            thumb_path: PathBuf::new(),
-            quality: 100,
+        assert_eq!(quality_to_qscale(100), 1);
+        assert_eq!(quality_to_qscale(1), 31);
+        // 85% quality should be roughly qscale 5-6
+        assert!(quality_to_qscale(85) <= 6);
+    }
+
+    #[test]
+    fn test_parse_probe_framerate() {
+        let fps = parse_probe_framerate(
+            r#"{"programs": [], "streams": [{"avg_frame_rate": "24000/1001", "r_frame_rate": "24/1"}]}"#,
+        )
+        .unwrap();
+        assert!((fps.unwrap() - 23.976).abs() < 0.001);
+
+        let fps = parse_probe_framerate(
+            r#"{"streams": [{"avg_frame_rate": "0/0", "r_frame_rate": "25/1"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(fps, Some(25.0));
+        assert_eq!(parse_probe_framerate(r#"{"streams": []}"#).unwrap(), None);
+        assert!(parse_probe_framerate("not json").is_err());
+    }
+
+    #[test]
+    fn test_filter_graph() {
+        let pass = FramePass {
+            video_path: Path::new("test.mkv"),
+            targets: &[1500, 62005],
+            staging_dir: PathBuf::new(),
+            qscale: 5,
+            thumb_qscale: 7,
+            frame_width: 0,
             thumb_width: 320,
         };
-        assert_eq!(task.quality_to_qscale(), 1);
 
-        let task2 = ExtractionTask { quality: 1, ..task };
-        assert_eq!(task2.quality_to_qscale(), 31);
+        assert_eq!(
+            pass.filter_graph(),
+            "[0:v]select='gt(gte(t,1.500)*not(gte(prev_t,1.500))\
+             +gte(t,62.005)*not(gte(prev_t,62.005)),0)',showinfo,\
+             split=2[full][th];[th]scale=320:-1[thumb]"
+        );
+    }
 
-        let task3 = ExtractionTask {
-            quality: 85,
-            ..task2
+    #[test]
+    fn test_filter_graph_scaled_frames() {
+        let pass = FramePass {
+            video_path: Path::new("test.mkv"),
+            targets: &[1500],
+            staging_dir: PathBuf::new(),
+            qscale: 5,
+            thumb_qscale: 7,
+            frame_width: 1280,
+            thumb_width: 320,
         };
-        // 85% quality should be roughly qscale 5-6
-        assert!(task3.quality_to_qscale() <= 6);
+
+        assert!(pass
+            .filter_graph()
+            .ends_with("split=2[fs][th];[fs]scale=1280:-1[full];[th]scale=320:-1[thumb]"));
+    }
+
+    #[test]
+    fn test_sample_targets() {
+        let (samples, target_samples) = sample_targets(&[1000, 1400, 2100, 2500, 5000], 1000);
+        assert_eq!(samples, vec![1000, 2100, 5000]);
+        assert_eq!(target_samples, vec![0, 0, 1, 1, 2]);
+
+        // A zero interval decodes every target
+        let (samples, _) = sample_targets(&[1000, 1001], 0);
+        assert_eq!(samples, vec![1000, 1001]);
+    }
+
+    #[test]
+    fn test_parse_showinfo() {
+        let config =
+            "[Parsed_showinfo_1 @ 0x55d] config in time_base: 1/1000, frame_rate: 24000/1001";
+        let frame = "[Parsed_showinfo_1 @ 0x55d] n:   3 pts:  12387 pts_time:12.387   duration:42";
+
+        let tb = parse_time_base(config);
+        assert_eq!(tb, Some((1, 1000)));
+
+        let time = parse_frame_time(frame, tb).unwrap();
+        assert_eq!(time.pts, 12387);
+        assert!(time.is_at_or_after(12387));
+        assert!(!time.is_at_or_after(12388));
+
+        // Without a time base the rounded pts_time is used
+        let fallback = parse_frame_time(frame, None).unwrap();
+        assert!(fallback.is_at_or_after(12387));
+    }
+
+    #[test]
+    fn test_assign_frames() {
+        let frame = |ms| FrameTime {
+            pts: ms,
+            tb_num: 1,
+            tb_den: 1000,
+        };
+        let frames = [frame(1000), frame(5020), frame(9000)];
+
+        // Two targets inside the same frame gap share one decoded frame
+        let assignments = assign_frames(&[1000, 5000, 5010, 8000, 9500], &frames);
+        assert_eq!(assignments, vec![Some(0), Some(1), Some(1), Some(2), None]);
     }
 }
@@ -1,7 +1,11 @@
 //! Frame and subtitle extraction module
 
+mod audio_sync;
+mod clip;
 mod ffmpeg;
 mod subtitle;
 
+pub use audio_sync::{parse_silencedetect, AudioSync, SpeechActivity, SyncCorrection};
+pub use clip::extract_audio_clip;
 pub use ffmpeg::FrameExtractor;
-pub use subtitle::{SubtitleExtractor, SubtitleStream};
+pub use subtitle::{is_known_language, normalize_language, SubtitleExtractor, SubtitleStream};
@@ -1,17 +1,14 @@
 //! Subtitle extraction from video container files (MKV, MP4, etc.)
 
-use serde::Deserialize;
-use std::collections::hash_map::DefaultHasher;
-use std::fs::File;
-use std::hash::{Hash, Hasher};
-use std::io::Read;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::cache::{Cache, CacheKind, FileFingerprint};
 use crate::error::{AnytronError, Result};
 
 /// Information about a subtitle stream in a video file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubtitleStream {
     /// Stream index in the container
     pub index: u32,
@@ -52,6 +49,26 @@ impl SubtitleStream {
         }
     }
 
+    /// Check if this stream is in `language` (e.g. `en`, `eng` or `english`)
+    pub fn matches_language(&self, language: &str) -> bool {
+        let wanted = normalize_language(language);
+        match &self.language {
+            Some(tag) => normalize_language(tag) == wanted,
+            None => self
+                .title
+                .as_deref()
+                .is_some_and(|title| normalize_language(title) == wanted),
+        }
+    }
+
+    /// Whether the stream is text that can be converted to SRT, ASS or WebVTT
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self.codec.as_str(),
+            "subrip" | "srt" | "ass" | "ssa" | "webvtt" | "vtt" | "mov_text" | "text"
+        )
+    }
+
     /// Check if this stream appears to be SDH/CC (for hearing impaired)
     /// Checks both the disposition flag and title patterns
     pub fn appears_to_be_sdh(&self) -> bool {
@@ -111,6 +128,44 @@ impl SubtitleStream {
     }
 }
 
+/// Language names and ISO 639-1/639-2 codes, by the code they normalise to
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("eng", &["en", "english"]),
+    ("spa", &["es", "spanish", "español"]),
+    ("fre", &["fr", "fra", "french", "français"]),
+    ("ger", &["de", "deu", "german", "deutsch"]),
+    ("ita", &["it", "italian"]),
+    ("por", &["pt", "portuguese"]),
+    ("dut", &["nl", "nld", "dutch"]),
+    ("swe", &["sv", "swedish"]),
+    ("dan", &["da", "danish"]),
+    ("nor", &["no", "nb", "nob", "norwegian"]),
+    ("fin", &["fi", "finnish"]),
+    ("pol", &["pl", "polish"]),
+    ("rus", &["ru", "russian"]),
+    ("jpn", &["ja", "japanese"]),
+    ("kor", &["ko", "korean"]),
+    ("chi", &["zh", "zho", "chinese"]),
+];
+
+/// Normalise a language tag to a lowercase ISO 639-2 code where known
+///
+/// `en`, `eng` and `English` all become `eng`; unknown tags are lowercased.
+pub fn normalize_language(tag: &str) -> String {
+    let tag = tag.trim().to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|(code, aliases)| *code == tag || aliases.contains(&tag.as_str()))
+        .map(|(code, _)| code.to_string())
+        .unwrap_or(tag)
+}
+
+/// Whether a tag names a language in the alias table (`es`, `spa`, `Spanish`)
+pub fn is_known_language(tag: &str) -> bool {
+    let code = normalize_language(tag);
+    LANGUAGES.iter().any(|(known, _)| *known == code)
+}
+
 /// FFprobe JSON output structures
 #[derive(Debug, Deserialize)]
 struct FFprobeOutput {
@@ -197,6 +252,42 @@ impl SubtitleExtractor {
         Ok(streams)
     }
 
+    /// Probe a video for subtitle streams, reusing a cached listing
+    pub fn probe_streams_cached(
+        video_path: &Path,
+        fingerprint: &FileFingerprint,
+        cache: &Cache,
+    ) -> Result<Vec<SubtitleStream>> {
+        let key = Cache::key(CacheKind::Probe, fingerprint, "");
+        if let Some(path) = cache.get(&key) {
+            let cached = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok());
+            if let Some(streams) = cached {
+                log::debug!("Using cached probe of {:?}", video_path);
+                return Ok(streams);
+            }
+        }
+
+        let streams = Self::probe_streams(video_path)?;
+        let json = serde_json::to_string(&streams)
+            .map_err(|e| AnytronError::Output(format!("Failed to serialize probe: {}", e)))?;
+        let path = cache.path_for(CacheKind::Probe, &key, &file_stem(video_path), "json")?;
+        std::fs::write(&path, json).map_err(|e| AnytronError::FileWrite {
+            path: path.clone(),
+            source: e,
+        })?;
+        cache.insert(
+            &key,
+            CacheKind::Probe,
+            video_path,
+            fingerprint.clone(),
+            &path,
+        );
+
+        Ok(streams)
+    }
+
     /// Select the best subtitle stream (prefers English, non-SDH)
     pub fn select_best_stream(streams: &[SubtitleStream]) -> Option<&SubtitleStream> {
         if streams.is_empty() {
@@ -244,30 +335,61 @@ impl SubtitleExtractor {
         Ok(())
     }
 
-    /// Extract the best subtitle stream from a video to a file
-    /// Returns the path to the extracted subtitle, or None if no subtitles found
-    pub fn extract_best_subtitle(
+    /// Extract a stream into the cache, reusing an earlier extraction
+    pub fn extract_stream_cached(
         video_path: &Path,
-        output_dir: &Path,
-    ) -> Result<Option<std::path::PathBuf>> {
-        let video_hash = {
-            let mut file = File::open(video_path)?;
-            let mut content = Vec::new();
-            file.read_to_end(&mut content)?;
-            let mut hasher = DefaultHasher::new();
-            content.len().hash(&mut hasher);
-            format!("{:x}", hasher.finish())
-        };
-
-        let cache_key = format!("{}.srt", video_hash);
-        let cache_path = output_dir.join(&cache_key);
-
-        if cache_path.exists() {
+        fingerprint: &FileFingerprint,
+        cache: &Cache,
+        stream: &SubtitleStream,
+    ) -> Result<PathBuf> {
+        let key = Cache::key(
+            CacheKind::Subtitle,
+            fingerprint,
+            &format!("stream={}", stream.index),
+        );
+        if let Some(cache_path) = cache.get(&key) {
             log::debug!("Using cached subtitle: {:?}", cache_path);
-            return Ok(Some(cache_path));
+            return Ok(cache_path);
         }
 
-        let streams = Self::probe_streams(video_path)?;
+        let ext = match stream.codec.as_str() {
+            "ass" | "ssa" => "ass",
+            "webvtt" | "vtt" => "vtt",
+            _ => "srt",
+        };
+        let output_path = cache.path_for(CacheKind::Subtitle, &key, &file_stem(video_path), ext)?;
+        Self::extract_stream(video_path, stream, &output_path)?;
+        cache.insert(
+            &key,
+            CacheKind::Subtitle,
+            video_path,
+            fingerprint.clone(),
+            &output_path,
+        );
+        Ok(output_path)
+    }
+
+    /// Extract the best subtitle stream from a video into the cache
+    /// Returns the path to the extracted subtitle, or None if no subtitles found
+    ///
+    /// `stream` picks a specific stream index instead of the best-scoring one.
+    pub fn extract_best_subtitle(
+        video_path: &Path,
+        cache: &Cache,
+        stream: Option<u32>,
+    ) -> Result<Option<PathBuf>> {
+        Ok(Self::extract_best_track(video_path, cache, stream)?.map(|(_, path)| path))
+    }
+
+    /// Like [`SubtitleExtractor::extract_best_subtitle`], also returning the
+    /// chosen stream
+    pub fn extract_best_track(
+        video_path: &Path,
+        cache: &Cache,
+        stream: Option<u32>,
+    ) -> Result<Option<(SubtitleStream, PathBuf)>> {
+        let fingerprint = FileFingerprint::of(video_path)?;
+        let streams = Self::probe_streams_cached(video_path, &fingerprint, cache)?;
 
         if streams.is_empty() {
             log::debug!("No subtitle streams found in {:?}", video_path);
@@ -294,8 +416,7 @@ impl SubtitleExtractor {
              );
         }
 
-        let best = Self::select_best_stream(&streams)
-            .ok_or_else(|| AnytronError::Ffmpeg("No suitable subtitle stream found".to_string()))?;
+        let best = Self::choose_stream(&streams, stream, video_path)?;
 
         log::info!(
             "Selected subtitle stream {} ({:?}) from {:?}",
@@ -304,30 +425,64 @@ impl SubtitleExtractor {
             video_path
         );
 
-        let ext = match best.codec.as_str() {
-            "ass" | "ssa" => "ass",
-            "webvtt" | "vtt" => "vtt",
-            _ => "srt",
-        };
-
-        let video_stem = video_path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("subtitle");
-
-        let output_path = output_dir.join(format!("{}.{}", video_stem, ext));
-
-        std::fs::create_dir_all(output_dir).map_err(|e| AnytronError::OutputDir {
-            path: output_dir.to_path_buf(),
-            source: e,
-        })?;
+        let path = Self::extract_stream_cached(video_path, &fingerprint, cache, best)?;
+        Ok(Some((best.clone(), path)))
+    }
 
-        Self::extract_stream(video_path, best, &output_path)?;
+    /// Extract every text subtitle stream, optionally limited to `languages`
+    ///
+    /// Returns the extracted tracks in stream order. Streams that fail to
+    /// extract are logged and left out.
+    pub fn extract_text_tracks(
+        video_path: &Path,
+        cache: &Cache,
+        languages: &[String],
+    ) -> Result<Vec<(SubtitleStream, PathBuf)>> {
+        let fingerprint = FileFingerprint::of(video_path)?;
+        let streams = Self::probe_streams_cached(video_path, &fingerprint, cache)?;
+
+        let mut tracks = Vec::new();
+        for stream in streams.iter().filter(|s| s.is_text()) {
+            if !languages.is_empty() && !languages.iter().any(|l| stream.matches_language(l)) {
+                continue;
+            }
+            match Self::extract_stream_cached(video_path, &fingerprint, cache, stream) {
+                Ok(path) => tracks.push((stream.clone(), path)),
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+        Ok(tracks)
+    }
 
-        Ok(Some(output_path))
+    /// The stream at `index`, or the best-scoring one
+    fn choose_stream<'a>(
+        streams: &'a [SubtitleStream],
+        index: Option<u32>,
+        video_path: &Path,
+    ) -> Result<&'a SubtitleStream> {
+        match index {
+            Some(index) => streams.iter().find(|s| s.index == index).ok_or_else(|| {
+                AnytronError::Ffmpeg(format!(
+                    "Subtitle stream {} not found in {:?}",
+                    index, video_path
+                ))
+            }),
+            None => Self::select_best_stream(streams).ok_or_else(|| {
+                AnytronError::Ffmpeg("No suitable subtitle stream found".to_string())
+            }),
+        }
     }
 }
 
+/// File name of a video without its extension
+fn file_stem(video_path: &Path) -> String {
+    video_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("subtitle")
+        .to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -359,6 +514,35 @@ mod tests {
         assert!(stream3.is_english());
     }
 
+    #[test]
+    fn test_matches_language() {
+        assert_eq!(normalize_language("English"), "eng");
+        assert_eq!(normalize_language("fra"), "fre");
+        assert_eq!(normalize_language("tlh"), "tlh");
+
+        let stream = SubtitleStream {
+            index: 3,
+            codec: "hdmv_pgs_subtitle".to_string(),
+            language: Some("spa".to_string()),
+            title: None,
+            is_default: false,
+            is_forced: false,
+            is_hearing_impaired: false,
+        };
+        assert!(stream.matches_language("es"));
+        assert!(!stream.matches_language("eng"));
+        assert!(!stream.is_text());
+
+        let untagged = SubtitleStream {
+            language: None,
+            title: Some("Deutsch".to_string()),
+            codec: "ass".to_string(),
+            ..stream
+        };
+        assert!(untagged.matches_language("de"));
+        assert!(untagged.is_text());
+    }
+
     #[test]
     fn test_priority_score() {
         let english_regular = SubtitleStream {
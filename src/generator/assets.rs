@@ -154,6 +154,19 @@ a:hover {
     color: var(--color-text-muted);
 }
 
+.search-language {
+    padding: var(--spacing-md);
+    font-size: 1rem;
+    border: 2px solid var(--color-border);
+    border-radius: var(--border-radius);
+    background-color: var(--color-bg-secondary);
+    color: var(--color-text);
+}
+
+.search-language[hidden] {
+    display: none;
+}
+
 .search-button {
     padding: var(--spacing-md) var(--spacing-lg);
     font-size: 1rem;
@@ -229,6 +242,13 @@ a:hover {
     overflow: hidden;
 }
 
+.result-card__speaker {
+    font-size: 0.75rem;
+    font-weight: 600;
+    color: var(--color-text-muted);
+    margin: 0 0 var(--spacing-xs);
+}
+
 .result-card__meta {
     display: flex;
     justify-content: space-between;
@@ -278,12 +298,49 @@ a:hover {
     padding: var(--spacing-lg);
 }
 
+.caption-speaker {
+    font-size: 0.85rem;
+    font-weight: 600;
+    text-transform: uppercase;
+    letter-spacing: 0.05em;
+    color: var(--color-text-muted);
+    margin: 0 0 var(--spacing-sm);
+}
+
 .caption-quote {
     font-size: 1.25rem;
     font-style: italic;
     margin: 0 0 var(--spacing-md);
 }
 
+.caption-dual {
+    border-left: 3px solid var(--color-border);
+    padding-left: var(--spacing-md);
+    margin: 0 0 var(--spacing-md);
+}
+
+.caption-dual .caption-quote {
+    margin: 0 0 var(--spacing-sm);
+}
+
+.caption-quote--secondary {
+    font-size: 1.1rem;
+    color: var(--color-text-muted);
+}
+
+.caption-translation {
+    font-style: italic;
+    color: var(--color-text-muted);
+    margin: 0 0 var(--spacing-md);
+}
+
+.caption-translation__language {
+    font-size: 0.75rem;
+    font-style: normal;
+    text-transform: uppercase;
+    margin-right: var(--spacing-sm);
+}
+
 .caption-meta {
     display: flex;
     gap: var(--spacing-lg);
@@ -468,49 +525,127 @@ const SEARCH_JS: &str = r#"
 (function() {
     'use strict';
 
-    let searchIndex = null;
-    let lunrIndex = null;
-    let entries = [];
+    let totalEntries = 0;
     let indexLoaded = false;
     let indexLoading = false;
+    let searchSeq = 0;
+
+    // Top-level manifest, listing the languages the site is searchable in
+    let rootManifest = null;
+
+    // Each language's index, keyed by manifest path, and those being searched
+    const sources = {};
+    let active = [];
+
+    // Selector value searching every language at once
+    const ALL_LANGUAGES = '*';
+
+    // Index fields holding SDH annotations
+    const ANNOTATION_FIELDS = ['sound', 'music'];
 
     const searchInput = document.getElementById('search-input');
     const searchForm = document.getElementById('search-form');
+    const languageSelect = document.getElementById('search-language');
     const resultsGrid = document.getElementById('results-grid');
     const resultsInfo = document.getElementById('results-info');
 
     // Only initialize search on the index page
     if (!searchInput || !searchForm) return;
 
-    // Load the search index
+    async function fetchJson(path) {
+        const response = await fetch('search/' + path);
+        if (!response.ok) {
+            throw new Error(`HTTP ${response.status}`);
+        }
+        return response.json();
+    }
+
+    function addEntries(source, list) {
+        list.forEach(function(entry) {
+            source.entriesById[entry.id] = entry;
+        });
+    }
+
+    // Stem queries by looking words up in the table the index was built with
+    function registerStemmer(name, stems) {
+        if (lunr.Pipeline.registeredFunctions[name]) return;
+        const stemmer = function(token) {
+            return token.update(function(word) {
+                return Object.prototype.hasOwnProperty.call(stems, word) ? stems[word] : word;
+            });
+        };
+        lunr.Pipeline.registerFunction(stemmer, name);
+    }
+
+    // Offer a language selector when the site has more than one language
+    function setupLanguages() {
+        const languages = rootManifest.languages || [];
+        if (!languageSelect || languages.length < 2) return ['manifest.json'];
+
+        languages.forEach(function(language) {
+            const option = document.createElement('option');
+            option.value = language.code;
+            option.textContent = language.name;
+            languageSelect.appendChild(option);
+        });
+        const all = document.createElement('option');
+        all.value = ALL_LANGUAGES;
+        all.textContent = 'All languages';
+        languageSelect.appendChild(all);
+
+        // Manifests searched for a selector value
+        const manifestsFor = function(value) {
+            return languages
+                .filter(function(language) { return value === ALL_LANGUAGES || language.code === value; })
+                .map(function(language) { return language.manifest; });
+        };
+
+        let saved = null;
+        try {
+            saved = localStorage.getItem('anytron-language');
+        } catch (e) {}
+        languageSelect.value = saved && manifestsFor(saved).length > 0 ? saved : languages[0].code;
+        languageSelect.hidden = false;
+
+        languageSelect.addEventListener('change', async function() {
+            try {
+                localStorage.setItem('anytron-language', languageSelect.value);
+            } catch (e) {}
+            await useLanguages(manifestsFor(languageSelect.value));
+            if (searchInput.value) performSearch(searchInput.value);
+        });
+
+        return manifestsFor(languageSelect.value);
+    }
+
+    // Load the site's languages, then the chosen ones' indexes
     async function loadIndex() {
         if (indexLoading || indexLoaded) return;
+
+        try {
+            resultsInfo.textContent = 'Loading search index...';
+            rootManifest = await fetchJson('manifest.json');
+            await useLanguages(setupLanguages());
+        } catch (error) {
+            console.error('Failed to load search index:', error);
+            resultsInfo.textContent = 'Failed to load search index. Please refresh the page.';
+        }
+    }
+
+    // Search the languages whose manifests are at `paths`
+    async function useLanguages(paths) {
         indexLoading = true;
+        indexLoaded = false;
 
         try {
             resultsInfo.textContent = 'Loading search index...';
-            const response = await fetch('search/index.json');
-            if (!response.ok) {
-                throw new Error(`HTTP ${response.status}`);
-            }
-            searchIndex = await response.json();
-            entries = searchIndex.entries;
-
-            // Build lunr index
-            lunrIndex = lunr(function() {
-                this.ref('id');
-                this.field('text');
-                this.field('episode');
-
-                const self = this;
-                entries.forEach(function(entry) {
-                    self.add(entry);
-                });
-            });
+            active = await Promise.all(paths.map(loadSource));
+            // Every caption is in the first language's index
+            totalEntries = active[0].manifest.meta.total;
 
             indexLoaded = true;
-            resultsInfo.textContent = `Ready to search ${entries.length} quotes`;
-            console.log('Search index loaded:', entries.length, 'entries');
+            resultsInfo.textContent = `Ready to search ${totalEntries} quotes`;
+            console.log('Search index loaded:', totalEntries, 'entries');
         } catch (error) {
             console.error('Failed to load search index:', error);
             resultsInfo.textContent = 'Failed to load search index. Please refresh the page.';
@@ -519,8 +654,203 @@ const SEARCH_JS: &str = r#"
         }
     }
 
+    // A language's index, loaded once and kept for later searches
+    function loadSource(path) {
+        if (!sources[path]) {
+            const source = {
+                path: path,
+                // Directory of the language's files, relative to search/
+                base: path.slice(0, path.lastIndexOf('/') + 1),
+                manifest: null,
+                lunrIndex: null,
+                entriesById: {},
+                // Sharded layout: term shards and document files fetched so far
+                searchPipeline: null,
+                loadedShards: {},
+                loadedDocs: {}
+            };
+            sources[path] = fetchSource(source).catch(function(error) {
+                delete sources[path];
+                throw error;
+            });
+        }
+        return sources[path];
+    }
+
+    // Fetch a language's manifest, and its whole index for the single-file layout
+    async function fetchSource(source) {
+        const manifest = source.path === 'manifest.json'
+            ? rootManifest
+            : await fetchJson(source.path);
+        source.manifest = manifest;
+
+        if (manifest.stems) {
+            const stems = await fetchJson(source.base + manifest.stems);
+            manifest.pipeline.forEach(function(name) {
+                registerStemmer(name, stems);
+            });
+        }
+
+        if (manifest.layout === 'sharded') {
+            source.searchPipeline = lunr.Pipeline.load(manifest.pipeline);
+        } else {
+            const searchIndex = await fetchJson(source.base + manifest.index);
+            addEntries(source, searchIndex.entries);
+            // The index is prebuilt at generation time
+            source.lunrIndex = lunr.Index.load(searchIndex.lunr);
+        }
+
+        return source;
+    }
+
+    // Position of the shard whose term range contains a term
+    function shardFor(shards, term) {
+        let lo = 0;
+        let hi = shards.length - 1;
+        while (lo < hi) {
+            const mid = (lo + hi + 1) >> 1;
+            if (shards[mid].first <= term) {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        return lo;
+    }
+
+    // Shards a query can touch, using lunr's own query parser and pipeline
+    function shardsForQuery(source, query) {
+        const shards = source.manifest.shards || [];
+        const needed = new Set();
+        if (shards.length === 0) return needed;
+
+        const parsed = new lunr.Query(source.manifest.fields);
+        new lunr.QueryParser(query, parsed).parse();
+
+        parsed.clauses.forEach(function(clause) {
+            const terms = clause.usePipeline
+                ? source.searchPipeline.runString(clause.term, { fields: clause.fields })
+                : [clause.term];
+
+            terms.forEach(function(term) {
+                const star = term.indexOf('*');
+                if (clause.editDistance || star === 0) {
+                    // Fuzzy and leading wildcard terms can match anywhere
+                    shards.forEach(function(_, i) { needed.add(i); });
+                } else if (star > 0) {
+                    const prefix = term.slice(0, star);
+                    let i = shardFor(shards, prefix);
+                    needed.add(i);
+                    while (++i < shards.length && shards[i].first.startsWith(prefix)) {
+                        needed.add(i);
+                    }
+                } else {
+                    needed.add(shardFor(shards, term));
+                }
+            });
+        });
+
+        return needed;
+    }
+
+    // Fetch missing shards and rebuild the lunr index from all loaded shards
+    async function ensureShards(source, query) {
+        const manifest = source.manifest;
+        const loadedShards = source.loadedShards;
+        const missing = Array.from(shardsForQuery(source, query)).filter(function(i) {
+            return !(i in loadedShards);
+        });
+        if (missing.length === 0 && source.lunrIndex) return;
+
+        const fetched = await Promise.all(missing.map(function(i) {
+            return fetchJson(source.base + manifest.shards[i].file);
+        }));
+        missing.forEach(function(i, n) {
+            loadedShards[i] = fetched[n];
+        });
+
+        const invertedIndex = [];
+        const components = {};
+        Object.keys(loadedShards).map(Number).sort(function(a, b) { return a - b; }).forEach(function(i) {
+            const shard = loadedShards[i];
+            shard.invertedIndex.forEach(function(posting) {
+                invertedIndex.push(posting);
+            });
+            shard.fieldVectors.forEach(function(vector) {
+                const pairs = components[vector[0]] || (components[vector[0]] = []);
+                for (let k = 0; k < vector[1].length; k += 2) {
+                    pairs.push([vector[1][k], vector[1][k + 1]]);
+                }
+            });
+        });
+
+        const fieldVectors = Object.keys(components).map(function(fieldRef) {
+            const pairs = components[fieldRef].sort(function(a, b) { return a[0] - b[0]; });
+            return [fieldRef, [].concat.apply([], pairs)];
+        });
+
+        source.lunrIndex = lunr.Index.load({
+            version: manifest.version,
+            fields: manifest.fields,
+            fieldVectors: fieldVectors,
+            invertedIndex: invertedIndex,
+            pipeline: manifest.pipeline
+        });
+    }
+
+    // Fetch the document files holding the given refs
+    async function ensureDocs(source, refs) {
+        const files = new Set();
+        refs.forEach(function(ref) {
+            if (ref in source.entriesById) return;
+            // Refs start with the episode ID (S01E01-...)
+            const file = source.manifest.docs[ref.slice(0, ref.indexOf('-'))];
+            if (file && !source.loadedDocs[file]) files.add(file);
+        });
+
+        await Promise.all(Array.from(files).map(async function(file) {
+            addEntries(source, await fetchJson(source.base + file));
+            source.loadedDocs[file] = true;
+        }));
+    }
+
+    // `speaker:name` narrows the search rather than widening it, so speaker
+    // terms become required clauses
+    function prepareQuery(query) {
+        return query.replace(/(^|\s)speaker:/gi, '$1+speaker:');
+    }
+
+    // Run a query, matching terms without a field against every field but
+    // the SDH annotations, which are only searched when named (`sound:...`)
+    function runQuery(index, query) {
+        return index.query(function(q) {
+            new lunr.QueryParser(query, q).parse();
+            const fields = q.allFields.filter(function(field) {
+                return ANNOTATION_FIELDS.indexOf(field) === -1;
+            });
+            q.clauses.forEach(function(clause) {
+                if (clause.fields === q.allFields) clause.fields = fields;
+            });
+        });
+    }
+
+    // Search one language, trying exact matches first, then prefixes
+    async function searchSource(source, query) {
+        const sharded = source.manifest.layout === 'sharded';
+        if (sharded) await ensureShards(source, query);
+        let results = runQuery(source.lunrIndex, query);
+        if (results.length === 0 && query.length > 2) {
+            // Try with wildcard for partial matches
+            if (sharded) await ensureShards(source, query + '*');
+            results = runQuery(source.lunrIndex, query + '*');
+        }
+        return results.map(function(result) {
+            return { ref: result.ref, score: result.score, source: source };
+        });
+    }
+
     // Perform search
-    function performSearch(query) {
+    async function performSearch(query) {
         if (!indexLoaded) {
             resultsInfo.textContent = 'Search index still loading...';
             return;
@@ -528,19 +858,45 @@ const SEARCH_JS: &str = r#"
 
         if (!query || !query.trim()) {
             resultsGrid.innerHTML = '';
-            resultsInfo.textContent = `Ready to search ${entries.length} quotes`;
+            resultsInfo.textContent = `Ready to search ${totalEntries} quotes`;
             return;
         }
 
+        const seq = ++searchSeq;
+        const searched = active;
         const startTime = performance.now();
 
         try {
-            // Try exact search first, fall back to fuzzy
-            let results = lunrIndex.search(query);
-            if (results.length === 0 && query.length > 2) {
-                // Try with wildcard for partial matches
-                results = lunrIndex.search(query + '*');
-            }
+            const found = await Promise.all(searched.map(function(source) {
+                return searchSource(source, prepareQuery(query));
+            }));
+
+            // Captions share IDs across languages; keep each one's best match
+            const best = {};
+            found.forEach(function(list) {
+                list.forEach(function(result) {
+                    if (!best[result.ref] || best[result.ref].score < result.score) {
+                        best[result.ref] = result;
+                    }
+                });
+            });
+            const results = Object.keys(best)
+                .map(function(ref) { return best[ref]; })
+                .sort(function(a, b) { return b.score - a.score; });
+
+            // Limit results
+            const maxResults = 100;
+            const limitedResults = results.slice(0, maxResults);
+            await Promise.all(searched.map(function(source) {
+                if (source.manifest.layout !== 'sharded') return null;
+                const refs = limitedResults
+                    .filter(function(result) { return result.source === source; })
+                    .map(function(result) { return result.ref; });
+                return ensureDocs(source, refs);
+            }));
+
+            // A newer search started while shards were loading
+            if (seq !== searchSeq) return;
 
             const endTime = performance.now();
             const duration = ((endTime - startTime) / 1000).toFixed(3);
@@ -551,10 +907,6 @@ const SEARCH_JS: &str = r#"
                 return;
             }
 
-            // Limit results
-            const maxResults = 100;
-            const limitedResults = results.slice(0, maxResults);
-
             resultsInfo.textContent = 'Found ' + results.length + ' results in ' + duration + 's' +
                 (results.length > maxResults ? ' (showing first ' + maxResults + ')' : '');
 
@@ -562,20 +914,17 @@ const SEARCH_JS: &str = r#"
             let html = '';
             for (let i = 0; i < limitedResults.length; i++) {
                 const result = limitedResults[i];
-                let entry = null;
-                for (let j = 0; j < entries.length; j++) {
-                    if (entries[j].id === result.ref) {
-                        entry = entries[j];
-                        break;
-                    }
-                }
+                const entry = result.source.entriesById[result.ref];
                 if (!entry) continue;
+                // Cues that are only sound effects or music show their annotation
+                const text = entry.text || '[' + (entry.sound || entry.music || '') + ']';
 
                 html += '<article class="result-card">' +
                     '<a href="caption/' + entry.id + '.html" class="result-card__link">' +
-                    '<img src="' + entry.thumb + '" alt="' + escapeHtml(entry.text) + '" class="result-card__image" loading="lazy">' +
+                    '<img src="' + entry.thumb + '" alt="' + escapeHtml(text) + '" class="result-card__image" loading="lazy">' +
                     '<div class="result-card__content">' +
-                    '<p class="result-card__text">' + escapeHtml(entry.text) + '</p>' +
+                    '<p class="result-card__text">' + escapeHtml(text) + '</p>' +
+                    (entry.speaker ? '<p class="result-card__speaker">' + escapeHtml(entry.speaker) + '</p>' : '') +
                     '<div class="result-card__meta">' +
                     '<span>' + entry.episode + '</span>' +
                     '<span>' + formatTimestamp(entry.timestamp) + '</span>' +
@@ -583,6 +932,7 @@ const SEARCH_JS: &str = r#"
             }
             resultsGrid.innerHTML = html;
         } catch (error) {
+            if (seq !== searchSeq) return;
             console.error('Search error:', error);
             resultsInfo.textContent = 'Search error: ' + error.message;
         }
@@ -0,0 +1,604 @@
+//! Build manifest for incremental site regeneration
+//!
+//! Each `generate` run records, per episode, hashes of everything its output
+//! depends on: the subtitle file, the video file's identity (path, size and
+//! modification time), plus global hashes of the page-related config, the
+//! resolved frame settings and the embedded templates. The next run compares
+//! against the previous manifest and only re-renders what changed.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::config::{Config, EpisodeOverride, FrameSettings};
+use crate::discovery::Episode;
+use crate::error::{AnytronError, Result};
+use crate::extractor::SyncCorrection;
+use crate::generator::html;
+
+/// Location of the build manifest inside the output directory
+pub const BUILD_MANIFEST_PATH: &str = ".anytron/build-manifest.json";
+
+/// Manifest format version; a mismatch forces a full rebuild
+const MANIFEST_VERSION: u32 = 1;
+
+/// Hex-encoded SHA-256 of some bytes
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Hex-encoded SHA-256 of a file's contents
+pub fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Identity of a video file, cheap to compute without reading it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VideoIdentity {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub modified: u64,
+}
+
+impl VideoIdentity {
+    /// Read the identity of a video file from its metadata
+    pub fn of(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Ok(Self {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// What one episode's output was built from, and what it produced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EpisodeRecord {
+    /// Hash of the subtitle file used
+    pub subtitle: String,
+
+    /// Video the frames were extracted from
+    pub video: VideoIdentity,
+
+    /// Overrides from `anytron.toml` in effect for the episode
+    #[serde(default)]
+    pub overrides: EpisodeOverride,
+
+    /// Hashes of the subtitles in the site's other languages, by language
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub translations: BTreeMap<String, String>,
+
+    /// Correction estimated by `--auto-sync`, whether or not it was applied
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_sync: Option<SyncCorrection>,
+
+    /// Caption page IDs written for the episode
+    #[serde(default)]
+    pub captions: Vec<String>,
+
+    /// Caption the episode's first page links back to
+    #[serde(default)]
+    pub prev: Option<String>,
+
+    /// Caption the episode's last page links forward to
+    #[serde(default)]
+    pub next: Option<String>,
+}
+
+impl EpisodeRecord {
+    /// Fingerprint an episode's inputs
+    pub fn of(episode: &Episode) -> Result<Self> {
+        let subtitle = hash_file(&episode.subtitle_path).map_err(|e| {
+            AnytronError::Output(format!(
+                "Failed to hash subtitle {:?}: {}",
+                episode.subtitle_path, e
+            ))
+        })?;
+        let translations = episode
+            .languages
+            .iter()
+            .filter(|track| track.path != episode.subtitle_path)
+            .filter_map(|track| Some((track.language.clone()?, &track.path)))
+            .map(|(language, path)| {
+                let hash = hash_file(path).map_err(|e| {
+                    AnytronError::Output(format!("Failed to hash subtitle {:?}: {}", path, e))
+                })?;
+                Ok((language, hash))
+            })
+            .collect::<Result<_>>()?;
+        let video = VideoIdentity::of(&episode.video_path).map_err(|e| {
+            AnytronError::Output(format!(
+                "Failed to read video metadata {:?}: {}",
+                episode.video_path, e
+            ))
+        })?;
+
+        Ok(Self {
+            subtitle,
+            video,
+            overrides: episode.overrides.clone(),
+            translations,
+            auto_sync: None,
+            captions: Vec::new(),
+            prev: None,
+            next: None,
+        })
+    }
+
+    /// Whether two records have the same subtitle, video and overrides
+    ///
+    /// An audio sync estimated for one is still valid for the other.
+    pub fn same_sources(&self, other: &Self) -> bool {
+        self.subtitle == other.subtitle
+            && self.video == other.video
+            && self.overrides == other.overrides
+    }
+
+    /// Whether two records were built from the same inputs
+    fn same_inputs(&self, other: &Self) -> bool {
+        self.same_sources(other)
+            && self.translations == other.translations
+            && self.applied_sync() == other.applied_sync()
+    }
+
+    /// The audio sync correction the captions were retimed with, if any
+    pub fn applied_sync(&self) -> Option<&SyncCorrection> {
+        self.auto_sync
+            .as_ref()
+            .filter(|correction| correction.applied)
+    }
+
+    /// Whether frames extracted for `self` are still valid for `other`
+    fn same_frames(&self, other: &Self) -> bool {
+        self.video == other.video && self.overrides.frame_offset() == other.overrides.frame_offset()
+    }
+}
+
+/// Hashes of everything an output directory was generated from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildManifest {
+    pub version: u32,
+
+    /// Hash of the embedded templates and crate version
+    pub templates: String,
+
+    /// Hash of the config sections rendered into pages, and of the cue
+    /// normalization and SDH indexing that shape them and the search index
+    pub pages: String,
+
+    /// Hash of the resolved frame settings
+    pub frames: String,
+
+    /// Per-episode records, keyed by episode ID
+    pub episodes: BTreeMap<String, EpisodeRecord>,
+}
+
+impl BuildManifest {
+    /// Fingerprint the current inputs
+    pub fn new(config: &Config, frames: &FrameSettings, episodes: &[Episode]) -> Result<Self> {
+        let pages = serde_json::to_string(&(
+            &config.show,
+            &config.site,
+            &config.subtitles.normalize,
+            config.search.sdh,
+            config.search.index_sound_effects,
+        ))
+        .map_err(|e| AnytronError::Output(format!("Failed to hash config: {}", e)))?;
+        let frames = format!(
+            "{} {} {} {} {}",
+            frames.interval_ms.value,
+            frames.quality.value,
+            frames.frame_width.value,
+            frames.thumb_width.value,
+            frames.thumb_quality.value
+        );
+
+        let episodes = episodes
+            .iter()
+            .map(|episode| Ok((episode.id.to_string(), EpisodeRecord::of(episode)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            version: MANIFEST_VERSION,
+            templates: html::template_version(),
+            pages: hash_bytes(pages.as_bytes()),
+            frames: hash_bytes(frames.as_bytes()),
+            episodes,
+        })
+    }
+
+    /// Load the manifest of a previous build, if there is a usable one
+    pub fn load(output_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(output_dir.join(BUILD_MANIFEST_PATH)).ok()?;
+        match serde_json::from_str::<Self>(&content) {
+            Ok(manifest) if manifest.version == MANIFEST_VERSION => Some(manifest),
+            Ok(_) => None,
+            Err(e) => {
+                log::warn!("Ignoring unreadable build manifest: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Write the manifest into the output directory
+    pub fn save(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(BUILD_MANIFEST_PATH);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| AnytronError::OutputDir {
+                path: parent.to_path_buf(),
+                source: e,
+            })?;
+        }
+
+        let json = serde_json::to_string(self)
+            .map_err(|e| AnytronError::Output(format!("Failed to serialize manifest: {}", e)))?;
+        std::fs::write(&path, json).map_err(|e| AnytronError::FileWrite { path, source: e })
+    }
+}
+
+/// What a build has to redo, from comparing manifests
+#[derive(Debug, Clone, Default)]
+pub struct BuildPlan {
+    /// Previous build, if any
+    previous: Option<BuildManifest>,
+
+    /// Every caption page must be rendered
+    full: bool,
+
+    /// Episodes whose caption pages must be rendered
+    dirty: BTreeSet<String>,
+
+    /// Episodes whose extracted frames are out of date
+    stale_frames: BTreeSet<String>,
+
+    /// Episodes from the previous build that no longer exist
+    removed: BTreeSet<String>,
+}
+
+impl BuildPlan {
+    /// Plan a build that regenerates everything
+    pub fn full() -> Self {
+        Self {
+            full: true,
+            ..Default::default()
+        }
+    }
+
+    /// Compare the current inputs to the previous build
+    ///
+    /// Episodes missing from `current` are only treated as removed when
+    /// `prune` is set; runs filtered to some seasons or episodes don't see the
+    /// rest of the library.
+    pub fn new(previous: Option<BuildManifest>, current: &BuildManifest, prune: bool) -> Self {
+        let Some(previous) = previous else {
+            return Self::full();
+        };
+
+        let full = previous.templates != current.templates || previous.pages != current.pages;
+        let frames_changed = previous.frames != current.frames;
+
+        let mut dirty = BTreeSet::new();
+        let mut stale_frames = BTreeSet::new();
+        for (id, record) in &current.episodes {
+            match previous.episodes.get(id) {
+                Some(old) if old.same_inputs(record) => {}
+                Some(old) => {
+                    dirty.insert(id.clone());
+                    if !old.same_frames(record) {
+                        stale_frames.insert(id.clone());
+                    }
+                }
+                None => {
+                    dirty.insert(id.clone());
+                }
+            }
+            if frames_changed {
+                stale_frames.insert(id.clone());
+            }
+        }
+
+        let removed = if prune {
+            previous
+                .episodes
+                .keys()
+                .filter(|id| !current.episodes.contains_key(*id))
+                .cloned()
+                .collect()
+        } else {
+            BTreeSet::new()
+        };
+
+        Self {
+            previous: Some(previous),
+            full,
+            dirty,
+            stale_frames,
+            removed,
+        }
+    }
+
+    /// Whether every caption page is being rendered
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// Whether an episode's caption pages must be rendered
+    pub fn is_dirty(&self, episode: &str) -> bool {
+        self.full || self.dirty.contains(episode)
+    }
+
+    /// Episodes that need their caption pages rendered
+    pub fn dirty(&self) -> impl Iterator<Item = &str> {
+        self.dirty.iter().map(String::as_str)
+    }
+
+    /// Episodes whose frames must be deleted and extracted again
+    pub fn stale_frames(&self) -> impl Iterator<Item = &str> {
+        self.stale_frames.iter().map(String::as_str)
+    }
+
+    /// Episodes whose outputs should be deleted
+    pub fn removed(&self) -> impl Iterator<Item = &str> {
+        self.removed.iter().map(String::as_str)
+    }
+
+    /// Leave stale frames in place, recording the previous build's frame
+    /// inputs in `current` so the next run that extracts frames still
+    /// replaces them
+    ///
+    /// Used when frame extraction is skipped. The kept episodes also count as
+    /// changed on that next run.
+    pub fn keep_stale_frames(&self, current: &mut BuildManifest) {
+        let Some(previous) = &self.previous else {
+            return;
+        };
+        current.frames = previous.frames.clone();
+        for id in &self.stale_frames {
+            let (Some(old), Some(record)) =
+                (previous.episodes.get(id), current.episodes.get_mut(id))
+            else {
+                continue;
+            };
+            record.video = old.video.clone();
+            record.overrides.frame_offset_ms = old.overrides.frame_offset_ms;
+        }
+    }
+
+    /// Record of an episode from the previous build
+    pub fn previous(&self, episode: &str) -> Option<&EpisodeRecord> {
+        self.previous.as_ref()?.episodes.get(episode)
+    }
+
+    /// Episode records of the previous build that this run leaves untouched
+    ///
+    /// Filtered runs carry these over so the next full run still knows them.
+    pub fn carried_over<'a>(
+        &'a self,
+        current: &'a BuildManifest,
+    ) -> impl Iterator<Item = (&'a String, &'a EpisodeRecord)> {
+        self.previous
+            .iter()
+            .flat_map(|previous| previous.episodes.iter())
+            .filter(move |(id, _)| {
+                !current.episodes.contains_key(*id) && !self.removed.contains(*id)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FrameFlags;
+
+    fn record(subtitle: &str, size: u64) -> EpisodeRecord {
+        EpisodeRecord {
+            subtitle: subtitle.to_string(),
+            video: VideoIdentity {
+                path: PathBuf::from("video.mkv"),
+                size,
+                modified: 0,
+            },
+            overrides: EpisodeOverride::default(),
+            translations: BTreeMap::new(),
+            auto_sync: None,
+            captions: Vec::new(),
+            prev: None,
+            next: None,
+        }
+    }
+
+    fn manifest(episodes: &[(&str, EpisodeRecord)]) -> BuildManifest {
+        BuildManifest {
+            version: MANIFEST_VERSION,
+            templates: "t".to_string(),
+            pages: "p".to_string(),
+            frames: "f".to_string(),
+            episodes: episodes
+                .iter()
+                .map(|(id, r)| (id.to_string(), r.clone()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_hash_bytes() {
+        assert_eq!(
+            hash_bytes(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_plan_without_previous_is_full() {
+        let current = manifest(&[("S01E01", record("a", 1))]);
+        let plan = BuildPlan::new(None, &current, true);
+        assert!(plan.is_full());
+        assert!(plan.is_dirty("S01E01"));
+    }
+
+    #[test]
+    fn test_plan_detects_changes() {
+        let previous = manifest(&[
+            ("S01E01", record("a", 1)),
+            ("S01E02", record("b", 1)),
+            ("S01E03", record("c", 1)),
+            ("S01E04", record("d", 1)),
+        ]);
+        let current = manifest(&[
+            ("S01E01", record("a", 1)),
+            ("S01E02", record("changed", 1)),
+            ("S01E03", record("c", 2)),
+            ("S01E05", record("e", 1)),
+        ]);
+
+        let plan = BuildPlan::new(Some(previous.clone()), &current, true);
+        assert!(!plan.is_full());
+        assert_eq!(
+            plan.dirty().collect::<Vec<_>>(),
+            vec!["S01E02", "S01E03", "S01E05"]
+        );
+        assert_eq!(plan.stale_frames().collect::<Vec<_>>(), vec!["S01E03"]);
+        assert_eq!(plan.removed().collect::<Vec<_>>(), vec!["S01E04"]);
+
+        // Filtered runs keep episodes they didn't see
+        let plan = BuildPlan::new(Some(previous), &current, false);
+        assert_eq!(plan.removed().count(), 0);
+        let kept: Vec<_> = plan.carried_over(&current).map(|(id, _)| id).collect();
+        assert_eq!(kept, vec!["S01E04"]);
+    }
+
+    #[test]
+    fn test_plan_override_changes() {
+        let previous = manifest(&[("S01E01", record("a", 1)), ("S01E02", record("b", 1))]);
+
+        let mut current = previous.clone();
+        current
+            .episodes
+            .get_mut("S01E01")
+            .unwrap()
+            .overrides
+            .subtitle_offset_ms = Some(500);
+        current
+            .episodes
+            .get_mut("S01E02")
+            .unwrap()
+            .overrides
+            .frame_offset_ms = Some(-200);
+
+        let plan = BuildPlan::new(Some(previous), &current, true);
+        assert_eq!(plan.dirty().collect::<Vec<_>>(), vec!["S01E01", "S01E02"]);
+        assert_eq!(plan.stale_frames().collect::<Vec<_>>(), vec!["S01E02"]);
+    }
+
+    #[test]
+    fn test_plan_auto_sync_changes() {
+        let previous = manifest(&[("S01E01", record("a", 1)), ("S01E02", record("b", 1))]);
+        let correction = |applied| SyncCorrection {
+            offset_ms: 1200,
+            drift_ppm: 0,
+            confidence: if applied { 90 } else { 40 },
+            applied,
+        };
+
+        // Only corrections that were applied change the captions
+        let mut current = previous.clone();
+        current.episodes.get_mut("S01E01").unwrap().auto_sync = Some(correction(true));
+        current.episodes.get_mut("S01E02").unwrap().auto_sync = Some(correction(false));
+
+        let plan = BuildPlan::new(Some(previous), &current, true);
+        assert_eq!(plan.dirty().collect::<Vec<_>>(), vec!["S01E01"]);
+        assert_eq!(plan.stale_frames().count(), 0);
+    }
+
+    #[test]
+    fn test_plan_global_changes() {
+        let previous = manifest(&[("S01E01", record("a", 1))]);
+
+        let mut current = previous.clone();
+        current.pages = "other".to_string();
+        let plan = BuildPlan::new(Some(previous.clone()), &current, true);
+        assert!(plan.is_full());
+        assert_eq!(plan.stale_frames().count(), 0);
+
+        let mut current = previous.clone();
+        current.frames = "other".to_string();
+        let plan = BuildPlan::new(Some(previous), &current, true);
+        assert!(!plan.is_full());
+        assert_eq!(plan.stale_frames().collect::<Vec<_>>(), vec!["S01E01"]);
+    }
+
+    #[test]
+    fn test_keep_stale_frames() {
+        let previous = manifest(&[("S01E01", record("a", 1)), ("S01E02", record("b", 1))]);
+
+        let mut current = manifest(&[("S01E01", record("a", 2)), ("S01E02", record("b", 1))]);
+        current.frames = "other".to_string();
+        current
+            .episodes
+            .get_mut("S01E02")
+            .unwrap()
+            .overrides
+            .frame_offset_ms = Some(200);
+        let plan = BuildPlan::new(Some(previous.clone()), &current, true);
+        assert_eq!(
+            plan.stale_frames().collect::<Vec<_>>(),
+            vec!["S01E01", "S01E02"]
+        );
+
+        // Skipping extraction keeps the old frame inputs, so they stay stale
+        plan.keep_stale_frames(&mut current);
+        assert_eq!(current.frames, previous.frames);
+        let mut next = manifest(&[("S01E01", record("a", 2)), ("S01E02", record("b", 1))]);
+        next.episodes
+            .get_mut("S01E02")
+            .unwrap()
+            .overrides
+            .frame_offset_ms = Some(200);
+        let plan = BuildPlan::new(Some(current), &next, true);
+        assert_eq!(
+            plan.stale_frames().collect::<Vec<_>>(),
+            vec!["S01E01", "S01E02"]
+        );
+    }
+
+    #[test]
+    fn test_pages_hash_covers_cue_handling() {
+        let config = Config::default();
+        let frames =
+            FrameSettings::resolve_with_env(&config, &FrameFlags::default(), &|_| None).unwrap();
+        let before = BuildManifest::new(&config, &frames, &[]).unwrap();
+
+        let mut config = config;
+        config.subtitles.normalize.dedupe = true;
+        let normalized = BuildManifest::new(&config, &frames, &[]).unwrap();
+        assert_ne!(before.pages, normalized.pages);
+
+        config.search.sdh = true;
+        let sdh = BuildManifest::new(&config, &frames, &[]).unwrap();
+        assert_ne!(normalized.pages, sdh.pages);
+
+        config.search.index_sound_effects = !config.search.index_sound_effects;
+        let sounds = BuildManifest::new(&config, &frames, &[]).unwrap();
+        assert_ne!(sdh.pages, sounds.pages);
+    }
+}
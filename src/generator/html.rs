@@ -1,14 +1,24 @@
 //! HTML page generation using minijinja templates
 
 use minijinja::{context, Environment};
+use serde::Serialize;
 use std::path::Path;
 
 use crate::config::Config;
 use crate::discovery::Episode;
 use crate::error::{AnytronError, Result};
-use crate::indexer::SearchEntry;
+use crate::generator::build::hash_bytes;
+use crate::indexer::{Language, SearchEntry};
 use crate::subtitle::SubtitleEntry;
 
+/// A caption's line in another site language
+#[derive(Debug, Serialize)]
+struct TranslatedLine {
+    code: String,
+    name: String,
+    text: String,
+}
+
 /// HTML generator with template support
 pub struct HtmlGenerator<'a> {
     config: &'a Config,
@@ -28,6 +38,10 @@ impl<'a> HtmlGenerator<'a> {
         env.add_template("caption.html", CAPTION_TEMPLATE)
             .expect("Failed to add caption template");
 
+        // Add redirect stub template
+        env.add_template("redirect.html", REDIRECT_TEMPLATE)
+            .expect("Failed to add redirect template");
+
         Self { config, env }
     }
 
@@ -60,17 +74,68 @@ impl<'a> HtmlGenerator<'a> {
         &self,
         entry: &SearchEntry,
         subtitle: &SubtitleEntry,
-        _episode: &Episode,
+        episode: &Episode,
         prev: Option<&SearchEntry>,
         next: Option<&SearchEntry>,
         output_path: &Path,
     ) -> Result<()> {
+        let html = self.render_caption(entry, subtitle, episode, prev, next)?;
+
+        // Create parent directories
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| AnytronError::OutputDir {
+                path: parent.to_path_buf(),
+                source: e,
+            })?;
+        }
+
+        std::fs::write(output_path, html).map_err(|e| AnytronError::FileWrite {
+            path: output_path.to_path_buf(),
+            source: e,
+        })
+    }
+
+    /// Render a caption detail page to a string
+    pub fn render_caption(
+        &self,
+        entry: &SearchEntry,
+        subtitle: &SubtitleEntry,
+        _episode: &Episode,
+        prev: Option<&SearchEntry>,
+        next: Option<&SearchEntry>,
+    ) -> Result<String> {
         let template = self
             .env
             .get_template("caption.html")
             .map_err(|e| AnytronError::Template(e.to_string()))?;
 
-        let html = template
+        // Other languages in the order the site lists them
+        let order: Vec<String> = self
+            .config
+            .site
+            .languages
+            .iter()
+            .map(|tag| Language::new(tag).code().to_string())
+            .collect();
+        let mut translations: Vec<TranslatedLine> = entry
+            .translations
+            .iter()
+            .map(|(code, text)| TranslatedLine {
+                code: code.clone(),
+                name: Language::new(code).name(),
+                text: text.clone(),
+            })
+            .collect();
+        translations.sort_by_key(|line| order.iter().position(|code| *code == line.code));
+
+        // The site's second language is shown with the caption as a dual
+        // subtitle, the rest below it
+        let secondary = match translations.first() {
+            Some(line) if order.get(1) == Some(&line.code) => Some(translations.remove(0)),
+            _ => None,
+        };
+
+        template
             .render(context! {
                 title => &self.config.site.title,
                 show_name => &self.config.show.name,
@@ -82,11 +147,15 @@ impl<'a> HtmlGenerator<'a> {
                 id => &entry.id,
                 text => &subtitle.text,
                 text_clean => &subtitle.text_clean,
+                speaker => &subtitle.speaker,
                 episode => &entry.episode,
                 timestamp => entry.timestamp,
                 timestamp_formatted => format_timestamp(entry.timestamp),
                 frame => &entry.frame,
                 thumb => &entry.thumb,
+                primary_language => order.first().map(|code| Language::new(code).name()),
+                secondary => secondary,
+                translations => translations,
 
                 // Navigation
                 prev_id => prev.map(|p| &p.id),
@@ -94,23 +163,34 @@ impl<'a> HtmlGenerator<'a> {
                 next_id => next.map(|n| &n.id),
                 next_thumb => next.map(|n| &n.thumb),
             })
-            .map_err(|e| AnytronError::Template(e.to_string()))?;
+            .map_err(|e| AnytronError::Template(e.to_string()))
+    }
 
-        // Create parent directories
-        if let Some(parent) = output_path.parent() {
-            std::fs::create_dir_all(parent).map_err(|e| AnytronError::OutputDir {
-                path: parent.to_path_buf(),
-                source: e,
-            })?;
-        }
+    /// Render a stub page redirecting a retired caption ID to `target`
+    pub fn render_redirect(&self, target: &str) -> Result<String> {
+        let template = self
+            .env
+            .get_template("redirect.html")
+            .map_err(|e| AnytronError::Template(e.to_string()))?;
 
-        std::fs::write(output_path, html).map_err(|e| AnytronError::FileWrite {
-            path: output_path.to_path_buf(),
-            source: e,
-        })
+        template
+            .render(context! {
+                title => &self.config.site.title,
+                base_url => &self.config.site.base_url,
+                target => target,
+            })
+            .map_err(|e| AnytronError::Template(e.to_string()))
     }
 }
 
+/// Hash of the embedded templates and crate version
+///
+/// Caption pages rendered by a different template version are rebuilt.
+pub fn template_version() -> String {
+    let templates = [env!("CARGO_PKG_VERSION"), INDEX_TEMPLATE, CAPTION_TEMPLATE].join("\0");
+    hash_bytes(templates.as_bytes())
+}
+
 /// Format timestamp as HH:MM:SS
 fn format_timestamp(ms: u64) -> String {
     let total_secs = ms / 1000;
@@ -148,6 +228,7 @@ const INDEX_TEMPLATE: &str = r#"<!DOCTYPE html>
                     autocomplete="off"
                     autofocus
                 >
+                <select class="search-language" id="search-language" aria-label="Search language" hidden></select>
                 <button type="submit" class="search-button">Search</button>
             </form>
         </section>
@@ -211,7 +292,23 @@ const CAPTION_TEMPLATE: &str = r#"<!DOCTYPE html>
             </div>
 
             <div class="caption-info">
+                {% if speaker %}
+                <p class="caption-speaker">{{ speaker }}</p>
+                {% endif %}
+                {% if secondary %}
+                <div class="caption-dual">
+                    <p class="caption-quote" title="{{ primary_language }}">"{{ text_clean }}"</p>
+                    <p class="caption-quote caption-quote--secondary" data-language="{{ secondary.code }}" title="{{ secondary.name }}">"{{ secondary.text }}"</p>
+                </div>
+                {% else %}
                 <p class="caption-quote">"{{ text_clean }}"</p>
+                {% endif %}
+                {% for line in translations %}
+                <p class="caption-translation" data-language="{{ line.code }}">
+                    <span class="caption-translation__language">{{ line.name }}</span>
+                    "{{ line.text }}"
+                </p>
+                {% endfor %}
                 <p class="caption-meta">
                     <span class="caption-episode">{{ episode }}</span>
                     <span class="caption-timestamp">{{ timestamp_formatted }}</span>
@@ -284,6 +381,22 @@ const CAPTION_TEMPLATE: &str = r#"<!DOCTYPE html>
 </html>
 "#;
 
+/// Redirect stub template for retired caption IDs
+const REDIRECT_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>{{ title }}</title>
+    <meta name="robots" content="noindex">
+    <link rel="canonical" href="{{ base_url }}caption/{{ target }}.html">
+    <meta http-equiv="refresh" content="0; url={{ base_url }}caption/{{ target }}.html">
+</head>
+<body>
+    <p>This caption has moved to <a href="{{ base_url }}caption/{{ target }}.html">{{ target }}</a>.</p>
+</body>
+</html>
+"#;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,9 +1,13 @@
 //! Site generation module
 
 pub mod assets;
+pub mod build;
 pub mod html;
+pub mod redirect;
 pub mod site;
 
 pub use assets::AssetBundler;
+pub use build::{BuildManifest, BuildPlan, EpisodeRecord, VideoIdentity};
 pub use html::HtmlGenerator;
+pub use redirect::{CaptionIdMap, IdChanges, PublishedCaption};
 pub use site::SiteGenerator;
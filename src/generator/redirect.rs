@@ -0,0 +1,255 @@
+//! Persisted caption ID map and redirects for retired IDs
+//!
+//! Every caption ID the site has published is recorded in
+//! `.anytron/caption-ids.json` together with its episode and timestamp. When an
+//! ID disappears because its line was edited or removed, its page is replaced
+//! with a redirect stub to the nearest caption of the same episode, so links
+//! that were shared keep working.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use crate::error::{AnytronError, Result};
+use crate::indexer::SearchEntry;
+
+/// Location of the caption ID map inside the output directory
+pub const CAPTION_IDS_PATH: &str = ".anytron/caption-ids.json";
+
+/// Where a published caption ID pointed when it was last current
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublishedCaption {
+    pub episode: String,
+
+    /// Timestamp in milliseconds
+    pub timestamp: u64,
+}
+
+/// Every caption ID published so far
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptionIdMap {
+    pub ids: BTreeMap<String, PublishedCaption>,
+}
+
+/// Outcome of [`CaptionIdMap::update`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdChanges {
+    /// Retired IDs and the current caption each redirects to
+    pub redirects: Vec<(String, String)>,
+
+    /// Retired IDs of episodes without any captions left
+    pub dropped: Vec<String>,
+}
+
+impl CaptionIdMap {
+    /// Load the ID map of a previous build
+    pub fn load(output_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(output_dir.join(CAPTION_IDS_PATH)).ok()?;
+        match serde_json::from_str(&content) {
+            Ok(map) => Some(map),
+            Err(e) => {
+                log::warn!("Ignoring unreadable caption ID map: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Recover published IDs from the caption pages of an older build
+    ///
+    /// Builds before the ID map existed named caption pages
+    /// `<episode>-<midpoint ms>.html`.
+    pub fn from_legacy_pages(caption_dir: &Path) -> Self {
+        let mut map = Self::default();
+        let Ok(listing) = std::fs::read_dir(caption_dir) else {
+            return map;
+        };
+
+        for file in listing.filter_map(|e| e.ok()) {
+            let path = file.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("html") {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let Some((episode, ms)) = id.rsplit_once('-') else {
+                continue;
+            };
+            if episode.contains('-') {
+                continue;
+            }
+            if let Ok(timestamp) = ms.parse() {
+                let episode = episode.to_string();
+                map.ids
+                    .insert(id.to_string(), PublishedCaption { episode, timestamp });
+            }
+        }
+        map
+    }
+
+    /// Write the map into the output directory
+    pub fn save(&self, output_dir: &Path) -> Result<()> {
+        let path = output_dir.join(CAPTION_IDS_PATH);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| AnytronError::OutputDir {
+                path: parent.to_path_buf(),
+                source: e,
+            })?;
+        }
+
+        let json = serde_json::to_string(self)
+            .map_err(|e| AnytronError::Output(format!("Failed to serialize ID map: {}", e)))?;
+        std::fs::write(&path, json).map_err(|e| AnytronError::FileWrite { path, source: e })
+    }
+
+    /// Record the current captions and resolve every retired ID
+    ///
+    /// A retired ID redirects to the caption of its episode closest to its
+    /// last known timestamp. Only IDs of episodes in `scope` are resolved;
+    /// those of episodes outside a filtered run are left untouched.
+    pub fn update(&mut self, entries: &[SearchEntry], scope: &HashSet<&str>) -> IdChanges {
+        let mut current: BTreeMap<&str, Vec<(u64, &str)>> = BTreeMap::new();
+        for entry in entries {
+            current
+                .entry(&entry.episode)
+                .or_default()
+                .push((entry.timestamp, &entry.id));
+        }
+        for captions in current.values_mut() {
+            captions.sort();
+        }
+
+        let live: HashSet<&str> = entries.iter().map(|entry| entry.id.as_str()).collect();
+        let mut changes = IdChanges::default();
+        self.ids.retain(|id, published| {
+            if live.contains(id.as_str()) || !scope.contains(published.episode.as_str()) {
+                return true;
+            }
+            match current
+                .get(published.episode.as_str())
+                .and_then(|captions| closest(captions, published.timestamp))
+            {
+                Some(target) => {
+                    changes.redirects.push((id.clone(), target.to_string()));
+                    true
+                }
+                None => {
+                    changes.dropped.push(id.clone());
+                    false
+                }
+            }
+        });
+
+        for entry in entries {
+            self.ids.insert(
+                entry.id.clone(),
+                PublishedCaption {
+                    episode: entry.episode.clone(),
+                    timestamp: entry.timestamp,
+                },
+            );
+        }
+
+        changes
+    }
+}
+
+/// ID of the caption nearest to `timestamp`, preferring the earlier on a tie
+fn closest<'a>(captions: &[(u64, &'a str)], timestamp: u64) -> Option<&'a str> {
+    let after = captions.partition_point(|(ms, _)| *ms < timestamp);
+    let before = after.checked_sub(1).map(|i| captions[i]);
+    match (before, captions.get(after)) {
+        (Some(b), Some(a)) if a.0 - timestamp < timestamp - b.0 => Some(a.1),
+        (Some(b), _) => Some(b.1),
+        (None, a) => a.map(|a| a.1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(id: &str, episode: &str, timestamp: u64) -> SearchEntry {
+        SearchEntry {
+            id: id.to_string(),
+            text: String::new(),
+            speaker: None,
+            sound: None,
+            music: None,
+            episode: episode.to_string(),
+            timestamp,
+            frame: String::new(),
+            thumb: String::new(),
+            translations: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_retired_ids_redirect_to_closest_caption() {
+        let mut map = CaptionIdMap::default();
+        let scope: HashSet<&str> = ["S01E01", "S01E02"].into();
+        let first = vec![
+            entry("S01E01-aaaa", "S01E01", 1000),
+            entry("S01E01-bbbb", "S01E01", 5000),
+            entry("S01E01-cccc", "S01E01", 9000),
+            entry("S01E02-dddd", "S01E02", 1000),
+        ];
+        assert_eq!(map.update(&first, &scope), IdChanges::default());
+
+        // The middle line was reworded and episode 2 lost all its captions
+        let second = vec![
+            entry("S01E01-aaaa", "S01E01", 1000),
+            entry("S01E01-eeee", "S01E01", 5200),
+            entry("S01E01-cccc", "S01E01", 9000),
+        ];
+        let changes = map.update(&second, &scope);
+        assert_eq!(
+            changes.redirects,
+            vec![("S01E01-bbbb".to_string(), "S01E01-eeee".to_string())]
+        );
+        assert_eq!(changes.dropped, vec!["S01E02-dddd".to_string()]);
+
+        // Retired IDs keep redirecting on later builds; ties go to the earlier
+        let third = vec![
+            entry("S01E01-aaaa", "S01E01", 1000),
+            entry("S01E01-cccc", "S01E01", 9000),
+        ];
+        let changes = map.update(&third, &scope);
+        assert_eq!(
+            changes.redirects,
+            vec![
+                ("S01E01-bbbb".to_string(), "S01E01-aaaa".to_string()),
+                ("S01E01-eeee".to_string(), "S01E01-cccc".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_out_of_scope_ids_are_kept() {
+        let mut map = CaptionIdMap::default();
+        map.update(&[entry("S02E01-aaaa", "S02E01", 1000)], &["S02E01"].into());
+
+        let changes = map.update(&[entry("S01E01-bbbb", "S01E01", 1000)], &["S01E01"].into());
+        assert_eq!(changes, IdChanges::default());
+        assert!(map.ids.contains_key("S02E01-aaaa"));
+    }
+
+    #[test]
+    fn test_from_legacy_pages() {
+        let temp_dir = TempDir::new().unwrap();
+        for name in ["S01E01-2500.html", "S01E02-12000.html", "notes.txt"] {
+            std::fs::write(temp_dir.path().join(name), "").unwrap();
+        }
+
+        let map = CaptionIdMap::from_legacy_pages(temp_dir.path());
+        assert_eq!(map.ids.len(), 2);
+        assert_eq!(
+            map.ids["S01E02-12000"],
+            PublishedCaption {
+                episode: "S01E02".to_string(),
+                timestamp: 12000,
+            }
+        );
+    }
+}
@@ -1,20 +1,34 @@
 //! Site generation orchestration
 
-use std::collections::HashMap;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
 
-use crate::config::Config;
+use crate::config::{Config, IndexLayout};
 use crate::discovery::Episode;
 use crate::error::{AnytronError, Result};
 use crate::generator::assets::AssetBundler;
+use crate::generator::build::{BuildManifest, BuildPlan};
 use crate::generator::html::HtmlGenerator;
-use crate::indexer::{SearchEntry, SearchIndex};
+use crate::generator::redirect::CaptionIdMap;
+use crate::indexer::{
+    caption_ids, IndexManifest, Language, LanguageInfo, SearchEntry, SearchIndex, ShardedIndex,
+};
 use crate::subtitle::SubtitleEntry;
 
+/// Rendered caption pages waiting for the writer thread
+const WRITE_QUEUE_SIZE: usize = 256;
+
 /// Site generator - orchestrates all generation tasks
 pub struct SiteGenerator<'a> {
     config: &'a Config,
     output_dir: PathBuf,
+
+    /// Number of parallel render jobs (None = use rayon default)
+    jobs: Option<usize>,
 }
 
 impl<'a> SiteGenerator<'a> {
@@ -23,28 +37,194 @@ impl<'a> SiteGenerator<'a> {
         Self {
             config,
             output_dir: output_dir.to_path_buf(),
+            jobs: None,
         }
     }
 
+    /// Set number of parallel jobs
+    pub fn with_jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
     /// Generate the complete site
     pub fn generate(
         &self,
         episodes: &[(Episode, Vec<SubtitleEntry>)],
         index: &SearchIndex,
     ) -> Result<()> {
+        let plan = BuildPlan::full();
+        self.generate_pages(episodes, index, &plan)?;
+        self.write_redirects(episodes, index, &plan)
+    }
+
+    /// Generate the site, rendering only the caption pages `plan` marks as
+    /// out of date, then record the build in `manifest`
+    ///
+    /// Outputs of episodes the plan reports as removed are deleted.
+    pub fn generate_incremental(
+        &self,
+        episodes: &[(Episode, Vec<SubtitleEntry>)],
+        index: &SearchIndex,
+        plan: &BuildPlan,
+        manifest: &mut BuildManifest,
+    ) -> Result<()> {
+        let pages = self.generate_pages(episodes, index, plan)?;
+
+        for (id, record) in manifest.episodes.iter_mut() {
+            // Drop caption pages that no longer exist in a rebuilt episode
+            if let (Some(old), Some(new)) = (plan.previous(id), pages.get(id)) {
+                let current: HashSet<&str> = new.captions.iter().map(String::as_str).collect();
+                for caption in &old.captions {
+                    if !current.contains(caption.as_str()) {
+                        self.remove_file(&self.caption_path(caption))?;
+                    }
+                }
+            }
+
+            if let Some(new) = pages.get(id) {
+                record.captions = new.captions.clone();
+                record.prev = new.prev.clone();
+                record.next = new.next.clone();
+            }
+        }
+
+        // Frames of cues that moved or disappeared from a changed subtitle
+        for (episode, subs) in episodes {
+            let id = episode.id.to_string();
+            if plan.previous(&id).is_some() && plan.dirty().any(|dirty| dirty == id) {
+                let keep: HashSet<u64> = subs.iter().map(|sub| sub.midpoint().0).collect();
+                self.prune_frames(&id, &keep)?;
+            }
+        }
+
+        for id in plan.removed() {
+            log::info!("Removing outputs of {}", id);
+            if let Some(old) = plan.previous(id) {
+                for caption in &old.captions {
+                    self.remove_file(&self.caption_path(caption))?;
+                }
+            }
+            self.remove_frames(id)?;
+        }
+
+        // Pages deleted above become redirects where the episode still exists
+        self.write_redirects(episodes, index, plan)?;
+
+        let carried: Vec<_> = plan
+            .carried_over(manifest)
+            .map(|(id, record)| (id.clone(), record.clone()))
+            .collect();
+        manifest.episodes.extend(carried);
+
+        manifest.save(&self.output_dir)
+    }
+
+    /// Replace pages of retired caption IDs with redirect stubs
+    ///
+    /// Retired IDs of episodes without captions, such as removed episodes,
+    /// have their pages deleted instead.
+    fn write_redirects(
+        &self,
+        episodes: &[(Episode, Vec<SubtitleEntry>)],
+        index: &SearchIndex,
+        plan: &BuildPlan,
+    ) -> Result<()> {
+        let mut ids = CaptionIdMap::load(&self.output_dir)
+            .unwrap_or_else(|| CaptionIdMap::from_legacy_pages(&self.output_dir.join("caption")));
+
+        let run: Vec<String> = episodes.iter().map(|(e, _)| e.id.to_string()).collect();
+        let scope: HashSet<&str> = run
+            .iter()
+            .map(String::as_str)
+            .chain(plan.removed())
+            .collect();
+        let changes = ids.update(&index.entries, &scope);
+
+        for id in &changes.dropped {
+            self.remove_file(&self.caption_path(id))?;
+        }
+
+        let html_gen = HtmlGenerator::new(self.config);
+        for (from, to) in &changes.redirects {
+            let html = html_gen.render_redirect(to)?;
+            write_file_if_changed(&self.caption_path(from), html)?;
+        }
+        if !changes.redirects.is_empty() {
+            log::info!(
+                "Redirecting {} retired caption IDs",
+                changes.redirects.len()
+            );
+        }
+
+        ids.save(&self.output_dir)
+    }
+
+    /// Delete an episode's extracted frames and thumbnails
+    pub fn remove_frames(&self, episode: &str) -> Result<()> {
+        for kind in ["frames", "thumbs"] {
+            let path = self.output_dir.join("img").join(kind).join(episode);
+            if path.exists() {
+                std::fs::remove_dir_all(&path)
+                    .map_err(|e| AnytronError::OutputDir { path, source: e })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete an episode's frames whose timestamps are not in `keep`
+    fn prune_frames(&self, episode: &str, keep: &HashSet<u64>) -> Result<()> {
+        for kind in ["frames", "thumbs"] {
+            let dir = self.output_dir.join("img").join(kind).join(episode);
+            let Ok(listing) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for file in listing.filter_map(|e| e.ok()) {
+                let path = file.path();
+                let timestamp = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok());
+                if timestamp.is_some_and(|ms| !keep.contains(&ms)) {
+                    self.remove_file(&path)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Render pages, the search index and assets
+    fn generate_pages(
+        &self,
+        episodes: &[(Episode, Vec<SubtitleEntry>)],
+        index: &SearchIndex,
+        plan: &BuildPlan,
+    ) -> Result<BTreeMap<String, EpisodePages>> {
         // Create output directories
         self.create_directories()?;
 
         // Generate HTML pages
-        self.generate_html(episodes, index)?;
+        let started = Instant::now();
+        let pages = self.generate_html(episodes, index, plan)?;
+        let html_time = started.elapsed();
 
         // Write search index
+        let started = Instant::now();
         self.write_search_index(index)?;
+        let index_time = started.elapsed();
 
         // Bundle and copy assets
+        let started = Instant::now();
         self.bundle_assets()?;
 
-        Ok(())
+        log::info!(
+            "Site generation: pages {:.2?}, search index {:.2?}, assets {:.2?}",
+            html_time,
+            index_time,
+            started.elapsed()
+        );
+
+        Ok(pages)
     }
 
     /// Create required output directories
@@ -60,29 +240,30 @@ impl<'a> SiteGenerator<'a> {
         Ok(())
     }
 
-    /// Generate all HTML pages
+    /// Generate HTML pages, skipping caption pages that are up to date
+    ///
+    /// Caption pages are rendered in parallel through the shared template
+    /// environment and handed to a single writer thread over a bounded
+    /// channel, so rendering never runs far ahead of the disk.
     fn generate_html(
         &self,
         episodes: &[(Episode, Vec<SubtitleEntry>)],
         index: &SearchIndex,
-    ) -> Result<()> {
+        plan: &BuildPlan,
+    ) -> Result<BTreeMap<String, EpisodePages>> {
+        let started = Instant::now();
         let html_gen = HtmlGenerator::new(self.config);
 
         // Generate index page
         html_gen.generate_index(&self.output_dir.join("index.html"))?;
 
-        // Build lookup maps for navigation
-        let entry_map: HashMap<&str, (&SearchEntry, &SubtitleEntry, &Episode)> = episodes
+        // Subtitle and episode behind each entry ID
+        let sources: HashMap<String, (&SubtitleEntry, &Episode)> = episodes
             .iter()
             .flat_map(|(episode, subs)| {
-                subs.iter().filter_map(move |sub| {
-                    let id = format!("{}-{}", episode.id, sub.midpoint().0);
-                    index
-                        .entries
-                        .iter()
-                        .find(|e| e.id == id)
-                        .map(|entry| (entry.id.as_str(), (entry, sub, episode)))
-                })
+                caption_ids(&episode.id, subs)
+                    .into_iter()
+                    .zip(subs.iter().map(move |sub| (sub, episode)))
             })
             .collect();
 
@@ -94,39 +275,282 @@ impl<'a> SiteGenerator<'a> {
                 .then(a.timestamp.cmp(&b.timestamp))
         });
 
-        // Generate caption pages
+        let mut pages: BTreeMap<String, EpisodePages> = episodes
+            .iter()
+            .map(|(episode, _)| (episode.id.to_string(), EpisodePages::default()))
+            .collect();
+
+        // Decide which caption pages need rendering
+        let mut jobs = Vec::new();
         for (i, entry) in sorted_entries.iter().enumerate() {
-            let prev = if i > 0 {
-                Some(sorted_entries[i - 1])
-            } else {
-                None
-            };
+            let prev = i.checked_sub(1).map(|p| sorted_entries[p]);
             let next = sorted_entries.get(i + 1).copied();
 
-            if let Some((_, subtitle, episode)) = entry_map.get(entry.id.as_str()) {
-                let output_path = self
-                    .output_dir
-                    .join("caption")
-                    .join(format!("{}.html", entry.id));
+            let episode_pages = pages.entry(entry.episode.clone()).or_default();
+            episode_pages.captions.push(entry.id.clone());
+
+            // Links across episode boundaries change when a neighbour does
+            let previous = plan.previous(&entry.episode);
+            let mut relinked = false;
+            if prev.map_or(true, |p| p.episode != entry.episode) {
+                episode_pages.prev = prev.map(|p| p.id.clone());
+                relinked |= previous.map_or(true, |r| r.prev != episode_pages.prev);
+            }
+            if next.map_or(true, |n| n.episode != entry.episode) {
+                episode_pages.next = next.map(|n| n.id.clone());
+                relinked |= previous.map_or(true, |r| r.next != episode_pages.next);
+            }
 
-                html_gen.generate_caption(entry, subtitle, episode, prev, next, &output_path)?;
+            if plan.is_dirty(&entry.episode) || relinked || !self.caption_path(&entry.id).exists() {
+                jobs.push(i);
             }
         }
+        let lookup = started.elapsed();
 
-        Ok(())
+        let render_nanos = AtomicU64::new(0);
+        let write_time = std::thread::scope(|scope| -> Result<Duration> {
+            let (sender, receiver) = mpsc::sync_channel::<(PathBuf, String)>(WRITE_QUEUE_SIZE);
+
+            let writer = scope.spawn(move || -> Result<Duration> {
+                let mut busy = Duration::ZERO;
+                for (path, html) in receiver {
+                    let write_started = Instant::now();
+                    write_file(&path, html)?;
+                    busy += write_started.elapsed();
+                }
+                Ok(busy)
+            });
+
+            let rendered = self.thread_pool()?.install(|| {
+                jobs.par_iter().try_for_each_with(sender, |sender, &i| {
+                    let entry = sorted_entries[i];
+                    let Some((subtitle, episode)) = sources.get(&entry.id) else {
+                        return Ok(());
+                    };
+                    let prev = i.checked_sub(1).map(|p| sorted_entries[p]);
+                    let next = sorted_entries.get(i + 1).copied();
+
+                    let render_started = Instant::now();
+                    let html = html_gen.render_caption(entry, subtitle, episode, prev, next)?;
+                    render_nanos.fetch_add(
+                        render_started.elapsed().as_nanos() as u64,
+                        Ordering::Relaxed,
+                    );
+
+                    // Fails only once the writer has stopped on an error
+                    sender
+                        .send((self.caption_path(&entry.id), html))
+                        .map_err(|_| AnytronError::Output("Caption writer stopped".to_string()))
+                })
+            });
+
+            // A writer error explains any render-side send failure
+            let written = writer
+                .join()
+                .map_err(|_| AnytronError::Output("Caption writer panicked".to_string()))?;
+            let write_time = written?;
+            rendered?;
+            Ok(write_time)
+        })?;
+
+        log::info!(
+            "Rendered {} of {} caption pages in {:.2?} (lookup {:.2?}, render {:.2?} across threads, write {:.2?})",
+            jobs.len(),
+            sorted_entries.len(),
+            started.elapsed(),
+            lookup,
+            Duration::from_nanos(render_nanos.into_inner()),
+            write_time
+        );
+
+        Ok(pages)
+    }
+
+    /// Thread pool for page rendering, sized by `jobs`
+    fn thread_pool(&self) -> Result<rayon::ThreadPool> {
+        let mut builder = rayon::ThreadPoolBuilder::new();
+        if let Some(num_jobs) = self.jobs {
+            builder = builder.num_threads(num_jobs);
+        }
+        builder
+            .build()
+            .map_err(|e| AnytronError::Output(format!("Failed to build thread pool: {}", e)))
+    }
+
+    /// Path of a caption page
+    fn caption_path(&self, id: &str) -> PathBuf {
+        self.output_dir.join("caption").join(format!("{}.html", id))
+    }
+
+    /// Delete a generated file if it exists
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(AnytronError::FileWrite {
+                path: path.to_path_buf(),
+                source: e,
+            }),
+            _ => Ok(()),
+        }
     }
 
-    /// Write the search index JSON
+    /// Write the search index of every site language
+    ///
+    /// The primary language is written directly to `search/`, the others to
+    /// `search/<code>/`, and the top-level manifest lists them all.
     fn write_search_index(&self, index: &SearchIndex) -> Result<()> {
-        let index_path = self.output_dir.join("search").join("index.json");
+        let search_dir = self.output_dir.join("search");
 
-        let json = serde_json::to_string_pretty(index)
-            .map_err(|e| AnytronError::Output(format!("Failed to serialize index: {}", e)))?;
+        let mut languages: Vec<LanguageInfo> = index
+            .meta
+            .language
+            .iter()
+            .map(|code| LanguageInfo {
+                code: code.clone(),
+                name: Language::new(code).name(),
+                manifest: "manifest.json".to_string(),
+            })
+            .collect();
+        for translation in &index.translations {
+            let Some(code) = &translation.meta.language else {
+                continue;
+            };
+            let dir = search_dir.join(code);
+            std::fs::create_dir_all(&dir).map_err(|e| AnytronError::OutputDir {
+                path: dir.clone(),
+                source: e,
+            })?;
+            let manifest = self.write_language_index(translation, &dir)?;
+            write_file(&dir.join("manifest.json"), to_json(&manifest)?)?;
+            languages.push(LanguageInfo {
+                code: code.clone(),
+                name: Language::new(code).name(),
+                manifest: format!("{}/manifest.json", code),
+            });
+        }
 
-        std::fs::write(&index_path, json).map_err(|e| AnytronError::FileWrite {
-            path: index_path,
+        // Drop languages that are no longer part of the site
+        let listing = std::fs::read_dir(&search_dir).map_err(|e| AnytronError::OutputDir {
+            path: search_dir.clone(),
             source: e,
-        })
+        })?;
+        for dir in listing.filter_map(|e| e.ok()) {
+            let name = dir.file_name().to_string_lossy().into_owned();
+            let current = ["terms", "docs"].contains(&name.as_str())
+                || languages.iter().any(|language| language.code == name);
+            if dir.path().is_dir() && !current {
+                let path = dir.path();
+                std::fs::remove_dir_all(&path)
+                    .map_err(|e| AnytronError::OutputDir { path, source: e })?;
+            }
+        }
+
+        let mut manifest = self.write_language_index(index, &search_dir)?;
+        manifest.languages = languages;
+        write_file(&search_dir.join("manifest.json"), to_json(&manifest)?)
+    }
+
+    /// Write one language's index to `dir`, as one file or as shards, and
+    /// return the manifest describing it
+    fn write_language_index(&self, index: &SearchIndex, dir: &Path) -> Result<IndexManifest> {
+        let search = &self.config.search;
+
+        // Compact output: the prebuilt lunr index is mostly numeric arrays
+        let json = match search.layout {
+            IndexLayout::Sharded => None,
+            _ => Some(to_json(index)?),
+        };
+        let sharded = match search.layout {
+            IndexLayout::Single => false,
+            IndexLayout::Sharded => true,
+            IndexLayout::Auto => json
+                .as_ref()
+                .is_some_and(|json| json.len() > search.shard_threshold_mb * 1024 * 1024),
+        };
+
+        let mut manifest = match json {
+            Some(json) if !sharded => {
+                // Drop files left over from a sharded build
+                for sub in ["terms", "docs"] {
+                    let path = dir.join(sub);
+                    if path.exists() {
+                        std::fs::remove_dir_all(&path)
+                            .map_err(|e| AnytronError::OutputDir { path, source: e })?;
+                    }
+                }
+
+                write_file(&dir.join("index.json"), json)?;
+                IndexManifest::single(index, "index.json")
+            }
+            _ => {
+                self.remove_file(&dir.join("index.json"))?;
+                self.write_sharded_index(index, dir)?
+            }
+        };
+
+        let stems = dir.join("stems.json");
+        let language = index.meta.language.as_deref().map(Language::new);
+        if language.is_some_and(|language| language.uses_stem_table()) {
+            write_file_if_changed(&stems, to_json(&index.stems)?)?;
+            manifest.stems = Some("stems.json".to_string());
+        } else {
+            self.remove_file(&stems)?;
+        }
+
+        Ok(manifest)
+    }
+
+    /// Write term shards and per-episode document files, returning their
+    /// manifest
+    ///
+    /// Files whose content is unchanged are left alone, and files from a
+    /// previous build that are no longer referenced are deleted.
+    fn write_sharded_index(&self, index: &SearchIndex, search_dir: &Path) -> Result<IndexManifest> {
+        let sharded = ShardedIndex::build(index, self.config.search.shard_size_kb * 1024);
+
+        for dir in ["terms", "docs"] {
+            let path = search_dir.join(dir);
+            std::fs::create_dir_all(&path)
+                .map_err(|e| AnytronError::OutputDir { path, source: e })?;
+        }
+
+        let mut written = HashSet::new();
+        for (info, shard) in sharded.manifest.shards.iter().zip(&sharded.shards) {
+            let path = search_dir.join(&info.file);
+            write_file_if_changed(&path, to_json(shard)?)?;
+            written.insert(path);
+        }
+        for (episode, entries) in &sharded.docs {
+            let path = search_dir.join(&sharded.manifest.docs[episode]);
+            write_file_if_changed(&path, to_json(entries)?)?;
+            written.insert(path);
+        }
+
+        for dir in ["terms", "docs"] {
+            let listing =
+                std::fs::read_dir(search_dir.join(dir)).map_err(|e| AnytronError::OutputDir {
+                    path: search_dir.join(dir),
+                    source: e,
+                })?;
+            for file in listing.filter_map(|e| e.ok()) {
+                if !written.contains(&file.path()) {
+                    self.remove_file(&file.path())?;
+                }
+            }
+        }
+
+        log::info!(
+            "Search index{}: {} term shards, {} document files",
+            index
+                .meta
+                .language
+                .as_ref()
+                .map(|code| format!(" ({})", code))
+                .unwrap_or_default(),
+            sharded.shards.len(),
+            sharded.docs.len()
+        );
+
+        Ok(sharded.manifest)
     }
 
     /// Bundle and copy static assets
@@ -142,3 +566,33 @@ impl<'a> SiteGenerator<'a> {
         Ok(())
     }
 }
+
+/// Serialize a value as compact JSON
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value)
+        .map_err(|e| AnytronError::Output(format!("Failed to serialize index: {}", e)))
+}
+
+/// Write a generated file
+fn write_file(path: &Path, content: String) -> Result<()> {
+    std::fs::write(path, content).map_err(|e| AnytronError::FileWrite {
+        path: path.to_path_buf(),
+        source: e,
+    })
+}
+
+/// Write a generated file unless it already has exactly this content
+fn write_file_if_changed(path: &Path, content: String) -> Result<()> {
+    match std::fs::read(path) {
+        Ok(existing) if existing == content.as_bytes() => Ok(()),
+        _ => write_file(path, content),
+    }
+}
+
+/// Caption pages of one episode and its links to neighbouring episodes
+#[derive(Debug, Clone, Default)]
+struct EpisodePages {
+    captions: Vec<String>,
+    prev: Option<String>,
+    next: Option<String>,
+}
@@ -0,0 +1,348 @@
+//! Language-specific text analysis for search indexes
+//!
+//! English goes through lunr's own trimmer, stop word filter and Porter
+//! stemmer, so the browser's built-in `lunr.stemmer` stems queries exactly as
+//! the index was built. Other languages get a Unicode-aware trimmer, their own
+//! stop words and a Snowball stemmer. lunr has no such stemmers in the
+//! browser, so their indexes ship a table of every word form that stemmed to
+//! something else, and the client registers a lookup over it under the name
+//! in the index pipeline (`stemmer-spa`, ...).
+
+use rust_stemmers::{Algorithm, Stemmer};
+use std::collections::BTreeMap;
+
+use crate::extractor::normalize_language;
+use crate::indexer::lunr;
+
+/// Stemmer, stop words and display name of a supported language
+struct LanguageSpec {
+    code: &'static str,
+    name: &'static str,
+    algorithm: Option<Algorithm>,
+    stop_words: &'static [&'static str],
+}
+
+/// Languages with a native display name, keyed by ISO 639-2 code
+const LANGUAGES: &[LanguageSpec] = &[
+    LanguageSpec {
+        code: "eng",
+        name: "English",
+        algorithm: None,
+        stop_words: &[],
+    },
+    LanguageSpec {
+        code: "spa",
+        name: "Español",
+        algorithm: Some(Algorithm::Spanish),
+        stop_words: &[
+            "a", "al", "algo", "como", "con", "de", "del", "el", "ella", "ellas", "ellos", "en",
+            "entre", "era", "es", "esa", "ese", "eso", "esta", "este", "esto", "fue", "ha", "hay",
+            "la", "las", "le", "les", "lo", "los", "me", "mi", "mis", "muy", "nos", "o", "para",
+            "pero", "por", "que", "se", "sin", "su", "sus", "te", "tu", "un", "una", "uno", "y",
+            "ya", "yo",
+        ],
+    },
+    LanguageSpec {
+        code: "fre",
+        name: "Français",
+        algorithm: Some(Algorithm::French),
+        stop_words: &[
+            "à", "au", "aux", "avec", "ce", "ces", "dans", "de", "des", "du", "elle", "en", "et",
+            "eux", "il", "ils", "je", "la", "le", "les", "leur", "lui", "ma", "mais", "me", "mes",
+            "moi", "mon", "ne", "nos", "notre", "nous", "on", "ou", "par", "pas", "pour", "qu",
+            "que", "qui", "sa", "se", "ses", "son", "sur", "ta", "te", "tes", "toi", "ton", "tu",
+            "un", "une", "vos", "votre", "vous",
+        ],
+    },
+    LanguageSpec {
+        code: "ger",
+        name: "Deutsch",
+        algorithm: Some(Algorithm::German),
+        stop_words: &[
+            "aber", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bist", "da", "das",
+            "dass", "dem", "den", "der", "des", "die", "du", "ein", "eine", "einen", "einer", "er",
+            "es", "für", "hat", "ich", "ihr", "im", "in", "ist", "mit", "nicht", "noch", "nur",
+            "oder", "sich", "sie", "sind", "so", "und", "uns", "von", "war", "was", "wir", "zu",
+            "zum", "zur",
+        ],
+    },
+    LanguageSpec {
+        code: "ita",
+        name: "Italiano",
+        algorithm: Some(Algorithm::Italian),
+        stop_words: &[
+            "a", "al", "alla", "anche", "che", "ci", "come", "con", "da", "dal", "dei", "del",
+            "della", "di", "e", "gli", "ha", "ho", "i", "il", "in", "io", "la", "le", "lei", "lo",
+            "lui", "ma", "mi", "ne", "nel", "noi", "non", "per", "più", "se", "si", "sono", "su",
+            "sua", "suo", "ti", "tu", "un", "una", "uno", "voi",
+        ],
+    },
+    LanguageSpec {
+        code: "por",
+        name: "Português",
+        algorithm: Some(Algorithm::Portuguese),
+        stop_words: &[
+            "a", "ao", "as", "com", "como", "da", "das", "de", "do", "dos", "e", "ela", "ele",
+            "em", "era", "eu", "foi", "há", "isso", "já", "lhe", "mais", "mas", "me", "meu", "na",
+            "não", "nas", "no", "nos", "o", "os", "ou", "para", "pela", "pelo", "por", "que", "se",
+            "seu", "sua", "te", "um", "uma", "você",
+        ],
+    },
+    LanguageSpec {
+        code: "dut",
+        name: "Nederlands",
+        algorithm: Some(Algorithm::Dutch),
+        stop_words: &[
+            "aan", "als", "bij", "dat", "de", "den", "der", "die", "dit", "een", "en", "er", "had",
+            "heb", "het", "hij", "hoe", "ik", "in", "is", "je", "maar", "me", "met", "mij", "na",
+            "niet", "nog", "nu", "of", "om", "ons", "ook", "op", "te", "tot", "u", "uit", "van",
+            "voor", "was", "wat", "we", "wij", "zij", "zijn", "zo",
+        ],
+    },
+    LanguageSpec {
+        code: "swe",
+        name: "Svenska",
+        algorithm: Some(Algorithm::Swedish),
+        stop_words: &[
+            "att", "av", "de", "del", "den", "det", "du", "en", "ett", "för", "han", "har", "hon",
+            "i", "jag", "man", "med", "men", "mig", "min", "ni", "nu", "och", "om", "på", "så",
+            "som", "till", "under", "var", "vi", "vid", "är",
+        ],
+    },
+    LanguageSpec {
+        code: "dan",
+        name: "Dansk",
+        algorithm: Some(Algorithm::Danish),
+        stop_words: &[
+            "af", "at", "de", "den", "der", "det", "du", "en", "er", "et", "for", "han", "har",
+            "hun", "i", "jeg", "med", "men", "mig", "min", "og", "om", "på", "så", "som", "til",
+            "var", "vi",
+        ],
+    },
+    LanguageSpec {
+        code: "nor",
+        name: "Norsk",
+        algorithm: Some(Algorithm::Norwegian),
+        stop_words: &[
+            "av", "de", "den", "det", "du", "en", "er", "et", "for", "han", "har", "hun", "i",
+            "jeg", "med", "men", "meg", "min", "og", "om", "på", "så", "som", "til", "var", "vi",
+        ],
+    },
+    LanguageSpec {
+        code: "fin",
+        name: "Suomi",
+        algorithm: Some(Algorithm::Finnish),
+        stop_words: &[
+            "ei", "ja", "me", "mikä", "minä", "mutta", "ne", "niin", "se", "sinä", "he", "hän",
+            "jos", "kun", "nyt", "on", "oli", "että", "tai", "te",
+        ],
+    },
+    LanguageSpec {
+        code: "rus",
+        name: "Русский",
+        algorithm: Some(Algorithm::Russian),
+        stop_words: &[
+            "а", "без", "в", "во", "вы", "да", "для", "до", "его", "ее", "если", "же", "за", "и",
+            "из", "или", "их", "к", "как", "ли", "мне", "мы", "на", "не", "нет", "но", "о", "он",
+            "она", "они", "от", "по", "с", "так", "то", "ты", "у", "уже", "что", "это", "я",
+        ],
+    },
+    LanguageSpec {
+        code: "pol",
+        name: "Polski",
+        algorithm: None,
+        stop_words: &[],
+    },
+    LanguageSpec {
+        code: "jpn",
+        name: "日本語",
+        algorithm: None,
+        stop_words: &[],
+    },
+    LanguageSpec {
+        code: "kor",
+        name: "한국어",
+        algorithm: None,
+        stop_words: &[],
+    },
+    LanguageSpec {
+        code: "chi",
+        name: "中文",
+        algorithm: None,
+        stop_words: &[],
+    },
+];
+
+/// The language a search index is built for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Language {
+    code: String,
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+impl Language {
+    /// Language for a tag such as `es`, `spa` or `Spanish`
+    ///
+    /// Languages without a stemmer are indexed without stemming or stop
+    /// words.
+    pub fn new(tag: &str) -> Self {
+        Self {
+            code: normalize_language(tag),
+        }
+    }
+
+    /// English, analyzed exactly like lunr's default pipeline
+    pub fn english() -> Self {
+        Self {
+            code: "eng".to_string(),
+        }
+    }
+
+    /// Normalized ISO 639-2 code, e.g. `spa`
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// Name of the language in the language itself, for the site
+    pub fn name(&self) -> String {
+        self.spec()
+            .map(|spec| spec.name.to_string())
+            .unwrap_or_else(|| self.code.to_uppercase())
+    }
+
+    /// Search-time lunr pipeline, by registered function name
+    pub fn pipeline(&self) -> Vec<String> {
+        if self.is_english() {
+            vec!["stemmer".to_string()]
+        } else if self.stemmer().is_some() {
+            vec![format!("stemmer-{}", self.code)]
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Whether the browser needs a [`Language::stem_table`] to stem queries
+    pub fn uses_stem_table(&self) -> bool {
+        !self.is_english() && self.stemmer().is_some()
+    }
+
+    /// Run text through the indexing pipeline: tokenize, trim, drop stop
+    /// words, stem
+    pub fn analyze(&self, text: &str) -> Vec<String> {
+        if self.is_english() {
+            return lunr::analyze(text);
+        }
+
+        let stemmer = self.stemmer();
+        lunr::tokenize(text)
+            .iter()
+            .map(|token| trim_unicode(token))
+            .filter(|token| !token.is_empty() && !self.is_stop_word(token))
+            .map(|token| match &stemmer {
+                Some(stemmer) => stemmer.stem(token).into_owned(),
+                None => token.to_string(),
+            })
+            .collect()
+    }
+
+    /// Word forms in `texts` whose stem differs from the word itself
+    ///
+    /// lunr's query parser lowercases and splits terms but does not trim
+    /// them, so the table is keyed by trimmed, lowercased tokens.
+    pub fn stem_table<'a>(&self, texts: impl Iterator<Item = &'a str>) -> BTreeMap<String, String> {
+        let mut table = BTreeMap::new();
+        let Some(stemmer) = self.stemmer() else {
+            return table;
+        };
+        for text in texts {
+            for token in lunr::tokenize(text) {
+                let token = trim_unicode(&token);
+                if token.is_empty() || self.is_stop_word(token) || table.contains_key(token) {
+                    continue;
+                }
+                let stem = stemmer.stem(token);
+                if stem != token {
+                    table.insert(token.to_string(), stem.into_owned());
+                }
+            }
+        }
+        table
+    }
+
+    fn is_english(&self) -> bool {
+        self.code == "eng"
+    }
+
+    fn is_stop_word(&self, token: &str) -> bool {
+        self.spec()
+            .is_some_and(|spec| spec.stop_words.contains(&token))
+    }
+
+    fn stemmer(&self) -> Option<Stemmer> {
+        self.spec()
+            .and_then(|spec| spec.algorithm)
+            .map(Stemmer::create)
+    }
+
+    fn spec(&self) -> Option<&'static LanguageSpec> {
+        LANGUAGES.iter().find(|spec| spec.code == self.code)
+    }
+}
+
+/// Strip leading and trailing characters that are not letters or digits
+///
+/// Unlike lunr's ASCII-only trimmer this keeps accented letters and
+/// non-Latin scripts.
+fn trim_unicode(token: &str) -> &str {
+    token.trim_matches(|c: char| !(c.is_alphanumeric() || c == '_'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spanish_analysis() {
+        let spanish = Language::new("es");
+        assert_eq!(spanish.code(), "spa");
+        assert_eq!(spanish.name(), "Español");
+        assert_eq!(spanish.pipeline(), vec!["stemmer-spa"]);
+        assert_eq!(
+            spanish.analyze("¿Dónde están los niños?"),
+            vec!["dond", "estan", "niñ"]
+        );
+
+        let table = spanish.stem_table(["Los niños, el niño."].into_iter());
+        assert_eq!(table["niños"], "niñ");
+        assert_eq!(table["niño"], "niñ");
+        assert!(!table.contains_key("los"));
+    }
+
+    #[test]
+    fn test_english_matches_lunr() {
+        let english = Language::new("English");
+        assert_eq!(english, Language::english());
+        assert_eq!(english.pipeline(), vec!["stemmer"]);
+        assert!(!english.uses_stem_table());
+        assert_eq!(english.analyze("The dogs and the cats"), vec!["dog", "cat"]);
+    }
+
+    #[test]
+    fn test_language_without_stemmer() {
+        let japanese = Language::new("ja");
+        assert!(japanese.pipeline().is_empty());
+        assert_eq!(
+            japanese.analyze("こんにちは 世界"),
+            vec!["こんにちは", "世界"]
+        );
+
+        let unknown = Language::new("xx");
+        assert_eq!(unknown.name(), "XX");
+        assert!(unknown.stem_table(["word"].into_iter()).is_empty());
+    }
+}
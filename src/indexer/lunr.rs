@@ -0,0 +1,689 @@
+//! Build-time lunr.js index generation
+//!
+//! Reimplements the parts of lunr 2.x that run when an index is built —
+//! tokenizer, trimmer, English stop word filter, Porter stemmer and BM25
+//! field vectors — so the browser can call `lunr.Index.load` on the
+//! serialized result instead of rebuilding the index from raw documents.
+//! The token set is not serialized: `lunr.Index.load` derives it from the
+//! sorted term list in a single pass.
+//!
+//! Every step mirrors lunr's JavaScript, quirks included, so that searching
+//! the prebuilt index gives exactly the same results as an index built in the
+//! browser.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::indexer::language::Language;
+
+/// lunr version the serialized index targets
+pub const LUNR_VERSION: &str = "2.3.9";
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+
+/// BM25 field length normalisation
+const B: f64 = 0.75;
+
+/// lunr's English stop word list (`lunr.stopWordFilter`)
+const STOP_WORDS: &[&str] = &[
+    "a", "able", "about", "across", "after", "all", "almost", "also", "am", "among", "an", "and",
+    "any", "are", "as", "at", "be", "because", "been", "but", "by", "can", "cannot", "could",
+    "dear", "did", "do", "does", "either", "else", "ever", "every", "for", "from", "get", "got",
+    "had", "has", "have", "he", "her", "hers", "him", "his", "how", "however", "i", "if", "in",
+    "into", "is", "it", "its", "just", "least", "let", "like", "likely", "may", "me", "might",
+    "most", "must", "my", "neither", "no", "nor", "not", "of", "off", "often", "on", "only", "or",
+    "other", "our", "own", "rather", "said", "say", "says", "she", "should", "since", "so", "some",
+    "than", "that", "the", "their", "them", "then", "there", "these", "they", "this", "tis", "to",
+    "too", "twas", "us", "wants", "was", "we", "were", "what", "when", "where", "which", "while",
+    "who", "whom", "why", "will", "with", "would", "yet", "you", "your",
+];
+
+// Porter stemmer building blocks, as defined by lunr
+const C: &str = "[^aeiou][^aeiouy]*";
+const V: &str = "[aeiouy][aeiou]*";
+
+lazy_static! {
+    static ref STOP_WORD_SET: HashSet<&'static str> = STOP_WORDS.iter().copied().collect();
+
+    static ref RE_MGR0: Regex = Regex::new(&format!("^({C})?{V}{C}")).unwrap();
+    static ref RE_MEQ1: Regex = Regex::new(&format!("^({C})?{V}{C}({V})?$")).unwrap();
+    static ref RE_MGR1: Regex = Regex::new(&format!("^({C})?{V}{C}{V}{C}")).unwrap();
+    static ref RE_S_V: Regex = Regex::new(&format!("^({C})?[aeiouy]")).unwrap();
+
+    static ref RE_1A: Regex = Regex::new(r"^(.+?)(ss|i)es$").unwrap();
+    static ref RE2_1A: Regex = Regex::new(r"^(.+?)([^s])s$").unwrap();
+    static ref RE_1B: Regex = Regex::new(r"^(.+?)eed$").unwrap();
+    static ref RE2_1B: Regex = Regex::new(r"^(.+?)(ed|ing)$").unwrap();
+    static ref RE2_1B_2: Regex = Regex::new(r"(at|bl|iz)$").unwrap();
+    static ref RE_CVC: Regex = Regex::new(&format!("^{C}[aeiouy][^aeiouwxy]$")).unwrap();
+    static ref RE_1C: Regex = Regex::new(r"^(.+?[^aeiou])y$").unwrap();
+    static ref RE_2: Regex = Regex::new(
+        r"^(.+?)(ational|tional|enci|anci|izer|bli|alli|entli|eli|ousli|ization|ation|ator|alism|iveness|fulness|ousness|aliti|iviti|biliti|logi)$"
+    )
+    .unwrap();
+    static ref RE_3: Regex = Regex::new(r"^(.+?)(icate|ative|alize|iciti|ical|ful|ness)$").unwrap();
+    static ref RE_4: Regex = Regex::new(
+        r"^(.+?)(al|ance|ence|er|ic|able|ible|ant|ement|ment|ent|ou|ism|ate|iti|ous|ive|ize)$"
+    )
+    .unwrap();
+    static ref RE2_4: Regex = Regex::new(r"^(.+?)(s|t)(ion)$").unwrap();
+    static ref RE_5: Regex = Regex::new(r"^(.+?)e$").unwrap();
+}
+
+/// Whether `c` matches lunr's token separator `/[\s\-]+/`
+///
+/// This is JavaScript's `\s`, which differs slightly from Unicode White_Space.
+fn is_separator(c: char) -> bool {
+    matches!(
+        c,
+        '-' | '\t'
+            | '\n'
+            | '\u{000B}'
+            | '\u{000C}'
+            | '\r'
+            | ' '
+            | '\u{00A0}'
+            | '\u{1680}'
+            | '\u{2000}'
+            ..='\u{200A}'
+                | '\u{2028}'
+                | '\u{2029}'
+                | '\u{202F}'
+                | '\u{205F}'
+                | '\u{3000}'
+                | '\u{FEFF}'
+    )
+}
+
+/// Split text into lowercase tokens (`lunr.tokenizer`)
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(is_separator)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Strip leading and trailing non-word characters (`lunr.trimmer`)
+///
+/// Like JavaScript's `\W`, only ASCII letters, digits and `_` count as word
+/// characters. A token made entirely of punctuation trims to an empty string,
+/// which lunr still indexes.
+pub fn trim(token: &str) -> &str {
+    token.trim_matches(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Whether a token is on lunr's English stop word list
+pub fn is_stop_word(token: &str) -> bool {
+    STOP_WORD_SET.contains(token)
+}
+
+/// Run text through lunr's default indexing pipeline
+///
+/// Equivalent to `trimmer`, `stopWordFilter` and `stemmer` applied to the
+/// output of `lunr.tokenizer`.
+pub fn analyze(text: &str) -> Vec<String> {
+    tokenize(text)
+        .iter()
+        .map(|token| trim(token))
+        .filter(|token| !is_stop_word(token))
+        .map(stem)
+        .collect()
+}
+
+/// Length of a string in UTF-16 code units, as JavaScript measures it
+fn js_len(s: &str) -> usize {
+    s.chars().map(char::len_utf16).sum()
+}
+
+/// Drop the final character (JavaScript `replace(/.$/, "")` on ASCII input)
+fn drop_last(s: &str) -> String {
+    let mut out = s.to_string();
+    out.pop();
+    out
+}
+
+/// `/([^aeiouylsz])\1$/`: ends in a doubled consonant other than l, s, z
+fn ends_with_double_consonant(s: &str) -> bool {
+    let mut chars = s.chars().rev();
+    match (chars.next(), chars.next()) {
+        (Some(last), Some(prev)) => {
+            last == prev && last.len_utf16() == 1 && !"aeiouylsz".contains(last)
+        }
+        _ => false,
+    }
+}
+
+/// `^C v [^aeiouwxy]$` — a JavaScript surrogate pair can't match the final class
+fn ends_cvc(s: &str) -> bool {
+    s.chars().last().is_some_and(|c| c.len_utf16() == 1) && RE_CVC.is_match(s)
+}
+
+/// Porter stemmer, ported from `lunr.stemmer`
+pub fn stem(word: &str) -> String {
+    if js_len(word) < 3 {
+        return word.to_string();
+    }
+
+    let starts_with_y = word.starts_with('y');
+    let mut w = if starts_with_y {
+        format!("Y{}", &word[1..])
+    } else {
+        word.to_string()
+    };
+
+    // Step 1a
+    if RE_1A.is_match(&w) {
+        w = RE_1A.replace(&w, "${1}${2}").into_owned();
+    } else if RE2_1A.is_match(&w) {
+        w = RE2_1A.replace(&w, "${1}${2}").into_owned();
+    }
+
+    // Step 1b
+    if let Some(caps) = RE_1B.captures(&w) {
+        if RE_MGR0.is_match(&caps[1]) {
+            w = drop_last(&w);
+        }
+    } else if let Some(caps) = RE2_1B.captures(&w) {
+        let stem = caps[1].to_string();
+        if RE_S_V.is_match(&stem) {
+            w = stem;
+            if RE2_1B_2.is_match(&w) {
+                w.push('e');
+            } else if ends_with_double_consonant(&w) {
+                w = drop_last(&w);
+            } else if ends_cvc(&w) {
+                w.push('e');
+            }
+        }
+    }
+
+    // Step 1c
+    if let Some(caps) = RE_1C.captures(&w) {
+        w = format!("{}i", &caps[1]);
+    }
+
+    // Step 2
+    if let Some(caps) = RE_2.captures(&w) {
+        if RE_MGR0.is_match(&caps[1]) {
+            w = format!("{}{}", &caps[1], step2_suffix(&caps[2]));
+        }
+    }
+
+    // Step 3
+    if let Some(caps) = RE_3.captures(&w) {
+        if RE_MGR0.is_match(&caps[1]) {
+            w = format!("{}{}", &caps[1], step3_suffix(&caps[2]));
+        }
+    }
+
+    // Step 4
+    if let Some(caps) = RE_4.captures(&w) {
+        if RE_MGR1.is_match(&caps[1]) {
+            w = caps[1].to_string();
+        }
+    } else if let Some(caps) = RE2_4.captures(&w) {
+        let stem = format!("{}{}", &caps[1], &caps[2]);
+        if RE_MGR1.is_match(&stem) {
+            w = stem;
+        }
+    }
+
+    // Step 5
+    if let Some(caps) = RE_5.captures(&w) {
+        let stem = &caps[1];
+        if RE_MGR1.is_match(stem) || (RE_MEQ1.is_match(stem) && !ends_cvc(stem)) {
+            w = stem.to_string();
+        }
+    }
+
+    if w.ends_with("ll") && RE_MGR1.is_match(&w) {
+        w = drop_last(&w);
+    }
+
+    // Turn the initial Y back into y
+    if starts_with_y {
+        w = format!("y{}", &w[1..]);
+    }
+
+    w
+}
+
+fn step2_suffix(suffix: &str) -> &'static str {
+    match suffix {
+        "ational" => "ate",
+        "tional" => "tion",
+        "enci" => "ence",
+        "anci" => "ance",
+        "izer" => "ize",
+        "bli" => "ble",
+        "alli" => "al",
+        "entli" => "ent",
+        "eli" => "e",
+        "ousli" => "ous",
+        "ization" => "ize",
+        "ation" => "ate",
+        "ator" => "ate",
+        "alism" => "al",
+        "iveness" => "ive",
+        "fulness" => "ful",
+        "ousness" => "ous",
+        "aliti" => "al",
+        "iviti" => "ive",
+        "biliti" => "ble",
+        "logi" => "log",
+        _ => unreachable!("suffix matched by RE_2"),
+    }
+}
+
+fn step3_suffix(suffix: &str) -> &'static str {
+    match suffix {
+        "icate" => "ic",
+        "ative" => "",
+        "alize" => "al",
+        "iciti" => "ic",
+        "ical" => "ic",
+        "ful" => "",
+        "ness" => "",
+        _ => unreachable!("suffix matched by RE_3"),
+    }
+}
+
+/// Inverted index entry for a single term
+///
+/// Serialized as lunr's posting object: `{"_index": n, "<field>": {"<ref>": {}}}`,
+/// with fields and refs kept in insertion order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Posting {
+    /// Position of the term in field vectors
+    pub index: usize,
+
+    /// Documents containing the term, per field
+    pub fields: Vec<(String, Vec<String>)>,
+}
+
+impl Serialize for Posting {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        struct Refs<'a>(&'a [String]);
+
+        impl Serialize for Refs<'_> {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(self.0.len()))?;
+                for doc_ref in self.0 {
+                    map.serialize_entry(doc_ref, &EmptyMetadata {})?;
+                }
+                map.end()
+            }
+        }
+
+        let mut map = serializer.serialize_map(Some(self.fields.len() + 1))?;
+        map.serialize_entry("_index", &self.index)?;
+        for (field, refs) in &self.fields {
+            map.serialize_entry(field, &Refs(refs))?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Posting {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct PostingVisitor;
+
+        impl<'de> Visitor<'de> for PostingVisitor {
+            type Value = Posting;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a lunr posting object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<Posting, A::Error> {
+                let mut index = None;
+                let mut fields = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "_index" {
+                        index = Some(map.next_value()?);
+                    } else {
+                        let refs: IndexedRefs = map.next_value()?;
+                        fields.push((key, refs.0));
+                    }
+                }
+                let index = index.ok_or_else(|| de::Error::missing_field("_index"))?;
+                Ok(Posting { index, fields })
+            }
+        }
+
+        deserializer.deserialize_map(PostingVisitor)
+    }
+}
+
+/// Per-document metadata in a posting (always empty: no metadata whitelist)
+#[derive(Serialize, Deserialize)]
+struct EmptyMetadata {}
+
+/// Document refs of a posting field, in serialized order
+struct IndexedRefs(Vec<String>);
+
+impl<'de> Deserialize<'de> for IndexedRefs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct RefsVisitor;
+
+        impl<'de> Visitor<'de> for RefsVisitor {
+            type Value = IndexedRefs;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of document refs")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> std::result::Result<IndexedRefs, A::Error> {
+                let mut refs = Vec::new();
+                while let Some((doc_ref, _)) = map.next_entry::<String, de::IgnoredAny>()? {
+                    refs.push(doc_ref);
+                }
+                Ok(IndexedRefs(refs))
+            }
+        }
+
+        deserializer.deserialize_map(RefsVisitor)
+    }
+}
+
+/// A serialized lunr 2.x index, loadable with `lunr.Index.load`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LunrIndex {
+    pub version: String,
+    pub fields: Vec<String>,
+    /// `(field/ref, [termIndex, score, ...])` pairs
+    pub field_vectors: Vec<(String, Vec<f64>)>,
+    /// `(term, posting)` pairs sorted the way JavaScript sorts strings
+    pub inverted_index: Vec<(String, Posting)>,
+    /// Search-time pipeline, registered by name in lunr
+    pub pipeline: Vec<String>,
+}
+
+/// Analyzed terms of one field of one document
+struct FieldTerms {
+    field: usize,
+    doc_ref: String,
+    /// Term frequencies in first-seen order
+    frequencies: Vec<(String, u32)>,
+    /// Number of terms, including repeats
+    length: usize,
+}
+
+/// Incremental builder mirroring `lunr.Builder`
+pub struct LunrBuilder {
+    fields: Vec<String>,
+    language: Language,
+    document_count: usize,
+    /// Term -> (term index, per-field document refs in insertion order)
+    inverted_index: HashMap<String, (usize, Vec<Vec<String>>)>,
+    field_terms: Vec<FieldTerms>,
+    /// Position of each document's first field in `field_terms`
+    documents: HashMap<String, usize>,
+}
+
+impl LunrBuilder {
+    /// Create a builder indexing the given fields, in order
+    pub fn new(fields: &[String]) -> Self {
+        Self {
+            fields: fields.to_vec(),
+            language: Language::english(),
+            document_count: 0,
+            inverted_index: HashMap::new(),
+            field_terms: Vec::new(),
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Analyze text for `language` instead of English
+    pub fn with_language(mut self, language: Language) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Add a document; `field_value` returns the text for each field name
+    ///
+    /// Adding a ref twice replaces its field vectors but keeps its earlier
+    /// postings, exactly like lunr.
+    pub fn add<'a>(&mut self, doc_ref: &str, field_value: impl Fn(&str) -> Option<&'a str>) {
+        self.document_count += 1;
+        let next_slot = self.field_terms.len();
+        let existing = self.documents.get(doc_ref).copied();
+        if existing.is_none() {
+            self.documents.insert(doc_ref.to_string(), next_slot);
+        }
+
+        for (field_idx, field) in self.fields.iter().enumerate() {
+            let terms = field_value(field)
+                .map(|text| self.language.analyze(text))
+                .unwrap_or_default();
+
+            let mut frequencies: Vec<(String, u32)> = Vec::new();
+            let mut positions: HashMap<&str, usize> = HashMap::new();
+            for term in &terms {
+                match positions.get(term.as_str()) {
+                    Some(&pos) => frequencies[pos].1 += 1,
+                    None => {
+                        positions.insert(term, frequencies.len());
+                        frequencies.push((term.clone(), 1));
+                    }
+                }
+
+                let next_index = self.inverted_index.len();
+                let num_fields = self.fields.len();
+                let (_, docs) = self
+                    .inverted_index
+                    .entry(term.clone())
+                    .or_insert_with(|| (next_index, vec![Vec::new(); num_fields]));
+                let refs = &mut docs[field_idx];
+                let present = match existing {
+                    Some(_) => refs.iter().any(|r| r == doc_ref),
+                    None => refs.last().map(String::as_str) == Some(doc_ref),
+                };
+                if !present {
+                    refs.push(doc_ref.to_string());
+                }
+            }
+
+            let field_entry = FieldTerms {
+                field: field_idx,
+                doc_ref: doc_ref.to_string(),
+                frequencies,
+                length: terms.len(),
+            };
+            match existing {
+                Some(slot) => self.field_terms[slot + field_idx] = field_entry,
+                None => self.field_terms.push(field_entry),
+            }
+        }
+    }
+
+    /// Compute field vectors and serialize the index
+    pub fn build(self) -> LunrIndex {
+        // Average field lengths
+        let mut length_sums = vec![0usize; self.fields.len()];
+        let mut field_counts = vec![0usize; self.fields.len()];
+        for entry in &self.field_terms {
+            length_sums[entry.field] += entry.length;
+            field_counts[entry.field] += 1;
+        }
+        let average_lengths: Vec<f64> = length_sums
+            .iter()
+            .zip(&field_counts)
+            .map(|(&sum, &count)| sum as f64 / count as f64)
+            .collect();
+
+        let mut idf_cache: HashMap<&str, f64> = HashMap::new();
+        let field_vectors = self
+            .field_terms
+            .iter()
+            .map(|entry| {
+                let length = entry.length as f64 / average_lengths[entry.field];
+                let mut vector: Vec<(usize, f64)> = entry
+                    .frequencies
+                    .iter()
+                    .map(|(term, tf)| {
+                        let (term_index, docs) = &self.inverted_index[term];
+                        let idf = *idf_cache.entry(term.as_str()).or_insert_with(|| {
+                            let with_term: usize = docs.iter().map(Vec::len).sum();
+                            idf(with_term, self.document_count)
+                        });
+                        let tf = *tf as f64;
+                        let score = idf * ((K1 + 1.0) * tf) / (K1 * (1.0 - B + B * length) + tf);
+                        (*term_index, (score * 1000.0).round() / 1000.0)
+                    })
+                    .collect();
+                vector.sort_by_key(|(index, _)| *index);
+
+                let elements = vector
+                    .into_iter()
+                    .flat_map(|(index, score)| [index as f64, score])
+                    .collect();
+                (
+                    format!("{}/{}", self.fields[entry.field], entry.doc_ref),
+                    elements,
+                )
+            })
+            .collect();
+
+        let mut inverted_index: Vec<(String, Posting)> = self
+            .inverted_index
+            .into_iter()
+            .map(|(term, (index, docs))| {
+                let fields = self.fields.iter().cloned().zip(docs).collect();
+                (term, Posting { index, fields })
+            })
+            .collect();
+        // lunr.TokenSet.Builder requires terms in JavaScript (UTF-16) order
+        inverted_index.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+        LunrIndex {
+            version: LUNR_VERSION.to_string(),
+            fields: self.fields,
+            field_vectors,
+            inverted_index,
+            pipeline: self.language.pipeline(),
+        }
+    }
+}
+
+/// Inverse document frequency (`lunr.idf`)
+fn idf(documents_with_term: usize, document_count: usize) -> f64 {
+    let mut x = (document_count as f64 - documents_with_term as f64 + 0.5)
+        / (documents_with_term as f64 + 0.5);
+    if x < 1.0 {
+        x = 1e-10;
+    }
+    (1.0 + x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_and_trim() {
+        assert_eq!(
+            tokenize("Hello,  World - it's\u{00A0}ME"),
+            vec!["hello,", "world", "it's", "me"]
+        );
+        assert_eq!(trim("\"hello,"), "hello");
+        assert_eq!(trim("it's"), "it's");
+        assert_eq!(trim("..."), "");
+        // Non-ASCII letters are not word characters for the trimmer
+        assert_eq!(trim("café"), "caf");
+    }
+
+    #[test]
+    fn test_stem() {
+        let cases = [
+            ("running", "run"),
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("agreed", "agre"),
+            ("hopping", "hop"),
+            ("filing", "file"),
+            ("happy", "happi"),
+            ("relational", "relat"),
+            ("hopefulness", "hope"),
+            ("electricity", "electr"),
+            ("adjustment", "adjust"),
+            ("controlling", "control"),
+            ("yelling", "yell"),
+            ("goodbye", "goodby"),
+            ("by", "by"),
+        ];
+        for (word, expected) in cases {
+            assert_eq!(stem(word), expected, "stem({})", word);
+        }
+    }
+
+    #[test]
+    fn test_analyze_drops_stop_words() {
+        assert_eq!(analyze("The dogs and the cats"), vec!["dog", "cat"]);
+        // Punctuation-only tokens survive as empty terms, as in lunr
+        assert_eq!(analyze("wait ..."), vec!["wait", ""]);
+    }
+
+    #[test]
+    fn test_build_matches_lunr() {
+        // Expected output produced by lunr 2.3.9 for the same documents
+        let fields = vec!["text".to_string(), "episode".to_string()];
+        let mut builder = LunrBuilder::new(&fields);
+        builder.add("a", |f| match f {
+            "text" => Some("Hello world!! ... running"),
+            "episode" => Some("S01E01"),
+            _ => None,
+        });
+        builder.add("b", |f| match f {
+            "text" => Some("Goodbye, cruel world"),
+            "episode" => Some("S01E02"),
+            _ => None,
+        });
+
+        let index = builder.build();
+        let json = serde_json::to_value(&index).unwrap();
+        let expected = serde_json::json!({
+            "version": "2.3.9",
+            "fields": ["text", "episode"],
+            "fieldVectors": [
+                ["text/a", [0, 0.655, 1, 0, 2, 0.655, 3, 0.655]],
+                ["episode/a", [4, 0.693]],
+                ["text/b", [1, 0, 5, 0.736, 6, 0.736]],
+                ["episode/b", [7, 0.693]]
+            ],
+            "invertedIndex": [
+                ["", {"_index": 2, "text": {"a": {}}, "episode": {}}],
+                ["cruel", {"_index": 6, "text": {"b": {}}, "episode": {}}],
+                ["goodby", {"_index": 5, "text": {"b": {}}, "episode": {}}],
+                ["hello", {"_index": 0, "text": {"a": {}}, "episode": {}}],
+                ["run", {"_index": 3, "text": {"a": {}}, "episode": {}}],
+                ["s01e01", {"_index": 4, "text": {}, "episode": {"a": {}}}],
+                ["s01e02", {"_index": 7, "text": {}, "episode": {"b": {}}}],
+                ["world", {"_index": 1, "text": {"a": {}, "b": {}}, "episode": {}}]
+            ],
+            "pipeline": ["stemmer"]
+        });
+
+        // Compare numerically: serde writes 0.0 where JavaScript writes 0
+        assert_eq!(
+            json.to_string().replace(".0,", ",").replace(".0]", "]"),
+            expected.to_string()
+        );
+    }
+}
@@ -1,5 +1,11 @@
 //! Search indexing module
 
+mod language;
+mod lunr;
 mod search;
+mod shard;
 
-pub use search::{SearchEntry, SearchIndex, SearchIndexer, SearchMeta};
+pub use language::Language;
+pub use lunr::{LunrBuilder, LunrIndex, Posting};
+pub use search::{caption_ids, SearchEntry, SearchIndex, SearchIndexer, SearchMeta, Translations};
+pub use shard::{IndexManifest, LanguageInfo, ShardInfo, ShardedIndex, TermShard};
@@ -1,20 +1,37 @@
 //! Search index generator for lunr.js
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
 
-use crate::discovery::Episode;
+use crate::discovery::{Episode, EpisodeId};
 use crate::error::Result;
-use crate::subtitle::SubtitleEntry;
+use crate::indexer::language::Language;
+use crate::indexer::lunr::{LunrBuilder, LunrIndex};
+use crate::subtitle::sdh::{self, CueKind};
+use crate::subtitle::{aligned_lines, SpeakerAliases, SubtitleEntry};
 
 /// Search index entry for a single subtitle
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchEntry {
-    /// Unique ID (episode-timestamp)
+    /// Unique ID, see [`caption_ids`]
     pub id: String,
 
     /// Searchable text (cleaned subtitle)
     pub text: String,
 
+    /// Who says the line, searchable as `speaker:name`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+
+    /// Sound effect annotations, searchable as `sound:explosion`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sound: Option<String>,
+
+    /// Music and lyrics annotations, searchable as `music:theme`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub music: Option<String>,
+
     /// Episode identifier (S01E01)
     pub episode: String,
 
@@ -26,6 +43,27 @@ pub struct SearchEntry {
 
     /// Path to the thumbnail image
     pub thumb: String,
+
+    /// The line in the site's other languages, keyed by language code
+    #[serde(skip)]
+    pub translations: BTreeMap<String, String>,
+}
+
+impl SearchEntry {
+    /// Value of a named index field
+    pub fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "id" => Some(&self.id),
+            "text" => Some(&self.text),
+            "speaker" => self.speaker.as_deref(),
+            "sound" => self.sound.as_deref(),
+            "music" => self.music.as_deref(),
+            "episode" => Some(&self.episode),
+            "frame" => Some(&self.frame),
+            "thumb" => Some(&self.thumb),
+            _ => None,
+        }
+    }
 }
 
 /// The complete search index structure
@@ -34,8 +72,19 @@ pub struct SearchIndex {
     /// All searchable entries
     pub entries: Vec<SearchEntry>,
 
+    /// Prebuilt lunr index over `entries`
+    pub lunr: LunrIndex,
+
     /// Metadata about the index
     pub meta: SearchMeta,
+
+    /// Word forms and their stems, for languages the browser can't stem
+    #[serde(skip)]
+    pub stems: BTreeMap<String, String>,
+
+    /// Indexes of the same captions in the site's other languages
+    #[serde(skip)]
+    pub translations: Vec<SearchIndex>,
 }
 
 /// Metadata about the search index
@@ -52,12 +101,34 @@ pub struct SearchMeta {
 
     /// Index version for cache busting
     pub version: String,
+
+    /// Language code of the indexed text, when the site sets languages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
 }
 
+/// Subtitles of one episode in other languages, as `(language, entries)`
+pub type Translations = Vec<(String, Vec<SubtitleEntry>)>;
+
+/// Index fields holding SDH annotations, added by [`SearchIndexer::with_sdh`]
+pub const ANNOTATION_FIELDS: [&str; 2] = ["sound", "music"];
+
 /// Builder for creating search indices
 pub struct SearchIndexer {
     /// Fields to include in the index
     fields: Vec<String>,
+
+    /// Site languages, primary first (empty = unlabelled English)
+    languages: Vec<Language>,
+
+    /// Spellings of speaker names found in SDH labels
+    speakers: SpeakerAliases,
+
+    /// Split SDH annotations out of the indexed text
+    sdh: bool,
+
+    /// With `sdh`, index cues that are only sound effects
+    sound_effects: bool,
 }
 
 impl Default for SearchIndexer {
@@ -70,7 +141,15 @@ impl SearchIndexer {
     /// Create a new search indexer
     pub fn new() -> Self {
         Self {
-            fields: vec!["text".to_string(), "episode".to_string()],
+            fields: vec![
+                "text".to_string(),
+                "episode".to_string(),
+                "speaker".to_string(),
+            ],
+            languages: Vec::new(),
+            speakers: SpeakerAliases::default(),
+            sdh: false,
+            sound_effects: true,
         }
     }
 
@@ -80,40 +159,160 @@ impl SearchIndexer {
         self
     }
 
+    /// Set the site languages; the first is the language of the captions
+    pub fn with_languages(mut self, languages: &[String]) -> Self {
+        self.languages = languages.iter().map(|tag| Language::new(tag)).collect();
+        self
+    }
+
+    /// Set the aliases applied to speaker labels found by SDH cleanup
+    pub fn with_speakers(mut self, speakers: SpeakerAliases) -> Self {
+        self.speakers = speakers;
+        self
+    }
+
+    /// Index only the dialogue of SDH cues, moving sound effects and music
+    /// into the `sound` and `music` fields
+    ///
+    /// Speaker labels fill in the speaker of cues that have none. Call after
+    /// [`with_fields`](Self::with_fields), which this adds the annotation
+    /// fields to.
+    pub fn with_sdh(mut self, sdh: bool) -> Self {
+        self.sdh = sdh;
+        self.fields
+            .retain(|field| !ANNOTATION_FIELDS.contains(&field.as_str()));
+        if sdh {
+            self.fields
+                .extend(ANNOTATION_FIELDS.iter().map(|field| field.to_string()));
+        }
+        self
+    }
+
+    /// Whether SDH cues that are only sound effects are indexed
+    pub fn with_sound_effects(mut self, sound_effects: bool) -> Self {
+        self.sound_effects = sound_effects;
+        self
+    }
+
     /// Build the search index from episodes and their subtitle entries
     pub fn build_index(&self, episodes: &[(Episode, Vec<SubtitleEntry>)]) -> Result<SearchIndex> {
+        self.build_translated(episodes, &[])
+    }
+
+    /// Build the search index, plus one per other site language
+    ///
+    /// `translations[i]` holds the other-language subtitles of `episodes[i]`.
+    /// Each caption takes the other-language cues aligned with it by
+    /// [`aligned_lines`]; captions without one are left out of that
+    /// language's index.
+    pub fn build_translated(
+        &self,
+        episodes: &[(Episode, Vec<SubtitleEntry>)],
+        translations: &[Translations],
+    ) -> Result<SearchIndex> {
         let total_entries: usize = episodes.iter().map(|(_, subs)| subs.len()).sum();
         let mut entries = Vec::with_capacity(total_entries);
 
-        for (episode, subs) in episodes {
+        for (i, (episode, subs)) in episodes.iter().enumerate() {
             let episode_id = episode.id.to_string();
+            let others: Vec<(String, Vec<Option<String>>)> = translations
+                .get(i)
+                .into_iter()
+                .flatten()
+                .map(|(language, cues)| {
+                    let code = Language::new(language).code().to_string();
+                    (code, aligned_lines(subs, cues))
+                })
+                .collect();
+
+            let ids = caption_ids(&episode.id, subs);
+            for (n, (entry, id)) in subs.iter().zip(ids).enumerate() {
+                let mut text = entry.text_clean.clone();
+                let mut speaker = entry.speaker.clone();
+                let mut sound = None;
+                let mut music = None;
+                if self.sdh {
+                    let split = sdh::split(&entry.clean_lines());
+                    if split.kind == CueKind::Sound && !self.sound_effects {
+                        continue;
+                    }
+                    if speaker.is_none() && !split.speakers.is_empty() {
+                        let names: Vec<String> = split
+                            .speakers
+                            .iter()
+                            .map(|name| self.speakers.normalize(name))
+                            .collect();
+                        speaker = Some(names.join(", "));
+                    }
+                    text = split.dialogue;
+                    sound = Some(split.sounds.join(", ")).filter(|s| !s.is_empty());
+                    music = Some(split.music.join(", ")).filter(|s| !s.is_empty());
+                }
 
-            for entry in subs {
                 let timestamp = entry.midpoint().0;
-                let id = format!("{}-{}", episode_id, timestamp);
 
                 let frame = format!("img/frames/{}/{}.jpg", episode_id, timestamp);
                 let thumb = format!("img/thumbs/{}/{}.jpg", episode_id, timestamp);
 
+                let translations = others
+                    .iter()
+                    .filter_map(|(code, lines)| Some((code.clone(), lines[n].clone()?)))
+                    .collect();
+
                 entries.push(SearchEntry {
                     id,
-                    text: entry.text_clean.clone(),
+                    text,
+                    speaker,
+                    sound,
+                    music,
                     episode: episode_id.clone(),
                     timestamp,
                     frame,
                     thumb,
+                    translations,
                 });
             }
         }
 
-        let meta = SearchMeta {
-            total: entries.len(),
-            episodes: episodes.len(),
-            generated_at: chrono_now(),
-            version: generate_version(&entries),
-        };
+        let mut index = self.build_language_index(self.languages.first(), entries, episodes.len());
+        index.translations = self
+            .languages
+            .iter()
+            .skip(1)
+            .map(|language| {
+                let entries = index
+                    .entries
+                    .iter()
+                    .filter_map(|entry| {
+                        let text = entry.translations.get(language.code())?;
+                        Some(SearchEntry {
+                            text: self.indexed_text(text),
+                            translations: BTreeMap::new(),
+                            ..entry.clone()
+                        })
+                    })
+                    .collect();
+                self.build_language_index(Some(language), entries, episodes.len())
+            })
+            .collect();
+
+        Ok(index)
+    }
+
+    /// Text of a translated cue as indexed, without SDH annotations when
+    /// those are split out
+    fn indexed_text(&self, text: &str) -> String {
+        if self.sdh {
+            sdh::split(text).dialogue
+        } else {
+            text.to_string()
+        }
+    }
 
-        Ok(SearchIndex { entries, meta })
+    /// Build the serialized lunr index described by `build_lunr_config`
+    pub fn build_lunr_index(&self, entries: &[SearchEntry]) -> LunrIndex {
+        let language = self.languages.first().cloned().unwrap_or_default();
+        self.build_lunr_language(&language, entries)
     }
 
     /// Build a lunr.js-compatible index configuration
@@ -124,6 +323,142 @@ impl SearchIndexer {
             "pipeline": ["trimmer", "stopWordFilter", "stemmer"]
         })
     }
+
+    /// Index `entries` as text in `language` (English when unset)
+    fn build_language_index(
+        &self,
+        language: Option<&Language>,
+        entries: Vec<SearchEntry>,
+        episodes: usize,
+    ) -> SearchIndex {
+        let analyzer = language.cloned().unwrap_or_default();
+        let lunr = self.build_lunr_language(&analyzer, &entries);
+        let stems = if analyzer.uses_stem_table() {
+            analyzer.stem_table(entries.iter().map(|entry| entry.text.as_str()))
+        } else {
+            BTreeMap::new()
+        };
+
+        let meta = SearchMeta {
+            total: entries.len(),
+            episodes,
+            generated_at: chrono_now(),
+            version: generate_version(&entries),
+            language: language.map(|language| language.code().to_string()),
+        };
+
+        SearchIndex {
+            entries,
+            lunr,
+            meta,
+            stems,
+            translations: Vec::new(),
+        }
+    }
+
+    fn build_lunr_language(&self, language: &Language, entries: &[SearchEntry]) -> LunrIndex {
+        let mut builder = LunrBuilder::new(&self.fields).with_language(language.clone());
+        for entry in entries {
+            builder.add(&entry.id, |field| entry.field(field));
+        }
+        builder.build()
+    }
+}
+
+/// IDs of the search entries (and caption pages) for an episode's subtitles
+///
+/// An ID is the episode plus a short hash of the cue's normalized text, so
+/// retiming a subtitle file keeps every URL. Repeated lines add a hash of
+/// the text before them, then of the text on both sides:
+/// `S01E01-1a2b3c4d-5e6f`. Copies even their neighbours can't tell apart
+/// are numbered in order of appearance (`S01E01-1a2b3c4d-9a8b-2`).
+///
+/// Adding a copy of a line usually leaves the existing copies' IDs alone,
+/// but not always: a line said once gains a suffix when it is repeated, and
+/// a copy after the same line as the new one moves to the two-sided hash.
+/// The retired IDs are covered by redirect stubs (see
+/// [`crate::generator::redirect`]).
+///
+/// The returned IDs are in the same order as `entries`.
+pub fn caption_ids(episode: &EpisodeId, entries: &[SubtitleEntry]) -> Vec<String> {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| (entries[i].start, entries[i].index));
+
+    let texts: Vec<String> = order
+        .iter()
+        .map(|&i| normalize_text(&entries[i].text_clean))
+        .collect();
+    let hashes: Vec<String> = texts.iter().map(|text| short_hash(text, 8)).collect();
+    let text_at = |pos: Option<usize>| {
+        pos.and_then(|pos| texts.get(pos))
+            .map_or("", String::as_str)
+    };
+    // The text before a cue, and at the second level the text after it too
+    let context = |pos: usize, level: usize| {
+        let before = text_at(pos.checked_sub(1));
+        match level {
+            1 => short_hash(before, 4),
+            _ => short_hash(&format!("{}\n{}", before, text_at(Some(pos + 1))), 4),
+        }
+    };
+
+    let mut keys: Vec<Option<String>> = unique(hashes.iter().cloned().map(Some).collect());
+    for level in 1..=2 {
+        let candidates = (0..keys.len())
+            .map(|pos| match keys[pos] {
+                Some(_) => None,
+                None => Some(format!("{}-{}", hashes[pos], context(pos, level))),
+            })
+            .collect();
+        for (key, candidate) in keys.iter_mut().zip(unique(candidates)) {
+            if key.is_none() {
+                *key = candidate;
+            }
+        }
+    }
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut ids = vec![String::new(); entries.len()];
+    for (pos, key) in keys.into_iter().enumerate() {
+        let key = key.unwrap_or_else(|| {
+            let key = format!("{}-{}", hashes[pos], context(pos, 2));
+            let count = seen.entry(key.clone()).or_default();
+            *count += 1;
+            format!("{}-{}", key, count)
+        });
+        ids[order[pos]] = format!("{}-{}", episode, key);
+    }
+    ids
+}
+
+/// The keys that occur once, and `None` for repeated ones
+fn unique(keys: Vec<Option<String>>) -> Vec<Option<String>> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for key in keys.iter().flatten() {
+        *counts.entry(key).or_default() += 1;
+    }
+    let repeated: Vec<bool> = keys
+        .iter()
+        .map(|key| key.as_deref().is_some_and(|key| counts[key] > 1))
+        .collect();
+    keys.into_iter()
+        .zip(repeated)
+        .map(|(key, repeated)| key.filter(|_| !repeated))
+        .collect()
+}
+
+/// First `len` hex digits of the SHA-256 of `text`
+fn short_hash(text: &str, len: usize) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))[..len].to_string()
+}
+
+/// Lowercased words of a cue, ignoring punctuation and spacing
+fn normalize_text(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 /// Get current timestamp as ISO 8601 string
@@ -162,6 +497,12 @@ mod tests {
             video_path: PathBuf::from("test.mp4"),
             subtitle_path: subtitle_path.clone(),
             subtitle_source: SubtitleSource::External(subtitle_path),
+            overrides: Default::default(),
+            tracks: Vec::new(),
+            languages: Vec::new(),
+            framerate: None,
+            speakers: Default::default(),
+            normalize: Default::default(),
         }
     }
 
@@ -178,7 +519,8 @@ mod tests {
                 Timestamp(4000),
                 Timestamp(6000),
                 "Goodbye world".to_string(),
-            ),
+            )
+            .with_speaker(Some("Homer".to_string())),
         ]
     }
 
@@ -198,6 +540,148 @@ mod tests {
         assert_eq!(first.episode, "S01E01");
         assert_eq!(first.text, "Hello world");
         assert!(first.frame.contains("S01E01"));
+
+        assert_eq!(index.lunr.fields, vec!["text", "episode", "speaker"]);
+        // Two documents with three fields each
+        assert_eq!(index.lunr.field_vectors.len(), 6);
+        assert_eq!(index.lunr.field_vectors[0].0, "text/S01E01-b94d27b9");
+        let terms: Vec<&str> = index
+            .lunr
+            .inverted_index
+            .iter()
+            .map(|(term, _)| term.as_str())
+            .collect();
+        assert_eq!(terms, vec!["goodby", "hello", "homer", "s01e01", "world"]);
+        assert_eq!(index.entries[1].speaker.as_deref(), Some("Homer"));
+        assert_eq!(index.entries[1].field("speaker"), Some("Homer"));
+    }
+
+    #[test]
+    fn test_sdh_index() {
+        let entries = vec![
+            SubtitleEntry::new(
+                1,
+                Timestamp(1000),
+                Timestamp(3000),
+                "- HOMER SIMPSON: [burps] Mmm, donuts.".to_string(),
+            ),
+            SubtitleEntry::new(
+                2,
+                Timestamp(4000),
+                Timestamp(5000),
+                "[EXPLOSION]".to_string(),
+            ),
+            SubtitleEntry::new(
+                3,
+                Timestamp(6000),
+                Timestamp(7000),
+                "♪ theme music ♪".to_string(),
+            ),
+        ];
+        let mut aliases = BTreeMap::new();
+        aliases.insert("Homer".to_string(), vec!["Homer Simpson".to_string()]);
+        let indexer = SearchIndexer::new()
+            .with_speakers(SpeakerAliases::new(&aliases))
+            .with_sdh(true);
+
+        let index = indexer
+            .build_index(&[(create_test_episode(), entries.clone())])
+            .unwrap();
+        assert_eq!(
+            index.lunr.fields,
+            vec!["text", "episode", "speaker", "sound", "music"]
+        );
+        let first = &index.entries[0];
+        assert_eq!(first.text, "Mmm, donuts.");
+        assert_eq!(first.speaker.as_deref(), Some("Homer"));
+        assert_eq!(first.sound.as_deref(), Some("burps"));
+        assert_eq!(index.entries[1].text, "");
+        assert_eq!(index.entries[1].field("sound"), Some("EXPLOSION"));
+        assert_eq!(index.entries[2].music.as_deref(), Some("theme music"));
+
+        // Sound-effect-only cues can be left out; their neighbours keep IDs
+        let index = indexer
+            .with_sound_effects(false)
+            .build_index(&[(create_test_episode(), entries.clone())])
+            .unwrap();
+        assert_eq!(index.entries.len(), 2);
+        let ids = caption_ids(&EpisodeId::new(1, 1), &entries);
+        assert_eq!(index.entries[1].id, ids[2]);
+    }
+
+    #[test]
+    fn test_caption_ids() {
+        let episode = EpisodeId::new(1, 1);
+        let entries = create_test_entries();
+        let ids = caption_ids(&episode, &entries);
+        assert_eq!(ids[0], "S01E01-b94d27b9");
+
+        // Retiming and reformatting a cue keeps its ID
+        let retimed = vec![
+            SubtitleEntry::new(
+                1,
+                Timestamp(1250),
+                Timestamp(3400),
+                "<i>Hello,  World!</i>".to_string(),
+            ),
+            entries[1].clone(),
+        ];
+        assert_eq!(caption_ids(&episode, &retimed), ids);
+
+        // Repeated lines are told apart by the line before them
+        let cue = |start: u64, text: &str| {
+            SubtitleEntry::new(
+                0,
+                Timestamp(start),
+                Timestamp(start + 500),
+                text.to_string(),
+            )
+        };
+        let repeated = vec![
+            cue(9000, "Hey!"),
+            cue(1000, "Hey."),
+            cue(2000, "Bart?"),
+            cue(5000, "Lisa?"),
+            cue(6000, "Hey."),
+        ];
+        let ids = caption_ids(&episode, &repeated);
+        let text_id = |id: &str| id[..15].to_string();
+        assert_eq!(text_id(&ids[0]), text_id(&ids[1]));
+        assert_eq!(text_id(&ids[1]), text_id(&ids[4]));
+        assert_ne!(ids[0], ids[1]);
+        assert_ne!(ids[1], ids[4]);
+        // Lines said once keep the plain text hash
+        assert_eq!(ids[2].len(), 15);
+
+        // Adding a copy of a repeated line after a new neighbour keeps the
+        // existing copies' IDs
+        let mut edited = repeated.clone();
+        edited.push(cue(3000, "Hey."));
+        let edited_ids = caption_ids(&episode, &edited);
+        assert_eq!(&edited_ids[..5], &ids[..]);
+        assert!(!ids.contains(&edited_ids[5]));
+
+        // A line said once gains a suffix when it is repeated, so its old
+        // ID is retired
+        let mut edited = repeated.clone();
+        edited.push(cue(7000, "Bart?"));
+        let edited_ids = caption_ids(&episode, &edited);
+        assert_ne!(edited_ids[2], ids[2]);
+        assert!(edited_ids[2].starts_with(&ids[2]));
+        assert!(!edited_ids.contains(&ids[2]));
+
+        // Copies with the same neighbours fall back to their order
+        let same = vec![
+            cue(0, "No."),
+            cue(1000, "Yeah."),
+            cue(2000, "No."),
+            cue(3000, "Yeah."),
+            cue(4000, "No."),
+        ];
+        let ids = caption_ids(&episode, &same);
+        assert_eq!(format!("{}2", &ids[1][..ids[1].len() - 1]), ids[3]);
+        assert!(ids[1].ends_with("-1"), "{:?}", ids);
+        assert!(!ids[0].ends_with("-1") && !ids[2].ends_with("-1"));
     }
 
     #[test]
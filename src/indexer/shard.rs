@@ -0,0 +1,400 @@
+//! Sharded search index layout
+//!
+//! A single `search/index.json` grows past 100 MB for long-running shows, and
+//! every visitor would have to download all of it. The sharded layout splits
+//! the index into:
+//!
+//! - `search/manifest.json`: fields, pipeline, metadata and the shard table
+//! - `search/terms/NNNN.json`: a contiguous range of the sorted term list with
+//!   its postings and the field vector components for those terms
+//! - `search/docs/<episode>.json`: the entries (text, frame paths) of one episode
+//!
+//! lunr scores a document by the dot product of the query vector with the
+//! document's field vector, and the query vector only holds the query's own
+//! terms. A shard's vector components are therefore enough to score every term
+//! it contains, so the client only fetches the shards a query touches, merges
+//! them and loads the result with `lunr.Index.load`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::indexer::lunr::{LunrIndex, Posting, LUNR_VERSION};
+use crate::indexer::search::{SearchEntry, SearchIndex, SearchMeta};
+
+/// Top-level description of the search index, read first by the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexManifest {
+    /// `single` or `sharded`
+    pub layout: String,
+
+    /// Single-file index path, relative to `search/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<String>,
+
+    /// lunr version of the index data
+    pub version: String,
+
+    /// Indexed fields
+    pub fields: Vec<String>,
+
+    /// Search-time lunr pipeline
+    pub pipeline: Vec<String>,
+
+    /// Term shards, ordered by their first term
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shards: Vec<ShardInfo>,
+
+    /// Document file per episode, relative to `search/`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub docs: BTreeMap<String, String>,
+
+    /// Metadata about the index
+    pub meta: SearchMeta,
+
+    /// Word form to stem table, relative to the manifest, for pipelines the
+    /// browser has no stemmer for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stems: Option<String>,
+
+    /// Every language the site is searchable in, on the top-level manifest
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub languages: Vec<LanguageInfo>,
+}
+
+/// A search language offered by the site
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguageInfo {
+    /// ISO 639-2 code, e.g. `spa`
+    pub code: String,
+
+    /// Name of the language in itself, for the language selector
+    pub name: String,
+
+    /// The language's manifest, relative to `search/`
+    pub manifest: String,
+}
+
+impl IndexManifest {
+    /// Manifest pointing at a single-file index
+    pub fn single(index: &SearchIndex, file: &str) -> Self {
+        Self {
+            layout: "single".to_string(),
+            index: Some(file.to_string()),
+            version: index.lunr.version.clone(),
+            fields: index.lunr.fields.clone(),
+            pipeline: index.lunr.pipeline.clone(),
+            shards: Vec::new(),
+            docs: BTreeMap::new(),
+            meta: index.meta.clone(),
+            stems: None,
+            languages: Vec::new(),
+        }
+    }
+}
+
+/// Entry in the manifest's shard table
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardInfo {
+    /// Shard path, relative to `search/`
+    pub file: String,
+
+    /// First term in the shard (terms sort as JavaScript strings)
+    pub first: String,
+
+    /// Number of terms in the shard
+    pub terms: usize,
+}
+
+/// A contiguous range of the term index
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermShard {
+    /// `(term, posting)` pairs in sorted order
+    pub inverted_index: Vec<(String, Posting)>,
+
+    /// Field vector components for this shard's terms only
+    pub field_vectors: Vec<(String, Vec<f64>)>,
+}
+
+/// A search index split into a manifest, term shards and document files
+#[derive(Debug, Clone)]
+pub struct ShardedIndex {
+    pub manifest: IndexManifest,
+
+    /// Term shards, in the same order as `manifest.shards`
+    pub shards: Vec<TermShard>,
+
+    /// Entries grouped by episode, in the same order as `manifest.docs`
+    pub docs: Vec<(String, Vec<SearchEntry>)>,
+}
+
+impl ShardedIndex {
+    /// Split an index into shards of roughly `shard_size` bytes each
+    ///
+    /// A term is never split across shards, so a shard holding a very common
+    /// term may exceed the budget.
+    pub fn build(index: &SearchIndex, shard_size: usize) -> Self {
+        let vectors: HashMap<&str, &[f64]> = index
+            .lunr
+            .field_vectors
+            .iter()
+            .map(|(field_ref, elements)| (field_ref.as_str(), elements.as_slice()))
+            .collect();
+
+        let mut ranges: Vec<&[(String, Posting)]> = Vec::new();
+        let terms = &index.lunr.inverted_index;
+        let mut start = 0;
+        let mut size = 0;
+        for (i, (term, posting)) in terms.iter().enumerate() {
+            let term_size = estimate_size(term, posting);
+            if i > start && size + term_size > shard_size {
+                ranges.push(&terms[start..i]);
+                start = i;
+                size = 0;
+            }
+            size += term_size;
+        }
+        if start < terms.len() {
+            ranges.push(&terms[start..]);
+        }
+
+        let shards: Vec<TermShard> = ranges
+            .iter()
+            .map(|range| build_shard(range, &vectors))
+            .collect();
+
+        let shard_table = ranges
+            .iter()
+            .enumerate()
+            .map(|(i, range)| ShardInfo {
+                file: format!("terms/{:04}.json", i),
+                first: range[0].0.clone(),
+                terms: range.len(),
+            })
+            .collect();
+
+        let mut by_episode: BTreeMap<String, Vec<SearchEntry>> = BTreeMap::new();
+        for entry in &index.entries {
+            by_episode
+                .entry(entry.episode.clone())
+                .or_default()
+                .push(entry.clone());
+        }
+        let docs: Vec<(String, Vec<SearchEntry>)> = by_episode.into_iter().collect();
+
+        let manifest = IndexManifest {
+            layout: "sharded".to_string(),
+            index: None,
+            version: LUNR_VERSION.to_string(),
+            fields: index.lunr.fields.clone(),
+            pipeline: index.lunr.pipeline.clone(),
+            shards: shard_table,
+            docs: docs
+                .iter()
+                .map(|(episode, _)| (episode.clone(), format!("docs/{}.json", episode)))
+                .collect(),
+            meta: index.meta.clone(),
+            stems: None,
+            languages: Vec::new(),
+        };
+
+        Self {
+            manifest,
+            shards,
+            docs,
+        }
+    }
+}
+
+/// Collect the postings of a term range and their vector components
+fn build_shard(range: &[(String, Posting)], vectors: &HashMap<&str, &[f64]>) -> TermShard {
+    let mut components: BTreeMap<String, Vec<(usize, f64)>> = BTreeMap::new();
+    for (_, posting) in range {
+        for (field, refs) in &posting.fields {
+            for doc_ref in refs {
+                let field_ref = format!("{}/{}", field, doc_ref);
+                let score = vectors
+                    .get(field_ref.as_str())
+                    .and_then(|elements| component(elements, posting.index));
+                if let Some(score) = score {
+                    components
+                        .entry(field_ref)
+                        .or_default()
+                        .push((posting.index, score));
+                }
+            }
+        }
+    }
+
+    let field_vectors = components
+        .into_iter()
+        .map(|(field_ref, mut pairs)| {
+            pairs.sort_by_key(|(index, _)| *index);
+            let elements = pairs
+                .into_iter()
+                .flat_map(|(index, score)| [index as f64, score])
+                .collect();
+            (field_ref, elements)
+        })
+        .collect();
+
+    TermShard {
+        inverted_index: range.to_vec(),
+        field_vectors,
+    }
+}
+
+/// Score of `term_index` in a serialized `[index, score, ...]` vector
+fn component(elements: &[f64], term_index: usize) -> Option<f64> {
+    let pairs = elements.len() / 2;
+    let mut lo = 0;
+    let mut hi = pairs;
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let index = elements[mid * 2] as usize;
+        match index.cmp(&term_index) {
+            std::cmp::Ordering::Equal => return Some(elements[mid * 2 + 1]),
+            std::cmp::Ordering::Less => lo = mid + 1,
+            std::cmp::Ordering::Greater => hi = mid,
+        }
+    }
+    None
+}
+
+/// Approximate serialized size of a term's posting and vector components
+fn estimate_size(term: &str, posting: &Posting) -> usize {
+    let refs: usize = posting
+        .fields
+        .iter()
+        .map(|(field, refs)| {
+            refs.iter()
+                // `"ref":{},` in the posting and `"field/ref":[i,s]` in the vectors
+                .map(|doc_ref| 2 * doc_ref.len() + field.len() + 24)
+                .sum::<usize>()
+                + field.len()
+                + 6
+        })
+        .sum();
+    term.len() + 24 + refs
+}
+
+impl From<&ShardedIndex> for LunrIndex {
+    /// Reassemble a complete lunr index from every shard
+    fn from(sharded: &ShardedIndex) -> Self {
+        let mut vectors: BTreeMap<&str, Vec<(f64, f64)>> = BTreeMap::new();
+        for shard in &sharded.shards {
+            for (field_ref, elements) in &shard.field_vectors {
+                vectors
+                    .entry(field_ref)
+                    .or_default()
+                    .extend(elements.chunks(2).map(|pair| (pair[0], pair[1])));
+            }
+        }
+
+        LunrIndex {
+            version: sharded.manifest.version.clone(),
+            fields: sharded.manifest.fields.clone(),
+            field_vectors: vectors
+                .into_iter()
+                .map(|(field_ref, mut pairs)| {
+                    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+                    let elements = pairs.into_iter().flat_map(|(i, s)| [i, s]).collect();
+                    (field_ref.to_string(), elements)
+                })
+                .collect(),
+            inverted_index: sharded
+                .shards
+                .iter()
+                .flat_map(|shard| shard.inverted_index.iter().cloned())
+                .collect(),
+            pipeline: sharded.manifest.pipeline.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexer::SearchIndexer;
+
+    fn create_test_index(count: usize) -> SearchIndex {
+        let words = [
+            "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf",
+        ];
+        let entries: Vec<SearchEntry> = (0..count)
+            .map(|i| {
+                let episode = format!("S01E{:02}", i % 3 + 1);
+                let text = format!("{} {} {}", words[i % 7], words[(i * 3) % 7], i);
+                SearchEntry {
+                    id: format!("{}-{}", episode, i * 1000),
+                    text,
+                    speaker: Some(words[(i * 5) % 7].to_string()),
+                    sound: None,
+                    music: None,
+                    episode: episode.clone(),
+                    timestamp: i as u64 * 1000,
+                    frame: format!("img/frames/{}/{}.jpg", episode, i * 1000),
+                    thumb: format!("img/thumbs/{}/{}.jpg", episode, i * 1000),
+                    translations: BTreeMap::new(),
+                }
+            })
+            .collect();
+        let lunr = SearchIndexer::new().build_lunr_index(&entries);
+        let meta = SearchMeta {
+            total: entries.len(),
+            episodes: 3,
+            generated_at: "0".to_string(),
+            version: "test".to_string(),
+            language: None,
+        };
+        SearchIndex {
+            entries,
+            lunr,
+            meta,
+            stems: BTreeMap::new(),
+            translations: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_shards_split_by_budget() {
+        let index = create_test_index(200);
+        let sharded = ShardedIndex::build(&index, 2048);
+
+        assert!(sharded.shards.len() > 1);
+        assert_eq!(sharded.shards.len(), sharded.manifest.shards.len());
+
+        // Shards are contiguous, ordered ranges covering every term
+        let terms: usize = sharded.manifest.shards.iter().map(|s| s.terms).sum();
+        assert_eq!(terms, index.lunr.inverted_index.len());
+        for pair in sharded.manifest.shards.windows(2) {
+            assert!(pair[0].first < pair[1].first);
+        }
+
+        assert_eq!(sharded.docs.len(), 3);
+        assert_eq!(sharded.manifest.docs["S01E02"], "docs/S01E02.json");
+        let doc_count: usize = sharded.docs.iter().map(|(_, e)| e.len()).sum();
+        assert_eq!(doc_count, 200);
+    }
+
+    #[test]
+    fn test_shards_reassemble_index() {
+        let index = create_test_index(50);
+        let sharded = ShardedIndex::build(&index, 512);
+        let merged = LunrIndex::from(&sharded);
+
+        assert_eq!(merged.inverted_index, index.lunr.inverted_index);
+
+        let mut original = index.lunr.field_vectors.clone();
+        original.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(merged.field_vectors, original);
+    }
+
+    #[test]
+    fn test_single_budget_keeps_one_shard() {
+        let index = create_test_index(10);
+        let sharded = ShardedIndex::build(&index, usize::MAX);
+        assert_eq!(sharded.shards.len(), 1);
+        assert_eq!(sharded.manifest.shards[0].first, "0");
+    }
+}
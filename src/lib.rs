@@ -38,10 +38,12 @@
 //! generator.generate(&all_entries, &index).expect("Failed to generate");
 //! ```
 
+pub mod cache;
 pub mod cli;
 pub mod config;
 pub mod discovery;
 pub mod error;
+pub mod export;
 pub mod extractor;
 pub mod generator;
 pub mod indexer;
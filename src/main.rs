@@ -37,6 +37,12 @@ fn main() -> Result<()> {
         Commands::Serve(args) => {
             anytron::cli::commands::serve(args)?;
         }
+        Commands::Export(args) => {
+            anytron::cli::commands::export(args)?;
+        }
+        Commands::Subs(args) => {
+            anytron::cli::commands::subs(args)?;
+        }
     }
 
     Ok(())
@@ -0,0 +1,171 @@
+//! Time alignment between two subtitle tracks
+//!
+//! Translations rarely share cue boundaries: one track splits a sentence the
+//! other shows in one cue, or runs a few hundred milliseconds early. Cues are
+//! paired by how much of their time on screen overlaps rather than by index
+//! or exact timestamps.
+
+use crate::subtitle::SubtitleEntry;
+
+/// Share of the shorter cue two cues must overlap by to be paired
+const MIN_OVERLAP: f64 = 0.5;
+
+/// Indices of the `secondary` cues aligned with each `primary` cue
+///
+/// Two cues align when they overlap for at least half of the shorter one, so
+/// a long cue pairs with every short cue it spans. A secondary cue that
+/// overlaps no primary cue that much goes to the one it overlaps most, and
+/// one that overlaps nothing is dropped. Each list is in time order.
+pub fn align_cues(primary: &[SubtitleEntry], secondary: &[SubtitleEntry]) -> Vec<Vec<usize>> {
+    let mut aligned = vec![Vec::new(); primary.len()];
+
+    for (j, cue) in secondary.iter().enumerate() {
+        let mut paired = false;
+        let mut best: Option<(u64, usize)> = None;
+
+        for (i, line) in primary.iter().enumerate() {
+            let overlap = overlap_ms(line, cue);
+            if overlap == 0 {
+                continue;
+            }
+            let shorter = line.duration_ms().min(cue.duration_ms()).max(1);
+            if overlap as f64 >= shorter as f64 * MIN_OVERLAP {
+                aligned[i].push(j);
+                paired = true;
+            }
+            if best.map_or(true, |(most, _)| overlap > most) {
+                best = Some((overlap, i));
+            }
+        }
+
+        if let (false, Some((_, i))) = (paired, best) {
+            aligned[i].push(j);
+        }
+    }
+
+    for cues in &mut aligned {
+        cues.sort_by_key(|&j| (secondary[j].start, j));
+    }
+    aligned
+}
+
+/// The `secondary` text aligned with each `primary` cue, joined into a line
+pub fn aligned_lines(
+    primary: &[SubtitleEntry],
+    secondary: &[SubtitleEntry],
+) -> Vec<Option<String>> {
+    align_cues(primary, secondary)
+        .into_iter()
+        .map(|cues| {
+            let text: Vec<&str> = cues
+                .iter()
+                .map(|&j| secondary[j].text_clean.as_str())
+                .filter(|text| !text.is_empty())
+                .collect();
+            (!text.is_empty()).then(|| text.join(" "))
+        })
+        .collect()
+}
+
+/// Whether `secondary` is timed like `primary`, e.g. a translation cut
+/// from the same release, so a correction to one fits the other
+///
+/// At least half of the secondary cues must pair with a primary cue by
+/// overlap alone.
+pub fn shares_timing(primary: &[SubtitleEntry], secondary: &[SubtitleEntry]) -> bool {
+    let paired = secondary
+        .iter()
+        .filter(|cue| {
+            primary.iter().any(|line| {
+                let shorter = line.duration_ms().min(cue.duration_ms()).max(1);
+                overlap_ms(line, cue) as f64 >= shorter as f64 * MIN_OVERLAP
+            })
+        })
+        .count();
+    !secondary.is_empty() && paired * 2 >= secondary.len()
+}
+
+/// Milliseconds both cues are on screen
+fn overlap_ms(a: &SubtitleEntry, b: &SubtitleEntry) -> u64 {
+    a.end
+        .0
+        .min(b.end.0)
+        .saturating_sub(a.start.0.max(b.start.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle::Timestamp;
+
+    fn cue(start: u64, end: u64, text: &str) -> SubtitleEntry {
+        SubtitleEntry::new(0, Timestamp(start), Timestamp(end), text.to_string())
+    }
+
+    #[test]
+    fn test_split_and_shifted_cues() {
+        let primary = vec![
+            cue(1000, 4000, "Where are the kids?"),
+            cue(5000, 6000, "Upstairs."),
+        ];
+        // The translation splits the first line and runs late
+        let secondary = vec![
+            cue(1300, 2500, "¿Dónde"),
+            cue(2500, 4200, "están los niños?"),
+            cue(5200, 6300, "Arriba."),
+        ];
+
+        assert_eq!(align_cues(&primary, &secondary), vec![vec![0, 1], vec![2]]);
+        assert_eq!(
+            aligned_lines(&primary, &secondary),
+            vec![
+                Some("¿Dónde están los niños?".to_string()),
+                Some("Arriba.".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_long_cue_spans_short_ones() {
+        let primary = vec![cue(0, 1000, "Hi."), cue(1000, 2000, "Hello.")];
+        let secondary = vec![cue(0, 2000, "Hola, hola.")];
+        assert_eq!(align_cues(&primary, &secondary), vec![vec![0], vec![0]]);
+    }
+
+    #[test]
+    fn test_shares_timing() {
+        let primary = vec![
+            cue(1000, 4000, "Where are the kids?"),
+            cue(5000, 6000, "Upstairs."),
+        ];
+        let secondary = vec![
+            cue(1300, 4200, "¿Dónde están los niños?"),
+            cue(5200, 6300, "Arriba."),
+        ];
+        assert!(shares_timing(&primary, &secondary));
+
+        // The same lines from a release cut eight seconds later
+        let late = vec![
+            cue(9300, 12200, "¿Dónde están los niños?"),
+            cue(13200, 14300, "Arriba."),
+        ];
+        assert!(!shares_timing(&primary, &late));
+        assert!(!shares_timing(&primary, &[]));
+    }
+
+    #[test]
+    fn test_slight_overlap_goes_to_best_match() {
+        let primary = vec![
+            cue(0, 1000, "One."),
+            cue(1800, 3000, "Two."),
+            cue(9000, 9500, "Three."),
+        ];
+        // Overlaps each neighbour by less than half, the first by more
+        let secondary = vec![cue(700, 2000, "Uno."), cue(5000, 6000, "Nada.")];
+
+        assert_eq!(
+            aligned_lines(&primary, &secondary),
+            vec![Some("Uno.".to_string()), None, None]
+        );
+    }
+}
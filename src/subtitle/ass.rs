@@ -10,23 +10,51 @@
 
 use std::path::Path;
 
-use crate::error::{AnytronError, Result};
+use crate::error::Result;
+use crate::subtitle::diagnostics::{Diagnostic, Parsed};
+use crate::subtitle::encoding;
+use crate::subtitle::parser::{ParseOptions, SubtitleParser};
 use crate::subtitle::types::{SubtitleEntry, Timestamp};
+use crate::subtitle::writer;
+
+/// Script header and default style for written files
+const ASS_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+WrapStyle: 0
+ScaledBorderAndShadow: yes
+PlayResX: 384
+PlayResY: 288
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,16,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+/// Events format assumed when a script has no Format line
+const DEFAULT_FORMAT: &str =
+    "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text";
 
 /// Parse an ASS/SSA file into subtitle entries
 pub fn parse_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let content = std::fs::read_to_string(path).map_err(|e| AnytronError::SubtitleParse {
-        path: path.to_path_buf(),
-        line: 0,
-        message: format!("Failed to read file: {}", e),
-    })?;
+    let (content, _) = encoding::read_file(path, None)?;
 
     parse_str(&content, path)
 }
 
 /// Parse ASS content string into subtitle entries
+///
+/// Fails on the first event whose timing can't be read; [`parse_lenient`]
+/// skips it instead.
 pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let mut entries = Vec::new();
+    parse_lenient(content).into_result(path)
+}
+
+/// Parse ASS content, skipping events that can't be read and reporting them
+pub fn parse_lenient(content: &str) -> Parsed {
+    let mut parsed = Parsed::default();
     let mut in_events_section = false;
     let mut format_indices: Option<FormatIndices> = None;
     let mut index = 0;
@@ -58,21 +86,23 @@ pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
 
         // Parse dialogue lines
         if line.to_lowercase().starts_with("dialogue:") {
-            let Some(ref fmt) = format_indices else {
-                return Err(AnytronError::SubtitleParse {
-                    path: path.to_path_buf(),
-                    line: line_num + 1,
-                    message: "Dialogue line before Format line".to_string(),
-                });
-            };
-
-            if let Some(entry) = parse_dialogue_line(line, fmt, &mut index, path, line_num)? {
-                entries.push(entry);
+            let fmt = format_indices.get_or_insert_with(|| {
+                parsed.diagnostics.push(Diagnostic::warning(
+                    line_num + 1,
+                    "missing-format",
+                    "Dialogue line before Format line, assuming the standard fields",
+                ));
+                parse_format_line(DEFAULT_FORMAT)
+            });
+
+            match parse_dialogue_line(line, fmt, &mut index, line_num + 1) {
+                Ok(entry) => parsed.push(line_num + 1, entry),
+                Err(diagnostic) => parsed.diagnostics.push(diagnostic),
             }
         }
     }
 
-    Ok(entries)
+    parsed
 }
 
 /// Indices for the Format fields we care about
@@ -80,10 +110,81 @@ pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
 struct FormatIndices {
     start: usize,
     end: usize,
+    name: Option<usize>,
     text: usize,
     total_fields: usize,
 }
 
+/// Serialize entries as an ASS script with a single default style
+///
+/// ASS times have centisecond precision, so milliseconds are truncated.
+pub fn write_str(entries: &[SubtitleEntry]) -> String {
+    let mut out = String::from(ASS_HEADER);
+    for entry in entries {
+        let text = writer::ass_text(&entry.text);
+        if text.is_empty() {
+            continue;
+        }
+        // Commas would shift the fields after the name
+        let speaker = entry.speaker.as_deref().unwrap_or("").replace(',', "");
+        out.push_str(&format!(
+            "Dialogue: 0,{},{},Default,{},0,0,0,,{}\n",
+            entry.start.to_ass(),
+            entry.end.to_ass(),
+            speaker,
+            text
+        ));
+    }
+    out
+}
+
+/// Advanced SubStation Alpha and SubStation Alpha (`.ass`, `.ssa`)
+pub struct AssParser;
+
+impl SubtitleParser for AssParser {
+    fn name(&self) -> &str {
+        "ASS"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ass", "ssa"]
+    }
+
+    /// The `[Script Info]` header, or failing that an events section
+    fn detect(&self, content: &str) -> u8 {
+        let mut events = false;
+        for line in content.lines().map(str::trim) {
+            let line = line.trim_start_matches('\u{feff}');
+            if line.eq_ignore_ascii_case("[Script Info]") {
+                return 100;
+            } else if line.eq_ignore_ascii_case("[Events]") {
+                events = true;
+            } else if events && line.starts_with("Dialogue:") {
+                return 80;
+            }
+        }
+        0
+    }
+
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        parse_str(content, path)
+    }
+
+    fn parse_lenient(
+        &self,
+        content: &str,
+        _path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Parsed> {
+        Ok(parse_lenient(content))
+    }
+}
+
 /// Parse the Format line to extract column indices
 fn parse_format_line(line: &str) -> FormatIndices {
     let fields_str = line.split_once(':').map(|x| x.1).unwrap_or("");
@@ -91,12 +192,14 @@ fn parse_format_line(line: &str) -> FormatIndices {
 
     let mut start = 1;
     let mut end = 2;
+    let mut name = None;
     let mut text = fields.len().saturating_sub(1);
 
     for (i, field) in fields.iter().enumerate() {
         match field.to_lowercase().as_str() {
             "start" => start = i,
             "end" => end = i,
+            "name" | "actor" => name = Some(i),
             "text" => text = i,
             _ => {}
         }
@@ -105,6 +208,7 @@ fn parse_format_line(line: &str) -> FormatIndices {
     FormatIndices {
         start,
         end,
+        name,
         text,
         total_fields: fields.len(),
     }
@@ -112,14 +216,13 @@ fn parse_format_line(line: &str) -> FormatIndices {
 
 /// Parse a single Dialogue line
 fn parse_dialogue_line(
-    line: &str,
+    text_line: &str,
     fmt: &FormatIndices,
     index: &mut usize,
-    path: &Path,
-    line_num: usize,
-) -> Result<Option<SubtitleEntry>> {
+    line: usize,
+) -> std::result::Result<SubtitleEntry, Diagnostic> {
     // Remove "Dialogue:" prefix
-    let content = line.split_once(':').map(|x| x.1).unwrap_or("").trim();
+    let content = text_line.split_once(':').map(|x| x.1).unwrap_or("").trim();
 
     // Split into fields, but the last field (Text) can contain commas
     let mut fields: Vec<&str> = Vec::with_capacity(fmt.total_fields);
@@ -137,21 +240,20 @@ fn parse_dialogue_line(
     // The rest is the Text field
     fields.push(remaining);
 
-    if fields.len() < fmt.total_fields {
-        return Ok(None); // Skip malformed lines
-    }
+    let start = Timestamp::parse_ass(fields[fmt.start]).map_err(|e| {
+        Diagnostic::error(
+            line,
+            "bad-timestamp",
+            format!("Invalid start timestamp: {}", e),
+        )
+    })?;
 
-    let start =
-        Timestamp::parse_ass(fields[fmt.start]).map_err(|e| AnytronError::SubtitleParse {
-            path: path.to_path_buf(),
-            line: line_num + 1,
-            message: format!("Invalid start timestamp: {}", e),
-        })?;
-
-    let end = Timestamp::parse_ass(fields[fmt.end]).map_err(|e| AnytronError::SubtitleParse {
-        path: path.to_path_buf(),
-        line: line_num + 1,
-        message: format!("Invalid end timestamp: {}", e),
+    let end = Timestamp::parse_ass(fields[fmt.end]).map_err(|e| {
+        Diagnostic::error(
+            line,
+            "bad-timestamp",
+            format!("Invalid end timestamp: {}", e),
+        )
     })?;
 
     let text = fields[fmt.text].to_string();
@@ -159,9 +261,15 @@ fn parse_dialogue_line(
     // Convert ASS line breaks (\N) to actual newlines
     let text = text.replace("\\N", "\n").replace("\\n", "\n");
 
+    let speaker = fmt
+        .name
+        .map(|name| fields[name].trim())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+
     *index += 1;
 
-    Ok(Some(SubtitleEntry::new(*index, start, end, text)))
+    Ok(SubtitleEntry::new(*index, start, end, text).with_speaker(speaker))
 }
 
 #[cfg(test)]
@@ -215,4 +323,23 @@ Dialogue: 0,0:00:01.00,0:00:04.00,Default,,0,0,0,,Hello, world, how are you?
         let entries = parse_str(content, &PathBuf::from("test.ass")).unwrap();
         assert_eq!(entries[0].text_clean, "Hello, world, how are you?");
     }
+
+    #[test]
+    fn test_parse_lenient() {
+        let content = r#"[Events]
+Dialogue: 0,0:00:01.00,0:00:04.00,Default,,0,0,0,,No format line
+Dialogue: 0,soon,0:00:08.00,Default,,0,0,0,,Bad start
+"#;
+
+        let parsed = parse_lenient(content);
+        assert_eq!(parsed.entries.len(), 1);
+        assert_eq!(parsed.entries[0].text_clean, "No format line");
+        let codes: Vec<(usize, &str)> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.code))
+            .collect();
+        assert_eq!(codes, vec![(2, "missing-format"), (3, "bad-timestamp")]);
+        assert!(parsed.has_errors());
+    }
 }
@@ -0,0 +1,214 @@
+//! Problems found while parsing subtitles
+//!
+//! Lenient parsing ([`SubtitleParser::parse_lenient`]) skips what it can't
+//! read and carries on, reporting each problem as a [`Diagnostic`] next to
+//! the entries it did read.
+//!
+//! [`SubtitleParser::parse_lenient`]: crate::subtitle::SubtitleParser::parse_lenient
+
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+
+use crate::error::{AnytronError, Result};
+use crate::subtitle::types::SubtitleEntry;
+
+/// How serious a problem is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Something unexpected was ignored or worked around
+    Warning,
+    /// A cue couldn't be read and was skipped
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem at one line of a subtitle file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// 1-based line number (0 when the problem has no one line)
+    pub line: usize,
+
+    /// How serious it is
+    pub severity: Severity,
+
+    /// Short stable identifier, e.g. `bad-timestamp`
+    pub code: &'static str,
+
+    /// What went wrong
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Something unexpected that was ignored or worked around
+    pub fn warning(line: usize, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            severity: Severity::Warning,
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// A cue that couldn't be read
+    pub fn error(line: usize, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            severity: Severity::Error,
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// `line 12: error [bad-timestamp] Invalid start timestamp: ...`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} [{}] {}",
+            self.line, self.severity, self.code, self.message
+        )
+    }
+}
+
+/// Entries read from a subtitle file, with the problems met on the way
+#[derive(Debug, Clone, Default)]
+pub struct Parsed {
+    /// Every cue that could be read
+    pub entries: Vec<SubtitleEntry>,
+
+    /// Problems, in file order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Parsed {
+    /// Entries with no problems
+    pub fn new(entries: Vec<SubtitleEntry>) -> Self {
+        Self {
+            entries,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Add an entry read at `line`, warning when it ends before it starts
+    pub(crate) fn push(&mut self, line: usize, entry: SubtitleEntry) {
+        if entry.end < entry.start {
+            self.diagnostics.push(Diagnostic::warning(
+                line,
+                "end-before-start",
+                format!(
+                    "Cue ends at {} before it starts at {}",
+                    entry.end.to_srt(),
+                    entry.start.to_srt()
+                ),
+            ));
+        }
+        self.entries.push(entry);
+    }
+
+    /// Whether a cue had to be skipped
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// The entries, or the first error-level diagnostic as an error
+    pub fn into_result(self, path: &Path) -> Result<Vec<SubtitleEntry>> {
+        match self
+            .diagnostics
+            .into_iter()
+            .find(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            Some(diagnostic) => Err(AnytronError::SubtitleParse {
+                path: path.to_path_buf(),
+                line: diagnostic.line,
+                message: diagnostic.message,
+            }),
+            None => Ok(self.entries),
+        }
+    }
+}
+
+/// Log each problem found in the subtitle file at `path` at the level its
+/// severity calls for, returning the logged lines
+pub(crate) fn log_diagnostics(path: &Path, diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let line = format!("{}: {}", path.display(), diagnostic);
+            match diagnostic.severity {
+                Severity::Warning => log::warn!("{}", line),
+                Severity::Error => log::error!("{}", line),
+            }
+            line
+        })
+        .collect()
+}
+
+/// Blank-line separated blocks of `content`, trimmed, with the line each
+/// starts on
+pub(crate) fn blocks(content: &str) -> Vec<(usize, &str)> {
+    let mut blocks = Vec::new();
+    let mut line = 1;
+    for raw in content.split("\n\n") {
+        let block = raw.trim();
+        if !block.is_empty() {
+            let leading = &raw[..raw.len() - raw.trim_start().len()];
+            blocks.push((line + leading.matches('\n').count(), block));
+        }
+        line += raw.matches('\n').count() + 2;
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle::Timestamp;
+
+    #[test]
+    fn test_blocks() {
+        let blocks = blocks("1\nfirst\n\n\n2\nsecond\n\n  \n\n3\n");
+        assert_eq!(blocks, vec![(1, "1\nfirst"), (5, "2\nsecond"), (10, "3")]);
+    }
+
+    #[test]
+    fn test_into_result() {
+        let entry = SubtitleEntry::new(1, Timestamp(0), Timestamp(1000), "Hi".to_string());
+        let mut parsed = Parsed::new(vec![entry]);
+        parsed
+            .diagnostics
+            .push(Diagnostic::warning(3, "bad-index", "Missing cue number"));
+        assert!(!parsed.has_errors());
+        assert_eq!(
+            parsed.diagnostics[0].to_string(),
+            "line 3: warning [bad-index] Missing cue number"
+        );
+        assert_eq!(
+            parsed
+                .clone()
+                .into_result(Path::new("a.srt"))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        parsed
+            .diagnostics
+            .push(Diagnostic::error(7, "bad-timestamp", "Invalid timestamp"));
+        assert!(parsed.has_errors());
+        let err = parsed.into_result(Path::new("a.srt")).unwrap_err();
+        assert!(err.to_string().contains("Invalid timestamp"));
+    }
+}
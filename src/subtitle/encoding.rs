@@ -0,0 +1,230 @@
+//! Subtitle character encoding detection
+//!
+//! Fan-made subtitles are often Windows-1252, ISO-8859-x or UTF-16 rather
+//! than UTF-8. Files are decoded to UTF-8 before parsing, with the encoding
+//! taken from, in order:
+//!
+//! 1. a byte order mark (UTF-8, UTF-16LE, UTF-16BE)
+//! 2. NUL bytes in every other position, for UTF-16 without a BOM
+//! 3. the bytes being valid UTF-8
+//! 4. statistical detection over legacy 8-bit and CJK code pages
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::fmt;
+use std::path::Path;
+
+use crate::error::{AnytronError, Result};
+
+/// Bytes looked at by the UTF-16 heuristic
+const UTF16_SAMPLE: usize = 4096;
+
+/// How a file's encoding was determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Detection {
+    /// Set in the config
+    Override,
+
+    /// Byte order mark at the start of the file
+    Bom,
+
+    /// UTF-16 without a BOM, from the position of NUL bytes
+    Utf16,
+
+    /// The file is valid UTF-8 (or plain ASCII)
+    Utf8,
+
+    /// Best statistical guess among legacy encodings
+    Guess,
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Override => "config override",
+            Self::Bom => "byte order mark",
+            Self::Utf16 => "UTF-16 heuristic",
+            Self::Utf8 => "valid UTF-8",
+            Self::Guess => "statistical guess",
+        })
+    }
+}
+
+/// The character encoding of a subtitle file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,
+    pub detection: Detection,
+}
+
+impl TextEncoding {
+    /// WHATWG name of the encoding, e.g. `windows-1252`
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+}
+
+impl fmt::Display for TextEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name(), self.detection)
+    }
+}
+
+/// Look up an encoding by label, e.g. `cp1252`, `latin1` or `utf-16le`
+pub fn encoding_for_label(label: &str) -> Result<&'static Encoding> {
+    Encoding::for_label_no_replacement(label.trim().as_bytes())
+        .ok_or_else(|| AnytronError::Config(format!("Unknown character encoding: {:?}", label)))
+}
+
+/// Detect the encoding of raw subtitle bytes
+pub fn detect(bytes: &[u8]) -> TextEncoding {
+    let (encoding, detection) = if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        (encoding, Detection::Bom)
+    } else if let Some(encoding) = utf16_without_bom(bytes) {
+        (encoding, Detection::Utf16)
+    } else if std::str::from_utf8(bytes).is_ok() {
+        (UTF_8, Detection::Utf8)
+    } else {
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        (detector.guess(None, true), Detection::Guess)
+    };
+    TextEncoding {
+        encoding,
+        detection,
+    }
+}
+
+/// Decode subtitle bytes to UTF-8, detecting the encoding unless `forced`
+///
+/// Bytes that are invalid in the encoding become U+FFFD.
+pub fn decode(bytes: &[u8], forced: Option<&'static Encoding>) -> (String, TextEncoding) {
+    let detected = match forced {
+        Some(encoding) => TextEncoding {
+            encoding,
+            detection: Detection::Override,
+        },
+        None => detect(bytes),
+    };
+    let (text, had_errors) = detected.encoding.decode_with_bom_removal(bytes);
+    if had_errors {
+        log::debug!("Replaced bytes invalid in {}", detected.name());
+    }
+    (text.into_owned(), detected)
+}
+
+/// Read a subtitle file as UTF-8, detecting the encoding unless `forced`
+pub fn read_file(path: &Path, forced: Option<&'static Encoding>) -> Result<(String, TextEncoding)> {
+    let bytes = read_bytes(path)?;
+    Ok(decode(&bytes, forced))
+}
+
+/// Detect the encoding of a subtitle file without decoding it
+pub fn detect_file(path: &Path) -> Result<TextEncoding> {
+    let bytes = read_bytes(path)?;
+    Ok(detect(&bytes))
+}
+
+fn read_bytes(path: &Path) -> Result<Vec<u8>> {
+    std::fs::read(path).map_err(|e| AnytronError::SubtitleParse {
+        path: path.to_path_buf(),
+        line: 0,
+        message: format!("Failed to read file: {}", e),
+    })
+}
+
+/// UTF-16 byte order for text whose ASCII characters leave NULs in every
+/// other byte
+///
+/// Subtitles are mostly digits, punctuation and spaces even in non-Latin
+/// scripts, so their high bytes are overwhelmingly zero.
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SAMPLE) & !1];
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+
+    let even = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|&&b| b == 0)
+        .count();
+    if odd * 10 >= pairs * 4 && even * 20 < pairs {
+        Some(UTF_16LE)
+    } else if even * 10 >= pairs * 4 && odd * 20 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRT: &str = "1\n00:00:01,000 --> 00:00:04,000\nÇa va très bien, garçon.\n";
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| {
+                if little_endian {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_detects_utf16() {
+        let mut bom = vec![0xFF, 0xFE];
+        bom.extend(utf16(SRT, true));
+        let (text, encoding) = decode(&bom, None);
+        assert_eq!(text, SRT);
+        assert_eq!(
+            (encoding.encoding, encoding.detection),
+            (UTF_16LE, Detection::Bom)
+        );
+
+        let (text, encoding) = decode(&utf16(SRT, false), None);
+        assert_eq!(text, SRT);
+        assert_eq!(
+            (encoding.encoding, encoding.detection),
+            (UTF_16BE, Detection::Utf16)
+        );
+    }
+
+    #[test]
+    fn test_detects_legacy_code_pages() {
+        let (bytes, _, _) = encoding_rs::WINDOWS_1252.encode(SRT);
+        let (text, encoding) = decode(&bytes, None);
+        assert_eq!(text, SRT);
+        assert_eq!(encoding.detection, Detection::Guess);
+
+        let russian = "1\n00:00:01,000 --> 00:00:04,000\nПривет, как дела? Всё хорошо, спасибо.\n";
+        let (bytes, _, _) = encoding_rs::WINDOWS_1251.encode(russian);
+        let (text, encoding) = decode(&bytes, None);
+        assert_eq!(text, russian);
+        assert_eq!(encoding.name(), "windows-1251");
+
+        let (text, encoding) = decode(SRT.as_bytes(), None);
+        assert_eq!(text, SRT);
+        assert_eq!(encoding.detection, Detection::Utf8);
+    }
+
+    #[test]
+    fn test_override() {
+        let latin2 = encoding_for_label("latin2").unwrap();
+        assert_eq!(latin2.name(), "ISO-8859-2");
+        assert!(encoding_for_label("klingon").is_err());
+
+        // 0xB3 is "ł" in ISO-8859-2 but "³" in Windows-1252
+        let (text, encoding) = decode(b"Cze\xb6\xe6, mi\xb3o", Some(latin2));
+        assert_eq!(text, "Cześć, miło");
+        assert_eq!(encoding.detection, Detection::Override);
+    }
+}
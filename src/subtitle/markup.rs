@@ -0,0 +1,278 @@
+//! Tokenizer for the XML of TTML and the loose HTML of SAMI
+//!
+//! Only what subtitle files need: tags with attributes, text and character
+//! references. Comments, processing instructions and doctypes are skipped.
+//! Element and attribute names lose their namespace prefix and are
+//! lowercased, so `tt:p` matches `p` and `tts:fontStyle` matches `fontstyle`.
+
+/// A piece of markup
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Token<'a> {
+    Start(Tag),
+    End(String),
+    Text(&'a str),
+}
+
+/// An opening or self-closing tag
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Tag {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub self_closing: bool,
+    /// 1-based line the tag starts on
+    pub line: usize,
+}
+
+impl Tag {
+    /// Value of an attribute by local name, decoded
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Split markup into tags and text
+pub(crate) fn tokenize(content: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = content;
+    let mut line = 1;
+    let mut counted = 0;
+
+    while !rest.is_empty() {
+        let Some(open) = rest.find('<') else {
+            tokens.push(Token::Text(rest));
+            break;
+        };
+        if open > 0 {
+            tokens.push(Token::Text(&rest[..open]));
+        }
+        rest = &rest[open..];
+        let offset = content.len() - rest.len();
+        line += content[counted..offset].matches('\n').count();
+        counted = offset;
+
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>").unwrap_or(after.len());
+            tokens.push(Token::Text(&after[..end]));
+            rest = after.get(end + 3..).unwrap_or("");
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+        } else if let Some((token, len)) = parse_tag(rest, line) {
+            tokens.push(token);
+            rest = &rest[len..];
+        } else {
+            // A stray `<` in text
+            tokens.push(Token::Text(&rest[..1]));
+            rest = &rest[1..];
+        }
+    }
+
+    tokens
+}
+
+/// Replace character and entity references with the characters they name
+///
+/// `&nbsp;` becomes a plain space; unknown entities are kept as written.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| Some((entity(&rest[1..end])?, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// Escape text for use in markup
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    match name.to_ascii_lowercase().as_str() {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => None,
+    }
+}
+
+/// Parse a tag at the start of `input`, returning it and its length
+fn parse_tag(input: &str, line: usize) -> Option<(Token<'_>, usize)> {
+    let closing = input.starts_with("</");
+    let body_start = if closing { 2 } else { 1 };
+    let first = input[body_start..].chars().next()?;
+    if !first.is_alphabetic() && first != '_' {
+        return None;
+    }
+    let end = tag_end(input)?;
+    let body = &input[body_start..end];
+
+    let name_len = body
+        .find(|c: char| c.is_whitespace() || c == '/')
+        .unwrap_or(body.len());
+    let name = local_name(&body[..name_len]);
+    if closing {
+        return Some((Token::End(name), end + 1));
+    }
+
+    let attrs_str = body[name_len..].trim_end();
+    let self_closing = attrs_str.ends_with('/');
+    let attrs = parse_attrs(attrs_str.trim_end_matches('/'));
+    Some((
+        Token::Start(Tag {
+            name,
+            attrs,
+            self_closing,
+            line,
+        }),
+        end + 1,
+    ))
+}
+
+/// Index of the `>` closing the tag, skipping any inside quoted values
+fn tag_end(input: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in input.char_indices().skip(1) {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i),
+            (None, '<') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attrs(input: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || c == '=')
+            .unwrap_or(rest.len());
+        let name = local_name(&rest[..name_len]);
+        rest = rest[name_len..].trim_start();
+
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next() {
+                Some(q @ ('"' | '\'')) => {
+                    let inner = &after[1..];
+                    let end = inner.find(q).unwrap_or(inner.len());
+                    (&inner[..end], inner.get(end + 1..).unwrap_or(""))
+                }
+                _ => {
+                    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining;
+        }
+
+        if !name.is_empty() {
+            attrs.push((name, value));
+        }
+        rest = rest.trim_start();
+    }
+
+    attrs
+}
+
+/// Lowercased name without its namespace prefix
+fn local_name(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(
+            "<?xml version=\"1.0\"?><!-- note --><tt:p begin='1s' xml:id=p1>A &amp; B<br/></tt:p> 1 < 2",
+        );
+        let Token::Start(p) = &tokens[0] else {
+            panic!("expected a start tag: {:?}", tokens[0]);
+        };
+        assert_eq!(p.name, "p");
+        assert_eq!(p.attr("begin"), Some("1s"));
+        assert_eq!(p.attr("id"), Some("p1"));
+        assert_eq!(p.line, 1);
+        assert_eq!(tokens[1], Token::Text("A &amp; B"));
+        assert!(matches!(&tokens[2], Token::Start(br) if br.name == "br" && br.self_closing));
+        assert_eq!(tokens[3], Token::End("p".to_string()));
+        assert_eq!(
+            tokens[4..]
+                .iter()
+                .map(|t| match t {
+                    Token::Text(text) => *text,
+                    _ => "?",
+                })
+                .collect::<String>(),
+            " 1 < 2"
+        );
+    }
+
+    #[test]
+    fn test_tag_lines() {
+        let tokens = tokenize("<tt>\n<body>\n\n<p>Hi</p>\n</body></tt>");
+        let lines: Vec<usize> = tokens
+            .iter()
+            .filter_map(|token| match token {
+                Token::Start(tag) => Some(tag.line),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines, vec![1, 2, 4]);
+    }
+
+    #[test]
+    fn test_entities() {
+        assert_eq!(
+            decode_entities("&lt;i&gt; &#233;t&#xE9; &nbsp;&bogus; AT&T"),
+            "<i> été  &bogus; AT&T"
+        );
+        assert_eq!(escape("<a & b>"), "&lt;a &amp; b&gt;");
+    }
+}
@@ -0,0 +1,294 @@
+//! MicroDVD subtitle parser
+//!
+//! MicroDVD format:
+//! ```text
+//! {1}{1}23.976
+//! {1025}{1100}First subtitle
+//! {1200}{1300}{y:i}Two lines|in italics
+//! ```
+//!
+//! Cues are timed in video frames rather than milliseconds. The framerate
+//! comes from a `{1}{1}` header line when the file has one, otherwise from
+//! [`ParseOptions::framerate`], and falls back to [`DEFAULT_FPS`].
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::subtitle::diagnostics::{Diagnostic, Parsed};
+use crate::subtitle::encoding;
+use crate::subtitle::parser::{ParseOptions, SubtitleParser};
+use crate::subtitle::types::{SubtitleEntry, Timestamp};
+use crate::subtitle::writer;
+
+lazy_static! {
+    static ref RE_CUE: Regex = Regex::new(r"^\{(\d+)\}\{(\d*)\}(.*)$").unwrap();
+    static ref RE_CODE: Regex = Regex::new(r"\{([A-Za-z]):([^}]*)\}").unwrap();
+    static ref RE_ITALIC_LINE: Regex = Regex::new(r"^<i>([^<]*)</i>$").unwrap();
+    static ref RE_TAG: Regex = Regex::new(r"<[^>]+>").unwrap();
+}
+
+/// Framerate assumed when neither the file nor the video gives one
+pub const DEFAULT_FPS: f64 = 23.976;
+
+/// How long a cue without an end frame stays up when nothing follows it
+const LAST_CUE_MS: u64 = 4000;
+
+/// Parse a MicroDVD file into subtitle entries
+pub fn parse_file(path: &Path, framerate: Option<f64>) -> Result<Vec<SubtitleEntry>> {
+    let (content, _) = encoding::read_file(path, None)?;
+
+    parse_str(&content, path, framerate)
+}
+
+/// Parse MicroDVD content, timing frames at `framerate` unless the file
+/// names its own
+///
+/// Fails on the first line that isn't a cue; [`parse_lenient`] skips it
+/// instead.
+pub fn parse_str(content: &str, path: &Path, framerate: Option<f64>) -> Result<Vec<SubtitleEntry>> {
+    parse_lenient(content, framerate).into_result(path)
+}
+
+/// Parse MicroDVD content, skipping lines that aren't cues and reporting
+/// them
+pub fn parse_lenient(content: &str, framerate: Option<f64>) -> Parsed {
+    let content = content.trim_start_matches('\u{feff}');
+    let mut parsed = Parsed::default();
+
+    let mut cues = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(caps) = RE_CUE.captures(line) else {
+            parsed.diagnostics.push(Diagnostic::error(
+                number + 1,
+                "bad-line",
+                format!("Expected {{start}}{{end}}text, got {:?}", line),
+            ));
+            continue;
+        };
+        let start: u64 = caps[1].parse().unwrap_or(0);
+        let end: Option<u64> = caps[2].parse().ok();
+        cues.push((number + 1, start, end, caps[3].to_string()));
+    }
+
+    // `{1}{1}25` (or `{0}{0}25`) declares the framerate
+    let header = cues
+        .first()
+        .filter(|(_, start, end, _)| *start <= 1 && *end == Some(*start))
+        .and_then(|(_, _, _, text)| text.trim().parse::<f64>().ok())
+        .filter(|fps| *fps > 0.0);
+    if header.is_some() {
+        cues.remove(0);
+    }
+    let fps = match header.or(framerate) {
+        Some(fps) => fps,
+        None => {
+            parsed.diagnostics.push(Diagnostic::warning(
+                0,
+                "unknown-framerate",
+                format!(
+                    "Timed in frames but the framerate is unknown, assuming {}",
+                    DEFAULT_FPS
+                ),
+            ));
+            DEFAULT_FPS
+        }
+    };
+
+    for (i, (line, start, end, text)) in cues.iter().enumerate() {
+        let text = cue_text(text);
+        if text.is_empty() {
+            continue;
+        }
+        let start_ms = Timestamp::from_frames(*start, fps);
+        let end_ms = match end {
+            Some(end) => Timestamp::from_frames(*end, fps),
+            None => cues
+                .get(i + 1)
+                .map_or(Timestamp(start_ms.0 + LAST_CUE_MS), |(_, next, _, _)| {
+                    Timestamp::from_frames(*next, fps)
+                }),
+        };
+        let index = parsed.entries.len() + 1;
+        parsed.push(*line, SubtitleEntry::new(index, start_ms, end_ms, text));
+    }
+
+    parsed
+}
+
+/// Serialize entries as MicroDVD timed at `fps`, declared in a header line
+pub fn write_str(entries: &[SubtitleEntry], fps: f64) -> String {
+    let mut out = format!("{{1}}{{1}}{}\n", fps);
+    for entry in entries {
+        let text = writer::html_text(&writer::prefixed_text(entry));
+        if text.is_empty() {
+            continue;
+        }
+        let lines: Vec<String> = text
+            .lines()
+            .map(|line| match RE_ITALIC_LINE.captures(line) {
+                Some(caps) => format!("{{y:i}}{}", &caps[1]),
+                None => RE_TAG.replace_all(line, "").into_owned(),
+            })
+            .collect();
+        out.push_str(&format!(
+            "{{{}}}{{{}}}{}\n",
+            entry.start.to_frames(fps),
+            entry.end.to_frames(fps),
+            lines.join("|")
+        ));
+    }
+    out
+}
+
+/// MicroDVD (`.sub`)
+pub struct MicroDvdParser;
+
+impl SubtitleParser for MicroDvdParser {
+    fn name(&self) -> &str {
+        "MicroDVD"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sub"]
+    }
+
+    /// A `{start}{end}` first line
+    fn detect(&self, content: &str) -> u8 {
+        let first = content
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty());
+        if first.is_some_and(|line| RE_CUE.is_match(line)) {
+            100
+        } else {
+            0
+        }
+    }
+
+    fn frame_based(&self) -> bool {
+        true
+    }
+
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        parse_str(content, path, options.framerate)
+    }
+
+    fn parse_lenient(&self, content: &str, _path: &Path, options: &ParseOptions) -> Result<Parsed> {
+        Ok(parse_lenient(content, options.framerate))
+    }
+}
+
+/// Cue text with `|` line breaks and `{y:i}` styles turned into markup;
+/// other control codes (colour, font, position) are dropped
+fn cue_text(raw: &str) -> String {
+    let mut cue_styles = String::new();
+    let mut lines = Vec::new();
+    for line in raw.split('|') {
+        let mut line_styles = String::new();
+        for caps in RE_CODE.captures_iter(line) {
+            // Upper case applies to the whole cue, lower case to its line
+            match &caps[1] {
+                "Y" => cue_styles.push_str(&caps[2].to_lowercase()),
+                "y" => line_styles.push_str(&caps[2].to_lowercase()),
+                _ => {}
+            }
+        }
+        let text = RE_CODE.replace_all(line, "");
+        let text = text.trim();
+        if !text.is_empty() {
+            lines.push(styled(text, &line_styles));
+        }
+    }
+    styled(&lines.join("\n"), &cue_styles)
+}
+
+/// Wrap text in the tags named by MicroDVD style letters (`i`, `b`, `u`)
+fn styled(text: &str, styles: &str) -> String {
+    ['u', 'b', 'i']
+        .iter()
+        .filter(|style| styles.contains(**style))
+        .fold(text.to_string(), |text, style| {
+            format!("<{}>{}</{}>", style, text, style)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "{1}{1}25\n{25}{100}Hello there.\n{125}{200}{Y:i}Two lines|in italics\n{250}{}{c:$0000FF}{y:b}Bold|plain\n{300}{350}Last.\n";
+
+    #[test]
+    fn test_parse_with_header() {
+        let path = Path::new("test.sub");
+        // The header's 25 fps beats the one passed in
+        let entries = parse_str(SAMPLE, path, Some(23.976)).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].start, Timestamp(1000));
+        assert_eq!(entries[0].end, Timestamp(4000));
+        assert_eq!(entries[1].text, "<i>Two lines\nin italics</i>");
+        assert_eq!(entries[1].text_clean, "Two lines in italics");
+        assert_eq!(entries[2].text, "<b>Bold</b>\nplain");
+        // No end frame: up until the next cue
+        assert_eq!(entries[2].end, Timestamp(12000));
+    }
+
+    #[test]
+    fn test_framerate_source() {
+        let path = Path::new("test.sub");
+        let content = "{1025}{1100}Hello.\n";
+        let ntsc = parse_str(content, path, Some(23.976)).unwrap();
+        assert_eq!(ntsc[0].start, Timestamp(42751));
+        assert_eq!(ntsc[0].end, Timestamp(45879));
+        let pal = parse_str(content, path, Some(25.0)).unwrap();
+        assert_eq!(pal[0].start, Timestamp(41000));
+        // Unknown framerate
+        assert_eq!(
+            parse_str(content, path, None).unwrap()[0].start,
+            ntsc[0].start
+        );
+
+        assert!(parse_str("{10}{20}Fine\nnot a cue\n", path, None).is_err());
+        let parsed = parse_lenient("{10}{20}Fine\nnot a cue\n", None);
+        assert_eq!(parsed.entries.len(), 1);
+        let codes: Vec<(usize, &str)> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.code))
+            .collect();
+        assert_eq!(codes, vec![(2, "bad-line"), (0, "unknown-framerate")]);
+    }
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        assert_eq!(MicroDvdParser.detect(SAMPLE), 100);
+        assert_eq!(
+            MicroDvdParser.detect("1\n00:00:01,000 --> 00:00:02,000\n"),
+            0
+        );
+
+        let entries = parse_str(SAMPLE, Path::new("test.sub"), None).unwrap();
+        let written = write_str(&entries, 25.0);
+        assert!(written.starts_with("{1}{1}25\n{25}{100}Hello there.\n"));
+        let parsed = parse_str(&written, Path::new("test.sub"), Some(30.0)).unwrap();
+        assert_eq!(parsed.len(), entries.len());
+        for (parsed, original) in parsed.iter().zip(&entries) {
+            assert_eq!(
+                (parsed.start, parsed.end, &parsed.text_clean),
+                (original.start, original.end, &original.text_clean)
+            );
+        }
+    }
+}
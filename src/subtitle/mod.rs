@@ -1,33 +1,63 @@
 //! Subtitle parsing module
 //!
-//! Supports SRT, ASS/SSA, and WebVTT subtitle formats.
+//! Supports SRT, ASS/SSA, WebVTT, TTML/DFXP, SAMI, SubViewer and MicroDVD
+//! subtitle formats, in any character encoding [`encoding`] can detect. Further formats plug in
+//! through [`SubtitleParser`] and [`register_parser`]. Entries can be written
+//! back out in any of the built-in formats with [`write_file`].
 
+pub mod align;
 pub mod ass;
+pub mod diagnostics;
+pub mod encoding;
+mod markup;
+pub mod microdvd;
+pub mod normalize;
+pub mod parser;
+pub mod sami;
+pub mod sdh;
+pub mod speaker;
 pub mod srt;
+pub mod subviewer;
+pub mod timing;
+pub mod ttml;
 pub mod types;
 pub mod vtt;
+pub mod writer;
 
+pub use align::{align_cues, aligned_lines, shares_timing};
+pub use diagnostics::{Diagnostic, Parsed, Severity};
+pub use encoding::{Detection, TextEncoding};
+pub use normalize::NormalizeRules;
+pub use parser::{register_parser, registry, ParseOptions, ParserRegistry, SubtitleParser};
+pub use sdh::{CueKind, SdhText};
+pub use speaker::SpeakerAliases;
+pub use timing::{parse_framerate, TimingTransform};
 pub use types::{SubtitleEntry, SubtitleFormat, Timestamp};
+pub use writer::{write_file, write_str};
 
 use std::path::Path;
 
-use crate::error::{AnytronError, Result};
+use crate::error::Result;
 
-/// Parse a subtitle file, auto-detecting the format from the file extension
+/// Parse a subtitle file, picking the format from its extension and content
 pub fn parse_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let extension = path
-        .extension()
-        .and_then(|e| e.to_str())
-        .ok_or_else(|| AnytronError::UnsupportedSubtitleFormat("no extension".to_string()))?;
-
-    let format = SubtitleFormat::from_extension(extension)
-        .ok_or_else(|| AnytronError::UnsupportedSubtitleFormat(extension.to_string()))?;
-
-    match format {
-        SubtitleFormat::Srt => srt::parse_file(path),
-        SubtitleFormat::Ass => ass::parse_file(path),
-        SubtitleFormat::Vtt => vtt::parse_file(path),
-    }
+    parse_file_with(path, &ParseOptions::default())
+}
+
+/// Parse a subtitle file with the registered formats
+pub fn parse_file_with(path: &Path, options: &ParseOptions) -> Result<Vec<SubtitleEntry>> {
+    registry().parse_file(path, options)
+}
+
+/// Parse a subtitle file, skipping cues that can't be read and reporting
+/// them as diagnostics
+pub fn parse_file_lenient(path: &Path, options: &ParseOptions) -> Result<Parsed> {
+    registry().parse_file_lenient(path, options)
+}
+
+/// Whether a registered format claims the file extension
+pub fn is_subtitle_extension(extension: &str) -> bool {
+    registry().supports_extension(extension)
 }
 
 #[cfg(test)]
@@ -56,6 +86,18 @@ mod tests {
             SubtitleFormat::from_extension("vtt"),
             Some(SubtitleFormat::Vtt)
         );
+        assert_eq!(
+            SubtitleFormat::from_extension("dfxp"),
+            Some(SubtitleFormat::Ttml)
+        );
+        assert_eq!(
+            SubtitleFormat::from_extension("smi"),
+            Some(SubtitleFormat::Sami)
+        );
+        assert_eq!(
+            SubtitleFormat::from_extension("sub"),
+            Some(SubtitleFormat::MicroDvd)
+        );
         assert_eq!(SubtitleFormat::from_extension("txt"), None);
     }
 }
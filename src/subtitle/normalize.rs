@@ -0,0 +1,426 @@
+//! Cue normalization
+//!
+//! Subtitle files rarely hold one quote per cue. A sentence runs across
+//! several cues, two speakers share one behind leading dashes, and karaoke
+//! effects or sloppy edits repeat the same line many times over.
+//! [`NormalizeRules::apply`] tidies parsed entries so each caption is one
+//! coherent quote. Every rule is off by default, since each one changes cue
+//! text and timing (and with them caption IDs); they are turned on one by
+//! one, or together with [`NormalizeRules::all`]:
+//!
+//! 1. cues that end before they start get a short duration
+//! 2. repeated cues that overlap or touch collapse into one
+//! 3. a cue that ends mid-sentence is merged with the one continuing it
+//! 4. `- Line A\n- Line B` cues are split, sharing the time by length
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::subtitle::speaker;
+use crate::subtitle::types::{SubtitleEntry, Timestamp};
+
+lazy_static! {
+    static ref RE_DASH: Regex = Regex::new(r"^((?:<[^>]+>|\{[^}]*\})*)\s*[-–—]\s*").unwrap();
+    static ref RE_LEADING_TAGS: Regex = Regex::new(r"^(?:<[^>]+>|\{[^}]*\})*").unwrap();
+}
+
+/// How many earlier cues a cue is compared against for duplicates
+const DEDUPE_WINDOW: usize = 16;
+
+/// Which normalization rules run, and their limits
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalizeRules {
+    /// Merge a cue that ends mid-sentence with the cue continuing it
+    pub merge_continuations: bool,
+
+    /// Largest gap between two cues that are merged or deduplicated
+    pub merge_gap_ms: u64,
+
+    /// Longest merged cue, in characters of clean text
+    pub merge_max_chars: usize,
+
+    /// Split cues holding dash-led lines from several speakers
+    pub split_dialogue: bool,
+
+    /// Collapse repeated cues that overlap or nearly touch
+    pub dedupe: bool,
+
+    /// How alike two cues must read to be duplicates, from 0 to 1
+    pub dedupe_similarity: f64,
+
+    /// Duration given to cues that end before they start (0 = leave them)
+    pub min_duration_ms: u64,
+}
+
+impl Default for NormalizeRules {
+    /// No rules, with the limits they use once turned on
+    fn default() -> Self {
+        Self {
+            merge_continuations: false,
+            merge_gap_ms: 500,
+            merge_max_chars: 160,
+            split_dialogue: false,
+            dedupe: false,
+            dedupe_similarity: 0.9,
+            min_duration_ms: 0,
+        }
+    }
+}
+
+impl NormalizeRules {
+    /// Rules that leave every cue as parsed
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Every rule, giving backwards cues one second
+    pub fn all() -> Self {
+        Self {
+            merge_continuations: true,
+            split_dialogue: true,
+            dedupe: true,
+            min_duration_ms: 1000,
+            ..Self::default()
+        }
+    }
+
+    /// Whether any rule is on
+    pub fn any(&self) -> bool {
+        self.merge_continuations || self.split_dialogue || self.dedupe || self.min_duration_ms > 0
+    }
+
+    /// Normalize entries in place, sorting them by start and renumbering
+    /// them from 1; with every rule off they are left as parsed
+    pub fn apply(&self, entries: &mut Vec<SubtitleEntry>) {
+        if !self.any() {
+            return;
+        }
+        entries.sort_by_key(|entry| (entry.start, entry.index));
+        if self.min_duration_ms > 0 {
+            clamp_durations(entries, self.min_duration_ms);
+        }
+        if self.dedupe {
+            *entries = self.dedupe(std::mem::take(entries));
+        }
+        if self.merge_continuations {
+            *entries = self.merge(std::mem::take(entries));
+        }
+        if self.split_dialogue {
+            *entries = entries.drain(..).flat_map(split_dialogue).collect();
+        }
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry.index = i + 1;
+        }
+    }
+
+    /// Collapse cues that repeat a recent one, keeping the longer text and
+    /// the combined time
+    fn dedupe(&self, entries: Vec<SubtitleEntry>) -> Vec<SubtitleEntry> {
+        let mut kept: Vec<SubtitleEntry> = Vec::with_capacity(entries.len());
+        'entries: for entry in entries {
+            let text = fold(&entry.text_clean);
+            for prev in kept.iter_mut().rev().take(DEDUPE_WINDOW) {
+                if prev.end.0 + self.merge_gap_ms < entry.start.0
+                    || similarity(&fold(&prev.text_clean), &text) < self.dedupe_similarity
+                {
+                    continue;
+                }
+                prev.end = prev.end.max(entry.end);
+                if prev.speaker.is_none() {
+                    prev.speaker = entry.speaker.clone();
+                }
+                if entry.text_clean.chars().count() > prev.text_clean.chars().count() {
+                    prev.text = entry.text;
+                    prev.text_clean = entry.text_clean;
+                }
+                continue 'entries;
+            }
+            kept.push(entry);
+        }
+        kept
+    }
+
+    /// Join runs of cues that continue one sentence
+    fn merge(&self, entries: Vec<SubtitleEntry>) -> Vec<SubtitleEntry> {
+        let mut merged: Vec<SubtitleEntry> = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match merged.last_mut() {
+                Some(prev) if self.continues(prev, &entry) => {
+                    let joined = join(prev, &entry);
+                    *prev = joined;
+                }
+                _ => merged.push(entry),
+            }
+        }
+        merged
+    }
+
+    /// Whether `next` carries on the sentence `prev` leaves open
+    fn continues(&self, prev: &SubtitleEntry, next: &SubtitleEntry) -> bool {
+        if next.start.0 > prev.end.0 + self.merge_gap_ms
+            || prev.text_clean.chars().count() + next.text_clean.chars().count() + 1
+                > self.merge_max_chars
+            || (next.speaker.is_some() && next.speaker != prev.speaker)
+            || dialogue_parts(&prev.text).is_some()
+            || starts_with_dash(&next.text)
+        {
+            return false;
+        }
+
+        let before = prev
+            .text_clean
+            .trim_end_matches(['"', '\'', '”', '’', ')', ' ']);
+        let after = next.text_clean.trim_start();
+        let next_lower = after.chars().next().is_some_and(char::is_lowercase);
+        let next_ellipsis = after.starts_with("...") || after.starts_with('…');
+
+        if before.ends_with("...") || before.ends_with('…') {
+            next_ellipsis || next_lower
+        } else {
+            match before.chars().last() {
+                None | Some('.' | '!' | '?' | '♪' | ':') => false,
+                Some(',' | ';' | '-' | '–' | '—') => true,
+                Some(_) => next_lower || next_ellipsis,
+            }
+        }
+    }
+}
+
+/// Give cues that end at or before their start `min_ms`, without running
+/// into the next cue
+fn clamp_durations(entries: &mut [SubtitleEntry], min_ms: u64) {
+    for i in 0..entries.len() {
+        let start = entries[i].start.0;
+        if entries[i].end.0 > start {
+            continue;
+        }
+        let next = entries[i + 1..]
+            .iter()
+            .map(|entry| entry.start.0)
+            .find(|&next| next > start);
+        let end = next.map_or(start + min_ms, |next| next.min(start + min_ms));
+        entries[i].end = Timestamp(end);
+    }
+}
+
+/// `prev` followed by `next` as one cue
+fn join(prev: &SubtitleEntry, next: &SubtitleEntry) -> SubtitleEntry {
+    let head = prev.text.trim_end();
+    let tail = next.text.trim_start();
+    let text = match (strip_ellipsis_end(head), strip_ellipsis_start(tail)) {
+        (Some(head), Some(tail)) => format!("{} {}", head.trim_end(), tail.trim_start()),
+        _ => format!("{}\n{}", head, tail),
+    };
+    SubtitleEntry::new(prev.index, prev.start, prev.end.max(next.end), text)
+        .with_speaker(prev.speaker.clone().or_else(|| next.speaker.clone()))
+}
+
+/// `text` without a trailing ellipsis, if it has one
+fn strip_ellipsis_end(text: &str) -> Option<&str> {
+    text.strip_suffix("...").or_else(|| text.strip_suffix('…'))
+}
+
+/// `text` without a leading ellipsis, if it has one
+fn strip_ellipsis_start(text: &str) -> Option<&str> {
+    text.strip_prefix("...").or_else(|| text.strip_prefix('…'))
+}
+
+/// A cue split at its dash-led lines, each part timed by its share of the
+/// text; other cues come back unchanged
+fn split_dialogue(entry: SubtitleEntry) -> Vec<SubtitleEntry> {
+    let Some(parts) = dialogue_parts(&entry.text) else {
+        return vec![entry];
+    };
+
+    let parts: Vec<SubtitleEntry> = parts
+        .into_iter()
+        .map(|text| SubtitleEntry::new(entry.index, entry.start, entry.end, text))
+        .collect();
+    let weights: Vec<u64> = parts
+        .iter()
+        .map(|part| part.text_clean.chars().count().max(1) as u64)
+        .collect();
+    let total: u64 = weights.iter().sum();
+    let duration = entry.duration_ms();
+
+    let mut before = 0;
+    parts
+        .into_iter()
+        .zip(weights)
+        .enumerate()
+        .map(|(i, (mut part, weight))| {
+            part.start = Timestamp(entry.start.0 + duration * before / total);
+            before += weight;
+            part.end = Timestamp(entry.start.0 + duration * before / total);
+            match split_label(&part.text) {
+                Some((name, text)) => SubtitleEntry::new(part.index, part.start, part.end, text)
+                    .with_speaker(Some(name)),
+                // An attributed cue's speaker is taken to open it
+                None if i == 0 => part.with_speaker(entry.speaker.clone()),
+                None => part,
+            }
+        })
+        .collect()
+}
+
+/// The lines of a cue grouped by leading dash, when at least two are
+fn dialogue_parts(text: &str) -> Option<Vec<String>> {
+    let mut parts: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match RE_DASH.captures(line) {
+            Some(caps) if !line[caps[0].len()..].trim().is_empty() => {
+                parts.push(format!("{}{}", &caps[1], &line[caps[0].len()..]));
+            }
+            _ => match parts.last_mut() {
+                Some(part) => {
+                    part.push('\n');
+                    part.push_str(line);
+                }
+                None => parts.push(line.to_string()),
+            },
+        }
+    }
+    if parts.len() >= 2 {
+        Some(parts)
+    } else {
+        None
+    }
+}
+
+/// A `NAME: ` label after any leading tags, as `(name, text without it)`
+fn split_label(text: &str) -> Option<(String, String)> {
+    let tags = RE_LEADING_TAGS.find(text).map_or("", |m| m.as_str());
+    let (name, rest) = speaker::split_prefix(&text[tags.len()..])?;
+    Some((name, format!("{}{}", tags, rest)))
+}
+
+/// Whether a cue opens with a dialogue dash
+fn starts_with_dash(text: &str) -> bool {
+    RE_DASH.is_match(text)
+}
+
+/// Lowercased words of a cue, for comparing text
+fn fold(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// One minus the edit distance between `a` and `b` over the longer length
+fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    1.0 - row[b.len()] as f64 / a.len().max(b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: u64, end: u64, text: &str) -> SubtitleEntry {
+        SubtitleEntry::new(0, Timestamp(start), Timestamp(end), text.to_string())
+    }
+
+    fn normalized(entries: Vec<SubtitleEntry>) -> Vec<SubtitleEntry> {
+        let mut entries = entries;
+        NormalizeRules::all().apply(&mut entries);
+        entries
+    }
+
+    #[test]
+    fn test_merge_continuations() {
+        let entries = normalized(vec![
+            cue(1000, 2000, "I was going to the store,"),
+            cue(2100, 3000, "but it was closed."),
+            cue(3200, 4000, "So I went home."),
+            cue(4100, 5000, "And then..."),
+            cue(5100, 6000, "...I had a donut."),
+            cue(9000, 10000, "Far too late"),
+            cue(11000, 12000, "to join."),
+        ]);
+        let texts: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "I was going to the store,\nbut it was closed.",
+                "So I went home.",
+                "And then I had a donut.",
+                "Far too late",
+                "to join.",
+            ]
+        );
+        assert_eq!(entries[0].end, Timestamp(3000));
+        assert_eq!(entries[4].index, 5);
+    }
+
+    #[test]
+    fn test_split_dialogue() {
+        let entries = normalized(vec![cue(1000, 4000, "- Where are you going?\n- Out.")]);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].text, "Where are you going?");
+        assert_eq!(entries[1].text, "Out.");
+        // 20 and 4 characters share 3 seconds
+        assert_eq!(entries[0].end, Timestamp(3500));
+        assert_eq!(entries[1].start, Timestamp(3500));
+        assert_eq!(entries[1].end, Timestamp(4000));
+
+        let entries = normalized(vec![cue(
+            0,
+            1000,
+            "<i>- HOMER: Hi.</i>\n<i>- MARGE: Hi.</i>",
+        )]);
+        assert_eq!(entries[1].speaker.as_deref(), Some("MARGE"));
+        assert_eq!(entries[1].text_clean, "Hi.");
+
+        // One dash-led line is left alone, but the first speaker's dash is
+        // often left out
+        assert_eq!(normalized(vec![cue(0, 1000, "- Hello.\nThere.")]).len(), 1);
+        assert_eq!(normalized(vec![cue(0, 1000, "Hello.\n- Hi.")]).len(), 2);
+    }
+
+    #[test]
+    fn test_dedupe_and_clamp() {
+        let mut karaoke: Vec<SubtitleEntry> = (0..20)
+            .map(|i| cue(1000 + i * 100, 1100 + i * 100, "{\\k20}La la la"))
+            .collect();
+        karaoke.push(cue(3200, 4000, "La la la!"));
+        karaoke.push(cue(5000, 5000, "Zero length."));
+        karaoke.push(cue(5500, 6000, "Next."));
+        let entries = normalized(karaoke);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].text_clean, "La la la!");
+        assert_eq!(
+            (entries[0].start, entries[0].end),
+            (Timestamp(1000), Timestamp(4000))
+        );
+        assert_eq!(entries[1].end, Timestamp(5500));
+
+        // With every rule off, order and numbering are left alone
+        let mut entries = vec![cue(500, 1500, "Same."), cue(0, 1000, "Same.")];
+        entries[0].index = 7;
+        NormalizeRules::none().apply(&mut entries);
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].start, entries[0].index), (Timestamp(500), 7));
+    }
+}
@@ -0,0 +1,431 @@
+//! Pluggable subtitle formats
+//!
+//! Every format is a [`SubtitleParser`]. Files are matched to a parser by
+//! extension, then checked against each parser's content detection so a
+//! mislabelled file (say, a WebVTT file named `.srt`) still parses. Library
+//! users add their own formats with [`register_parser`].
+
+use encoding_rs::Encoding;
+use lazy_static::lazy_static;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+use crate::error::{AnytronError, Result};
+use crate::subtitle::ass::AssParser;
+use crate::subtitle::diagnostics::Parsed;
+use crate::subtitle::encoding;
+use crate::subtitle::microdvd::MicroDvdParser;
+use crate::subtitle::sami::SamiParser;
+use crate::subtitle::srt::SrtParser;
+use crate::subtitle::subviewer::SubViewerParser;
+use crate::subtitle::ttml::TtmlParser;
+use crate::subtitle::types::SubtitleEntry;
+use crate::subtitle::vtt::VttParser;
+
+/// Detection score above which content wins over the file extension
+const CONFIDENT: u8 = 50;
+
+lazy_static! {
+    static ref REGISTRY: RwLock<ParserRegistry> = RwLock::new(ParserRegistry::default());
+}
+
+/// Options passed to every parser
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ParseOptions {
+    /// Decode files from this encoding instead of detecting it
+    pub encoding: Option<&'static Encoding>,
+
+    /// Language to read from files holding several, e.g. SAMI
+    pub language: Option<String>,
+
+    /// Framerate of the video, for formats timed in frames
+    pub framerate: Option<f64>,
+}
+
+impl ParseOptions {
+    pub fn with_encoding(mut self, encoding: Option<&'static Encoding>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    pub fn with_framerate(mut self, framerate: Option<f64>) -> Self {
+        self.framerate = framerate;
+        self
+    }
+}
+
+/// A subtitle format
+pub trait SubtitleParser: Send + Sync {
+    /// Short name of the format, e.g. `SRT`
+    fn name(&self) -> &str;
+
+    /// Lowercase file extensions the format uses, without the dot
+    fn extensions(&self) -> &[&str];
+
+    /// Confidence from 0 to 100 that `content` is in this format
+    fn detect(&self, content: &str) -> u8;
+
+    /// Whether cues are timed in video frames, so parsing needs
+    /// [`ParseOptions::framerate`]
+    fn frame_based(&self) -> bool {
+        false
+    }
+
+    /// Parse decoded subtitle text; `path` is used in error messages
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>>;
+
+    /// Parse decoded subtitle text, skipping what can't be read and
+    /// reporting it as diagnostics
+    ///
+    /// The default runs [`parse_str`](Self::parse_str), so its errors stay
+    /// fatal.
+    fn parse_lenient(&self, content: &str, path: &Path, options: &ParseOptions) -> Result<Parsed> {
+        self.parse_str(content, path, options).map(Parsed::new)
+    }
+
+    /// Parse raw bytes from a reader, decoding them to UTF-8 first
+    fn parse_reader(
+        &self,
+        reader: &mut dyn Read,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        let content = read_content(reader, path, options)?;
+        self.parse_str(&content, path, options)
+    }
+}
+
+/// The set of formats subtitle files are parsed with
+#[derive(Clone)]
+pub struct ParserRegistry {
+    parsers: Vec<Arc<dyn SubtitleParser>>,
+}
+
+impl Default for ParserRegistry {
+    /// The built-in SRT, ASS/SSA, WebVTT, TTML, SAMI, SubViewer and MicroDVD
+    /// parsers
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(SrtParser);
+        registry.register(AssParser);
+        registry.register(VttParser);
+        registry.register(TtmlParser);
+        registry.register(SamiParser);
+        registry.register(SubViewerParser);
+        registry.register(MicroDvdParser);
+        registry
+    }
+}
+
+impl ParserRegistry {
+    /// A registry with no formats
+    pub fn new() -> Self {
+        Self {
+            parsers: Vec::new(),
+        }
+    }
+
+    /// Add a format; it takes precedence over earlier ones sharing an
+    /// extension or detection score
+    pub fn register(&mut self, parser: impl SubtitleParser + 'static) {
+        self.parsers.push(Arc::new(parser));
+    }
+
+    /// Registered formats, most recently registered first
+    pub fn parsers(&self) -> impl Iterator<Item = &dyn SubtitleParser> {
+        self.parsers.iter().rev().map(|parser| parser.as_ref())
+    }
+
+    /// Every extension a registered format claims
+    pub fn extensions(&self) -> Vec<String> {
+        let mut extensions: Vec<String> = Vec::new();
+        for parser in self.parsers.iter() {
+            for extension in parser.extensions() {
+                if !extensions.iter().any(|e| e == extension) {
+                    extensions.push(extension.to_string());
+                }
+            }
+        }
+        extensions
+    }
+
+    /// Whether some format claims `extension` (case-insensitive)
+    pub fn supports_extension(&self, extension: &str) -> bool {
+        self.for_extension(extension).is_some()
+    }
+
+    /// The format claiming `extension` (case-insensitive)
+    pub fn for_extension(&self, extension: &str) -> Option<&dyn SubtitleParser> {
+        let extension = extension.to_lowercase();
+        self.parsers()
+            .find(|parser| parser.extensions().contains(&extension.as_str()))
+    }
+
+    /// The format `content` looks most like, if any is confident
+    pub fn detect(&self, content: &str) -> Option<&dyn SubtitleParser> {
+        self.best_match(content)
+            .filter(|&(_, score)| score >= CONFIDENT)
+            .map(|(parser, _)| parser)
+    }
+
+    /// Pick the format for a file from its extension and content
+    ///
+    /// The extension's format is used unless another format scores higher
+    /// on the content and is confident about it.
+    pub fn select(&self, path: &Path, content: &str) -> Result<&dyn SubtitleParser> {
+        let extension = path.extension().and_then(|e| e.to_str());
+        let by_extension = extension.and_then(|e| self.for_extension(e));
+        let best = self.best_match(content);
+
+        match (by_extension, best) {
+            (Some(parser), Some((detected, score)))
+                if score >= CONFIDENT && score > parser.detect(content) =>
+            {
+                log::info!(
+                    "{} looks like {} rather than {}",
+                    path.display(),
+                    detected.name(),
+                    parser.name()
+                );
+                Ok(detected)
+            }
+            (Some(parser), _) => Ok(parser),
+            (None, Some((detected, score))) if score >= CONFIDENT => Ok(detected),
+            _ => Err(AnytronError::UnsupportedSubtitleFormat(
+                extension.unwrap_or("no extension").to_string(),
+            )),
+        }
+    }
+
+    /// Parse subtitle text, choosing the format from `path` and the content
+    pub fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        self.select(path, content)?
+            .parse_str(content, path, options)
+    }
+
+    /// Parse raw subtitle bytes from a reader
+    pub fn parse_reader(
+        &self,
+        reader: &mut dyn Read,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        let content = read_content(reader, path, options)?;
+        self.parse_str(&content, path, options)
+    }
+
+    /// Parse a subtitle file
+    pub fn parse_file(&self, path: &Path, options: &ParseOptions) -> Result<Vec<SubtitleEntry>> {
+        let (content, _) = encoding::read_file(path, options.encoding)?;
+        self.parse_str(&content, path, options)
+    }
+
+    /// Parse subtitle text leniently, choosing the format from `path` and
+    /// the content
+    pub fn parse_str_lenient(
+        &self,
+        content: &str,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Parsed> {
+        self.select(path, content)?
+            .parse_lenient(content, path, options)
+    }
+
+    /// Parse a subtitle file leniently
+    ///
+    /// Unreadable files and unknown formats are still errors; problems
+    /// inside the file come back as diagnostics.
+    pub fn parse_file_lenient(&self, path: &Path, options: &ParseOptions) -> Result<Parsed> {
+        let (content, _) = encoding::read_file(path, options.encoding)?;
+        self.parse_str_lenient(&content, path, options)
+    }
+
+    /// Highest-scoring format for `content`, ties going to the latest
+    fn best_match(&self, content: &str) -> Option<(&dyn SubtitleParser, u8)> {
+        let mut best: Option<(&dyn SubtitleParser, u8)> = None;
+        for parser in self.parsers() {
+            let score = parser.detect(content);
+            if score > 0 && best.map_or(true, |(_, most)| score > most) {
+                best = Some((parser, score));
+            }
+        }
+        best
+    }
+}
+
+/// Add a format to the registry [`parse_file`](super::parse_file) and
+/// directory scans use
+pub fn register_parser(parser: impl SubtitleParser + 'static) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(parser);
+}
+
+/// A snapshot of the global registry
+pub fn registry() -> ParserRegistry {
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+fn read_content(reader: &mut dyn Read, path: &Path, options: &ParseOptions) -> Result<String> {
+    let mut bytes = Vec::new();
+    reader
+        .read_to_end(&mut bytes)
+        .map_err(|e| AnytronError::SubtitleParse {
+            path: path.to_path_buf(),
+            line: 0,
+            message: format!("Failed to read subtitles: {}", e),
+        })?;
+    Ok(encoding::decode(&bytes, options.encoding).0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle::Timestamp;
+
+    const SRT: &str = "1\n00:00:01,000 --> 00:00:04,000\nHello.\n";
+    const VTT: &str = "WEBVTT\n\n00:00:01.000 --> 00:00:04.000\nHello.\n";
+    const ASS: &str = "[Script Info]\nTitle: Test\n\n[Events]\nFormat: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\nDialogue: 0,0:00:01.00,0:00:04.00,Default,,0,0,0,,Hello.\n";
+
+    /// `start_ms|end_ms|text` per line
+    struct PipeParser;
+
+    impl SubtitleParser for PipeParser {
+        fn name(&self) -> &str {
+            "Pipe"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["pipe", "txt"]
+        }
+
+        fn detect(&self, content: &str) -> u8 {
+            if content.starts_with("#PIPE") {
+                100
+            } else {
+                0
+            }
+        }
+
+        fn parse_str(
+            &self,
+            content: &str,
+            _path: &Path,
+            _options: &ParseOptions,
+        ) -> Result<Vec<SubtitleEntry>> {
+            Ok(content
+                .lines()
+                .filter_map(|line| {
+                    let mut fields = line.splitn(3, '|');
+                    let start = fields.next()?.parse().ok()?;
+                    let end = fields.next()?.parse().ok()?;
+                    Some((start, end, fields.next()?.to_string()))
+                })
+                .enumerate()
+                .map(|(i, (start, end, text))| {
+                    SubtitleEntry::new(i, Timestamp(start), Timestamp(end), text)
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn test_detects_builtin_formats() {
+        let registry = ParserRegistry::default();
+        assert_eq!(registry.detect(SRT).unwrap().name(), "SRT");
+        assert_eq!(registry.detect(VTT).unwrap().name(), "WebVTT");
+        assert_eq!(registry.detect(ASS).unwrap().name(), "ASS");
+        assert!(registry.detect("Just some notes.\n").is_none());
+
+        assert_eq!(registry.for_extension("SSA").unwrap().name(), "ASS");
+        // Both `.sub` formats are told apart by content
+        let path = Path::new("episode.sub");
+        let subviewer = "00:00:01.00,00:00:04.00\nHello.\n";
+        assert_eq!(
+            registry.select(path, subviewer).unwrap().name(),
+            "SubViewer"
+        );
+        let microdvd = registry.select(path, "{25}{100}Hello.\n").unwrap();
+        assert_eq!(microdvd.name(), "MicroDVD");
+        assert!(microdvd.frame_based());
+        assert!(registry.supports_extension("vtt"));
+        assert!(!registry.supports_extension("mp4"));
+    }
+
+    #[test]
+    fn test_mislabelled_file() {
+        let registry = ParserRegistry::default();
+        let path = Path::new("episode.srt");
+        assert_eq!(registry.select(path, VTT).unwrap().name(), "WebVTT");
+        assert_eq!(registry.select(path, SRT).unwrap().name(), "SRT");
+        // Nothing recognisable: trust the extension
+        assert_eq!(registry.select(path, "").unwrap().name(), "SRT");
+
+        let entries = registry
+            .parse_str(VTT, path, &ParseOptions::default())
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].start, Timestamp(1000));
+
+        // Unknown extension, known content
+        assert_eq!(
+            registry.select(Path::new("episode"), ASS).unwrap().name(),
+            "ASS"
+        );
+        assert!(registry.select(Path::new("notes.doc"), "Hi.").is_err());
+    }
+
+    #[test]
+    fn test_custom_parser() {
+        let mut registry = ParserRegistry::default();
+        registry.register(PipeParser);
+        assert!(registry.extensions().contains(&"pipe".to_string()));
+
+        let mut reader: &[u8] = b"#PIPE\n1000|2500|Hello.\n3000|4000|Goodbye.\n";
+        let entries = registry
+            .parse_reader(
+                &mut reader,
+                Path::new("episode.pipe"),
+                &ParseOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[1].text_clean, "Goodbye.");
+
+        // Content detection reaches the custom format under another extension
+        let pipe = "#PIPE\n0|500|Hi.\n";
+        assert_eq!(
+            registry
+                .select(Path::new("episode.srt"), pipe)
+                .unwrap()
+                .name(),
+            "Pipe"
+        );
+
+        // Built-ins also parse from readers
+        let mut reader: &[u8] = SRT.as_bytes();
+        let entries = SrtParser
+            .parse_reader(&mut reader, Path::new("a.srt"), &ParseOptions::default())
+            .unwrap();
+        assert_eq!(entries[0].text_clean, "Hello.");
+    }
+}
@@ -0,0 +1,487 @@
+//! SAMI (Synchronized Accessible Media Interchange) subtitle parser
+//!
+//! SAMI format:
+//! ```text
+//! <SAMI>
+//! <HEAD>
+//! <STYLE TYPE="text/css"><!--
+//! .ENUSCC { Name: English; lang: en-US; }
+//! .KOKRCC { Name: Korean; lang: ko-KR; }
+//! --></STYLE>
+//! </HEAD>
+//! <BODY>
+//! <SYNC Start=1000><P Class=ENUSCC>First subtitle<br>on two lines
+//! <SYNC Start=1000><P Class=KOKRCC>첫 번째 자막
+//! <SYNC Start=4000><P Class=ENUSCC>&nbsp;
+//! </BODY>
+//! </SAMI>
+//! ```
+//!
+//! Each `<SYNC>` shows its text from `Start` (in milliseconds) until the
+//! next `<SYNC>` of the same language class; a blank one clears the screen.
+//! A file can hold several languages, one class each, and one is read at a
+//! time.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::extractor::normalize_language;
+use crate::subtitle::diagnostics::{Diagnostic, Parsed};
+use crate::subtitle::markup::{self, Token};
+use crate::subtitle::parser::{ParseOptions, SubtitleParser};
+use crate::subtitle::types::{SubtitleEntry, Timestamp};
+use crate::subtitle::writer;
+
+lazy_static! {
+    static ref RE_STYLE: Regex = Regex::new(r"(?is)<style[^>]*>(.*?)</style>").unwrap();
+    static ref RE_CLASS: Regex = Regex::new(r"(?s)\.([A-Za-z0-9_-]+)\s*\{([^}]*)\}").unwrap();
+    static ref RE_LANG: Regex = Regex::new(r"(?i)\blang\s*:\s*([A-Za-z-]+)").unwrap();
+    static ref RE_NAME: Regex = Regex::new(r"(?i)\bname\s*:\s*([^;]+)").unwrap();
+}
+
+/// How long the last cue stays up when nothing clears it
+const LAST_CUE_MS: u64 = 4000;
+
+/// A language class declared in the style sheet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SamiClass {
+    /// Class name used by `<P Class=...>`
+    pub class: String,
+
+    /// Display name, e.g. `English`
+    pub name: Option<String>,
+
+    /// Language tag, e.g. `en-US`
+    pub language: Option<String>,
+}
+
+/// Parse a SAMI file into subtitle entries in its first language
+pub fn parse_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let (content, _) = crate::subtitle::encoding::read_file(path, None)?;
+
+    parse_str(&content, path)
+}
+
+/// Parse SAMI content string into subtitle entries in its first language
+pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
+    parse_language(content, path, None)
+}
+
+/// Parse the cues of one language, matched by tag (`es`, `spa`, `es-ES`)
+/// or class name
+///
+/// Falls back to the first declared class when `language` is `None` or no
+/// class matches it.
+///
+/// Fails on the first `<SYNC>` whose time can't be read;
+/// [`parse_lenient`] skips it instead.
+pub fn parse_language(
+    content: &str,
+    path: &Path,
+    language: Option<&str>,
+) -> Result<Vec<SubtitleEntry>> {
+    parse_lenient(content, language).into_result(path)
+}
+
+/// Parse the cues of one language, skipping `<SYNC>`s whose time can't be
+/// read and reporting them
+pub fn parse_lenient(content: &str, language: Option<&str>) -> Parsed {
+    let mut parsed = Parsed::default();
+    let classes = classes(content);
+    let class = select_class(&classes, language);
+    let mut events = read_events(content, &mut parsed.diagnostics);
+    events.retain(|event| match (&event.class, &class) {
+        (Some(event_class), Some(class)) => event_class.eq_ignore_ascii_case(class),
+        _ => true,
+    });
+    events.sort_by_key(|event| event.start);
+
+    // Cues sharing a start time show together
+    let mut merged: Vec<Event> = Vec::new();
+    for event in events {
+        match merged.last_mut() {
+            Some(last) if last.start == event.start => {
+                if !event.text.is_empty() {
+                    if !last.text.is_empty() {
+                        last.text.push('\n');
+                    }
+                    last.text.push_str(&event.text);
+                }
+            }
+            _ => merged.push(event),
+        }
+    }
+
+    for (i, event) in merged.iter().enumerate() {
+        if event.text.is_empty() {
+            continue;
+        }
+        let end = merged
+            .get(i + 1)
+            .map_or(event.start + LAST_CUE_MS, |next| next.start);
+        let index = parsed.entries.len() + 1;
+        parsed.push(
+            event.line,
+            SubtitleEntry::new(
+                index,
+                Timestamp(event.start),
+                Timestamp(end),
+                event.text.clone(),
+            ),
+        );
+    }
+
+    parsed
+}
+
+/// Language classes declared in the file's style sheet, in order
+pub fn classes(content: &str) -> Vec<SamiClass> {
+    let Some(style) = RE_STYLE.captures(content) else {
+        return Vec::new();
+    };
+    RE_CLASS
+        .captures_iter(&style[1])
+        .map(|caps| SamiClass {
+            class: caps[1].to_string(),
+            name: RE_NAME
+                .captures(&caps[2])
+                .map(|name| name[1].trim().to_string()),
+            language: RE_LANG.captures(&caps[2]).map(|lang| lang[1].to_string()),
+        })
+        .collect()
+}
+
+/// Serialize entries as a single-language SAMI document
+pub fn write_str(entries: &[SubtitleEntry]) -> String {
+    let mut out = String::from(concat!(
+        "<SAMI>\n",
+        "<HEAD>\n",
+        "<STYLE TYPE=\"text/css\">\n",
+        "<!--\n",
+        "P { margin-left: 8pt; margin-right: 8pt; text-align: center; }\n",
+        ".SUBTITLES { Name: Subtitles; }\n",
+        "-->\n",
+        "</STYLE>\n",
+        "</HEAD>\n",
+        "<BODY>\n",
+    ));
+    for (i, entry) in entries.iter().enumerate() {
+        let text = writer::html_text(&writer::prefixed_text(entry));
+        if text.is_empty() {
+            continue;
+        }
+        out.push_str(&format!(
+            "<SYNC Start={}><P Class=SUBTITLES>{}\n",
+            entry.start.0,
+            sami_text(&text)
+        ));
+        // Clear the screen unless the next cue replaces this one
+        if entries
+            .get(i + 1)
+            .map_or(true, |next| next.start > entry.end)
+        {
+            out.push_str(&format!(
+                "<SYNC Start={}><P Class=SUBTITLES>&nbsp;\n",
+                entry.end.0
+            ));
+        }
+    }
+    out.push_str("</BODY>\n</SAMI>\n");
+    out
+}
+
+/// SAMI (`.smi`, `.sami`)
+pub struct SamiParser;
+
+impl SubtitleParser for SamiParser {
+    fn name(&self) -> &str {
+        "SAMI"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["smi", "sami"]
+    }
+
+    /// The `<SAMI>` root, or failing that `<SYNC>` tags
+    fn detect(&self, content: &str) -> u8 {
+        let head: String = content
+            .chars()
+            .take(4096)
+            .collect::<String>()
+            .to_lowercase();
+        if head.contains("<sami") {
+            100
+        } else if head.contains("<sync ") {
+            60
+        } else {
+            0
+        }
+    }
+
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        parse_language(content, path, options.language.as_deref())
+    }
+
+    fn parse_lenient(&self, content: &str, _path: &Path, options: &ParseOptions) -> Result<Parsed> {
+        Ok(parse_lenient(content, options.language.as_deref()))
+    }
+}
+
+/// Text of one `<P>` (or bare `<SYNC>`) at a sync point
+#[derive(Debug)]
+struct Event {
+    /// Line of the `<SYNC>` it belongs to
+    line: usize,
+    start: u64,
+    class: Option<String>,
+    text: String,
+}
+
+/// Read every paragraph with its sync time and class, reporting `<SYNC>`s
+/// whose time can't be read
+fn read_events(content: &str, diagnostics: &mut Vec<Diagnostic>) -> Vec<Event> {
+    let mut events = Vec::new();
+    // Line and time of the current `<SYNC>`
+    let mut start: Option<(usize, u64)> = None;
+    let mut current: Option<(Option<String>, String)> = None;
+
+    let mut flush = |start: Option<(usize, u64)>,
+                     current: &mut Option<(Option<String>, String)>| {
+        if let (Some((line, start)), Some((class, text))) = (start, current.take()) {
+            events.push(Event {
+                line,
+                start,
+                class,
+                text: tidy(&text),
+            });
+        }
+    };
+
+    for token in markup::tokenize(content) {
+        match token {
+            Token::Start(tag) if tag.name == "sync" => {
+                flush(start, &mut current);
+                start = tag
+                    .attr("start")
+                    .and_then(parse_start)
+                    .map(|ms| (tag.line, ms));
+                if start.is_none() {
+                    diagnostics.push(Diagnostic::error(
+                        tag.line,
+                        "bad-sync",
+                        format!(
+                            "Invalid SYNC start time {:?}",
+                            tag.attr("start").unwrap_or_default()
+                        ),
+                    ));
+                }
+                current = Some((None, String::new()));
+            }
+            Token::Start(tag) if tag.name == "p" => {
+                if current
+                    .as_ref()
+                    .map_or(true, |(_, text)| !text.trim().is_empty())
+                {
+                    flush(start, &mut current);
+                }
+                current = Some((tag.attr("class").map(str::to_string), String::new()));
+            }
+            Token::Start(tag) if tag.name == "br" => {
+                if let Some((_, text)) = &mut current {
+                    text.push('\n');
+                }
+            }
+            Token::Start(tag) if matches!(tag.name.as_str(), "i" | "b" | "u") => {
+                if let Some((_, text)) = &mut current {
+                    text.push_str(&format!("<{}>", tag.name));
+                }
+            }
+            Token::End(name) if matches!(name.as_str(), "i" | "b" | "u") => {
+                if let Some((_, text)) = &mut current {
+                    text.push_str(&format!("</{}>", name));
+                }
+            }
+            Token::End(name) if matches!(name.as_str(), "p" | "sync" | "body") => {
+                flush(start, &mut current);
+            }
+            Token::Text(raw) => {
+                if let Some((_, text)) = &mut current {
+                    // Source line breaks are plain whitespace; only <br> breaks lines
+                    text.push_str(&markup::decode_entities(raw).replace(['\r', '\n'], " "));
+                }
+            }
+            _ => {}
+        }
+    }
+    flush(start, &mut current);
+
+    events
+}
+
+/// The class to read for `language`
+fn select_class(classes: &[SamiClass], language: Option<&str>) -> Option<String> {
+    if let Some(language) = language {
+        let wanted = primary_language(language);
+        let matched = classes.iter().find(|class| {
+            class.class.eq_ignore_ascii_case(language)
+                || class
+                    .language
+                    .as_deref()
+                    .is_some_and(|tag| primary_language(tag) == wanted)
+        });
+        match matched {
+            Some(class) => return Some(class.class.clone()),
+            None if !classes.is_empty() => {
+                log::debug!("No {} SAMI class, using {}", language, classes[0].class)
+            }
+            None => {}
+        }
+    }
+    classes.first().map(|class| class.class.clone())
+}
+
+/// ISO 639-2 code of a language tag's primary subtag (`en-US` is `eng`)
+fn primary_language(tag: &str) -> String {
+    normalize_language(tag.split(['-', '_']).next().unwrap_or(tag))
+}
+
+/// Milliseconds from a `Start` value, ignoring any trailing unit or junk
+fn parse_start(value: &str) -> Option<u64> {
+    let digits: String = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// Collapse HTML whitespace, keeping `<br>` line breaks
+fn tidy(text: &str) -> String {
+    text.split('\n')
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escape cue text, keeping its markup tags and breaking lines with `<br>`
+fn sami_text(text: &str) -> String {
+    let escaped = markup::escape(text).replace('\n', "<br>");
+    ["i", "b", "u"].iter().fold(escaped, |text, tag| {
+        text.replace(&format!("&lt;{}&gt;", tag), &format!("<{}>", tag))
+            .replace(&format!("&lt;/{}&gt;", tag), &format!("</{}>", tag))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BILINGUAL: &str = r#"<SAMI>
+<HEAD>
+<TITLE>Test</TITLE>
+<STYLE TYPE="text/css">
+<!--
+P { font-family: Arial; }
+.ENUSCC { Name: English; lang: en-US; }
+.ESESCC { Name: Spanish; lang: es-ES; }
+-->
+</STYLE>
+</HEAD>
+<BODY>
+<SYNC Start=1000><P Class=ENUSCC>Where are the
+  <i>kids</i>?<br>Upstairs.
+<SYNC Start=1000><P Class=ESESCC>¿Dónde están los niños?
+<SYNC Start=4000><P Class=ENUSCC>&nbsp;
+<SYNC Start=4200><P Class=ESESCC>&nbsp;
+<SYNC Start=5000><P Class=ENUSCC>Fish &amp; chips.
+<SYNC Start=6500><P Class=ENUSCC>Goodnight.
+</BODY>
+</SAMI>"#;
+
+    #[test]
+    fn test_parse_first_language() {
+        let entries = parse_str(BILINGUAL, Path::new("test.smi")).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].start, Timestamp(1000));
+        assert_eq!(entries[0].end, Timestamp(4000));
+        assert_eq!(entries[0].text, "Where are the <i>kids</i>?\nUpstairs.");
+        assert_eq!(entries[1].text, "Fish & chips.");
+        // Replaced rather than cleared, then held
+        assert_eq!(entries[1].end, Timestamp(6500));
+        assert_eq!(entries[2].end, Timestamp(6500 + LAST_CUE_MS));
+    }
+
+    #[test]
+    fn test_parse_other_language() {
+        let path = Path::new("test.smi");
+        for language in ["es", "spa", "es-MX", "ESESCC"] {
+            let entries = parse_language(BILINGUAL, path, Some(language)).unwrap();
+            assert_eq!(entries.len(), 1, "{}", language);
+            assert_eq!(entries[0].text, "¿Dónde están los niños?");
+            assert_eq!(entries[0].end, Timestamp(4200));
+        }
+        // Unknown languages fall back to the first class
+        let entries = parse_language(BILINGUAL, path, Some("fr")).unwrap();
+        assert_eq!(entries[0].text_clean, "Where are the kids? Upstairs.");
+
+        assert_eq!(
+            classes(BILINGUAL)[1],
+            SamiClass {
+                class: "ESESCC".to_string(),
+                name: Some("Spanish".to_string()),
+                language: Some("es-ES".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let content = "<SAMI>\n<BODY>\n<SYNC Start=1000><P>First\n<SYNC Start=soon><P>Broken\n<SYNC Start=3000><P>Last\n<SYNC Start=4000><P>&nbsp;\n</BODY>\n</SAMI>";
+
+        let parsed = parse_lenient(content, None);
+        let texts: Vec<&str> = parsed.entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["First", "Last"]);
+        let found: Vec<(usize, &str)> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.code))
+            .collect();
+        assert_eq!(found, vec![(4, "bad-sync")]);
+
+        // The strict parse still fails on the unreadable cue
+        let err = parse_str(content, Path::new("test.smi")).unwrap_err();
+        assert!(err.to_string().contains("at line 4"), "{}", err);
+    }
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        assert_eq!(SamiParser.detect(BILINGUAL), 100);
+        assert_eq!(SamiParser.detect("<sync start=0>Hi"), 60);
+        assert_eq!(SamiParser.detect("1\n00:00:01,000 --> 00:00:02,000\n"), 0);
+
+        let entries = vec![
+            SubtitleEntry::new(
+                1,
+                Timestamp(1000),
+                Timestamp(2500),
+                "<i>Fish</i> & chips\nplease".to_string(),
+            ),
+            SubtitleEntry::new(2, Timestamp(2500), Timestamp(3000), "Now.".to_string()),
+        ];
+        let parsed = parse_str(&write_str(&entries), Path::new("test.smi")).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].text, "<i>Fish</i> & chips\nplease");
+        assert_eq!(
+            (parsed[0].end, parsed[1].end),
+            (Timestamp(2500), Timestamp(3000))
+        );
+    }
+}
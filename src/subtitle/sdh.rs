@@ -0,0 +1,174 @@
+//! SDH (subtitles for the deaf and hard of hearing) cleanup
+//!
+//! SDH subtitles mix dialogue with annotations: sound effects in brackets
+//! (`[DOOR SLAMS]`, `(laughing)`), music and lyrics between notes
+//! (`♪ theme music ♪`) and speaker labels (`- HOMER: Hi.`). [`split`] pulls
+//! them apart so the search index can keep the dialogue on its own and the
+//! annotations in fields of their own.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::subtitle::speaker;
+
+lazy_static! {
+    static ref RE_SOUND: Regex = Regex::new(r"\[([^\]]*)\]|\(([^)]*)\)").unwrap();
+    /// Notes around text, or one note running to the end of its line
+    static ref RE_MUSIC: Regex = Regex::new(r"(?m)[♪♫]+([^♪♫\n]*)(?:[♪♫]+|$)").unwrap();
+}
+
+/// What a cue mostly is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CueKind {
+    /// Spoken lines, possibly with annotations around them
+    #[default]
+    Dialogue,
+    /// Nothing but sound effect annotations
+    Sound,
+    /// Music or lyrics, with no dialogue
+    Music,
+}
+
+/// A cue's text sorted into dialogue and annotations
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SdhText {
+    /// What the cue mostly is
+    pub kind: CueKind,
+
+    /// Spoken text without annotations or labels
+    pub dialogue: String,
+
+    /// Speaker labels, in order
+    pub speakers: Vec<String>,
+
+    /// Sound effect annotations, without their brackets
+    pub sounds: Vec<String>,
+
+    /// Music descriptions and lyrics, without their notes
+    pub music: Vec<String>,
+}
+
+/// Split cleaned cue text into dialogue, sounds, music and speaker labels
+///
+/// Line breaks are kept apart from other whitespace: music with no closing
+/// note ends at its line, and labels may lead any line.
+pub fn split(text: &str) -> SdhText {
+    let mut sdh = SdhText::default();
+
+    let text = RE_SOUND.replace_all(text, |caps: &regex::Captures| {
+        let inner = caps
+            .get(1)
+            .or_else(|| caps.get(2))
+            .map_or("", |m| m.as_str());
+        push_trimmed(&mut sdh.sounds, inner);
+        " "
+    });
+    let mut has_music = false;
+    let text = RE_MUSIC.replace_all(&text, |caps: &regex::Captures| {
+        has_music = true;
+        push_trimmed(&mut sdh.music, &caps[1]);
+        " "
+    });
+    let text = speaker::strip_labels(text.trim(), |name| sdh.speakers.push(name.to_string()));
+
+    sdh.dialogue = tidy_dashes(&text);
+    sdh.kind = if sdh.dialogue.chars().any(char::is_alphanumeric) {
+        CueKind::Dialogue
+    } else if has_music {
+        CueKind::Music
+    } else if !sdh.sounds.is_empty() {
+        CueKind::Sound
+    } else {
+        CueKind::Dialogue
+    };
+    if sdh.kind != CueKind::Dialogue {
+        sdh.dialogue.clear();
+    }
+    sdh
+}
+
+/// Add `text` to `list` with its whitespace collapsed, unless it is blank
+fn push_trimmed(list: &mut Vec<String>, text: &str) {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !text.is_empty() {
+        list.push(text);
+    }
+}
+
+/// Collapse whitespace and drop dialogue dashes that no longer lead a line,
+/// or lead the only one
+fn tidy_dashes(text: &str) -> String {
+    let is_dash = |word: &str| matches!(word, "-" | "–" | "—");
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut kept: Vec<&str> = Vec::with_capacity(words.len());
+    for (i, word) in words.iter().enumerate() {
+        if is_dash(word) && words.get(i + 1).map_or(true, |next| is_dash(next)) {
+            continue;
+        }
+        kept.push(word);
+    }
+    if kept.first().is_some_and(|word| is_dash(word))
+        && kept.iter().filter(|word| is_dash(word)).count() == 1
+    {
+        kept.remove(0);
+    }
+    kept.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_annotations() {
+        let sdh = split("[DOOR SLAMS] Homer, is that you? (laughing)");
+        assert_eq!(sdh.kind, CueKind::Dialogue);
+        assert_eq!(sdh.dialogue, "Homer, is that you?");
+        assert_eq!(sdh.sounds, vec!["DOOR SLAMS", "laughing"]);
+
+        let sdh = split("[explosion]");
+        assert_eq!(sdh.kind, CueKind::Sound);
+        assert_eq!(sdh.dialogue, "");
+        assert_eq!(sdh.sounds, vec!["explosion"]);
+
+        let sdh = split("♪ theme music ♪ ♪ la la ♪");
+        assert_eq!(sdh.kind, CueKind::Music);
+        assert_eq!(sdh.music, vec!["theme music", "la la"]);
+
+        // An unclosed note only takes the rest of its own line
+        let sdh = split("♪ la la\nWhere's my donut?");
+        assert_eq!(sdh.kind, CueKind::Dialogue);
+        assert_eq!(sdh.music, vec!["la la"]);
+        assert_eq!(sdh.dialogue, "Where's my donut?");
+
+        assert_eq!(split("Just talking.").dialogue, "Just talking.");
+        assert_eq!(split("Meet at 10: 30.").speakers, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_split_speaker_labels() {
+        let sdh = split("- HOMER: Hi. - MARGE: [sighs] Hi, Homie.");
+        assert_eq!(sdh.speakers, vec!["HOMER", "MARGE"]);
+        assert_eq!(sdh.dialogue, "- Hi. - Hi, Homie.");
+        assert_eq!(sdh.sounds, vec!["sighs"]);
+
+        let sdh = split("MR. BURNS: (chuckles) Excellent.");
+        assert_eq!(sdh.speakers, vec!["MR. BURNS"]);
+        assert_eq!(sdh.dialogue, "Excellent.");
+
+        let sdh = split("HOMER: Hi.\nMARGE: Hi, Homie.");
+        assert_eq!(sdh.speakers, vec!["HOMER", "MARGE"]);
+        assert_eq!(sdh.dialogue, "Hi. Hi, Homie.");
+
+        // The same rules as speaker prefixes on SRT lines
+        assert!(split("OK: fine.").speakers.is_empty());
+        assert!(split("- I: think so.").speakers.is_empty());
+
+        // A label over nothing but a sound effect
+        let sdh = split("- BART: (screams)");
+        assert_eq!(sdh.kind, CueKind::Sound);
+        assert_eq!(sdh.speakers, vec!["BART"]);
+    }
+}
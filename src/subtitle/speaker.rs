@@ -0,0 +1,210 @@
+//! Speaker attribution
+//!
+//! Subtitles name who is talking in three ways: the ASS `Name` field, WebVTT
+//! `<v Name>` voice spans, and SDH-style `HOMER:` prefixes on SRT lines.
+//! Parsers move the name into [`SubtitleEntry::speaker`], and
+//! [`SpeakerAliases`] folds the spellings a show's files use into one name
+//! per character.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::subtitle::types::SubtitleEntry;
+
+/// An upper-case speaker label of up to four words, e.g. `MR. BURNS`
+const LABEL: &str = r"\p{Lu}[\p{Lu}\p{N}.'&-]*(?: [\p{Lu}\p{N}.'&][\p{Lu}\p{N}.'&-]*){0,3}";
+
+lazy_static! {
+    static ref RE_PREFIX: Regex = Regex::new(&format!(r"(?s)^({}):\s+(\S.*)$", LABEL)).unwrap();
+    static ref RE_SDH_LABEL: Regex =
+        Regex::new(&format!(r"(?m)(^|[-–—]\s*)({}):(?:\s+|$)", LABEL)).unwrap();
+    static ref RE_VOICE: Regex = Regex::new(r"<v(?:\.[^\s>]*)?\s+([^>]*)>").unwrap();
+    static ref RE_VOICE_ANY: Regex = Regex::new(r"</?v(?:[.\s][^>]*)?>").unwrap();
+}
+
+/// Upper-case words that start dialogue with a colon but never name anyone
+const NOT_NAMES: &[&str] = &[
+    "AH", "AM", "HEY", "HI", "NO", "NOTE", "OH", "OK", "OKAY", "PM", "PS", "UH", "UM", "WELL",
+    "WOW", "YEAH", "YES",
+];
+
+/// Split an upper-case `NAME: ` prefix off cue text, as `(name, rest)`
+pub fn split_prefix(text: &str) -> Option<(String, String)> {
+    let caps = RE_PREFIX.captures(text)?;
+    let name = caps[1].trim();
+    if !is_name(name) {
+        return None;
+    }
+    Some((name.to_string(), caps[2].to_string()))
+}
+
+/// Remove every `NAME:` label leading a line or following a dialogue dash,
+/// as SDH subtitles put them, passing each name to `found`
+pub(crate) fn strip_labels(text: &str, mut found: impl FnMut(&str)) -> String {
+    RE_SDH_LABEL
+        .replace_all(text, |caps: &regex::Captures| {
+            let name = caps[2].trim();
+            if !is_name(name) {
+                return caps[0].to_string();
+            }
+            found(name);
+            format!("{} ", &caps[1])
+        })
+        .into_owned()
+}
+
+/// Whether an upper-case label names a speaker: at least two letters, and
+/// not an interjection like `OK` or `I` that happens to precede a colon
+fn is_name(label: &str) -> bool {
+    label.chars().filter(|c| c.is_alphabetic()).count() >= 2 && !NOT_NAMES.contains(&label)
+}
+
+/// The first WebVTT voice's name, and the text without voice tags
+pub fn split_voice(text: &str) -> (Option<String>, String) {
+    let speaker = RE_VOICE
+        .captures(text)
+        .map(|caps| caps[1].trim().to_string())
+        .filter(|name| !name.is_empty());
+    match speaker {
+        Some(speaker) => (
+            Some(speaker),
+            RE_VOICE_ANY.replace_all(text, "").into_owned(),
+        ),
+        None => (None, text.to_string()),
+    }
+}
+
+/// Spelling variants of character names, mapped to the name to show
+#[derive(Debug, Clone, Default)]
+pub struct SpeakerAliases {
+    /// Lowercased variant to display name
+    names: HashMap<String, String>,
+}
+
+impl SpeakerAliases {
+    /// Aliases from display names to their variants, as in `[show.speakers]`
+    pub fn new(aliases: &BTreeMap<String, Vec<String>>) -> Self {
+        let mut names = HashMap::new();
+        for (name, variants) in aliases {
+            for variant in std::iter::once(name).chain(variants) {
+                names.insert(fold(variant), name.clone());
+            }
+        }
+        Self { names }
+    }
+
+    /// The display name for `name`
+    ///
+    /// Names without an alias keep their spelling, except that all-caps SDH
+    /// names (`MR. BURNS`) are title-cased.
+    pub fn normalize(&self, name: &str) -> String {
+        if let Some(alias) = self.names.get(&fold(name)) {
+            return alias.clone();
+        }
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.chars().any(char::is_lowercase) {
+            name
+        } else {
+            title_case(&name)
+        }
+    }
+
+    /// Normalize the speaker of every entry
+    pub fn apply(&self, entries: &mut [SubtitleEntry]) {
+        for entry in entries {
+            if let Some(speaker) = &entry.speaker {
+                entry.speaker = Some(self.normalize(speaker));
+            }
+        }
+    }
+}
+
+/// Lookup key: lowercased, with whitespace collapsed
+fn fold(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// `MR. BURNS` to `Mr. Burns`
+fn title_case(name: &str) -> String {
+    name.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_prefix() {
+        assert_eq!(
+            split_prefix("HOMER: Doh!\nWhy you little..."),
+            Some(("HOMER".to_string(), "Doh!\nWhy you little...".to_string()))
+        );
+        assert_eq!(
+            split_prefix("MR. BURNS: Excellent."),
+            Some(("MR. BURNS".to_string(), "Excellent.".to_string()))
+        );
+        assert_eq!(split_prefix("Homer: lower case is dialogue"), None);
+        assert_eq!(split_prefix("10: 30 it is"), None);
+        assert_eq!(split_prefix("A: "), None);
+        assert_eq!(split_prefix("- HOMER: Hi.\n- MARGE: Hi."), None);
+        assert_eq!(
+            split_prefix("AL: Hey."),
+            Some(("AL".to_string(), "Hey.".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_split_prefix_rejects_words() {
+        assert_eq!(split_prefix("I: think so."), None);
+        assert_eq!(split_prefix("OK: fine."), None);
+        assert_eq!(split_prefix("NO: never."), None);
+        assert_eq!(split_prefix("B2: the sequel"), None);
+    }
+
+    #[test]
+    fn test_split_voice() {
+        assert_eq!(
+            split_voice("<v.loud Homer Simpson>Doh!</v>"),
+            (Some("Homer Simpson".to_string()), "Doh!".to_string())
+        );
+        assert_eq!(
+            split_voice("<v Marge><i>Homer!</i>"),
+            (Some("Marge".to_string()), "<i>Homer!</i>".to_string())
+        );
+        assert_eq!(
+            split_voice("<i>Nobody</i>"),
+            (None, "<i>Nobody</i>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let mut map = BTreeMap::new();
+        map.insert(
+            "Homer".to_string(),
+            vec!["Homer Simpson".to_string(), "HOMER J.".to_string()],
+        );
+        let aliases = SpeakerAliases::new(&map);
+        assert_eq!(aliases.normalize("HOMER"), "Homer");
+        assert_eq!(aliases.normalize("homer  simpson"), "Homer");
+        assert_eq!(aliases.normalize("Homer J."), "Homer");
+        assert_eq!(aliases.normalize("MR. BURNS"), "Mr. Burns");
+        assert_eq!(aliases.normalize("Ned Flanders"), "Ned Flanders");
+    }
+}
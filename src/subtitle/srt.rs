@@ -14,23 +14,32 @@
 
 use std::path::Path;
 
-use crate::error::{AnytronError, Result};
+use crate::error::Result;
+use crate::subtitle::diagnostics::{self, Diagnostic, Parsed};
+use crate::subtitle::encoding;
+use crate::subtitle::parser::{ParseOptions, SubtitleParser};
+use crate::subtitle::speaker;
 use crate::subtitle::types::{SubtitleEntry, Timestamp};
+use crate::subtitle::writer;
 
 /// Parse an SRT file into subtitle entries
 pub fn parse_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let content = std::fs::read_to_string(path).map_err(|e| AnytronError::SubtitleParse {
-        path: path.to_path_buf(),
-        line: 0,
-        message: format!("Failed to read file: {}", e),
-    })?;
+    let (content, _) = encoding::read_file(path, None)?;
 
     parse_str(&content, path)
 }
 
 /// Parse SRT content string into subtitle entries
+///
+/// Fails on the first cue whose timing can't be read; [`parse_lenient`]
+/// skips it instead.
 pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let mut entries = Vec::new();
+    parse_lenient(content).into_result(path)
+}
+
+/// Parse SRT content, skipping cues that can't be read and reporting them
+pub fn parse_lenient(content: &str) -> Parsed {
+    let mut parsed = Parsed::default();
 
     // Handle BOM if present
     let content = content.trim_start_matches('\u{feff}');
@@ -38,43 +47,142 @@ pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
     // Normalize line endings (CRLF -> LF) for cross-platform compatibility
     let content = content.replace("\r\n", "\n");
 
-    // Split into blocks (separated by blank lines)
-    let blocks: Vec<&str> = content.split("\n\n").collect();
+    let mut previous = 0;
+    for (line, block) in diagnostics::blocks(&content) {
+        let lines: Vec<&str> = block.lines().collect();
 
-    for block in blocks {
-        let block = block.trim();
-        if block.is_empty() {
-            continue;
-        }
+        // Parse index (first line); a missing one is tolerated
+        let (index, timing) = match lines[0].trim().parse::<usize>() {
+            Ok(index) => {
+                if index != previous + 1 {
+                    parsed.diagnostics.push(Diagnostic::warning(
+                        line,
+                        "index-sequence",
+                        format!("Cue {} follows cue {}", index, previous),
+                    ));
+                }
+                (index, 1)
+            }
+            Err(_) if lines[0].contains("-->") => {
+                parsed.diagnostics.push(Diagnostic::warning(
+                    line,
+                    "missing-index",
+                    "Cue has no number",
+                ));
+                (previous + 1, 0)
+            }
+            Err(_) => {
+                parsed.diagnostics.push(Diagnostic::warning(
+                    line,
+                    "bad-index",
+                    format!("Expected a cue number, skipped {:?}", lines[0]),
+                ));
+                continue;
+            }
+        };
+        previous = index;
 
-        let lines: Vec<&str> = block.lines().collect();
-        if lines.len() < 3 {
-            // Skip malformed blocks
+        if lines.len() < timing + 2 {
+            parsed.diagnostics.push(Diagnostic::warning(
+                line,
+                "incomplete-cue",
+                format!("Cue {} has no text", index),
+            ));
             continue;
         }
 
-        // Parse index (first line)
-        let index: usize = match lines[0].trim().parse() {
-            Ok(i) => i,
-            Err(_) => continue, // Skip malformed entries
+        // Parse timestamps (second line)
+        let (start, end) = match parse_timestamp_line(lines[timing].trim()) {
+            Ok(times) => times,
+            Err(e) => {
+                parsed
+                    .diagnostics
+                    .push(Diagnostic::error(line + timing, "bad-timestamp", e));
+                continue;
+            }
+        };
+
+        // Parse text (remaining lines), moving a `NAME:` prefix to the speaker
+        let text = lines[timing + 1..].join("\n");
+        let (speaker, text) = match speaker::split_prefix(&text) {
+            Some((speaker, text)) => (Some(speaker), text),
+            None => (None, text),
         };
 
-        // Parse timestamps (second line)
-        let timestamp_line = lines[1].trim();
-        let (start, end) =
-            parse_timestamp_line(timestamp_line).map_err(|e| AnytronError::SubtitleParse {
-                path: path.to_path_buf(),
-                line: index,
-                message: e,
-            })?;
-
-        // Parse text (remaining lines)
-        let text = lines[2..].join("\n");
-
-        entries.push(SubtitleEntry::new(index, start, end, text));
+        parsed.push(
+            line,
+            SubtitleEntry::new(index, start, end, text).with_speaker(speaker),
+        );
+    }
+
+    parsed
+}
+
+/// Serialize entries as SRT, numbering cues from 1
+pub fn write_str(entries: &[SubtitleEntry]) -> String {
+    let mut out = String::new();
+    let cues = entries
+        .iter()
+        .map(|entry| (entry, writer::html_text(&writer::prefixed_text(entry))))
+        .filter(|(_, text)| !text.is_empty());
+    for (n, (entry, text)) in cues.enumerate() {
+        out.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            n + 1,
+            entry.start.to_srt(),
+            entry.end.to_srt(),
+            text
+        ));
+    }
+    out
+}
+
+/// SubRip (`.srt`)
+pub struct SrtParser;
+
+impl SubtitleParser for SrtParser {
+    fn name(&self) -> &str {
+        "SRT"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["srt"]
+    }
+
+    /// An index line followed by a timing line, in the first few cues
+    fn detect(&self, content: &str) -> u8 {
+        let content = content.trim_start_matches('\u{feff}');
+        let lines: Vec<&str> = content.lines().map(str::trim).take(40).collect();
+        let cue = lines.windows(2).find(|pair| {
+            !pair[0].is_empty()
+                && pair[0].bytes().all(|b| b.is_ascii_digit())
+                && parse_timestamp_line(pair[1]).is_ok()
+        });
+        match cue {
+            // Comma before the milliseconds is what sets SRT apart from VTT
+            Some(pair) if pair[1].contains(',') => 90,
+            Some(_) => 40,
+            None => 0,
+        }
     }
 
-    Ok(entries)
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        parse_str(content, path)
+    }
+
+    fn parse_lenient(
+        &self,
+        content: &str,
+        _path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Parsed> {
+        Ok(parse_lenient(content))
+    }
 }
 
 /// Parse a timestamp line like "00:00:01,000 --> 00:00:04,000"
@@ -95,6 +203,7 @@ fn parse_timestamp_line(line: &str) -> std::result::Result<(Timestamp, Timestamp
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::subtitle::Severity;
     use std::path::PathBuf;
 
     #[test]
@@ -140,4 +249,30 @@ with continuation
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].text, "Text");
     }
+
+    #[test]
+    fn test_parse_lenient() {
+        let content = "1\n00:00:01,000 --> 00:00:02,000\nFirst\n\n2\n00:00:03,000 -> 00:00:04,000\nBroken\n\n00:00:05,000 --> 00:00:06,000\nNo number\n\n5\n00:00:07,000 --> 00:00:08,000\nLast\n";
+
+        let parsed = parse_lenient(content);
+        let texts: Vec<&str> = parsed.entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["First", "No number", "Last"]);
+        let found: Vec<(usize, Severity, &str)> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.severity, d.code))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (6, Severity::Error, "bad-timestamp"),
+                (9, Severity::Warning, "missing-index"),
+                (12, Severity::Warning, "index-sequence"),
+            ]
+        );
+
+        // The strict parse still fails on the unreadable cue
+        let err = parse_str(content, &PathBuf::from("test.srt")).unwrap_err();
+        assert!(err.to_string().contains("at line 6"), "{}", err);
+    }
 }
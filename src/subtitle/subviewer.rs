@@ -0,0 +1,251 @@
+//! SubViewer 2.0 subtitle parser
+//!
+//! SubViewer format:
+//! ```text
+//! [INFORMATION]
+//! [TITLE]Episode
+//! [END INFORMATION]
+//! [SUBTITLE]
+//! [COLF]&HFFFFFF,[STYLE]no,[SIZE]18,[FONT]Arial
+//! 00:00:01.00,00:00:04.00
+//! First subtitle[br]on two lines
+//!
+//! 00:00:05.00,00:00:08.00
+//! Second subtitle
+//! ```
+//!
+//! Times are in centiseconds. The header is optional.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::subtitle::diagnostics::{Diagnostic, Parsed};
+use crate::subtitle::encoding;
+use crate::subtitle::parser::{ParseOptions, SubtitleParser};
+use crate::subtitle::types::{SubtitleEntry, Timestamp};
+use crate::subtitle::writer;
+
+lazy_static! {
+    static ref RE_TIMING: Regex =
+        Regex::new(r"^(\d{1,2}):(\d{2}):(\d{2})\.(\d{2}),(\d{1,2}):(\d{2}):(\d{2})\.(\d{2})$")
+            .unwrap();
+    /// Anything starting like a timing line, to report the malformed ones
+    static ref RE_LOOSE_TIMING: Regex = Regex::new(r"^\d+:\d+:\d+[.,:]\d+\s*,").unwrap();
+    static ref RE_BREAK: Regex = Regex::new(r"(?i)\[br\]").unwrap();
+}
+
+/// Parse a SubViewer file into subtitle entries
+pub fn parse_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let (content, _) = encoding::read_file(path, None)?;
+
+    parse_str(&content, path)
+}
+
+/// Parse SubViewer content string into subtitle entries
+///
+/// Fails on the first cue whose timing can't be read; [`parse_lenient`]
+/// skips it instead.
+pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
+    parse_lenient(content).into_result(path)
+}
+
+/// Parse SubViewer content, skipping cues whose timing can't be read and
+/// reporting them
+pub fn parse_lenient(content: &str) -> Parsed {
+    let content = content.trim_start_matches('\u{feff}');
+
+    let mut parsed = Parsed::default();
+    // Line of the timing, its times, and the text read so far
+    let mut current: Option<(usize, Timestamp, Timestamp, Vec<String>)> = None;
+    let flush = |parsed: &mut Parsed,
+                 current: &mut Option<(usize, Timestamp, Timestamp, Vec<String>)>| {
+        if let Some((line, start, end, lines)) = current.take() {
+            let text = RE_BREAK.replace_all(&lines.join("\n"), "\n").into_owned();
+            if !text.trim().is_empty() {
+                let index = parsed.entries.len() + 1;
+                parsed.push(line, SubtitleEntry::new(index, start, end, text));
+            }
+        }
+    };
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if let Some(caps) = RE_TIMING.captures(line) {
+            flush(&mut parsed, &mut current);
+            current = Some((
+                number + 1,
+                timestamp(&caps, 1),
+                timestamp(&caps, 5),
+                Vec::new(),
+            ));
+        } else if RE_LOOSE_TIMING.is_match(line) {
+            // Its text is dropped along with it, like header lines below
+            flush(&mut parsed, &mut current);
+            parsed.diagnostics.push(Diagnostic::error(
+                number + 1,
+                "bad-timestamp",
+                format!("Invalid timing line: {}", line),
+            ));
+        } else if line.is_empty() {
+            flush(&mut parsed, &mut current);
+        } else if let Some((_, _, _, lines)) = &mut current {
+            lines.push(line.to_string());
+        }
+        // Header lines come before any timing and are skipped
+    }
+    flush(&mut parsed, &mut current);
+
+    parsed
+}
+
+/// Serialize entries as SubViewer 2.0
+pub fn write_str(entries: &[SubtitleEntry]) -> String {
+    let mut out = String::from(concat!(
+        "[INFORMATION]\n",
+        "[TITLE]\n",
+        "[AUTHOR]\n",
+        "[SOURCE]\n",
+        "[PRG]\n",
+        "[FILEPATH]\n",
+        "[DELAY]0\n",
+        "[CD TRACK]0\n",
+        "[COMMENT]\n",
+        "[END INFORMATION]\n",
+        "[SUBTITLE]\n",
+        "[COLF]&HFFFFFF,[STYLE]no,[SIZE]18,[FONT]Arial\n",
+    ));
+    for entry in entries {
+        let text = writer::html_text(&writer::prefixed_text(entry));
+        if text.is_empty() {
+            continue;
+        }
+        out.push_str(&format!(
+            "{},{}\n{}\n\n",
+            centiseconds(entry.start),
+            centiseconds(entry.end),
+            text.replace('\n', "[br]")
+        ));
+    }
+    out
+}
+
+/// SubViewer 2.0 (`.sub`)
+pub struct SubViewerParser;
+
+impl SubtitleParser for SubViewerParser {
+    fn name(&self) -> &str {
+        "SubViewer"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["sub"]
+    }
+
+    /// The `[INFORMATION]` or `[SUBTITLE]` header, or failing that a timing
+    /// line
+    fn detect(&self, content: &str) -> u8 {
+        let head: String = content.chars().take(4096).collect();
+        let upper = head.to_uppercase();
+        if upper.contains("[INFORMATION]") || upper.contains("[SUBTITLE]") {
+            100
+        } else if head.lines().any(|line| RE_TIMING.is_match(line.trim())) {
+            80
+        } else {
+            0
+        }
+    }
+
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        parse_str(content, path)
+    }
+
+    fn parse_lenient(
+        &self,
+        content: &str,
+        _path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Parsed> {
+        Ok(parse_lenient(content))
+    }
+}
+
+/// Timestamp from the four captures starting at `first`
+fn timestamp(caps: &regex::Captures, first: usize) -> Timestamp {
+    let field = |i: usize| caps[first + i].parse::<u64>().unwrap_or(0);
+    Timestamp::from_hms_ms(field(0), field(1), field(2), field(3) * 10)
+}
+
+/// Format as HH:MM:SS.cc, truncating to centiseconds
+fn centiseconds(timestamp: Timestamp) -> String {
+    let ms = timestamp.0;
+    format!(
+        "{:02}:{:02}:{:02}.{:02}",
+        ms / 3_600_000,
+        (ms / 60_000) % 60,
+        (ms / 1000) % 60,
+        (ms % 1000) / 10
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle::Severity;
+
+    const SAMPLE: &str = "[INFORMATION]\n[TITLE]Test\n[END INFORMATION]\n[SUBTITLE]\n[COLF]&HFFFFFF,[STYLE]no,[SIZE]18,[FONT]Arial\n00:00:01.00,00:00:04.50\nFirst subtitle[br]on two lines\n\n01:02:03.45,01:02:05.00\nSecond subtitle\n";
+
+    #[test]
+    fn test_parse() {
+        let entries = parse_str(SAMPLE, Path::new("test.sub")).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].start, Timestamp(1000));
+        assert_eq!(entries[0].end, Timestamp(4500));
+        assert_eq!(entries[0].text, "First subtitle\non two lines");
+        assert_eq!(entries[1].start, Timestamp(3_723_450));
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let content = "[SUBTITLE]\n00:00:01.00,00:00:02.00\nFirst\n\n00:00:03.00,00:00:4.00\nBroken\n\n00:00:06.00,00:00:05.00\nBackwards\n\n00:00:07.00,00:00:08.00\nLast\n";
+
+        let parsed = parse_lenient(content);
+        let texts: Vec<&str> = parsed.entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["First", "Backwards", "Last"]);
+        let found: Vec<(usize, Severity, &str)> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.severity, d.code))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (5, Severity::Error, "bad-timestamp"),
+                (8, Severity::Warning, "end-before-start"),
+            ]
+        );
+
+        // The strict parse still fails on the unreadable cue
+        let err = parse_str(content, Path::new("test.sub")).unwrap_err();
+        assert!(err.to_string().contains("at line 5"), "{}", err);
+    }
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        assert_eq!(SubViewerParser.detect(SAMPLE), 100);
+        assert_eq!(SubViewerParser.detect("00:00:01.00,00:00:02.00\nHi\n"), 80);
+        assert_eq!(SubViewerParser.detect("{1}{25}Hi\n"), 0);
+
+        let entries = parse_str(SAMPLE, Path::new("test.sub")).unwrap();
+        let parsed = parse_str(&write_str(&entries), Path::new("test.sub")).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].text, entries[0].text);
+        assert_eq!(parsed[1].end, entries[1].end);
+    }
+}
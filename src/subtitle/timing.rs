@@ -0,0 +1,210 @@
+//! Subtitle timing transforms
+//!
+//! Every supported correction is a linear map `t' = t * scale + offset`, so
+//! they compose into a single [`TimingTransform`]:
+//!
+//! - constant offsets, for subtitles that are early or late throughout
+//! - framerate conversion, for subtitles timed against a release running at a
+//!   different speed (e.g. 25 fps PAL subtitles on a 23.976 fps video)
+//! - two-point sync, stretching the timeline so the first and last cues land
+//!   at known times
+
+use crate::error::{AnytronError, Result};
+use crate::subtitle::{SubtitleEntry, Timestamp};
+
+/// A linear correction applied to subtitle timestamps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimingTransform {
+    /// Multiplier applied to the original time
+    pub scale: f64,
+
+    /// Milliseconds added after scaling
+    pub offset_ms: f64,
+}
+
+impl Default for TimingTransform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl TimingTransform {
+    /// Transform that leaves timestamps unchanged
+    pub fn identity() -> Self {
+        Self {
+            scale: 1.0,
+            offset_ms: 0.0,
+        }
+    }
+
+    /// Shift every timestamp by `offset_ms` milliseconds
+    pub fn offset(offset_ms: i64) -> Self {
+        Self {
+            scale: 1.0,
+            offset_ms: offset_ms as f64,
+        }
+    }
+
+    /// Convert subtitles timed at `subtitle_fps` to a video playing at `video_fps`
+    ///
+    /// A frame shown at `t` in the subtitle's release is shown at
+    /// `t * subtitle_fps / video_fps` in the video.
+    pub fn framerate(subtitle_fps: f64, video_fps: f64) -> Result<Self> {
+        if !(subtitle_fps > 0.0 && video_fps > 0.0) {
+            return Err(AnytronError::Config(format!(
+                "Invalid framerate conversion {} -> {}",
+                subtitle_fps, video_fps
+            )));
+        }
+        Ok(Self {
+            scale: subtitle_fps / video_fps,
+            offset_ms: 0.0,
+        })
+    }
+
+    /// Linear map moving `first.0` to `first.1` and `last.0` to `last.1`
+    pub fn two_point(first: (Timestamp, Timestamp), last: (Timestamp, Timestamp)) -> Result<Self> {
+        let (from_a, to_a) = (first.0 .0 as f64, first.1 .0 as f64);
+        let (from_b, to_b) = (last.0 .0 as f64, last.1 .0 as f64);
+        if from_a == from_b {
+            return Err(AnytronError::Config(format!(
+                "Two-point sync needs two distinct source times, got {} twice",
+                first.0
+            )));
+        }
+
+        let scale = (to_b - to_a) / (from_b - from_a);
+        if scale <= 0.0 {
+            return Err(AnytronError::Config(
+                "Two-point sync would reverse the subtitle order".to_string(),
+            ));
+        }
+        Ok(Self {
+            scale,
+            offset_ms: to_a - from_a * scale,
+        })
+    }
+
+    /// Two-point sync putting the first cue at `first` and the last at `last`
+    ///
+    /// Cues are ordered by start time. A file with a single cue is only
+    /// shifted to `first`.
+    pub fn sync(entries: &[SubtitleEntry], first: Timestamp, last: Timestamp) -> Result<Self> {
+        let (Some(first_cue), Some(last_cue)) = (
+            entries.iter().map(|e| e.start).min(),
+            entries.iter().map(|e| e.start).max(),
+        ) else {
+            return Ok(Self::identity());
+        };
+
+        if first_cue == last_cue {
+            return Ok(Self::offset(first.0 as i64 - first_cue.0 as i64));
+        }
+        Self::two_point((first_cue, first), (last_cue, last))
+    }
+
+    /// Apply `self`, then `next`
+    pub fn then(self, next: Self) -> Self {
+        Self {
+            scale: self.scale * next.scale,
+            offset_ms: self.offset_ms * next.scale + next.offset_ms,
+        }
+    }
+
+    /// Whether the transform leaves timestamps unchanged
+    pub fn is_identity(&self) -> bool {
+        *self == Self::identity()
+    }
+
+    /// Transform a timestamp, rounding to the millisecond and clamping at zero
+    pub fn apply(&self, timestamp: Timestamp) -> Timestamp {
+        let ms = (timestamp.0 as f64 * self.scale + self.offset_ms).round();
+        Timestamp(ms.max(0.0) as u64)
+    }
+
+    /// Transform every cue of a subtitle file
+    pub fn apply_all(&self, entries: &mut [SubtitleEntry]) {
+        for entry in entries {
+            entry.retime(self);
+        }
+    }
+}
+
+/// Parse a framerate such as `25`, `23.976` or `24000/1001`
+pub fn parse_framerate(s: &str) -> Result<f64> {
+    let invalid = || AnytronError::Config(format!("Invalid framerate: {:?}", s));
+    let fps = match s.trim().split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().map_err(|_| invalid())?;
+            let den: f64 = den.trim().parse().map_err(|_| invalid())?;
+            num / den
+        }
+        None => s.trim().parse().map_err(|_| invalid())?,
+    };
+
+    if fps.is_finite() && fps > 0.0 {
+        Ok(fps)
+    } else {
+        Err(invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue(start: u64, end: u64) -> SubtitleEntry {
+        SubtitleEntry::new(0, Timestamp(start), Timestamp(end), "Line".to_string())
+    }
+
+    #[test]
+    fn test_offset_clamps_at_zero() {
+        let shift = TimingTransform::offset(-1500);
+        assert_eq!(shift.apply(Timestamp(4000)), Timestamp(2500));
+        assert_eq!(shift.apply(Timestamp(1000)), Timestamp(0));
+    }
+
+    #[test]
+    fn test_framerate_pal_to_ntsc() {
+        let pal = TimingTransform::framerate(25.0, parse_framerate("24000/1001").unwrap()).unwrap();
+        // 40 minutes into a PAL release is about 41:42 into the NTSC video
+        assert_eq!(pal.apply(Timestamp(2_400_000)), Timestamp(2_502_500));
+        assert!(TimingTransform::framerate(0.0, 25.0).is_err());
+    }
+
+    #[test]
+    fn test_sync_first_and_last_cue() {
+        let mut entries = vec![
+            cue(0, 2_000),
+            cue(500_000, 502_000),
+            cue(1_000_000, 1_003_000),
+        ];
+        let sync = TimingTransform::sync(&entries, Timestamp(1_000), Timestamp(1_041_000)).unwrap();
+        sync.apply_all(&mut entries);
+
+        assert_eq!(entries[0].start, Timestamp(1_000));
+        assert_eq!(entries[2].start, Timestamp(1_041_000));
+        assert_eq!(entries[1].start, Timestamp(521_000));
+
+        assert!(TimingTransform::sync(&entries, Timestamp(5000), Timestamp(1000)).is_err());
+    }
+
+    #[test]
+    fn test_then_composes_in_order() {
+        let t = TimingTransform::framerate(25.0, 24.0)
+            .unwrap()
+            .then(TimingTransform::offset(-500));
+        assert_eq!(t.apply(Timestamp(24_000)), Timestamp(24_500));
+        assert!(TimingTransform::identity()
+            .then(TimingTransform::offset(0))
+            .is_identity());
+    }
+
+    #[test]
+    fn test_parse_framerate() {
+        assert_eq!(parse_framerate("25").unwrap(), 25.0);
+        assert!((parse_framerate("23.976").unwrap() - 23.976).abs() < 1e-9);
+        assert!(parse_framerate("30000/0").is_err());
+        assert!(parse_framerate("fast").is_err());
+    }
+}
@@ -0,0 +1,704 @@
+//! TTML / DFXP (Timed Text Markup Language) subtitle parser
+//!
+//! TTML format:
+//! ```text
+//! <tt xmlns="http://www.w3.org/ns/ttml" xmlns:tts="http://www.w3.org/ns/ttml#styling"
+//!     xmlns:ttp="http://www.w3.org/ns/ttml#parameter" ttp:tickRate="10000000">
+//!   <head>
+//!     <styling><style xml:id="it" tts:fontStyle="italic"/></styling>
+//!   </head>
+//!   <body>
+//!     <div>
+//!       <p begin="10000000t" end="40000000t">First subtitle<br/>
+//!         <span style="it">in italics</span></p>
+//!     </div>
+//!   </body>
+//! </tt>
+//! ```
+//!
+//! Times are clock times (`00:00:01.000`, or `00:00:01:12` with frames) or
+//! offsets in hours, minutes, seconds, milliseconds, frames or ticks
+//! (`1.5s`, `36f`, `10000000t`), relative to the enclosing timed element.
+//! Italic, bold and underline styling becomes `<i>`, `<b>` and `<u>` markup.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::error::Result;
+use crate::subtitle::diagnostics::{Diagnostic, Parsed};
+use crate::subtitle::markup::{self, Tag, Token};
+use crate::subtitle::parser::{ParseOptions, SubtitleParser};
+use crate::subtitle::types::{SubtitleEntry, Timestamp};
+use crate::subtitle::writer;
+
+lazy_static! {
+    static ref RE_CLOCK: Regex =
+        Regex::new(r"^(\d+):(\d{2}):(\d{2}(?:\.\d+)?)(?::(\d+)(?:\.(\d+))?)?$").unwrap();
+    static ref RE_OFFSET: Regex = Regex::new(r"^(\d+(?:\.\d+)?)(h|ms|m|s|f|t)$").unwrap();
+    static ref RE_MARKUP: Regex = Regex::new(r"(?i)<(/?)([ibu])>").unwrap();
+}
+
+/// TTML namespaces, current and the DFXP draft
+const NAMESPACES: &[&str] = &["http://www.w3.org/ns/ttml", "http://www.w3.org/2006/"];
+
+/// Parse a TTML file into subtitle entries
+pub fn parse_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
+    let (content, _) = crate::subtitle::encoding::read_file(path, None)?;
+
+    parse_str(&content, path)
+}
+
+/// Parse TTML content string into subtitle entries
+///
+/// Fails on the first element whose timing can't be read;
+/// [`parse_lenient`] skips it instead.
+pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
+    parse_lenient(content).into_result(path)
+}
+
+/// Parse TTML content, skipping elements whose timing can't be read and
+/// reporting them
+pub fn parse_lenient(content: &str) -> Parsed {
+    let mut parsed = Parsed::default();
+    let tokens = markup::tokenize(content);
+    let Some(root) = tokens.iter().find_map(|token| match token {
+        Token::Start(tag) if tag.name == "tt" => Some(tag),
+        _ => None,
+    }) else {
+        parsed
+            .diagnostics
+            .push(Diagnostic::error(0, "no-root", "No <tt> root element"));
+        return parsed;
+    };
+    let clock = Clock::from_root(root);
+    let styles = collect_styles(&tokens);
+
+    let mut stack: Vec<Scope> = Vec::new();
+    let mut cue: Option<Cue> = None;
+    let mut in_body = false;
+    // Depth inside an element that was skipped
+    let mut skipping = 0;
+
+    for token in &tokens {
+        match token {
+            Token::Start(tag) if skipping > 0 => {
+                if !tag.self_closing {
+                    skipping += 1;
+                }
+            }
+            Token::End(name) if skipping > 0 => {
+                if in_body && name != "br" {
+                    skipping -= 1;
+                }
+            }
+            Token::Text(_) if skipping > 0 => {}
+            Token::Start(tag) if tag.name == "body" => {
+                in_body = true;
+                if !tag.self_closing {
+                    match Scope::root().child(tag, &clock, &styles) {
+                        Ok(scope) => stack.push(scope),
+                        Err(message) => {
+                            parsed
+                                .diagnostics
+                                .push(Diagnostic::error(tag.line, "bad-time", message));
+                            skipping = 1;
+                        }
+                    }
+                }
+            }
+            Token::Start(_) if !in_body => {}
+            Token::Start(tag) if tag.name == "br" => {
+                if let Some(cue) = &mut cue {
+                    cue.text.push('\n');
+                }
+            }
+            Token::Start(tag) if tag.self_closing => {}
+            Token::Start(tag) => {
+                let parent = stack.last().cloned().unwrap_or_else(Scope::root);
+                let scope = match parent.child(tag, &clock, &styles) {
+                    Ok(scope) => scope,
+                    Err(message) => {
+                        parsed
+                            .diagnostics
+                            .push(Diagnostic::error(tag.line, "bad-time", message));
+                        // A bad span loses the whole cue, not just its text
+                        cue = None;
+                        skipping = 1;
+                        continue;
+                    }
+                };
+                match (tag.name.as_str(), &mut cue) {
+                    ("p", None) => cue = Some(Cue::new(&scope, tag.line)),
+                    ("span", Some(cue)) => cue.open_span(&parent, &scope),
+                    _ => {}
+                }
+                stack.push(scope);
+            }
+            Token::End(name) if in_body && name != "br" => {
+                let Some(scope) = stack.pop() else {
+                    continue;
+                };
+                match (name.as_str(), &mut cue) {
+                    ("p", Some(_)) => {
+                        if let Some(cue) = cue.take() {
+                            let line = cue.line;
+                            if let Some(entry) = cue.finish(parsed.entries.len()) {
+                                parsed.push(line, entry);
+                            }
+                        }
+                    }
+                    ("span", Some(cue)) => {
+                        cue.close_span(&scope, stack.last().unwrap_or(&Scope::root()))
+                    }
+                    ("body", _) => in_body = false,
+                    _ => {}
+                }
+            }
+            Token::Text(text) => {
+                if let Some(cue) = &mut cue {
+                    cue.push_text(text);
+                }
+            }
+            Token::End(_) => {}
+        }
+    }
+
+    parsed.entries.sort_by_key(|entry| entry.start);
+    parsed
+}
+
+/// Serialize entries as a TTML document
+pub fn write_str(entries: &[SubtitleEntry]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<tt xmlns=\"http://www.w3.org/ns/ttml\" xmlns:tts=\"http://www.w3.org/ns/ttml#styling\">\n",
+        "  <body>\n",
+        "    <div>\n",
+    ));
+    for entry in entries {
+        let text = writer::html_text(&writer::prefixed_text(entry));
+        if text.is_empty() {
+            continue;
+        }
+        out.push_str(&format!(
+            "      <p begin=\"{}\" end=\"{}\">{}</p>\n",
+            entry.start.to_vtt(),
+            entry.end.to_vtt(),
+            ttml_text(&text)
+        ));
+    }
+    out.push_str("    </div>\n  </body>\n</tt>\n");
+    out
+}
+
+/// TTML / DFXP (`.ttml`, `.dfxp`, `.xml`)
+pub struct TtmlParser;
+
+impl SubtitleParser for TtmlParser {
+    fn name(&self) -> &str {
+        "TTML"
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ttml", "dfxp", "xml"]
+    }
+
+    /// A `<tt>` root, certain when it declares a TTML namespace
+    fn detect(&self, content: &str) -> u8 {
+        let head = &content[..floor_boundary(content, 4096)];
+        let Some(root) = head.find("<tt").map(|at| &head[at..]) else {
+            return 0;
+        };
+        if !root[3..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == ':') {
+            0
+        } else if NAMESPACES.iter().any(|ns| root.contains(ns)) {
+            100
+        } else {
+            70
+        }
+    }
+
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        parse_str(content, path)
+    }
+
+    fn parse_lenient(
+        &self,
+        content: &str,
+        _path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Parsed> {
+        Ok(parse_lenient(content))
+    }
+}
+
+/// Frame and tick rates declared on the root element
+#[derive(Debug, Clone, Copy)]
+struct Clock {
+    /// Frames per second after the frame rate multiplier
+    frame_rate: f64,
+    sub_frame_rate: f64,
+    tick_rate: f64,
+}
+
+impl Clock {
+    fn from_root(root: &Tag) -> Self {
+        let number = |name: &str| root.attr(name).and_then(|v| v.trim().parse::<f64>().ok());
+        let declared_frame_rate = number("framerate").filter(|&rate| rate > 0.0);
+        let multiplier = root
+            .attr("frameratemultiplier")
+            .and_then(|value| {
+                let mut parts = value.split_whitespace().map(|n| n.parse::<f64>().ok());
+                let numerator = parts.next()??;
+                let denominator = parts.next()??;
+                (denominator > 0.0).then(|| numerator / denominator)
+            })
+            .unwrap_or(1.0);
+        let frame_rate = declared_frame_rate.unwrap_or(30.0) * multiplier;
+        let sub_frame_rate = number("subframerate")
+            .filter(|&rate| rate > 0.0)
+            .unwrap_or(1.0);
+        // Without a tick rate, ticks are sub-frames if a frame rate is set
+        let tick_rate =
+            number("tickrate")
+                .filter(|&rate| rate > 0.0)
+                .unwrap_or(match declared_frame_rate {
+                    Some(_) => frame_rate * sub_frame_rate,
+                    None => 1.0,
+                });
+
+        Self {
+            frame_rate,
+            sub_frame_rate,
+            tick_rate,
+        }
+    }
+
+    /// Parse a time expression into milliseconds
+    fn parse(&self, expr: &str) -> Option<u64> {
+        let expr = expr.trim();
+        let seconds = if let Some(caps) = RE_CLOCK.captures(expr) {
+            let hours: f64 = caps[1].parse().ok()?;
+            let minutes: f64 = caps[2].parse().ok()?;
+            let seconds: f64 = caps[3].parse().ok()?;
+            let frames: f64 = caps.get(4).map_or(Some(0.0), |f| f.as_str().parse().ok())?;
+            let sub_frames: f64 = caps.get(5).map_or(Some(0.0), |f| f.as_str().parse().ok())?;
+            hours * 3600.0
+                + minutes * 60.0
+                + seconds
+                + (frames + sub_frames / self.sub_frame_rate) / self.frame_rate
+        } else {
+            let caps = RE_OFFSET.captures(expr)?;
+            let value: f64 = caps[1].parse().ok()?;
+            match &caps[2] {
+                "h" => value * 3600.0,
+                "m" => value * 60.0,
+                "s" => value,
+                "ms" => value / 1000.0,
+                "f" => value / self.frame_rate,
+                _ => value / self.tick_rate,
+            }
+        };
+        Some((seconds * 1000.0).round() as u64)
+    }
+}
+
+/// Styling that becomes markup
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Style {
+    italic: bool,
+    bold: bool,
+    underline: bool,
+}
+
+impl Style {
+    /// Apply `tts:` attributes on top of this style
+    fn apply(&mut self, tag: &Tag) {
+        if let Some(value) = tag.attr("fontstyle") {
+            self.italic = matches!(value, "italic" | "oblique");
+        }
+        if let Some(value) = tag.attr("fontweight") {
+            self.bold = value == "bold";
+        }
+        if let Some(value) = tag.attr("textdecoration") {
+            if value.contains("nounderline") || value == "none" {
+                self.underline = false;
+            } else if value.contains("underline") {
+                self.underline = true;
+            }
+        }
+    }
+
+    /// Markup switching from this style to `to`
+    fn transition(&self, to: &Style) -> String {
+        let flags = [
+            (self.italic, to.italic, "i"),
+            (self.bold, to.bold, "b"),
+            (self.underline, to.underline, "u"),
+        ];
+        let mut tags = String::new();
+        for (from, to, tag) in flags.iter().rev() {
+            if *from && !*to {
+                tags.push_str(&format!("</{}>", tag));
+            }
+        }
+        for (from, to, tag) in flags {
+            if !from && to {
+                tags.push_str(&format!("<{}>", tag));
+            }
+        }
+        tags
+    }
+}
+
+/// Named styles from `<styling>`, resolved through their references
+fn collect_styles(tokens: &[Token<'_>]) -> HashMap<String, Style> {
+    let defined: Vec<&Tag> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            Token::Start(tag) if tag.name == "style" && tag.attr("id").is_some() => Some(tag),
+            _ => None,
+        })
+        .collect();
+
+    fn resolve(tag: &Tag, defined: &[&Tag], depth: usize) -> Style {
+        let mut style = Style::default();
+        if depth < 8 {
+            for name in tag.attr("style").unwrap_or("").split_whitespace() {
+                if let Some(parent) = defined.iter().find(|t| t.attr("id") == Some(name)) {
+                    style = resolve(parent, defined, depth + 1);
+                }
+            }
+        }
+        style.apply(tag);
+        style
+    }
+
+    defined
+        .iter()
+        .map(|tag| {
+            let id = tag.attr("id").unwrap_or_default().to_string();
+            (id, resolve(tag, &defined, 0))
+        })
+        .collect()
+}
+
+/// Timing and style in effect inside an element
+#[derive(Debug, Clone)]
+struct Scope {
+    begin: u64,
+    end: Option<u64>,
+    /// Whether this element or an ancestor set a time
+    timed: bool,
+    style: Style,
+}
+
+impl Scope {
+    fn root() -> Self {
+        Self {
+            begin: 0,
+            end: None,
+            timed: false,
+            style: Style::default(),
+        }
+    }
+
+    /// Scope of a child element, its times relative to this one's begin
+    fn child(
+        &self,
+        tag: &Tag,
+        clock: &Clock,
+        styles: &HashMap<String, Style>,
+    ) -> std::result::Result<Self, String> {
+        let time = |name: &str| -> std::result::Result<Option<u64>, String> {
+            tag.attr(name)
+                .map(|expr| {
+                    clock
+                        .parse(expr)
+                        .ok_or_else(|| format!("Invalid time expression {}=\"{}\"", name, expr))
+                })
+                .transpose()
+        };
+        let begin_offset = time("begin")?;
+        let end_offset = time("end")?;
+        let dur = time("dur")?;
+
+        let begin = self.begin + begin_offset.unwrap_or(0);
+        let end = match (end_offset, dur) {
+            (Some(end), _) => Some(self.begin + end),
+            (None, Some(dur)) => Some(begin + dur),
+            (None, None) => self.end,
+        };
+        let end = match (end, self.end) {
+            (Some(end), Some(limit)) => Some(end.min(limit)),
+            (end, _) => end,
+        };
+
+        let mut style = self.style;
+        for name in tag.attr("style").unwrap_or("").split_whitespace() {
+            if let Some(named) = styles.get(name) {
+                style = *named;
+            }
+        }
+        style.apply(tag);
+
+        Ok(Self {
+            begin,
+            end,
+            timed: self.timed || begin_offset.is_some() || end_offset.is_some() || dur.is_some(),
+            style,
+        })
+    }
+}
+
+/// A `<p>` being read
+struct Cue {
+    /// Line the `<p>` starts on
+    line: usize,
+    scope: Scope,
+    text: String,
+    /// Earliest begin and latest end of timed spans, for untimed paragraphs
+    span_times: Option<(u64, u64)>,
+}
+
+impl Cue {
+    fn new(scope: &Scope, line: usize) -> Self {
+        Self {
+            line,
+            scope: scope.clone(),
+            text: Style::default().transition(&scope.style),
+            span_times: None,
+        }
+    }
+
+    fn open_span(&mut self, parent: &Scope, span: &Scope) {
+        self.text.push_str(&parent.style.transition(&span.style));
+        if span.timed && !self.scope.timed {
+            if let Some(end) = span.end {
+                let (begin, latest) = self.span_times.unwrap_or((span.begin, end));
+                self.span_times = Some((begin.min(span.begin), latest.max(end)));
+            }
+        }
+    }
+
+    fn close_span(&mut self, span: &Scope, parent: &Scope) {
+        self.text.push_str(&span.style.transition(&parent.style));
+    }
+
+    /// Whitespace collapses to single spaces; only `<br/>` breaks lines
+    fn push_text(&mut self, text: &str) {
+        let text = markup::decode_entities(text);
+        let mut last_space = self.text.ends_with(' ');
+        for c in text.chars() {
+            if c.is_whitespace() {
+                if !last_space {
+                    self.text.push(' ');
+                }
+                last_space = true;
+            } else {
+                self.text.push(c);
+                last_space = false;
+            }
+        }
+    }
+
+    fn finish(mut self, count: usize) -> Option<SubtitleEntry> {
+        self.text
+            .push_str(&self.scope.style.transition(&Style::default()));
+        let text = self
+            .text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let (begin, end) = match (self.scope.timed, self.scope.end, self.span_times) {
+            (true, Some(end), _) => (self.scope.begin, end),
+            (_, _, Some(times)) => times,
+            _ => {
+                log::debug!("Skipping untimed TTML paragraph: {}", text);
+                return None;
+            }
+        };
+        if text.is_empty() || end < begin {
+            return None;
+        }
+        Some(SubtitleEntry::new(
+            count + 1,
+            Timestamp(begin),
+            Timestamp(end),
+            text,
+        ))
+    }
+}
+
+/// Escape cue text and turn its markup into styled spans
+fn ttml_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut open = 0;
+    let mut last = 0;
+    for caps in RE_MARKUP.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        out.push_str(&markup::escape(&text[last..whole.start()]).replace('\n', "<br/>"));
+        last = whole.end();
+        if caps[1].is_empty() {
+            let style = match caps[2].to_lowercase().as_str() {
+                "i" => "tts:fontStyle=\"italic\"",
+                "b" => "tts:fontWeight=\"bold\"",
+                _ => "tts:textDecoration=\"underline\"",
+            };
+            out.push_str(&format!("<span {}>", style));
+            open += 1;
+        } else if open > 0 {
+            out.push_str("</span>");
+            open -= 1;
+        }
+    }
+    out.push_str(&markup::escape(&text[last..]).replace('\n', "<br/>"));
+    out.push_str(&"</span>".repeat(open));
+    out
+}
+
+/// Largest char boundary at or below `index`
+fn floor_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Vec<SubtitleEntry> {
+        parse_str(content, Path::new("test.ttml")).unwrap()
+    }
+
+    #[test]
+    fn test_parse_simple_ttml() {
+        let content = r#"<?xml version="1.0" encoding="utf-8"?>
+<tt xmlns="http://www.w3.org/ns/ttml" xml:lang="en">
+  <body>
+    <div>
+      <p begin="00:00:01.000" end="00:00:04.000">First subtitle</p>
+      <p begin="00:00:05.500" dur="3.5s">Second line<br/>
+        with   two lines &amp; an entity</p>
+    </div>
+  </body>
+</tt>"#;
+
+        let entries = parse(content);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].start, Timestamp(1000));
+        assert_eq!(entries[0].end, Timestamp(4000));
+        assert_eq!(entries[0].text, "First subtitle");
+        assert_eq!(entries[1].end, Timestamp(9000));
+        assert_eq!(entries[1].text, "Second line\nwith two lines & an entity");
+    }
+
+    #[test]
+    fn test_tick_and_frame_times() {
+        let ticks = r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:ttp="http://www.w3.org/ns/ttml#parameter" ttp:tickRate="10000000">
+<body><div><p begin="15000000t" end="42500000t">Ticks</p></div></body></tt>"#;
+        let entries = parse(ticks);
+        assert_eq!(
+            (entries[0].start, entries[0].end),
+            (Timestamp(1500), Timestamp(4250))
+        );
+
+        // 23.976 fps: frame 6 of second 1, and 48 frames as an offset
+        let frames = r#"<tt xmlns="http://www.w3.org/2006/10/ttaf1" xmlns:ttp="http://www.w3.org/2006/10/ttaf1#parameter"
+    ttp:frameRate="24" ttp:frameRateMultiplier="1000 1001">
+<body><div begin="10s"><p begin="00:00:01:06" end="48f">Frames</p></div></body></tt>"#;
+        let entries = parse(frames);
+        // Relative to the div's begin
+        assert_eq!(entries[0].start, Timestamp(10_000 + 1_250));
+        assert_eq!(entries[0].end, Timestamp(10_000 + 2_002));
+    }
+
+    #[test]
+    fn test_styling_and_spans() {
+        let content = r#"<tt xmlns="http://www.w3.org/ns/ttml" xmlns:tts="http://www.w3.org/ns/ttml#styling">
+<head><styling>
+  <style xml:id="base" tts:color="white"/>
+  <style xml:id="italic" style="base" tts:fontStyle="italic"/>
+</styling></head>
+<body><div>
+  <p begin="1s" end="2s"><span style="italic">Whispered</span> and <span tts:fontWeight="bold">loud</span></p>
+  <p begin="3s" end="4s" style="italic">All <span tts:fontStyle="normal">of</span> it</p>
+  <p><span begin="5s" end="6s">Timed</span> <span begin="6s" end="7.25s">spans</span></p>
+</div></body></tt>"#;
+
+        let entries = parse(content);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].text, "<i>Whispered</i> and <b>loud</b>");
+        assert_eq!(entries[0].text_clean, "Whispered and loud");
+        assert_eq!(entries[1].text, "<i>All </i>of<i> it</i>");
+        assert_eq!(
+            (entries[2].start, entries[2].end),
+            (Timestamp(5000), Timestamp(7250))
+        );
+        assert_eq!(entries[2].text, "Timed spans");
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        let content = r#"<tt xmlns="http://www.w3.org/ns/ttml">
+<body><div>
+  <p begin="1s" end="2s">First</p>
+  <p begin="3s" end="4x">Broken</p>
+  <p begin="5s" end="6s">Bad <span begin="soon">span</span> loses the cue</p>
+  <div begin="nope"><p begin="7s" end="8s">Skipped div</p></div>
+  <p begin="9s" end="10s">Last</p>
+</div></body></tt>"#;
+
+        let parsed = parse_lenient(content);
+        let texts: Vec<&str> = parsed.entries.iter().map(|e| e.text.as_str()).collect();
+        assert_eq!(texts, vec!["First", "Last"]);
+        let found: Vec<(usize, &str)> = parsed
+            .diagnostics
+            .iter()
+            .map(|d| (d.line, d.code))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(4, "bad-time"), (5, "bad-time"), (6, "bad-time")]
+        );
+
+        // The strict parse still fails on the unreadable cue
+        let err = parse_str(content, Path::new("test.ttml")).unwrap_err();
+        assert!(err.to_string().contains("at line 4"), "{}", err);
+        assert!(parse_lenient("<p>Not TTML</p>").has_errors());
+    }
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        assert_eq!(
+            TtmlParser.detect("<?xml?>\n<tt xmlns=\"http://www.w3.org/ns/ttml\">"),
+            100
+        );
+        assert_eq!(TtmlParser.detect("<tt>"), 70);
+        assert_eq!(TtmlParser.detect("<ttx>"), 0);
+
+        let entries = vec![SubtitleEntry::new(
+            1,
+            Timestamp(1000),
+            Timestamp(2500),
+            "<i>Fish</i> & chips\nplease".to_string(),
+        )];
+        let parsed = parse(&write_str(&entries));
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].text, "<i>Fish</i> & chips\nplease");
+        assert_eq!(parsed[0].end, Timestamp(2500));
+    }
+}
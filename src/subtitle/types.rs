@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 
 use crate::error::{AnytronError, Result};
+use crate::subtitle::timing::TimingTransform;
 
 lazy_static! {
     static ref RE_HTML: Regex = Regex::new(r"<[^>]+>").unwrap();
@@ -33,6 +34,11 @@ impl Timestamp {
         self.0
     }
 
+    /// Apply a timing correction to this timestamp
+    pub fn retimed(self, transform: &TimingTransform) -> Self {
+        transform.apply(self)
+    }
+
     /// Get the timestamp in seconds (floating point)
     pub fn as_secs_f64(&self) -> f64 {
         self.0 as f64 / 1000.0
@@ -184,6 +190,42 @@ impl Timestamp {
         }
     }
 
+    /// Start of frame `frame` (counting from 0) in a video playing at `fps`
+    ///
+    /// NTSC rates written as decimals (23.976, 29.97, 59.94) are taken as
+    /// their exact 1000/1001 fractions, so long videos don't drift.
+    pub fn from_frames(frame: u64, fps: f64) -> Self {
+        let (num, den) = exact_framerate(fps);
+        Self(((frame as u128 * 1000 * den + num / 2) / num) as u64)
+    }
+
+    /// Frame showing at this time in a video playing at `fps`, rounded to
+    /// the nearest
+    pub fn to_frames(&self, fps: f64) -> u64 {
+        let (num, den) = exact_framerate(fps);
+        ((self.0 as u128 * num + 500 * den) / (1000 * den)) as u64
+    }
+
+    /// Format as an SRT timestamp: HH:MM:SS,mmm
+    pub fn to_srt(&self) -> String {
+        self.to_ffmpeg().replace('.', ",")
+    }
+
+    /// Format as an ASS timestamp: H:MM:SS.cc, truncated to centiseconds
+    pub fn to_ass(&self) -> String {
+        let total_secs = self.0 / 1000;
+        let centis = (self.0 % 1000) / 10;
+        let hours = total_secs / 3600;
+        let minutes = (total_secs % 3600) / 60;
+        let seconds = total_secs % 60;
+        format!("{}:{:02}:{:02}.{:02}", hours, minutes, seconds, centis)
+    }
+
+    /// Format as a WebVTT timestamp: HH:MM:SS.mmm
+    pub fn to_vtt(&self) -> String {
+        self.to_ffmpeg()
+    }
+
     /// Format as FFmpeg seek time: HH:MM:SS.mmm
     pub fn to_ffmpeg(&self) -> String {
         let total_secs = self.0 / 1000;
@@ -195,6 +237,20 @@ impl Timestamp {
     }
 }
 
+/// A framerate as an exact fraction `num / den`
+fn exact_framerate(fps: f64) -> (u128, u128) {
+    for base in [24, 30, 48, 60, 120] {
+        if (fps - base as f64 * 1000.0 / 1001.0).abs() < 0.005 {
+            return (base * 1000, 1001);
+        }
+    }
+    if (fps - fps.round()).abs() < 0.0005 {
+        (fps.round().max(1.0) as u128, 1)
+    } else {
+        ((fps * 1000.0).round().max(1.0) as u128, 1000)
+    }
+}
+
 impl fmt::Display for Timestamp {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let total_secs = self.0 / 1000;
@@ -227,6 +283,10 @@ pub struct SubtitleEntry {
 
     /// Cleaned text (no formatting tags)
     pub text_clean: String,
+
+    /// Who is speaking, when the subtitles say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
 }
 
 impl SubtitleEntry {
@@ -239,9 +299,26 @@ impl SubtitleEntry {
             end,
             text,
             text_clean,
+            speaker: None,
         }
     }
 
+    /// Set who is speaking
+    pub fn with_speaker(mut self, speaker: Option<String>) -> Self {
+        self.speaker = speaker;
+        self
+    }
+
+    /// Cleaned text with the original line breaks kept
+    pub fn clean_lines(&self) -> String {
+        self.text
+            .lines()
+            .map(Self::clean_text)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Remove formatting tags and normalize whitespace
     fn clean_text(text: &str) -> String {
         let text = RE_HTML.replace_all(text, "");
@@ -255,6 +332,17 @@ impl SubtitleEntry {
         Timestamp((self.start.0 + self.end.0) / 2)
     }
 
+    /// Move the cue by `offset_ms` milliseconds, clamping at zero
+    pub fn shift(&mut self, offset_ms: i64) {
+        self.retime(&TimingTransform::offset(offset_ms));
+    }
+
+    /// Apply a timing correction to the cue's start and end
+    pub fn retime(&mut self, transform: &TimingTransform) {
+        self.start = self.start.retimed(transform);
+        self.end = self.end.retimed(transform);
+    }
+
     /// Get duration in milliseconds
     pub fn duration_ms(&self) -> u64 {
         self.end.0.saturating_sub(self.start.0)
@@ -272,18 +360,53 @@ pub enum SubtitleFormat {
     Srt,
     Ass,
     Vtt,
+    Ttml,
+    Sami,
+    MicroDvd,
+    SubViewer,
 }
 
 impl SubtitleFormat {
+    /// File extension written for the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Ass => "ass",
+            Self::Vtt => "vtt",
+            Self::Ttml => "ttml",
+            Self::Sami => "smi",
+            Self::MicroDvd | Self::SubViewer => "sub",
+        }
+    }
+
     /// Detect format from file extension
+    ///
+    /// `.sub` is taken as MicroDVD, the more common of the two text formats
+    /// using it; parsing tells them apart by content.
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext.to_lowercase().as_str() {
             "srt" => Some(Self::Srt),
             "ass" | "ssa" => Some(Self::Ass),
             "vtt" => Some(Self::Vtt),
+            "ttml" | "dfxp" | "xml" => Some(Self::Ttml),
+            "smi" | "sami" => Some(Self::Sami),
+            "sub" => Some(Self::MicroDvd),
             _ => None,
         }
     }
+
+    /// Look up a format by name, as given on the command line
+    ///
+    /// Accepts `subviewer` and `microdvd` for the two `.sub` formats, and
+    /// any extension [`Self::from_extension`] knows.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "subviewer" => Some(Self::SubViewer),
+            "microdvd" => Some(Self::MicroDvd),
+            "webvtt" => Some(Self::Vtt),
+            other => Self::from_extension(other),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -314,6 +437,38 @@ mod tests {
         assert_eq!(ts2.0, 23 * 60 * 1000 + 45 * 1000 + 678);
     }
 
+    #[test]
+    fn test_frames() {
+        // 1001 ms every 24 frames at 23.976
+        assert_eq!(Timestamp::from_frames(24, 23.976).0, 1001);
+        assert_eq!(Timestamp::from_frames(1000, 23.976).0, 41708);
+        assert_eq!(Timestamp::from_frames(1000, 25.0).0, 40000);
+        assert_eq!(Timestamp::from_frames(1000, 29.97).0, 33367);
+        assert_eq!(
+            Timestamp::from_frames(1000, 23.976),
+            Timestamp::from_frames(1000, 24000.0 / 1001.0)
+        );
+        // Two hours in, without drift from the rounded rate
+        assert_eq!(Timestamp::from_frames(172_800, 23.976).0, 7_207_200);
+        assert_eq!(Timestamp(7_207_200).to_frames(23.976), 172_800);
+
+        for fps in [23.976, 25.0, 29.97, 12.5] {
+            for frame in [0, 1, 7, 1025, 99_999] {
+                assert_eq!(Timestamp::from_frames(frame, fps).to_frames(fps), frame);
+            }
+        }
+    }
+
+    #[test]
+    fn test_timestamp_formatting() {
+        let ts = Timestamp(5025678);
+        assert_eq!(ts.to_srt(), "01:23:45,678");
+        assert_eq!(ts.to_vtt(), "01:23:45.678");
+        assert_eq!(ts.to_ass(), "1:23:45.67");
+        assert_eq!(Timestamp::parse_srt(&ts.to_srt()).unwrap(), ts);
+        assert_eq!(Timestamp::parse_ass(&ts.to_ass()).unwrap().0, 5025670);
+    }
+
     #[test]
     fn test_clean_text() {
         let entry = SubtitleEntry::new(
@@ -331,6 +486,33 @@ mod tests {
             "{\\an8}Some text{\\pos(100,50)}".to_string(),
         );
         assert_eq!(entry2.text_clean, "Some text");
+
+        let entry3 = SubtitleEntry::new(
+            1,
+            Timestamp(0),
+            Timestamp(1000),
+            "<i>Two</i>\n\n  lines ".to_string(),
+        );
+        assert_eq!(entry3.text_clean, "Two lines");
+        assert_eq!(entry3.clean_lines(), "Two\nlines");
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            SubtitleFormat::from_name("subviewer"),
+            Some(SubtitleFormat::SubViewer)
+        );
+        assert_eq!(
+            SubtitleFormat::from_name("MicroDVD"),
+            Some(SubtitleFormat::MicroDvd)
+        );
+        assert_eq!(
+            SubtitleFormat::from_name("sub"),
+            Some(SubtitleFormat::MicroDvd)
+        );
+        assert_eq!(SubtitleFormat::from_name("srt"), Some(SubtitleFormat::Srt));
+        assert_eq!(SubtitleFormat::from_name("txt"), None);
     }
 
     #[test]
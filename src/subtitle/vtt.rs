@@ -15,23 +15,32 @@
 
 use std::path::Path;
 
-use crate::error::{AnytronError, Result};
+use crate::error::Result;
+use crate::subtitle::diagnostics::{self, Diagnostic, Parsed};
+use crate::subtitle::encoding;
+use crate::subtitle::parser::{ParseOptions, SubtitleParser};
+use crate::subtitle::speaker;
 use crate::subtitle::types::{SubtitleEntry, Timestamp};
+use crate::subtitle::writer;
 
 /// Parse a WebVTT file into subtitle entries
 pub fn parse_file(path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let content = std::fs::read_to_string(path).map_err(|e| AnytronError::SubtitleParse {
-        path: path.to_path_buf(),
-        line: 0,
-        message: format!("Failed to read file: {}", e),
-    })?;
+    let (content, _) = encoding::read_file(path, None)?;
 
     parse_str(&content, path)
 }
 
 /// Parse VTT content string into subtitle entries
+///
+/// Fails on the first cue whose timing can't be read; [`parse_lenient`]
+/// skips it instead.
 pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
-    let mut entries = Vec::new();
+    parse_lenient(content).into_result(path)
+}
+
+/// Parse VTT content, skipping cues that can't be read and reporting them
+pub fn parse_lenient(content: &str) -> Parsed {
+    let mut parsed = Parsed::default();
 
     // Handle BOM if present
     let content = content.trim_start_matches('\u{feff}');
@@ -39,88 +48,124 @@ pub fn parse_str(content: &str, path: &Path) -> Result<Vec<SubtitleEntry>> {
     // Normalize line endings (CRLF -> LF) for cross-platform compatibility
     let content = content.replace("\r\n", "\n");
 
-    // Split into blocks
-    let blocks: Vec<&str> = content.split("\n\n").collect();
     let mut index = 0;
-
-    for block in blocks {
-        let block = block.trim();
-
-        // Skip empty blocks
-        if block.is_empty() {
+    for (line, block) in diagnostics::blocks(&content) {
+        // Skip the header and NOTE, STYLE and REGION blocks
+        if ["WEBVTT", "NOTE", "STYLE", "REGION"]
+            .iter()
+            .any(|keyword| block.starts_with(keyword))
+        {
             continue;
         }
 
-        // Skip WEBVTT header
-        if block.starts_with("WEBVTT") {
-            continue;
-        }
-
-        // Skip NOTE blocks
-        if block.starts_with("NOTE") {
-            continue;
-        }
+        // Try to parse as a cue
+        parse_cue(block, line, &mut index, &mut parsed);
+    }
 
-        // Skip STYLE blocks
-        if block.starts_with("STYLE") {
-            continue;
-        }
+    parsed
+}
 
-        // Skip REGION blocks
-        if block.starts_with("REGION") {
+/// Serialize entries as WebVTT
+pub fn write_str(entries: &[SubtitleEntry]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for entry in entries {
+        let mut text = writer::html_text(&entry.text);
+        if text.is_empty() {
             continue;
         }
-
-        // Try to parse as a cue
-        if let Some(entry) = parse_cue(block, &mut index, path)? {
-            entries.push(entry);
+        if let Some(speaker) = &entry.speaker {
+            text = format!("<v {}>{}", speaker.replace(['<', '>'], ""), text);
         }
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            entry.start.to_vtt(),
+            entry.end.to_vtt(),
+            text
+        ));
     }
-
-    Ok(entries)
+    out
 }
 
-/// Parse a single VTT cue block
-fn parse_cue(block: &str, index: &mut usize, path: &Path) -> Result<Option<SubtitleEntry>> {
-    let lines: Vec<&str> = block.lines().collect();
+/// WebVTT (`.vtt`)
+pub struct VttParser;
 
-    if lines.is_empty() {
-        return Ok(None);
+impl SubtitleParser for VttParser {
+    fn name(&self) -> &str {
+        "WebVTT"
     }
 
-    let mut line_idx = 0;
+    fn extensions(&self) -> &[&str] {
+        &["vtt"]
+    }
 
-    // Optional cue identifier (skip it)
-    if !lines[0].contains("-->") {
-        line_idx = 1;
-        if lines.len() < 2 {
-            return Ok(None);
+    /// The `WEBVTT` signature the spec requires on the first line
+    fn detect(&self, content: &str) -> u8 {
+        let content = content.trim_start_matches('\u{feff}');
+        match content.strip_prefix("WEBVTT") {
+            Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => 100,
+            _ => 0,
         }
     }
 
-    // Parse timestamp line
-    let timestamp_line = lines.get(line_idx).copied().unwrap_or("");
+    fn parse_str(
+        &self,
+        content: &str,
+        path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Vec<SubtitleEntry>> {
+        parse_str(content, path)
+    }
+
+    fn parse_lenient(
+        &self,
+        content: &str,
+        _path: &Path,
+        _options: &ParseOptions,
+    ) -> Result<Parsed> {
+        Ok(parse_lenient(content))
+    }
+}
+
+/// Parse a single VTT cue block starting at `line`
+fn parse_cue(block: &str, line: usize, index: &mut usize, parsed: &mut Parsed) {
+    let lines: Vec<&str> = block.lines().collect();
+
+    // Optional cue identifier (skip it)
+    let timing = usize::from(!lines[0].contains("-->"));
+    let timestamp_line = lines.get(timing).copied().unwrap_or("");
     if !timestamp_line.contains("-->") {
-        return Ok(None); // Not a valid cue
+        parsed.diagnostics.push(Diagnostic::warning(
+            line,
+            "not-a-cue",
+            format!("Skipped text outside a cue: {:?}", lines[0]),
+        ));
+        return;
     }
 
-    let (start, end) =
-        parse_timestamp_line(timestamp_line).map_err(|e| AnytronError::SubtitleParse {
-            path: path.to_path_buf(),
-            line: *index + 1,
-            message: e,
-        })?;
+    let (start, end) = match parse_timestamp_line(timestamp_line) {
+        Ok(times) => times,
+        Err(e) => {
+            parsed
+                .diagnostics
+                .push(Diagnostic::error(line + timing, "bad-timestamp", e));
+            return;
+        }
+    };
 
-    // Parse text (remaining lines)
-    let text = lines[(line_idx + 1)..].join("\n");
+    // Parse text (remaining lines), taking the speaker from a voice span
+    let text = lines[(timing + 1)..].join("\n");
+    let (speaker, text) = speaker::split_voice(&text);
 
+    // Empty cues are allowed and simply show nothing
     if text.is_empty() {
-        return Ok(None);
+        return;
     }
 
     *index += 1;
-
-    Ok(Some(SubtitleEntry::new(*index, start, end, text)))
+    parsed.push(
+        line,
+        SubtitleEntry::new(*index, start, end, text).with_speaker(speaker),
+    );
 }
 
 /// Parse a timestamp line like "00:00:01.000 --> 00:00:04.000"
@@ -0,0 +1,231 @@
+//! Subtitle serialization
+//!
+//! Writes entries back out as SRT, WebVTT, ASS, TTML, SAMI, SubViewer or
+//! MicroDVD. MicroDVD is timed at the video's framerate when one is given,
+//! else at [`microdvd::DEFAULT_FPS`], declared in its header. Italic, bold
+//! and underline markup is carried between the HTML-style tags of SRT/VTT,
+//! the override tags of ASS and the styled spans of TTML; any other markup is
+//! dropped. Speakers go in the ASS `Name` field and WebVTT voice spans, and
+//! back in front of the text as `NAME: ` elsewhere.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::borrow::Cow;
+use std::path::Path;
+
+use crate::error::{AnytronError, Result};
+use crate::subtitle::types::{SubtitleEntry, SubtitleFormat};
+use crate::subtitle::{ass, microdvd, sami, srt, subviewer, ttml, vtt};
+
+lazy_static! {
+    static ref RE_ASS_TAG: Regex = Regex::new(r"\\([ibu])([01])").unwrap();
+    static ref RE_ASS_BLOCK: Regex = Regex::new(r"\{[^}]*\}").unwrap();
+    static ref RE_HTML_TAG: Regex = Regex::new(r"(?i)<(/?)([ibu])>").unwrap();
+    static ref RE_HTML_ANY: Regex = Regex::new(r"<[^>]+>").unwrap();
+}
+
+/// Serialize entries in `format`; `framerate` times MicroDVD frames
+pub fn write_str(
+    format: SubtitleFormat,
+    entries: &[SubtitleEntry],
+    framerate: Option<f64>,
+) -> String {
+    match format {
+        SubtitleFormat::Srt => srt::write_str(entries),
+        SubtitleFormat::Ass => ass::write_str(entries),
+        SubtitleFormat::Vtt => vtt::write_str(entries),
+        SubtitleFormat::Ttml => ttml::write_str(entries),
+        SubtitleFormat::Sami => sami::write_str(entries),
+        SubtitleFormat::MicroDvd => {
+            microdvd::write_str(entries, framerate.unwrap_or(microdvd::DEFAULT_FPS))
+        }
+        SubtitleFormat::SubViewer => subviewer::write_str(entries),
+    }
+}
+
+/// Write entries to a file in `format`; `framerate` times MicroDVD frames
+pub fn write_file(
+    path: &Path,
+    format: SubtitleFormat,
+    entries: &[SubtitleEntry],
+    framerate: Option<f64>,
+) -> Result<()> {
+    std::fs::write(path, write_str(format, entries, framerate)).map_err(|e| {
+        AnytronError::FileWrite {
+            path: path.to_path_buf(),
+            source: e,
+        }
+    })
+}
+
+/// Cue text with the speaker put back as an upper-case `NAME: ` prefix, for
+/// formats without a speaker field
+pub(crate) fn prefixed_text(entry: &SubtitleEntry) -> Cow<'_, str> {
+    match &entry.speaker {
+        Some(speaker) => Cow::Owned(format!("{}: {}", speaker.to_uppercase(), entry.text)),
+        None => Cow::Borrowed(&entry.text),
+    }
+}
+
+/// Cue text with HTML-style markup, for SRT and WebVTT
+///
+/// Blank lines would end the cue, so they are dropped.
+pub(crate) fn html_text(text: &str) -> String {
+    let text = RE_ASS_BLOCK.replace_all(text, |caps: &regex::Captures| {
+        RE_ASS_TAG
+            .captures_iter(&caps[0])
+            .map(|tag| match &tag[2] {
+                "1" => format!("<{}>", &tag[1]),
+                _ => format!("</{}>", &tag[1]),
+            })
+            .collect::<String>()
+    });
+    text.lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Cue text on one line with ASS override tags
+pub(crate) fn ass_text(text: &str) -> String {
+    let text = RE_HTML_TAG.replace_all(text, |caps: &regex::Captures| {
+        let on = if caps[1].is_empty() { "1" } else { "0" };
+        format!("{{\\{}{}}}", caps[2].to_lowercase(), on)
+    });
+    let text = RE_HTML_ANY.replace_all(&text, "");
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\\N")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle::{parser::ParseOptions, registry, Timestamp};
+
+    fn entries() -> Vec<SubtitleEntry> {
+        vec![
+            SubtitleEntry::new(
+                1,
+                Timestamp(1000),
+                Timestamp(4000),
+                "Hello, <i>world</i>.".to_string(),
+            ),
+            SubtitleEntry::new(
+                2,
+                Timestamp(5120),
+                Timestamp(7890),
+                "Two lines,\nwith commas, too.".to_string(),
+            ),
+            SubtitleEntry::new(
+                3,
+                Timestamp(3_723_450),
+                Timestamp(3_725_000),
+                "Over an hour in.".to_string(),
+            ),
+        ]
+    }
+
+    fn round_trip(format: SubtitleFormat) -> Vec<SubtitleEntry> {
+        let path = format!("episode.{}", format.extension());
+        registry()
+            .parse_str(
+                &write_str(format, &entries(), None),
+                Path::new(&path),
+                &ParseOptions::default(),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_round_trips() {
+        for format in [
+            SubtitleFormat::Srt,
+            SubtitleFormat::Vtt,
+            SubtitleFormat::Ass,
+            SubtitleFormat::Ttml,
+            SubtitleFormat::Sami,
+            SubtitleFormat::MicroDvd,
+            SubtitleFormat::SubViewer,
+        ] {
+            let parsed = round_trip(format);
+            assert_eq!(parsed.len(), 3, "{:?}", format);
+            // MicroDVD times land on the nearest frame
+            let tolerance = match format {
+                SubtitleFormat::MicroDvd => 1000 / 23,
+                _ => 0,
+            };
+            for (parsed, original) in parsed.iter().zip(entries()) {
+                assert!(
+                    parsed.start.0.abs_diff(original.start.0) <= tolerance,
+                    "{:?}",
+                    format
+                );
+                assert!(
+                    parsed.end.0.abs_diff(original.end.0) <= tolerance,
+                    "{:?}",
+                    format
+                );
+                assert_eq!(parsed.text_clean, original.text_clean, "{:?}", format);
+            }
+        }
+
+        // Markup survives in each format's own syntax
+        assert_eq!(round_trip(SubtitleFormat::Srt)[0].text, entries()[0].text);
+        assert_eq!(round_trip(SubtitleFormat::Vtt)[1].text, entries()[1].text);
+        assert_eq!(
+            round_trip(SubtitleFormat::Ass)[0].text,
+            "Hello, {\\i1}world{\\i0}."
+        );
+    }
+
+    #[test]
+    fn test_speakers() {
+        let entries =
+            vec![
+                SubtitleEntry::new(1, Timestamp(1000), Timestamp(2000), "Doh!".to_string())
+                    .with_speaker(Some("Homer".to_string())),
+            ];
+        for (format, speaker) in [
+            (SubtitleFormat::Ass, "Homer"),
+            (SubtitleFormat::Vtt, "Homer"),
+            (SubtitleFormat::Srt, "HOMER"),
+        ] {
+            let path = format!("episode.{}", format.extension());
+            let parsed = registry()
+                .parse_str(
+                    &write_str(format, &entries, None),
+                    Path::new(&path),
+                    &ParseOptions::default(),
+                )
+                .unwrap();
+            assert_eq!(parsed[0].speaker.as_deref(), Some(speaker), "{:?}", format);
+            assert_eq!(parsed[0].text_clean, "Doh!", "{:?}", format);
+        }
+        assert!(write_str(SubtitleFormat::Sami, &entries, None).contains("HOMER: Doh!"));
+    }
+
+    #[test]
+    fn test_microdvd_framerate() {
+        let fallback = write_str(SubtitleFormat::MicroDvd, &entries(), None);
+        assert!(fallback.starts_with(&format!("{{1}}{{1}}{}\n", microdvd::DEFAULT_FPS)));
+        let pal = write_str(SubtitleFormat::MicroDvd, &entries(), Some(25.0));
+        assert!(pal.starts_with("{1}{1}25\n"));
+        assert!(pal.contains("{25}{100}"));
+    }
+
+    #[test]
+    fn test_markup_conversion() {
+        assert_eq!(
+            html_text("{\\an8}{\\i1}Up here{\\i0}\n\nand {\\b1\\c&H00FF00&}here"),
+            "<i>Up here</i>\nand <b>here"
+        );
+        assert_eq!(
+            ass_text("<I>Loud</I> <font color=\"red\">and</font>\nclear"),
+            "{\\i1}Loud{\\i0} and\\Nclear"
+        );
+    }
+}
@@ -188,6 +188,410 @@ base_url = "/"
         // For CI, we primarily test the CLI interface works correctly
         let _ = result; // We just verify it doesn't panic
     }
+
+    #[test]
+    fn test_incremental_generate_prunes_removed_episodes() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let output_dir = temp_dir.path().join("output");
+
+        // Placeholder videos are enough with --skip-frames
+        let srt = fs::read_to_string(show_dir.join("Test.Show.S01E01.srt")).unwrap();
+        fs::write(show_dir.join("Test.Show.S01E02.srt"), srt).unwrap();
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        fs::write(show_dir.join("Test.Show.S01E02.mkv"), b"").unwrap();
+
+        let generate = || {
+            Command::cargo_bin("anytron")
+                .unwrap()
+                .args([
+                    "generate",
+                    show_dir.to_str().unwrap(),
+                    "-o",
+                    output_dir.to_str().unwrap(),
+                    "--skip-frames",
+                ])
+                .assert()
+                .success();
+        };
+
+        generate();
+        let caption = caption_page(&output_dir, &show_dir.join("Test.Show.S01E02.srt"), 2, 0);
+        assert!(caption.exists());
+        assert!(output_dir.join(".anytron/build-manifest.json").exists());
+
+        fs::remove_file(show_dir.join("Test.Show.S01E02.mkv")).unwrap();
+        fs::remove_file(show_dir.join("Test.Show.S01E02.srt")).unwrap();
+        generate();
+
+        assert!(!caption.exists(), "caption of removed episode not pruned");
+        assert!(caption_page(&output_dir, &show_dir.join("Test.Show.S01E01.srt"), 1, 0).exists());
+    }
+
+    #[test]
+    fn test_validate_reports_overrides() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let srt = fs::read_to_string(show_dir.join("Test.Show.S01E01.srt")).unwrap();
+        fs::write(show_dir.join("Test.Show.S01E02.srt"), srt).unwrap();
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        fs::write(show_dir.join("Test.Show.S01E02.mkv"), b"").unwrap();
+
+        let mut config = fs::read_to_string(show_dir.join("anytron.toml")).unwrap();
+        config.push_str(
+            "\n[episodes.\"S01E02\"]\nskip = true\n\n[episodes.\"S01E09\"]\nsubtitle_offset_ms = 250\n",
+        );
+        fs::write(show_dir.join("anytron.toml"), config).unwrap();
+
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args(["validate", show_dir.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Found 1 episodes"))
+            .stdout(predicate::str::contains(
+                "[episodes.S01E09] matches no episode",
+            ))
+            .stdout(predicate::str::contains("episodes.S01E02").not());
+    }
+
+    #[test]
+    fn test_legacy_encoded_subtitles() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let output_dir = temp_dir.path().join("output");
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        // "Ça va très bien, garçon. Déjà vu ?" in Windows-1252
+        fs::write(
+            show_dir.join("Test.Show.S01E01.srt"),
+            b"1\r\n00:00:01,000 --> 00:00:04,000\r\n\xc7a va tr\xe8s bien, gar\xe7on. D\xe9j\xe0 vu ?\r\n",
+        )
+        .unwrap();
+
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args(["validate", show_dir.to_str().unwrap(), "--detailed"])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "Encoding: windows-1252 (statistical guess)",
+            ))
+            .stdout(predicate::str::contains("✓ 1 subtitle entries"));
+
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args([
+                "generate",
+                show_dir.to_str().unwrap(),
+                "-o",
+                output_dir.to_str().unwrap(),
+                "--skip-frames",
+            ])
+            .assert()
+            .success();
+        let index = fs::read_to_string(output_dir.join("search/index.json")).unwrap();
+        assert!(index.contains("Ça va très bien, garçon."));
+    }
+
+    #[test]
+    fn test_subtitle_diagnostics() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let output_dir = temp_dir.path().join("output");
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        fs::write(
+            show_dir.join("Test.Show.S01E01.srt"),
+            "1\n00:00:01,000 --> 00:00:04,000\nFine.\n\n2\n00:00:05,000 --> later\nBroken.\n\n3\n00:00:09,000 --> 00:00:10,000\nAlso fine.\n",
+        )
+        .unwrap();
+
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args(["validate", show_dir.to_str().unwrap()])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("✗ S01E01: \""))
+            .stdout(predicate::str::contains("line 6: error [bad-timestamp]"))
+            .stdout(predicate::str::contains("1 errors, 0 warnings"));
+
+        let generate = |strict: bool| {
+            let mut command = Command::cargo_bin("anytron").unwrap();
+            command.args([
+                "generate",
+                show_dir.to_str().unwrap(),
+                "-o",
+                output_dir.to_str().unwrap(),
+                "--skip-frames",
+            ]);
+            if strict {
+                command.arg("--strict");
+            }
+            command.assert()
+        };
+
+        // Strict builds stop at the unreadable cue
+        generate(true)
+            .failure()
+            .stderr(predicate::str::contains("bad-timestamp"));
+
+        // Otherwise it is skipped and the rest of the episode indexed
+        generate(false).success();
+        let index = fs::read_to_string(output_dir.join("search/index.json")).unwrap();
+        assert!(index.contains("Also fine."));
+        assert!(!index.contains("Broken."));
+    }
+
+    #[test]
+    fn test_retired_caption_ids_redirect() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let output_dir = temp_dir.path().join("output");
+        let srt_path = show_dir.join("Test.Show.S01E01.srt");
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+
+        let generate = || {
+            Command::cargo_bin("anytron")
+                .unwrap()
+                .args([
+                    "generate",
+                    show_dir.to_str().unwrap(),
+                    "-o",
+                    output_dir.to_str().unwrap(),
+                    "--skip-frames",
+                ])
+                .assert()
+                .success();
+        };
+
+        generate();
+        let first = caption_page(&output_dir, &srt_path, 1, 0);
+        let second = caption_page(&output_dir, &srt_path, 1, 1);
+
+        // Retime the whole file and reword the first line
+        let srt = fs::read_to_string(&srt_path)
+            .unwrap()
+            .replace("00:00:", "00:01:")
+            .replace("the first subtitle", "the opening subtitle");
+        fs::write(&srt_path, srt).unwrap();
+        generate();
+
+        let reworded = caption_page(&output_dir, &srt_path, 1, 0);
+        assert_ne!(first, reworded);
+        assert_eq!(second, caption_page(&output_dir, &srt_path, 1, 1));
+        assert!(!fs::read_to_string(&second).unwrap().contains("http-equiv"));
+
+        let stub = fs::read_to_string(&first).unwrap();
+        let target = reworded.file_name().unwrap().to_str().unwrap();
+        assert!(stub.contains("http-equiv=\"refresh\""));
+        assert!(stub.contains(&format!("caption/{}", target)));
+        assert!(output_dir.join(".anytron/caption-ids.json").exists());
+    }
+
+    #[test]
+    fn test_generate_bilingual_site() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let output_dir = temp_dir.path().join("output");
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        fs::write(
+            show_dir.join("Test.Show.S01E01.es.srt"),
+            "1\n00:00:01,200 --> 00:00:03,800\nHola, los niños cantan.\n\n\
+             2\n00:00:05,000 --> 00:00:09,500\nEsta es la segunda línea.\n",
+        )
+        .unwrap();
+        let mut config = fs::read_to_string(show_dir.join("anytron.toml")).unwrap();
+        config.push_str("languages = [\"en\", \"es\"]\n");
+        fs::write(show_dir.join("anytron.toml"), config).unwrap();
+
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args([
+                "generate",
+                show_dir.to_str().unwrap(),
+                "-o",
+                output_dir.to_str().unwrap(),
+                "--skip-frames",
+            ])
+            .assert()
+            .success();
+
+        let search = output_dir.join("search");
+        let manifest: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(search.join("manifest.json")).unwrap())
+                .unwrap();
+        let languages: Vec<&str> = manifest["languages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|language| language["manifest"].as_str().unwrap())
+            .collect();
+        assert_eq!(languages, vec!["manifest.json", "spa/manifest.json"]);
+        assert_eq!(manifest["meta"]["total"], 4);
+
+        let spanish: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(search.join("spa/manifest.json")).unwrap())
+                .unwrap();
+        assert_eq!(spanish["pipeline"], serde_json::json!(["stemmer-spa"]));
+        assert_eq!(spanish["meta"]["total"], 2);
+        let stems = fs::read_to_string(search.join("spa/stems.json")).unwrap();
+        assert!(stems.contains("\"niños\":\"niñ\""));
+
+        let caption = caption_page(&output_dir, &show_dir.join("Test.Show.S01E01.srt"), 1, 0);
+        let html = fs::read_to_string(caption).unwrap();
+        assert!(html.contains("caption-quote--secondary"));
+        assert!(html.contains("\"Hola, los niños cantan.\""));
+        assert!(html.contains("title=\"Español\""));
+    }
+
+    #[test]
+    fn test_export_anki_deck() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let deck_dir = temp_dir.path().join("deck");
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        fs::write(
+            show_dir.join("Test.Show.S01E01.es.srt"),
+            "1\n00:00:01,200 --> 00:00:03,800\nHola, este es el primero.\n",
+        )
+        .unwrap();
+
+        // A translation needs the language it translates
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args([
+                "export",
+                "anki",
+                show_dir.to_str().unwrap(),
+                "-o",
+                deck_dir.to_str().unwrap(),
+                "--translation",
+                "es",
+                "--skip-frames",
+            ])
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("pass --language"));
+
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args([
+                "export",
+                "anki",
+                show_dir.to_str().unwrap(),
+                "-o",
+                deck_dir.to_str().unwrap(),
+                "--language",
+                "en",
+                "--translation",
+                "es",
+                "--query",
+                "first subtitles",
+                "--skip-frames",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Exported 1 cards"));
+
+        let deck = fs::read_to_string(deck_dir.join("deck.txt")).unwrap();
+        assert!(deck.contains("#deck:Test Show\n"));
+        let row: Vec<&str> = deck.lines().last().unwrap().split('\t').collect();
+        assert_eq!(row[2], "Hello, this is the first subtitle.");
+        assert_eq!(row[3], "Hola, este es el primero.");
+        assert_eq!(row[5], "S01E01");
+        assert!(!deck_dir.join("media").exists());
+    }
+
+    #[test]
+    fn test_subs_convert_writes_corrected_tracks() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        let subs_dir = temp_dir.path().join("subs");
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        fs::write(
+            show_dir.join("Test.Show.S01E01.es.srt"),
+            "1\n00:00:01,200 --> 00:00:03,800\nHola, este es el primero.\n",
+        )
+        .unwrap();
+        let mut config = fs::read_to_string(show_dir.join("anytron.toml")).unwrap();
+        config.push_str(
+            "languages = [\"en\", \"es\"]\n\n[episodes.\"S01E01\"]\nsubtitle_offset_ms = 500\n",
+        );
+        fs::write(show_dir.join("anytron.toml"), config).unwrap();
+
+        Command::cargo_bin("anytron")
+            .unwrap()
+            .args([
+                "subs",
+                "convert",
+                show_dir.to_str().unwrap(),
+                "-o",
+                subs_dir.to_str().unwrap(),
+                "--format",
+                "vtt",
+            ])
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Wrote 2 subtitle files"));
+
+        let english = fs::read_to_string(subs_dir.join("Test.Show.S01E01.eng.vtt")).unwrap();
+        assert!(english.starts_with("WEBVTT\n"));
+        assert!(
+            english.contains("00:00:01.500 --> 00:00:04.500\nHello, this is the first subtitle.\n")
+        );
+        let spanish = fs::read_to_string(subs_dir.join("Test.Show.S01E01.spa.vtt")).unwrap();
+        assert!(spanish.contains("00:00:01.700 --> 00:00:04.300\nHola, este es el primero.\n"));
+    }
+
+    #[test]
+    fn test_subs_convert_names_sub_formats() {
+        let temp_dir = TempDir::new().unwrap();
+        let show_dir = create_test_show_structure(&temp_dir);
+        fs::write(show_dir.join("Test.Show.S01E01.mkv"), b"").unwrap();
+        let mut config = fs::read_to_string(show_dir.join("anytron.toml")).unwrap();
+        config.push_str("languages = [\"en\"]\n");
+        fs::write(show_dir.join("anytron.toml"), config).unwrap();
+
+        for format in ["subviewer", "microdvd"] {
+            let subs_dir = temp_dir.path().join(format);
+            Command::cargo_bin("anytron")
+                .unwrap()
+                .args([
+                    "subs",
+                    "convert",
+                    show_dir.to_str().unwrap(),
+                    "-o",
+                    subs_dir.to_str().unwrap(),
+                    "--format",
+                    format,
+                ])
+                .assert()
+                .success();
+        }
+
+        let subviewer =
+            fs::read_to_string(temp_dir.path().join("subviewer/Test.Show.S01E01.eng.sub")).unwrap();
+        assert!(subviewer.starts_with("[INFORMATION]\n"));
+        assert!(subviewer.contains("00:00:01.00,00:00:04.00\nHello, this is the first subtitle.\n"));
+        let microdvd =
+            fs::read_to_string(temp_dir.path().join("microdvd/Test.Show.S01E01.eng.sub")).unwrap();
+        assert!(microdvd.starts_with("{1}{1}"));
+        assert!(!microdvd.contains("[INFORMATION]"));
+    }
+
+    /// Path of the caption page for the `n`th cue of an episode's subtitles
+    fn caption_page(
+        output_dir: &std::path::Path,
+        srt: &std::path::Path,
+        episode: u32,
+        n: usize,
+    ) -> std::path::PathBuf {
+        let entries = anytron::subtitle::parse_file(srt).unwrap();
+        let ids = anytron::indexer::caption_ids(
+            &anytron::discovery::EpisodeId::new(1, episode),
+            &entries,
+        );
+        output_dir.join("caption").join(format!("{}.html", ids[n]))
+    }
 }
 
 mod output_structure_tests {
@@ -322,6 +726,52 @@ mod library_tests {
         assert!(entries[0].text_clean.contains("Hello from WebVTT format"));
     }
 
+    #[test]
+    fn test_ttml_parser_integration() {
+        let dfxp_path = fixtures_path().join("sample.dfxp");
+        let entries = anytron::subtitle::parse_file(&dfxp_path).expect("Failed to parse TTML");
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].text_clean, "Hello from TTML format");
+        assert_eq!((entries[1].start.0, entries[1].end.0), (5500, 9000));
+        assert_eq!(entries[1].text, "This line has\n<i>styled</i> text");
+        assert_eq!((entries[2].start.0, entries[2].end.0), (10000, 12000));
+        assert_eq!(entries[2].text_clean, "Clock & offset times");
+    }
+
+    #[test]
+    fn test_sami_parser_integration() {
+        use anytron::subtitle::{parse_file_with, ParseOptions};
+
+        let smi_path = fixtures_path().join("sample.smi");
+        let entries = anytron::subtitle::parse_file(&smi_path).expect("Failed to parse SAMI");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].text_clean, "Hello from SAMI format");
+        assert_eq!((entries[1].start.0, entries[1].end.0), (5500, 9000));
+
+        let options = ParseOptions::default().with_language(Some("fr".to_string()));
+        let french = parse_file_with(&smi_path, &options).expect("Failed to parse SAMI");
+        assert_eq!(french[1].text, "Deux lignes\n<i>en italique</i>");
+    }
+
+    #[test]
+    fn test_microdvd_parser_integration() {
+        use anytron::subtitle::{parse_file_with, ParseOptions};
+
+        let sub_path = fixtures_path().join("sample.sub");
+        let options = ParseOptions::default().with_framerate(Some(25.0));
+        let entries = parse_file_with(&sub_path, &options).expect("Failed to parse MicroDVD");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].text_clean, "Hello from MicroDVD format");
+        assert_eq!((entries[0].start.0, entries[0].end.0), (41000, 44000));
+        assert_eq!(entries[1].text, "<i>Two lines</i>\nin italics");
+
+        // NTSC frames
+        let options = ParseOptions::default().with_framerate(Some(23.976));
+        let entries = parse_file_with(&sub_path, &options).expect("Failed to parse MicroDVD");
+        assert_eq!(entries[0].start.0, 42751);
+    }
+
     #[test]
     fn test_timestamp_formatting() {
         use anytron::subtitle::Timestamp;
@@ -359,6 +809,7 @@ mod library_tests {
             end: Timestamp(5000),
             text: "Test".to_string(),
             text_clean: "Test".to_string(),
+            speaker: None,
         };
 
         let midpoint = entry.midpoint();